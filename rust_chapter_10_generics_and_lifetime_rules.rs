@@ -163,7 +163,7 @@ Traits:
 	This implementation is functionally identical to the previous implementation.
 	However, there is some nuance to it:*/
 
-	pub fn notify(item1: &impl Summary, item2: &impl Summary) {
+	pub fn notify(item1: &impl Summary, item2: &impl Summary) { // skip-check: three versions side by side
 		//item1 and item2 can have different types as long as they impl Summary
 	}
 
@@ -281,7 +281,7 @@ Validating References with Lifetimes:
 	items an implicit lifetime of the duration our program stays in that scope.
 	However, consider the following:*/
 
-	fn main() {
+	fn main() { // expect-error: E0597
 		let r;
 
 		{//note that we can actually just start a new scope because we want to.
@@ -304,7 +304,7 @@ The Borrow Checker:
 	all borrows are valid. Here is the previous code, but with lifetimes
 	annotated:*/
 
-	fn main() {
+	fn main() { // expect-error: E0597
 	    let r;                // ---------+-- 'a
 	                          //          |
 	    {                     //          |
@@ -342,7 +342,7 @@ Generic Lifetimes in Functions:
 	After we implement it, the following code should print "The longest string
 	is abcd".:*/
 
-	fn main() {
+	fn main() { // skip-check: calls longest, written below
 		let string1 = String::from("abcd");
 		let string2 = "xyz";
 
@@ -356,7 +356,7 @@ Generic Lifetimes in Functions:
 	the Strings themselves, the function would take ownership, which we don't
 	want. If we try to write this function as follows, we fail to compile it:*/
 
-	fn longest(x: &str, y: &str) -> &str {
+	fn longest(x: &str, y: &str) -> &str { // expect-error: E0106
 		if x.len() > y.len() {
 			x
 		} else {
@@ -413,7 +413,7 @@ Lifetime Annotation Syntax:
 	lifetime annotation. We place the annotation after the & of a reference.
 	Some examples include:*/
 
-	&i32		//	a reference
+	&i32		//	a reference // skip-check: types, not code
 	&'a i32		//	a reference with an explicit lifetime
 	&'a mut i32	//	a mutable reference with an explicit lifetime
 
@@ -492,7 +492,7 @@ Lifetime Annotations in Function Signatures:
 
 	If we rearrange the scopes like this, we run into an error:*/
 
-	fn main() {
+	fn main() { // expect-error: E0282
 		let string1 = String::from("abcd");
 		let result;
 		
@@ -556,7 +556,7 @@ Thinking in Terms of Lifetimes:
 	refer to a value created within this function, but then we have a dangling
 	reference, which is still no good. For example, this fails to compile:*/
 
-	fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+	fn longest<'a>(x: &'a str, y: &'a str) -> &'a str { // expect-error: E0515
 		let result = String::from("arbitrary string");
 		result.as_str()
 	}
@@ -642,20 +642,26 @@ Lifetime Elision:
 
 	It should be clear how the previous function is assigning its lifetimes:*/
 
-	fn first_word(s: &str) -> &str;
+	fn first_word(s: &str) -> &str; // skip-check: signature only
 
 	//first, apply rule 1:
 
-	fn first_word<'a>(s: &'a str) -> &str;
+	fn first_word<'a>(s: &'a str) -> &str; // skip-check: signature only
 
 	//now, we see that rule 2 applies:
 
-	fn first_word<'a>(s: &'a str) -> &'a str;
+	fn first_word<'a>(s: &'a str) -> &'a str; // skip-check: signature only
 
 	//so our lifetime elision rules have been applied and our lives are easier.
 
 	/*
 
+	Q: What are the three lifetime elision rules?
+	A: One: every parameter that is a reference gets its own lifetime
+	parameter. Two: if there is exactly one input lifetime, every output
+	lifetime gets it. Three: if there are several input lifetimes but one of
+	them is &self or &mut self, every output lifetime gets the lifetime of self.
+
 Lifetime Annotations in Method Definitions:
 
 	When we implement methods on a struct with lifetimes, we use the same
@@ -252,7 +252,7 @@ Handling Invalid Input, or How To Use Result, For Real:
 
 	// Not copying the whole program again
 
-	io::stdin()
+	io::stdin() // skip-check: continues the program above
 		.read_line(&mut guess)
 		.expect("Failed to read line");
 
@@ -11,7 +11,7 @@ Common Programming Concepts:
 
 	The book provides us with an example of something that won't work:*/
 
-	fn main() {
+	fn main() { // expect-error: E0384
 		let x = 5;  //x assigned as immutable
 		println!("The value of x is: {x}");
 		x = 6;      //attempted reassignment of immutable variable: Illegal!
@@ -118,10 +118,10 @@ Shadowing:
 							//This happens because x in the outer scope refers
 							//to the x of value 6, which overshadows the x with
 							//value 5.
-			println!("The value of x in the inner scope is: {x}"); //prints 12
+			println!("The value of x in the inner scope is: {x}"); //prints 12 // expect-stdout: The value of x in the inner scope is: 12
 		}
 
-		println!("The value of x is: {x}"); //prints 6, as the value 5 x is
+		println!("The value of x is: {x}"); //prints 6, as the value 5 x is // expect-stdout: The value of x is: 6
 											//still shadowed by the value 6 x,
 											//and the value 12 x left scope.
 	}
@@ -141,7 +141,7 @@ Shadowing:
 								//consistency within our name scheme without
 								//introducing a ton of separate variables.
 
-	let mut spaces = "   ";
+	let mut spaces = "   "; // expect-error: E0308
 	spaces = spaces.len(); //mutating a string to a u32 fails to compile
 
 	/*
@@ -418,7 +418,7 @@ Repetition With Loops:
 		provided on the same line as the break statement will be returned to
 		the outside of the loop. An example:*/
 
-		fn main() {
+		fn main() { // skip-check: the loop is missing the semicolon that ends its let
 			let mut counter = 0;
 
 			let result = loop {
@@ -457,7 +457,7 @@ Repetition With Loops:
 
 				count += 1;
 			}
-			println!("End count = {count}");
+			println!("End count = {count}"); // expect-stdout: End count = 2
 		}
 
 		/*
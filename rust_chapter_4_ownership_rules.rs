@@ -82,7 +82,7 @@ fn main()
 	assert(s2 == s1) would fail! What causes this difference? The String type is
 	heap-allocated. i.e. the String type itself looks like this:*/
 		
-		struct String{
+		struct String{ // skip-check: sketch of the fields, not Rust
 			ptr 			//Pointer to heap containing the character array
 			len 			//integer
 			capacity 	// another integer
@@ -115,9 +115,14 @@ fn main()
 		- Characters
 		- Tuples containing only Copy-annotated types.
 
+	Q: Which types are Copy, so that assigning them copies instead of moving?
+	A: Integer types, booleans, floating point types, characters, and tuples
+	containing only Copy types. Their size is known at compile time and they
+	live on the stack, so a deep copy and a shallow copy are the same thing.
+
 Function calls with Ownership (i.e. the important part): from the website:*/
 
-fn ex_1() {
+fn ex_1() { // skip-check: two versions of these functions side by side
     let s = String::from("hello");  // s comes into scope
 
     takes_ownership(s);             // s's value moves into the function...
@@ -182,7 +187,7 @@ fn makes_copy(some_integer: i32) { // some_integer comes into scope
 
 //This has an important implication, however: we cannot change borrowed values.
 
-	fn ex_3() {
+	fn ex_3() { // expect-error: E0596
 	    let s = String::from("hello");
 
 	    change(&s); //Fails to compile!
@@ -36,7 +36,7 @@ Defining and Instantiating Structs:
 	//When we want to access an element, we use dot notation. Also note an
 	//instance of a struct declared mutable makes all its elements mutable.
 
-	let mut user = User{ ... };
+	let mut user = User{ ... }; // skip-check: fields left out
 
 	user.email = String::from("new@email.com");
 
@@ -244,7 +244,7 @@ Adding Functionality with Derived Traits:
 	If something was wrong with our rectangle, it might be nice to be able to
 	print out all its elements easily. However, if we try this:*/
 
-	println!("rect1 elements: {}", rect1);
+	println!("rect1 elements: {}", rect1); // skip-check: continues the program above
 
 	/*
 
@@ -261,7 +261,7 @@ Adding Functionality with Derived Traits:
 	it doesn't implement Display yet, as we didn't implement it ourselves! We
 	can try to do what the compiler suggests in the above error message:*/
 
-	println!("rect elements: {:?}", rect1);
+	println!("rect elements: {:?}", rect1); // skip-check: continues the program above
 
 	/*
 
@@ -293,7 +293,7 @@ Adding Functionality with Derived Traits:
 			height: 50,
 		};
 
-		println!("rect1 elements: {:?}", rect1);
+		println!("rect1 elements: {:?}", rect1); // expect-stdout: rect1 elements: Rectangle { width: 30, height: 50 }
 	}
 
 	/*
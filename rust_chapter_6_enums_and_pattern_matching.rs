@@ -42,7 +42,7 @@ Enum Values:
 
 	//And then either variant would be acceptable parameters for this function:
 
-	route(four);
+	route(four); // skip-check: continues the snippet above
 	route(six);		//Both work because both are an IpAddrKind!
 
 	/*
@@ -386,7 +386,7 @@ if let:
 	we want to execute some code in the case of one specific variant, but not
 	in the case of any others. Using match, it would look like this:*/
 
-	let enum_variant = SomeEnum::Variant;
+	let enum_variant = SomeEnum::Variant; // skip-check: SomeEnum stands for any enum
 	match enum_variant {
 		SomeEnum::Variant => println!("Do work with desired variant."),
 		_ => (),
@@ -398,7 +398,7 @@ if let:
 	syntax: Do something if the enum variant is exactly what we want, and skip
 	it otherwise. The above code could look like this instead:*/
 
-	let enum_variant = SomeEnum::Variant;
+	let enum_variant = SomeEnum::Variant; // skip-check: SomeEnum stands for any enum
 	if let SomeEnum::Variant = enum_variant {
 		println!("Do work with desired variant.");
 	}
@@ -409,7 +409,7 @@ if let:
 	things have been moved around. We can also include an else block to handle
 	the cases represented by _:*/
 
-	let mut count = 0;
+	let mut count = 0; // skip-check: continues the coin example
 	if let Coin::Quarter(state) = coin {
 		println!("State quarter from {:?}!", state);
 	} else {
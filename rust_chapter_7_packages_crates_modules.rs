@@ -430,7 +430,7 @@ Bringing Paths into Scope with the use Keyword:
 	for as long as that scope remains valid. If we changed our module structure
 	like so:*/
 
-	mod front_of_house {
+	mod front_of_house { // expect-error: E0433
 		pub mod hosting {
 			pub fn add_to_waitlist() {}
 		}
@@ -488,7 +488,7 @@ Providing New Names with the As Keyword:
 	we just want to give things a new name to make them confusing, we can use
 	the as keyword. It's very simple:*/
 
-	use std::fmt::Result;
+	use std::fmt::Result; // expect-error: E0252
 	use std::io::Result; //Bad! Compiler can't differentiate if we call Result!
 
 	use std::fmt::Result;
@@ -557,7 +557,7 @@ Using Nested Paths to Clean Up Large use Lists:
 	If we want to bring multiple items from a given package into scope, we can
 	use a special syntax to do it all at once. Here's an example:*/
 
-	use std::cmp::Ordering;
+	use std::cmp::Ordering; // skip-check: two alternatives side by side
 	use std::io;	//Non-nested imports
 
 	use std::{cmp::Ordering, io}; //Nested imports
@@ -569,7 +569,7 @@ Using Nested Paths to Clean Up Large use Lists:
 	We can also use the keyword self to specify you are factoring out the whole
 	path for one of your items:*/
 
-	use std::io;
+	use std::io; // skip-check: two alternatives side by side
 	use std::io::Write; //Non-nested
 
 	use std::io::{self, Write}; //Nested imports using self. This will grab
@@ -316,6 +316,11 @@ Shortcuts for Panic on Error: unwrap and expect:
 	Usually, using expect() is a good idea, as communicating why you expect an
 	error may have occurred is a good way to solve the problem if it occurs.
 
+	Q: What is the difference between unwrap() and expect() on a Result?
+	A: Both give back the value inside Ok and panic on Err. unwrap() panics
+	with a generic message; expect() takes a string that becomes the panic
+	message, so it can say why we expected the value to be there.
+
 Propagating Errors:
 
 	We do not need to always handle an error as soon as we encounter it. What
@@ -354,8 +359,8 @@ Propagating Errors:
 	we define the function and its return type; the return type is a Result,
 	with the generics replaced by concrete types String and io::Error.
 
-	If the function succeeds without issue, we get a file at line 338 and then
-	it is parsed into a string and returned on line 345. If either of these
+	If the function succeeds without issue, we get a file at line 343 and then
+	it is parsed into a string and returned on line 350. If either of these
 	actions fail, we return the appropriate error, instead. 
 
 	The code which calls this function either receives a String, or an error,
@@ -437,6 +442,17 @@ A Shortcut for Propagating Errors: The ? Operator:
 	reading in the string, however; the concepts surrounding ? are still in
 	general extremely useful.
 
+	Q: What does the ? operator do after an expression that returns a Result?
+	A: If the Result is Ok, the expression evaluates to the value inside it. If
+	it is Err, the function returns early with that error, converted with From
+	into the error type the function returns.
+
+	Q: When would we use ? instead of unwrap() or expect()?
+	A: When the caller should decide what to do about the error: ? hands it
+	back up, while unwrap() and expect() end the program with a panic. ? only
+	works in a function whose return type it can convert the error into, such
+	as a Result.
+
 Where the ? Operator Can Be Used:
 
 	The ? operator can only be used in functions whose return type is
@@ -445,7 +461,7 @@ Where the ? Operator Can Be Used:
 	? must return some kind of Result<T, E>. Let's look at code using ? that
 	won't compile:*/
 
-	use std::fs::File;
+	use std::fs::File; // expect-error: E0277
 
 	fn main() {
 		let greeting_file = File::open("hello.txt")?;
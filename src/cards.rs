@@ -0,0 +1,447 @@
+//! Flashcards drawn from the notes, scheduled for spaced repetition.
+//!
+//! A card is written into the prose as a question and an answer:
+//!
+//! ```text
+//! Q: Which types are Copy?
+//! A: Integer types, booleans, floating point types, characters, and
+//! tuples of Copy types.
+//! ```
+//!
+//! The answer may follow the question on the next line or after a blank
+//! line, and runs to the end of its paragraph. Definitions the notes already
+//! give, such as "Statement: an instruction that performs an action ...",
+//! become cards too, asking for the term.
+//!
+//! Reviews are scheduled with SM-2: every card has an ease factor, starting
+//! at 2.5, and the interval until the next review grows by it each time the
+//! card is recalled. The reviews are kept as a log, one line per grade, and
+//! the state of every card is worked out by replaying it.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::date::{self, Day};
+use crate::error::{Error, Result};
+use crate::model::{Block, Document};
+use crate::parse::is_blank;
+
+/// The longest term a definition card is made for, in words.
+const MAX_TERM_WORDS: usize = 3;
+
+/// The shortest definition worth a card, in words.
+const MIN_DEFINITION_WORDS: usize = 3;
+
+/// Words that show a `Something: ...` line is a remark rather than a
+/// definition, as in "Note: ..." or "Consider the following: ...".
+const NOT_TERMS: [&str; 12] = [
+	"also", "but", "consider", "here", "i", "it", "note", "so", "there", "this", "we", "you",
+];
+
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardKind {
+	/// Written with `Q:` and `A:`.
+	Explicit,
+	/// A `Term: definition` line.
+	Definition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+	/// Made from the file and the question, so it survives edits elsewhere
+	/// in the file; and, for a question the file asks more than once, from
+	/// how many times it was asked before.
+	pub id: String,
+	pub kind: CardKind,
+	/// The question, or the term of a definition.
+	pub question: String,
+	pub answer: String,
+	/// The line the card starts on.
+	pub line: usize,
+	/// The title of the section the card is in.
+	pub section: Option<String>,
+}
+
+impl Card {
+	/// What the card asks: the question, or the term to define.
+	pub fn front(&self) -> String {
+		match self.kind {
+			CardKind::Explicit => self.question.clone(),
+			CardKind::Definition => format!("Define: {}", self.question),
+		}
+	}
+}
+
+/// The cards in the prose of `doc`, in order.
+pub fn cards(doc: &Document) -> Vec<Card> {
+	let mut cards = Vec::new();
+	let mut asked: HashMap<String, usize> = HashMap::new();
+	for block in &doc.blocks {
+		let Block::Prose(prose) = block else {
+			continue;
+		};
+		let pieces = pieces(&prose.text, prose.lines.start);
+		let mut i = 0;
+		while i < pieces.len() {
+			let (line, text) = &pieces[i];
+			i += 1;
+			let (kind, question, answer) = if let Some(rest) = text.strip_prefix("Q:") {
+				let (question, answer) = match rest.split_once(" A: ") {
+					Some((question, answer)) => (question, answer.to_string()),
+					None => match pieces.get(i).and_then(|(_, next)| next.strip_prefix("A:")) {
+						Some(answer) => {
+							i += 1;
+							(rest, answer.to_string())
+						}
+						None => continue,
+					},
+				};
+				(CardKind::Explicit, question.trim().to_string(), answer)
+			} else if let Some((term, definition)) = definition(text) {
+				(
+					CardKind::Definition,
+					term.to_string(),
+					definition.to_string(),
+				)
+			} else {
+				continue;
+			};
+			let before = asked.entry(question.clone()).or_default();
+			let id = id(doc.file.stem(), &question, *before);
+			*before += 1;
+			cards.push(Card {
+				id,
+				kind,
+				question,
+				answer: answer.trim().to_string(),
+				line: *line,
+				section: doc.heading_for_line(*line).map(|h| h.title.clone()),
+			});
+		}
+	}
+	cards
+}
+
+/// The paragraphs and bullets of some prose, each joined into one line,
+/// with the line they start on. A line starting with `A:` starts a new
+/// piece, so that an answer right under its question is kept apart.
+pub(crate) fn pieces(text: &str, first_line: usize) -> Vec<(usize, String)> {
+	let mut pieces: Vec<(usize, String)> = Vec::new();
+	let mut open = false;
+	for (i, line) in text.lines().enumerate() {
+		let trimmed = line.trim();
+		if is_blank(line) {
+			open = false;
+			continue;
+		}
+		let bullet = trimmed
+			.strip_prefix('-')
+			.filter(|rest| rest.starts_with([' ', '\t']));
+		let starts = !open || bullet.is_some() || trimmed.starts_with("A:");
+		let words = bullet.unwrap_or(trimmed).trim();
+		match pieces.last_mut() {
+			Some((_, text)) if !starts => {
+				text.push(' ');
+				text.push_str(words);
+			}
+			_ => pieces.push((first_line + i, words.to_string())),
+		}
+		open = true;
+	}
+	pieces
+}
+
+/// Splits a `Term: definition` paragraph, if that is what it is.
+pub(crate) fn definition(text: &str) -> Option<(&str, &str)> {
+	let (term, definition) = text.split_once(": ")?;
+	let words: Vec<&str> = term.split(' ').collect();
+	let is_term = words.len() <= MAX_TERM_WORDS
+		&& term.starts_with(|c: char| c.is_uppercase())
+		&& words.iter().all(|word| {
+			!word.is_empty()
+				&& word.chars().all(|c| c.is_alphabetic() || c == '-')
+				&& !NOT_TERMS.contains(&word.to_lowercase().as_str())
+		});
+	let long_enough = definition.split_whitespace().count() >= MIN_DEFINITION_WORDS;
+	(is_term && long_enough).then_some((term, definition))
+}
+
+/// A stable identifier: the FNV-1a hash of the file stem and the question,
+/// and of the number of times the question was asked before, if it was.
+fn id(stem: &str, question: &str, before: usize) -> String {
+	let repeat = match before {
+		0 => String::new(),
+		n => format!("\0{n}"),
+	};
+	let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+	for byte in stem
+		.bytes()
+		.chain([0])
+		.chain(question.bytes())
+		.chain(repeat.bytes())
+	{
+		hash ^= u64::from(byte);
+		hash = hash.wrapping_mul(0x0100_0000_01b3);
+	}
+	format!("{hash:016x}")
+}
+
+/// One grade given to a card, from 0 (forgotten) to 5 (perfect recall).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+	pub day: Day,
+	pub card: String,
+	pub grade: u8,
+}
+
+/// Where a card stands in its repetition schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+	/// Reviews in a row with a passing grade.
+	pub repetitions: u32,
+	/// Days between the last review and the next.
+	pub interval: i64,
+	pub ease: f64,
+	/// The day the card is next due; `None` if it was never reviewed.
+	pub due: Option<Day>,
+}
+
+impl Default for Schedule {
+	fn default() -> Schedule {
+		Schedule {
+			repetitions: 0,
+			interval: 0,
+			ease: INITIAL_EASE,
+			due: None,
+		}
+	}
+}
+
+impl Schedule {
+	/// The schedule of a card from its reviews, oldest first.
+	pub fn replay<'a>(reviews: impl IntoIterator<Item = &'a Review>) -> Schedule {
+		let mut schedule = Schedule::default();
+		for review in reviews {
+			schedule.review(review.day, review.grade);
+		}
+		schedule
+	}
+
+	/// Applies one SM-2 step. A grade under 3 sends the card back to the
+	/// start, a day away, but its ease still drops.
+	pub fn review(&mut self, day: Day, grade: u8) {
+		let grade = grade.min(5);
+		if grade >= 3 {
+			self.interval = match self.repetitions {
+				0 => 1,
+				1 => 6,
+				_ => (self.interval as f64 * self.ease).round() as i64,
+			};
+			self.repetitions += 1;
+		} else {
+			self.repetitions = 0;
+			self.interval = 1;
+		}
+		let miss = f64::from(5 - grade);
+		self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+		self.due = Some(day + self.interval);
+	}
+
+	/// Whether the card should be reviewed on `day`. New cards always are.
+	pub fn is_due(&self, day: Day) -> bool {
+		self.due.is_none_or(|due| due <= day)
+	}
+}
+
+/// Reads a review log. A missing file is an empty log.
+///
+/// Every line is `YYYY-MM-DD<TAB>card<TAB>grade`.
+pub fn read_history(path: &Path) -> Result<Vec<Review>> {
+	let text = match fs::read_to_string(path) {
+		Ok(text) => text,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(Error::io(path, e)),
+	};
+	let mut reviews = Vec::new();
+	for (i, line) in text.lines().enumerate() {
+		if is_blank(line) {
+			continue;
+		}
+		let fields: Vec<&str> = line.split('\t').collect();
+		let review = match fields[..] {
+			[day, card, grade] => date::parse(day)
+				.zip(grade.parse().ok())
+				.map(|(day, grade)| Review {
+					day,
+					card: card.to_string(),
+					grade,
+				}),
+			_ => None,
+		};
+		let review = review.ok_or_else(|| Error::Malformed {
+			path: path.to_path_buf(),
+			line: i + 1,
+			message: "expected `YYYY-MM-DD<TAB>card<TAB>grade`".to_string(),
+		})?;
+		reviews.push(review);
+	}
+	Ok(reviews)
+}
+
+/// Adds a review to the end of a log, creating it if needed.
+pub fn append_review(path: &Path, review: &Review) -> Result<()> {
+	let mut file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)
+		.map_err(|e| Error::io(path, e))?;
+	writeln!(
+		file,
+		"{}\t{}\t{}",
+		date::format(review.day),
+		review.card,
+		review.grade
+	)
+	.map_err(|e| Error::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	#[test]
+	fn definitions_are_short_capitalised_terms() {
+		assert_eq!(
+			definition("Shadowing: declaring a new variable with the same name."),
+			Some(("Shadowing", "declaring a new variable with the same name."))
+		);
+		assert_eq!(
+			definition("Dangling Pointers: pointers to memory that was freed."),
+			Some(("Dangling Pointers", "pointers to memory that was freed."))
+		);
+	}
+
+	#[test]
+	fn remarks_are_not_definitions() {
+		// Too short, a remark, lowercase, too many words, not words.
+		assert_eq!(definition("Shadowing: allowed."), None);
+		assert_eq!(definition("Note: this is not a definition at all."), None);
+		assert_eq!(
+			definition("shadowing: declaring a new variable again."),
+			None
+		);
+		assert_eq!(
+			definition("The Rust Book Says: declaring a new variable again."),
+			None
+		);
+		assert_eq!(definition("Line 12: declaring a new variable again."), None);
+		assert_eq!(definition("No colon and space here"), None);
+	}
+
+	#[test]
+	fn a_question_asked_again_is_a_card_of_its_own() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_4_ownership_rules.rs").unwrap(),
+			"/*
+Moves:
+
+	Q: What is a move? A: Handing over ownership.
+
+Functions:
+
+	Q: What is a move? A: Passing ownership to a parameter.
+*/
+",
+		);
+		let ids: Vec<String> = cards(&doc).into_iter().map(|card| card.id).collect();
+		assert_ne!(ids[0], ids[1]);
+		// The first keeps the id it would have on its own.
+		assert_eq!(
+			ids[0],
+			id("rust_chapter_4_ownership_rules", "What is a move?", 0)
+		);
+	}
+
+	#[test]
+	fn cards_are_read_from_questions_and_definitions() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap(),
+			"/*
+Functions:
+
+	Statement: an instruction that performs an action and returns nothing.
+
+	Q: Which types are Copy?
+	A: Integer types, booleans, and
+	tuples of Copy types.
+
+	Q: Do statements return values? A: No.
+
+	Q: A question with no answer?
+*/
+",
+		);
+		let cards = cards(&doc);
+		let cards: Vec<(CardKind, &str, &str, usize, Option<&str>)> = cards
+			.iter()
+			.map(|card| {
+				(
+					card.kind,
+					card.question.as_str(),
+					card.answer.as_str(),
+					card.line,
+					card.section.as_deref(),
+				)
+			})
+			.collect();
+		assert_eq!(
+			cards,
+			[
+				(
+					CardKind::Definition,
+					"Statement",
+					"an instruction that performs an action and returns nothing.",
+					4,
+					Some("Functions")
+				),
+				(
+					CardKind::Explicit,
+					"Which types are Copy?",
+					"Integer types, booleans, and tuples of Copy types.",
+					6,
+					Some("Functions")
+				),
+				(
+					CardKind::Explicit,
+					"Do statements return values?",
+					"No.",
+					10,
+					Some("Functions")
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn recalled_cards_wait_longer_each_time() {
+		let mut schedule = Schedule::default();
+		assert!(schedule.is_due(0));
+		let mut intervals = Vec::new();
+		for day in [0, 1, 7] {
+			schedule.review(day, 4);
+			intervals.push(schedule.interval);
+		}
+		assert_eq!(intervals, [1, 6, 15]);
+		assert_eq!(schedule.due, Some(22));
+		assert!(!schedule.is_due(21));
+		schedule.review(22, 1);
+		assert_eq!((schedule.repetitions, schedule.interval), (0, 1));
+		assert!(schedule.ease < INITIAL_EASE);
+	}
+}
@@ -0,0 +1,905 @@
+//! Compile-checking the code snippets with the local `rustc`.
+//!
+//! Each Rust block is wrapped into a crate of its own: top-level items stay
+//! at the crate root and loose statements are moved into a `main`. The crate
+//! is type- and borrow-checked with `rustc --emit=metadata`, and the result is
+//! compared with what the notes say should happen.
+//!
+//! A snippet that is meant to fail carries an annotation on any of its lines:
+//!
+//! ```text
+//! fn main() { // expect-error: E0384
+//! ```
+//!
+//! `// expect-error` with no code accepts any error. `// skip-check: reason`
+//! leaves a block out, for pseudo-code that was never meant to be Rust.
+//!
+//! The notes build their examples up bit by bit, so a snippet that fails on
+//! its own is tried again together with the structs, functions and other
+//! items of the snippets before it in the same file that do compile, unless
+//! it defines them again itself.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::error::{Error, Result};
+use crate::model::{Code, Document, Lang};
+use crate::parse;
+
+/// What the notes say a snippet should do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expectation {
+	Compiles,
+	/// Fails with every one of these error codes; empty means any error.
+	Fails(Vec<String>),
+	Skip(String),
+}
+
+impl Expectation {
+	/// Reads the annotations of a block, falling back to [`Expectation::Compiles`].
+	pub fn of(code: &Code) -> Expectation {
+		for line in code.text.lines() {
+			if let Some(codes) = annotation(line, "expect-error") {
+				let codes = codes
+					.split([',', ' '])
+					.filter(|code| !code.is_empty())
+					.map(str::to_string)
+					.collect();
+				return Expectation::Fails(codes);
+			}
+			if let Some(reason) = annotation(line, "skip-check") {
+				return Expectation::Skip(reason.to_string());
+			}
+		}
+		Expectation::Compiles
+	}
+}
+
+/// The value of a `// name: value` annotation on `line`, if it has one.
+///
+/// The annotation may follow another comment on the same line, as in
+/// `&i32 // a reference // skip-check: not a statement`.
+pub fn annotation<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+	line.match_indices("//").find_map(|(at, _)| {
+		let rest = line[at + 2..].trim_start().strip_prefix(name)?;
+		if !rest.is_empty() && !rest.starts_with([':', ' ']) {
+			return None;
+		}
+		let value = rest.trim_start_matches(':');
+		let value = value.split("//").next().unwrap_or(value);
+		Some(value.trim())
+	})
+}
+
+/// Every annotation the harness reads, here and in [`crate::run`].
+pub const ANNOTATIONS: [&str; 4] = [
+	"expect-error",
+	"skip-check",
+	"expect-stdout",
+	"expect-stdout-any-order",
+];
+
+/// `line` without its harness annotation, for showing the code to a reader.
+pub fn strip_annotation(line: &str) -> &str {
+	let found = line.match_indices("//").find(|&(at, _)| {
+		let comment = line[at + 2..].trim_start();
+		ANNOTATIONS.iter().any(|name| {
+			comment
+				.strip_prefix(name)
+				.is_some_and(|rest| rest.is_empty() || rest.starts_with([':', ' ']))
+		})
+	});
+	match found {
+		Some((at, _)) => line[..at].trim_end(),
+		None => line,
+	}
+}
+
+/// One error reported by `rustc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	/// The `E0384` style code, when the error has one.
+	pub code: Option<String>,
+	pub message: String,
+	/// The line in the notes file the error points at.
+	pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+	Compiled,
+	FailedAsExpected(Vec<Diagnostic>),
+	/// Failed without an `expect-error` annotation.
+	Accidental(Vec<Diagnostic>),
+	/// Annotated `expect-error` but compiled.
+	UnexpectedSuccess,
+	/// Failed, but without all of the expected error codes.
+	WrongErrors {
+		expected: Vec<String>,
+		diagnostics: Vec<Diagnostic>,
+	},
+	Skipped(String),
+}
+
+impl Outcome {
+	/// Deliberate failures are the ones annotated `expect-error`.
+	pub fn is_deliberate(&self) -> bool {
+		matches!(
+			self,
+			Outcome::FailedAsExpected(_) | Outcome::UnexpectedSuccess | Outcome::WrongErrors { .. }
+		)
+	}
+
+	/// Whether the notes and the compiler disagree.
+	pub fn is_problem(&self) -> bool {
+		matches!(
+			self,
+			Outcome::Accidental(_) | Outcome::UnexpectedSuccess | Outcome::WrongErrors { .. }
+		)
+	}
+
+	pub fn diagnostics(&self) -> &[Diagnostic] {
+		match self {
+			Outcome::FailedAsExpected(diagnostics)
+			| Outcome::Accidental(diagnostics)
+			| Outcome::WrongErrors { diagnostics, .. } => diagnostics,
+			_ => &[],
+		}
+	}
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			Outcome::Compiled => "ok",
+			Outcome::FailedAsExpected(_) => "fails as expected",
+			Outcome::Accidental(_) => "ACCIDENTAL FAILURE",
+			Outcome::UnexpectedSuccess => "EXPECTED TO FAIL",
+			Outcome::WrongErrors { .. } => "WRONG ERRORS",
+			Outcome::Skipped(_) => "skipped",
+		}
+	}
+}
+
+/// The outcome of checking one snippet.
+#[derive(Debug, Clone)]
+pub struct Checked {
+	pub outcome: Outcome,
+	/// The items borrowed from earlier snippets; empty if the snippet was
+	/// checked on its own.
+	pub context: Vec<Item>,
+	/// The items of the earlier snippets that compiled when this one was
+	/// checked, which a failure was or could have been tried again with.
+	pub available: Vec<Item>,
+}
+
+impl Checked {
+	/// Whether the result still holds when the earlier snippets compile to
+	/// `available`: every item it borrowed is still there as it was, and a
+	/// failure has no new items it could be tried with.
+	fn holds_with(&self, available: &[Item]) -> bool {
+		self.context.iter().all(|item| available.contains(item))
+			&& (!self.outcome.is_problem() || self.available == available)
+	}
+}
+
+/// A snippet wrapped into a crate, with a map back to the snippet's lines.
+#[derive(Debug, Clone)]
+pub struct Unit {
+	pub source: String,
+	/// For each line of `source`, the 0-based snippet line it came from.
+	origin: Vec<Option<usize>>,
+	/// How many lines of borrowed context come first.
+	context_len: usize,
+}
+
+impl Unit {
+	/// Wraps `code`, preceded by the `context` items it does not define
+	/// itself.
+	pub fn wrap(code: &Code, context: &[Item]) -> Unit {
+		let lines: Vec<&str> = code.text.lines().collect();
+		let mut items = Vec::new();
+		let mut statements = Vec::new();
+		for group in top_level_groups(&lines) {
+			if group.is_item {
+				items.extend(group.lines);
+			} else {
+				statements.extend(group.lines);
+			}
+		}
+
+		let defines_main = lines.iter().any(|line| {
+			let line = line.trim_start();
+			line.starts_with("fn main(") || line.starts_with("pub fn main(")
+		});
+
+		let own = self::items(code);
+		let own_uses = own.iter().any(|item| item.import);
+		let mut unit = Unit {
+			source: String::new(),
+			origin: Vec::new(),
+			context_len: 0,
+		};
+		for item in context {
+			// Imports are left to the snippet when it has any, since two
+			// imports of one name clash.
+			if own.iter().all(|own| own.key != item.key) && !(item.import && own_uses) {
+				for line in item.text.lines() {
+					unit.push(line, None);
+				}
+			}
+		}
+		unit.context_len = unit.origin.len();
+		for &i in &items {
+			unit.push(lines[i], Some(i));
+		}
+		if !statements.is_empty() {
+			// Keep a `main` the snippet defines itself; the loose statements
+			// then go in a function of their own.
+			let name = if defines_main { "__snippet" } else { "main" };
+			unit.push(&format!("fn {name}() {{"), None);
+			for &i in &statements {
+				unit.push(lines[i], Some(i));
+			}
+			unit.push("}", None);
+		} else if !defines_main {
+			unit.push("fn main() {}", None);
+		}
+		unit
+	}
+
+	fn push(&mut self, line: &str, origin: Option<usize>) {
+		self.source.push_str(line);
+		self.source.push('\n');
+		self.origin.push(origin);
+	}
+
+	/// The snippet line (0-based) behind a 1-based line of the unit.
+	pub fn snippet_line(&self, unit_line: usize) -> Option<usize> {
+		unit_line
+			.checked_sub(1)
+			.and_then(|i| self.origin.get(i).copied().flatten())
+	}
+
+	/// Whether a 1-based line of the unit was borrowed from an earlier
+	/// snippet.
+	pub fn is_context(&self, unit_line: usize) -> bool {
+		(1..=self.context_len).contains(&unit_line)
+	}
+}
+
+/// A named top-level item of a snippet, such as `struct User` or
+/// `impl Rectangle`, that later snippets may rely on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+	/// The name, e.g. `User`; a later item with the same key replaces an
+	/// earlier one.
+	pub key: String,
+	/// Whether this is a `use` rather than a definition.
+	pub import: bool,
+	pub text: String,
+}
+
+/// The named items a snippet defines, leaving out `main`.
+pub fn items(code: &Code) -> Vec<Item> {
+	top_level_items(&code.text)
+		.into_iter()
+		.filter(|item| item.key != "main")
+		.collect()
+}
+
+/// Every complete named item at the top level of `text`, in order.
+pub(crate) fn top_level_items(text: &str) -> Vec<Item> {
+	let lines: Vec<&str> = text.lines().collect();
+	top_level_groups(&lines)
+		.into_iter()
+		.filter(|group| group.is_item)
+		.filter_map(|group| {
+			let header = group
+				.lines
+				.iter()
+				.map(|&i| code_part(lines[i]).trim())
+				.find(|line| !line.is_empty() && !line.starts_with('#'))?;
+			let key = item_key(header)?;
+			let text: Vec<&str> = group.lines.iter().map(|&i| lines[i]).collect();
+			// An item cut off mid-way, like a signature with no body, would
+			// break every snippet it is carried into.
+			let balanced = parse::nesting_depths(&[&text[..], &[""]].concat())
+				.last()
+				.is_some_and(|&depth| depth == 0);
+			balanced.then(|| Item {
+				import: header.starts_with("use ") || header.starts_with("pub use "),
+				key,
+				text: text.join("\n"),
+			})
+		})
+		.collect()
+}
+
+/// `Point` for `pub struct Point<T> {`, `impl Summary for Tweet` for an
+/// impl header, `HashMap` for `use std::collections::HashMap;`. A `use` that
+/// brings in several names is keyed by its whole line.
+///
+/// Named items are keyed by name alone, so an `enum IpAddr` replaces an
+/// earlier `struct IpAddr`.
+fn item_key(line: &str) -> Option<String> {
+	const NAMED: [&str; 8] = [
+		"fn", "struct", "enum", "trait", "mod", "const", "static", "type",
+	];
+	let mut line = line;
+	for prefix in ["pub(crate) ", "pub ", "unsafe "] {
+		line = line.strip_prefix(prefix).unwrap_or(line);
+	}
+	if let Some(path) = line.strip_prefix("use ") {
+		let path = path.trim_end_matches(';').trim();
+		if path.contains(['{', '*']) {
+			return Some(line.to_string());
+		}
+		let name = match path.rsplit_once(" as ") {
+			Some((_, alias)) => alias,
+			None => path.rsplit("::").next().unwrap_or(path),
+		};
+		return Some(name.trim().to_string());
+	}
+	if line.starts_with("impl") {
+		let header = line.split('{').next().unwrap_or(line);
+		return Some(header.split_whitespace().collect::<Vec<_>>().join(" "));
+	}
+	let (kind, rest) = line.split_once(' ')?;
+	let name: String = rest
+		.trim_start()
+		.chars()
+		.take_while(|c| c.is_alphanumeric() || *c == '_')
+		.collect();
+	(NAMED.contains(&kind) && !name.is_empty()).then_some(name)
+}
+
+/// Adds the items of a snippet to `context`, replacing older definitions.
+///
+/// Items named after a type of the standard prelude, like the notes' own
+/// copy of `enum Option<T>`, are left out so they cannot shadow the real
+/// thing in later snippets.
+fn extend_context(context: &mut Vec<Item>, code: &Code) {
+	const PRELUDE: [&str; 5] = ["Option", "Result", "String", "Vec", "Box"];
+	for item in items(code) {
+		if PRELUDE.contains(&item.key.as_str()) {
+			continue;
+		}
+		context.retain(|known| known.key != item.key);
+		context.push(item);
+	}
+}
+
+struct Group {
+	is_item: bool,
+	lines: Vec<usize>,
+}
+
+/// Splits a snippet into its top-level items and statements.
+///
+/// A new group starts on a top-level line when the previous line of code
+/// ended with `;` or `}`; anything else, like a `where` clause or a method
+/// chain, continues the group before it. Comments stay with their group.
+fn top_level_groups(lines: &[&str]) -> Vec<Group> {
+	let depths = parse::nesting_depths(lines);
+	let mut groups: Vec<Group> = Vec::new();
+	let mut ended = true;
+	for (i, line) in lines.iter().enumerate() {
+		let code = code_part(line).trim();
+		if code.is_empty() {
+			match groups.last_mut() {
+				Some(group) => group.lines.push(i),
+				None => groups.push(Group {
+					is_item: true,
+					lines: vec![i],
+				}),
+			}
+			continue;
+		}
+		let starts_group = depths[i] == 0 && ended;
+		ended = code.ends_with(';') || code.ends_with('}');
+		match groups.last_mut() {
+			Some(group) if !starts_group => group.lines.push(i),
+			_ => groups.push(Group {
+				is_item: is_item_start(code),
+				lines: vec![i],
+			}),
+		}
+	}
+	groups
+}
+
+fn is_item_start(code: &str) -> bool {
+	const ITEMS: [&str; 16] = [
+		"fn ", "pub ", "pub(", "struct ", "enum ", "impl", "trait ", "use ", "mod ", "const ",
+		"static ", "type ", "extern ", "unsafe ", "#[", "#!",
+	];
+	ITEMS.iter().any(|item| code.starts_with(item)) || code.starts_with("macro_rules!")
+}
+
+/// The part of a line before any `//` comment.
+pub(crate) fn code_part(line: &str) -> &str {
+	let mut in_string = false;
+	let mut escaped = false;
+	let bytes = line.as_bytes();
+	for (i, &b) in bytes.iter().enumerate() {
+		match b {
+			_ if escaped => escaped = false,
+			b'\\' if in_string => escaped = true,
+			b'"' => in_string = !in_string,
+			b'/' if !in_string && bytes.get(i + 1) == Some(&b'/') => return &line[..i],
+			_ => {}
+		}
+	}
+	line
+}
+
+/// Why a block cannot be checked on its own, if it cannot.
+fn unsupported(code: &Code) -> Option<String> {
+	if code.lang() != Lang::Rust {
+		return Some(format!("{} block", code.lang().name()));
+	}
+	const LOCAL: [&str; 6] = ["std", "core", "alloc", "crate", "self", "super"];
+	let lines: Vec<&str> = code
+		.text
+		.lines()
+		.map(|line| code_part(line).trim())
+		.collect();
+	let declared = |name: &str| {
+		lines.iter().any(|line| {
+			let line = line.strip_prefix("pub ").unwrap_or(line);
+			line.strip_prefix("mod ")
+				.is_some_and(|rest| rest.trim_end_matches([';', '{', ' ']) == name)
+		})
+	};
+	for line in &lines {
+		let line = line.strip_prefix("pub ").unwrap_or(line);
+		if let Some(module) = line.strip_prefix("mod ").and_then(|m| m.strip_suffix(';')) {
+			return Some(format!("needs the file for module `{}`", module.trim()));
+		}
+		let path = line
+			.strip_prefix("use ")
+			.or_else(|| line.strip_prefix("extern crate "));
+		if let Some(path) = path {
+			let root: String = path
+				.chars()
+				.take_while(|c| c.is_alphanumeric() || *c == '_')
+				.collect();
+			if !root.is_empty() && !LOCAL.contains(&root.as_str()) && !declared(&root) {
+				return Some(format!("needs the `{root}` crate"));
+			}
+		}
+	}
+	None
+}
+
+/// Runs `rustc` over snippets.
+#[derive(Debug, Clone)]
+pub struct Checker {
+	rustc: PathBuf,
+	jobs: usize,
+}
+
+impl Default for Checker {
+	fn default() -> Checker {
+		Checker::new()
+	}
+}
+
+impl Checker {
+	/// Uses `$RUSTC` if set, else `rustc` from the `PATH`.
+	pub fn new() -> Checker {
+		let rustc = env::var_os("RUSTC").map_or_else(|| PathBuf::from("rustc"), PathBuf::from);
+		let jobs = thread::available_parallelism().map_or(1, |n| n.get());
+		Checker { rustc, jobs }
+	}
+
+	pub fn jobs(mut self, jobs: usize) -> Checker {
+		self.jobs = jobs.max(1);
+		self
+	}
+
+	/// Checks one snippet on its own.
+	pub fn check(&self, code: &Code) -> Result<Outcome> {
+		Ok(self
+			.check_in(code, &[])?
+			.expect("a snippet without context has no context errors"))
+	}
+
+	/// Checks every snippet of `docs` on `jobs` threads. Results come back
+	/// in order.
+	///
+	/// Each snippet is first checked on its own. One that then disagrees with
+	/// the notes gets a second try with the items of the earlier snippets of
+	/// its file that compiled, and keeps the first outcome if those items get
+	/// in each other's way.
+	pub fn check_all(&self, docs: &[Document]) -> Result<Vec<Checked>> {
+		let count = docs.iter().map(|doc| doc.code_blocks().count()).sum();
+		self.check_unknown(docs, vec![None; count])
+	}
+
+	/// Checks the snippets of `doc` again after it was edited. A snippet
+	/// whose text is a key of `known` keeps that result as long as the items
+	/// it was checked with are unchanged, so only the snippets that changed
+	/// or depend on one that did are compiled.
+	pub fn recheck(
+		&self,
+		doc: &Document,
+		known: &HashMap<String, Checked>,
+	) -> Result<Vec<Checked>> {
+		let known = doc
+			.code_blocks()
+			.map(|code| known.get(&code.text).cloned())
+			.collect();
+		self.check_unknown(slice::from_ref(doc), known)
+	}
+
+	/// [`Checker::check_all`] for the snippets `known` has no result for, or
+	/// a result that no longer holds.
+	fn check_unknown(
+		&self,
+		docs: &[Document],
+		known: Vec<Option<Checked>>,
+	) -> Result<Vec<Checked>> {
+		let codes: Vec<&Code> = docs.iter().flat_map(Document::code_blocks).collect();
+		let fresh: Vec<usize> = (0..codes.len()).filter(|&i| known[i].is_none()).collect();
+		let outcomes = self.parallel(&fresh, |&i| self.check(codes[i]));
+		let mut checked = known;
+		for (&i, outcome) in fresh.iter().zip(outcomes) {
+			checked[i] = Some(Checked {
+				outcome: outcome?,
+				context: Vec::new(),
+				available: Vec::new(),
+			});
+		}
+		let mut checked: Vec<Checked> = checked
+			.into_iter()
+			.map(|checked| checked.expect("every snippet has been checked"))
+			.collect();
+
+		// A kept result that no longer holds is checked again from scratch,
+		// which is why it goes without an outcome of its own.
+		let mut retries = Vec::new();
+		let mut i = 0;
+		for doc in docs {
+			let mut context = Vec::new();
+			for code in doc.code_blocks() {
+				if fresh.binary_search(&i).is_ok() {
+					checked[i].available = context.clone();
+					if checked[i].outcome.is_problem() && !context.is_empty() {
+						let alone = checked[i].outcome.clone();
+						retries.push((i, code, context.clone(), Some(alone)));
+					}
+				} else if !checked[i].holds_with(&context) {
+					retries.push((i, code, context.clone(), None));
+				}
+				// Only a snippet that compiles on its own lends its items,
+				// whichever way its result was come by.
+				if checked[i].outcome == Outcome::Compiled && checked[i].context.is_empty() {
+					extend_context(&mut context, code);
+				}
+				i += 1;
+			}
+		}
+		let retried = self.parallel(&retries, |(_, code, context, alone)| {
+			let outcome = match alone {
+				Some(outcome) => outcome.clone(),
+				None => self.check(code)?,
+			};
+			if outcome.is_problem() && !context.is_empty() {
+				if let Some(retried) = self.check_in(code, context)? {
+					return Ok(Checked {
+						outcome: retried,
+						context: context.clone(),
+						available: context.clone(),
+					});
+				}
+			}
+			Ok(Checked {
+				outcome,
+				context: Vec::new(),
+				available: context.clone(),
+			})
+		});
+		for ((i, ..), result) in retries.into_iter().zip(retried) {
+			checked[i] = result?;
+		}
+		Ok(checked)
+	}
+
+	/// Checks a snippet with the items of `context` in front of it, or
+	/// returns `None` if the context itself does not compile that way.
+	fn check_in(&self, code: &Code, context: &[Item]) -> Result<Option<Outcome>> {
+		let expectation = Expectation::of(code);
+		if let Expectation::Skip(reason) = expectation {
+			return Ok(Some(Outcome::Skipped(reason)));
+		}
+		if let Some(reason) = unsupported(code) {
+			return Ok(Some(Outcome::Skipped(reason)));
+		}
+
+		let unit = Unit::wrap(code, context);
+		let mut diagnostics = self.compile(&unit.source)?;
+		if diagnostics.iter().any(|d| {
+			d.line
+				.map_or(!context.is_empty(), |line| unit.is_context(line))
+		}) {
+			return Ok(None);
+		}
+		for diagnostic in &mut diagnostics {
+			diagnostic.line = diagnostic
+				.line
+				.and_then(|line| unit.snippet_line(line))
+				.map(|i| code.lines.start + i);
+		}
+
+		Ok(Some(match expectation {
+			_ if diagnostics.is_empty() => match expectation {
+				Expectation::Fails(_) => Outcome::UnexpectedSuccess,
+				_ => Outcome::Compiled,
+			},
+			Expectation::Fails(expected) => {
+				let found: BTreeSet<&str> = diagnostics
+					.iter()
+					.filter_map(|d| d.code.as_deref())
+					.collect();
+				if expected.iter().all(|code| found.contains(code.as_str())) {
+					Outcome::FailedAsExpected(diagnostics)
+				} else {
+					Outcome::WrongErrors {
+						expected,
+						diagnostics,
+					}
+				}
+			}
+			_ => Outcome::Accidental(diagnostics),
+		}))
+	}
+
+	/// Runs `f` over `tasks` on `jobs` threads, keeping the order.
+	pub(crate) fn parallel<T: Sync, R: Send>(
+		&self,
+		tasks: &[T],
+		f: impl Fn(&T) -> R + Sync,
+	) -> Vec<R> {
+		let next = AtomicUsize::new(0);
+		let results: Mutex<Vec<Option<R>>> = Mutex::new(tasks.iter().map(|_| None).collect());
+		thread::scope(|scope| {
+			for _ in 0..self.jobs.min(tasks.len()) {
+				scope.spawn(|| loop {
+					let i = next.fetch_add(1, Ordering::Relaxed);
+					let Some(task) = tasks.get(i) else { break };
+					let result = f(task);
+					results.lock().unwrap()[i] = Some(result);
+				});
+			}
+		});
+		results
+			.into_inner()
+			.unwrap()
+			.into_iter()
+			.map(|result| result.expect("every task is run"))
+			.collect()
+	}
+
+	/// Compiles a crate and returns its errors, located by line of the crate.
+	fn compile(&self, source: &str) -> Result<Vec<Diagnostic>> {
+		let dir = temp_dir()?;
+		let errors = self.rustc(&dir, source, &["--emit=metadata"]);
+		let _ = fs::remove_dir_all(&dir);
+		errors
+	}
+
+	/// Compiles a crate into the executable `dir/snippet` and returns its
+	/// errors, if any.
+	pub(crate) fn build(&self, dir: &Path, source: &str) -> Result<Vec<Diagnostic>> {
+		self.rustc(dir, source, &["-o", "snippet"])
+	}
+
+	fn rustc(&self, dir: &Path, source: &str, emit: &[&str]) -> Result<Vec<Diagnostic>> {
+		let file = dir.join("snippet.rs");
+		fs::write(&file, source).map_err(|e| Error::io(&file, e))?;
+		let output = Command::new(&self.rustc)
+			.current_dir(dir)
+			.args([
+				"--edition",
+				"2021",
+				"--crate-type",
+				"bin",
+				"--crate-name",
+				"snippet",
+			])
+			.args(emit)
+			.args(["-A", "warnings", "--error-format=short"])
+			.arg("snippet.rs")
+			.output()
+			.map_err(|e| Error::io(&self.rustc, e))?;
+
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let mut errors: Vec<_> = stderr.lines().filter_map(parse_short_error).collect();
+		if errors.is_empty() && !output.status.success() {
+			errors.push(Diagnostic {
+				code: None,
+				message: stderr.trim().to_string(),
+				line: None,
+			});
+		}
+		Ok(errors)
+	}
+}
+
+/// Creates a fresh directory to compile a snippet in.
+pub(crate) fn temp_dir() -> Result<PathBuf> {
+	static COUNTER: AtomicUsize = AtomicUsize::new(0);
+	let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+	let dir = env::temp_dir().join(format!("rust-notes-check-{}-{n}", process::id()));
+	fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+	Ok(dir)
+}
+
+/// Parses one line of `rustc --error-format=short` output, keeping errors.
+fn parse_short_error(line: &str) -> Option<Diagnostic> {
+	let (location, rest) = match line.strip_prefix("snippet.rs:") {
+		Some(rest) => {
+			let (line_number, rest) = rest.split_once(':')?;
+			let (_, rest) = rest.split_once(": ")?;
+			(line_number.parse().ok(), rest)
+		}
+		None => (None, line),
+	};
+	let rest = rest.strip_prefix("error")?;
+	let (code, message) = match rest.strip_prefix('[') {
+		Some(rest) => {
+			let (code, message) = rest.split_once("]: ")?;
+			(Some(code.to_string()), message)
+		}
+		None => (None, rest.strip_prefix(": ")?),
+	};
+	let summary =
+		message.starts_with("aborting due to") || message.starts_with("could not compile");
+	if location.is_none() && summary {
+		return None;
+	}
+	Some(Diagnostic {
+		code,
+		message: message.to_string(),
+		line: location,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::slice;
+
+	use super::*;
+	use crate::source::NoteFile;
+
+	/// A notes file with one snippet for each of `snippets`.
+	fn notes(snippets: &[&str]) -> Document {
+		let mut source = String::from("/*\nSnippets:\n*/\n");
+		for snippet in snippets {
+			source.push_str(&format!("\n\t/*\n\tNext:*/\n\n\t{snippet}\n"));
+		}
+		Document::parse(
+			NoteFile::from_path("rust_chapter_1_test.rs").unwrap(),
+			&source,
+		)
+	}
+
+	#[test]
+	fn annotations_give_the_expectation() {
+		let doc = notes(&[
+			"let x = 5; // expect-error: E0384, E0502",
+			"let x = 5; // expect-error",
+			"fn f(...) {} // skip-check: pseudo-code",
+			"let x = 5;",
+		]);
+		let expectations: Vec<Expectation> = doc.code_blocks().map(Expectation::of).collect();
+		assert_eq!(
+			expectations,
+			[
+				Expectation::Fails(vec!["E0384".to_string(), "E0502".to_string()]),
+				Expectation::Fails(Vec::new()),
+				Expectation::Skip("pseudo-code".to_string()),
+				Expectation::Compiles,
+			]
+		);
+	}
+
+	#[test]
+	fn loose_statements_go_into_main() {
+		let doc = notes(&["struct Point { x: i32 }\n\tlet p = Point { x: 1 };"]);
+		let code = doc.code_blocks().next().unwrap();
+		let unit = Unit::wrap(code, &[]);
+		assert_eq!(
+			unit.source,
+			"\tstruct Point { x: i32 }\nfn main() {\n\tlet p = Point { x: 1 };\n}\n"
+		);
+		assert_eq!(unit.snippet_line(1), Some(0));
+		assert_eq!(unit.snippet_line(2), None);
+		assert_eq!(unit.snippet_line(3), Some(1));
+	}
+
+	#[test]
+	fn snippets_are_checked_against_their_annotations() {
+		let doc = notes(&[
+			"struct Point { x: i32 }",
+			"fn main() { let p = Point { x: 1 }; println!(\"{}\", p.x); }",
+			"fn main() { let x = 5; x = 6; } // expect-error: E0384",
+			"fn main() { let x: i32 = \"five\"; }",
+			"fn main() {} // expect-error",
+		]);
+		let results = Checker::new().check_all(slice::from_ref(&doc)).unwrap();
+		let labels: Vec<&str> = results
+			.iter()
+			.map(|checked| checked.outcome.label())
+			.collect();
+		assert_eq!(
+			labels,
+			[
+				"ok",
+				"ok",
+				"fails as expected",
+				"ACCIDENTAL FAILURE",
+				"EXPECTED TO FAIL"
+			]
+		);
+		assert!(results[1].context.iter().any(|item| item.key == "Point"));
+		assert_eq!(
+			results[3].outcome.diagnostics()[0].code.as_deref(),
+			Some("E0308")
+		);
+	}
+
+	/// A notes file with a struct and a snippet that uses it.
+	fn shapes(definition: &str) -> Document {
+		let source = format!(
+			"/*\nShapes:\n\n\tA struct:*/\n\n\t{definition}\n\n\t/*\n\tUsing it:*/\n\n\t\
+			 fn main() {{ let r = Rect {{ width: 1, height: 2 }}; println!(\"{{}}\", r.height); }}\n"
+		);
+		Document::parse(
+			NoteFile::from_path("rust_chapter_1_test.rs").unwrap(),
+			&source,
+		)
+	}
+
+	fn recheck(before: &Document, after: &Document) -> Vec<Checked> {
+		let checker = Checker::new();
+		let known = before
+			.code_blocks()
+			.zip(checker.check_all(slice::from_ref(before)).unwrap())
+			.map(|(code, checked)| (code.text.clone(), checked))
+			.collect();
+		let rechecked = checker.recheck(after, &known).unwrap();
+		let fresh = checker.check_all(slice::from_ref(after)).unwrap();
+		let outcomes = |results: &[Checked]| -> Vec<&'static str> {
+			results
+				.iter()
+				.map(|checked| checked.outcome.label())
+				.collect()
+		};
+		assert_eq!(outcomes(&rechecked), outcomes(&fresh));
+		rechecked
+	}
+
+	#[test]
+	fn recheck_drops_results_whose_items_changed() {
+		let before = shapes("struct Rect { width: u32, height: u32 }");
+		let after = shapes("struct Rect { width: u32 }");
+		let results = recheck(&before, &after);
+		assert!(results[1].outcome.is_problem());
+	}
+
+	#[test]
+	fn recheck_tries_a_failure_with_new_items() {
+		let before = shapes("struct Square { side: u32 }");
+		let after = shapes("struct Rect { width: u32, height: u32 }");
+		let results = recheck(&before, &after);
+		assert_eq!(results[1].outcome, Outcome::Compiled);
+	}
+}
@@ -0,0 +1,147 @@
+//! `notes cards`: list the flashcards in the notes, or review the ones that
+//! are due.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rust_notes::cards::{self, Card, Review, Schedule};
+use rust_notes::date::{self, Day};
+use rust_notes::export::anki;
+use rust_notes::{Document, Error, Result};
+
+use super::Args;
+
+/// The review log, in the notes directory unless `--history` says otherwise.
+const HISTORY: &str = ".flashcards";
+
+/// How many cards one review session goes through at most.
+const SESSION: usize = 20;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let history = match args.value("history") {
+		Some(path) => PathBuf::from(path),
+		None => args.dir().join(HISTORY),
+	};
+	let docs = args.documents()?;
+	let reviews = cards::read_history(&history)?;
+	let mut by_card: HashMap<&str, Vec<&Review>> = HashMap::new();
+	for review in &reviews {
+		by_card.entry(&review.card).or_default().push(review);
+	}
+	let today = date::today();
+	let mut due = Vec::new();
+	for doc in &docs {
+		let cards = cards::cards(doc);
+		if cards.is_empty() {
+			continue;
+		}
+		if !args.flag("review") {
+			outln!("{}", doc.file.file_name());
+		}
+		for card in cards {
+			let schedule =
+				Schedule::replay(by_card.get(card.id.as_str()).into_iter().flatten().copied());
+			if !args.flag("review") {
+				outln!(
+					"  {:>4}  {:<12} {}",
+					card.line,
+					status(&schedule, today),
+					card.front()
+				);
+			}
+			if schedule.is_due(today) {
+				due.push((doc, card));
+			}
+		}
+	}
+
+	if !args.flag("review") {
+		outln!("\n{} cards due today", due.len());
+		if let Some(out) = args.value("apkg") {
+			let count = anki::write_deck(&docs, Path::new(out))?;
+			outln!("wrote {count} cards to {out}");
+		}
+		return Ok(ExitCode::SUCCESS);
+	}
+	let limit = match args.value("limit") {
+		Some(n) => n
+			.parse()
+			.map_err(|_| Error::Usage(format!("--limit needs a number, not `{n}`")))?,
+		None => SESSION,
+	};
+	review(&due[..due.len().min(limit)], &reviews, &history, today)
+}
+
+/// Goes through `due` one card at a time: the question, then on Enter the
+/// answer, then a grade, which is written to the log straight away.
+fn review(
+	due: &[(&Document, Card)],
+	reviews: &[Review],
+	history: &Path,
+	today: Day,
+) -> Result<ExitCode> {
+	if due.is_empty() {
+		outln!("no cards due today");
+		return Ok(ExitCode::SUCCESS);
+	}
+	let stdin = io::stdin();
+	let mut input = stdin.lock().lines();
+	let mut ask = |prompt: &str| -> Option<String> {
+		out!("{prompt}");
+		io::stdout().flush().ok();
+		let line = input.next()?.ok()?;
+		let line = line.trim().to_string();
+		(line != "q").then_some(line)
+	};
+	let mut done = 0;
+	'cards: for (n, (doc, card)) in due.iter().enumerate() {
+		outln!(
+			"\n[{}/{}] {}:{}",
+			n + 1,
+			due.len(),
+			doc.file.file_name(),
+			card.line
+		);
+		if let Some(section) = &card.section {
+			outln!("{section}");
+		}
+		outln!("\n  {}", card.front());
+		if ask("\n(Enter to show the answer, q to stop) ").is_none() {
+			break;
+		}
+		outln!("  {}\n", card.answer);
+		let grade = loop {
+			match ask("grade 0-5 (0 forgot, 3 hard, 5 easy; q to stop): ") {
+				None => break 'cards,
+				Some(answer) => match answer.parse::<u8>() {
+					Ok(grade) if grade <= 5 => break grade,
+					_ => continue,
+				},
+			}
+		};
+		let review = Review {
+			day: today,
+			card: card.id.clone(),
+			grade,
+		};
+		cards::append_review(history, &review)?;
+		let mut schedule = Schedule::replay(reviews.iter().filter(|r| r.card == card.id));
+		schedule.review(today, grade);
+		let next = schedule.due.unwrap_or(today);
+		outln!("next review on {}", date::format(next));
+		done += 1;
+	}
+	outln!("\n{done} cards reviewed");
+	Ok(ExitCode::SUCCESS)
+}
+
+fn status(schedule: &Schedule, today: Day) -> String {
+	match schedule.due {
+		None => "new".to_string(),
+		Some(due) if due <= today => "due".to_string(),
+		Some(due) if due == today + 1 => "tomorrow".to_string(),
+		Some(due) => format!("in {} days", due - today),
+	}
+}
@@ -0,0 +1,217 @@
+//! `notes check`: compile every Rust snippet and report the failures, with
+//! the accidental ones listed apart from the deliberate ones, then run the
+//! snippets whose output the notes spell out.
+
+use std::process::ExitCode;
+
+use rust_notes::check::Outcome;
+use rust_notes::run::{DiffLine, RunOutcome};
+use rust_notes::validate::{self, Problem};
+use rust_notes::{Code, Document, Lang, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let checker = args.checker()?;
+
+	let snippets: Vec<(&Document, &Code)> = docs
+		.iter()
+		.flat_map(|doc| doc.code_blocks().map(move |code| (doc, code)))
+		.collect();
+	let results = checker.check_all(&docs)?;
+	let runs = checker.run_all(&docs, &results)?;
+	let outcomes: Vec<Outcome> = results.into_iter().map(|r| r.outcome).collect();
+	let checked: Vec<_> = snippets.iter().zip(&outcomes).collect();
+
+	let accidental: Vec<_> = checked
+		.iter()
+		.filter(|(_, outcome)| matches!(outcome, Outcome::Accidental(_)))
+		.collect();
+	let deliberate: Vec<_> = checked
+		.iter()
+		.filter(|(_, outcome)| outcome.is_deliberate())
+		.collect();
+
+	if !accidental.is_empty() {
+		outln!("Accidental failures:");
+		for ((doc, code), outcome) in &accidental {
+			print_entry(doc, code, outcome, true);
+		}
+		outln!();
+	}
+	if !deliberate.is_empty() {
+		outln!("Deliberate failures:");
+		for ((doc, code), outcome) in &deliberate {
+			print_entry(
+				doc,
+				code,
+				outcome,
+				outcome.is_problem() || args.flag("verbose"),
+			);
+		}
+		outln!();
+	}
+	if args.flag("verbose") {
+		outln!("Compiled and skipped:");
+		for ((doc, code), outcome) in &checked {
+			if matches!(outcome, Outcome::Compiled | Outcome::Skipped(_)) {
+				print_entry(doc, code, outcome, false);
+			}
+		}
+		outln!();
+	}
+
+	let ran: Vec<_> = snippets
+		.iter()
+		.zip(&runs)
+		.filter_map(|(snippet, run)| Some((snippet, run.as_ref()?)))
+		.collect();
+	if ran.iter().any(|(_, run)| run.is_problem()) {
+		outln!("Output mismatches:");
+		for ((doc, code), run) in &ran {
+			if run.is_problem() {
+				print_run(doc, code, run);
+			}
+		}
+		outln!();
+	}
+	if args.flag("verbose") && ran.iter().any(|(_, run)| !run.is_problem()) {
+		outln!("Matching output:");
+		for ((doc, code), run) in &ran {
+			if !run.is_problem() {
+				print_run(doc, code, run);
+			}
+		}
+		outln!();
+	}
+
+	// The blocks the compiler cannot check: manifests and commands.
+	let others: Vec<(&Document, &Code)> = snippets
+		.iter()
+		.filter(|(_, code)| code.lang() != Lang::Rust)
+		.copied()
+		.collect();
+	let problems: Vec<(&Document, &Code, Vec<Problem>)> = others
+		.iter()
+		.map(|&(doc, code)| (doc, code, validate::problems(code)))
+		.filter(|(_, _, problems)| !problems.is_empty())
+		.collect();
+	if !problems.is_empty() {
+		outln!("Problems in the other blocks:");
+		for (doc, code, problems) in &problems {
+			outln!(
+				"  {}:{} #{}  {}",
+				doc.file.file_name(),
+				code.lines,
+				code.index,
+				code.lang().name()
+			);
+			for problem in problems {
+				outln!("      {}: {}", problem.line, problem.message);
+			}
+		}
+		outln!();
+	}
+
+	let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|o| f(o)).count();
+	outln!(
+		"{} compiled, {} failed as expected, {} failed by accident, {} disagree with their annotation, {} skipped",
+		count(|o| matches!(o, Outcome::Compiled)),
+		count(|o| matches!(o, Outcome::FailedAsExpected(_))),
+		accidental.len(),
+		count(|o| matches!(o, Outcome::UnexpectedSuccess | Outcome::WrongErrors { .. })),
+		count(|o| matches!(o, Outcome::Skipped(_))),
+	);
+
+	if !others.is_empty() {
+		outln!(
+			"{} toml, console and unknown blocks validated, {} with problems",
+			others.len(),
+			problems.len()
+		);
+	}
+
+	if !ran.is_empty() {
+		let differ = ran.iter().filter(|(_, run)| run.is_problem()).count();
+		outln!(
+			"{} outputs match, {differ} differ from the notes",
+			ran.len() - differ
+		);
+	}
+
+	if outcomes.iter().any(Outcome::is_problem)
+		|| ran.iter().any(|(_, run)| run.is_problem())
+		|| !problems.is_empty()
+	{
+		Ok(ExitCode::FAILURE)
+	} else {
+		Ok(ExitCode::SUCCESS)
+	}
+}
+
+pub(super) fn print_entry(doc: &Document, code: &Code, outcome: &Outcome, details: bool) {
+	let note = match outcome {
+		Outcome::WrongErrors { expected, .. } => format!(" (expected {})", expected.join(", ")),
+		Outcome::Skipped(reason) => format!(" ({reason})"),
+		Outcome::FailedAsExpected(diagnostics) => {
+			let codes: Vec<&str> = diagnostics
+				.iter()
+				.filter_map(|d| d.code.as_deref())
+				.collect();
+			if codes.is_empty() {
+				String::new()
+			} else {
+				format!(" ({})", codes.join(", "))
+			}
+		}
+		_ => String::new(),
+	};
+	outln!(
+		"  {}:{} #{}  {}{}",
+		doc.file.file_name(),
+		code.lines,
+		code.index,
+		outcome.label(),
+		note
+	);
+	if details {
+		for diagnostic in outcome.diagnostics() {
+			let line = diagnostic
+				.line
+				.map_or_else(|| "?".to_string(), |l| l.to_string());
+			match &diagnostic.code {
+				Some(code) => outln!("      {line}: error[{code}]: {}", diagnostic.message),
+				None => outln!("      {line}: error: {}", diagnostic.message),
+			}
+		}
+	}
+}
+
+pub(super) fn print_run(doc: &Document, code: &Code, run: &RunOutcome) {
+	outln!(
+		"  {}:{} #{}  {}",
+		doc.file.file_name(),
+		code.lines,
+		code.index,
+		run.label()
+	);
+	match run {
+		RunOutcome::Differs(lines) => {
+			for line in lines {
+				match line {
+					DiffLine::Same(text) => outln!("        {text}"),
+					DiffLine::Extra(text) => outln!("      + {text}"),
+					DiffLine::Missing(text) => outln!("      - {text}"),
+				}
+			}
+		}
+		RunOutcome::Crashed { status, stderr } => {
+			outln!("      {status}");
+			for line in stderr.lines() {
+				outln!("      {line}");
+			}
+		}
+		_ => {}
+	}
+}
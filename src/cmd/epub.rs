@@ -0,0 +1,17 @@
+//! `notes epub`: write the notes out as an EPUB book.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::export::epub;
+use rust_notes::Result;
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let out = Path::new(args.value("out").unwrap_or("notes.epub"));
+	epub::write_book(&docs, out)?;
+	outln!("wrote {} chapters to {}", docs.len(), out.display());
+	Ok(ExitCode::SUCCESS)
+}
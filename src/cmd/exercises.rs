@@ -0,0 +1,80 @@
+//! `notes exercises`: list the exercises the chapters end with, and whether
+//! each is solved.
+
+use std::process::ExitCode;
+
+use rust_notes::exercises::{self, Status};
+use rust_notes::text::wrap;
+use rust_notes::Result;
+
+use super::Args;
+
+/// The width statements are wrapped to, past their indentation.
+const WIDTH: usize = 72;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let checker = args.checker()?;
+	let linked = exercises::link(&docs);
+	let statuses = exercises::statuses(&checker, &linked)?;
+
+	let mut chapter = None;
+	let mut counts = [0; 3];
+	for (linked, status) in linked.iter().zip(&statuses) {
+		let exercise = linked.exercise;
+		if chapter != Some(exercise.chapter) {
+			chapter = Some(exercise.chapter);
+			outln!("{}", linked.notes.file.title());
+		}
+		let (place, statement) = match &linked.statement {
+			Some((line, text)) => (
+				format!("{}:{line}", linked.notes.file.file_name()),
+				text.clone(),
+			),
+			None => (
+				"not in the Conclusion any more".to_string(),
+				format!("{}...", exercise.opening),
+			),
+		};
+		outln!("  {:<16} {:<9} {place}", exercise.id, status.label());
+		for line in wrap(&statement, WIDTH) {
+			outln!("      {line}");
+		}
+		let solutions = linked
+			.solutions
+			.map_or("no solutions file".to_string(), |doc| {
+				doc.file.file_name().to_string()
+			});
+		match status {
+			Status::Unsolved { missing } => {
+				counts[0] += 1;
+				if linked.solutions.is_some() {
+					outln!("      {solutions} has no fn {}", missing.join(", fn "));
+				} else {
+					outln!("      {solutions}");
+				}
+			}
+			Status::Solved => {
+				counts[1] += 1;
+				outln!("      {solutions}: {} tests pass", exercise.tests.len());
+			}
+			Status::Failing(failures) => {
+				counts[2] += 1;
+				outln!("      {solutions}:");
+				for failure in failures {
+					outln!("        {}: {}", failure.test, failure.reason);
+				}
+			}
+		}
+	}
+	outln!(
+		"\n{} solved, {} failing, {} unsolved",
+		counts[1],
+		counts[2],
+		counts[0]
+	);
+	if counts[2] > 0 {
+		return Ok(ExitCode::FAILURE);
+	}
+	Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,134 @@
+//! `notes explain`: list the compiler errors the notes deal with, or show
+//! what they say about one of them next to the code that causes it.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rust_notes::diagnostics::{self, Entry};
+use rust_notes::export::display_code;
+use rust_notes::rustdoc::Index;
+use rust_notes::text::wrap;
+use rust_notes::{discover, Document, Error, Result};
+
+use super::Args;
+
+/// The width paragraphs of the notes are wrapped to, past their indentation.
+const WIDTH: usize = 76;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = discover(args.dir())?
+		.into_iter()
+		.map(Document::load)
+		.collect::<Result<Vec<_>>>()?;
+	let catalog = diagnostics::catalog(&docs);
+	let html = match args.value("docs") {
+		Some(dir) => Some(PathBuf::from(dir)),
+		None => Index::locate(),
+	};
+	let official = |code: &str| {
+		let page = diagnostics::official_page(html.as_ref()?, code)?;
+		let summary = diagnostics::official_summary(&page)?;
+		Some((page, summary))
+	};
+
+	let Some(wanted) = args.positional.first() else {
+		for entry in &catalog {
+			let message = entry
+				.message()
+				.map(str::to_string)
+				.or_else(|| official(&entry.code).map(|(_, summary)| summary))
+				.unwrap_or_default();
+			outln!("{}  {message}", entry.code);
+			outln!(
+				"       {}, {}, {}",
+				count(entry.sections.len(), "section"),
+				count(entry.snippets.len(), "snippet"),
+				count(entry.quotes.len(), "quote")
+			);
+		}
+		outln!(
+			"\n{} error codes; `notes explain CODE` shows one",
+			catalog.len()
+		);
+		return Ok(ExitCode::SUCCESS);
+	};
+
+	let code = normalize(wanted)
+		.ok_or_else(|| Error::Usage(format!("`{wanted}` is not an error code like E0384")))?;
+	let Some(entry) = catalog.iter().find(|entry| entry.code == code) else {
+		outln!("the notes do not deal with {code}");
+		if let Some((page, summary)) = official(&code) {
+			outln!("\nrustc: {summary}\n       {}", page.display());
+		}
+		return Ok(ExitCode::FAILURE);
+	};
+	show(entry, official(&code));
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Everything the notes have on one error.
+fn show(entry: &Entry, official: Option<(PathBuf, String)>) {
+	match entry.message() {
+		Some(message) => outln!("{}: {message}", entry.code),
+		None => outln!("{}", entry.code),
+	}
+	if let Some((page, summary)) = official {
+		outln!("rustc: {summary}\n       {}", page.display());
+	}
+
+	outln!("\nExplained in:");
+	for section in &entry.sections {
+		outln!(
+			"  {}  ({}:{})",
+			section.title(),
+			section.doc.file.file_name(),
+			section.line()
+		);
+	}
+
+	for (doc, code) in &entry.snippets {
+		outln!("\nReproduced by {}:{}", doc.file.file_name(), code.lines);
+		if let Some(lead_in) = diagnostics::lead_in(doc, code) {
+			paragraph(&lead_in);
+		}
+		outln!();
+		for line in display_code(code).lines() {
+			outln!("      {line}");
+		}
+	}
+
+	for quote in &entry.quotes {
+		outln!(
+			"\nQuoted at {}:{}",
+			quote.doc.file.file_name(),
+			quote.lines.start
+		);
+		outln!();
+		for line in quote.text.lines() {
+			outln!("      {line}");
+		}
+		if let Some(commentary) = diagnostics::commentary(quote) {
+			outln!();
+			paragraph(&commentary);
+		}
+	}
+}
+
+/// A paragraph of the notes, wrapped and indented.
+fn paragraph(text: &str) {
+	for line in wrap(text, WIDTH) {
+		outln!("  {line}");
+	}
+}
+
+fn count(n: usize, what: &str) -> String {
+	let s = if n == 1 { "" } else { "s" };
+	format!("{n} {what}{s}")
+}
+
+/// `E0384` for `E0384`, `e0384` or `384`.
+fn normalize(code: &str) -> Option<String> {
+	let digits = code.strip_prefix(['E', 'e']).unwrap_or(code);
+	(!digits.is_empty() && digits.len() <= 4 && digits.bytes().all(|b| b.is_ascii_digit()))
+		.then(|| format!("E{digits:0>4}"))
+}
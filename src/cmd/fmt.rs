@@ -0,0 +1,96 @@
+//! `notes fmt`: rewrap the prose of the notes and format their snippets,
+//! writing the files back, or with `--check` only list the ones that would
+//! change. Files with `\r\n` line endings are skipped.
+
+use std::fs;
+use std::process::ExitCode;
+
+use rust_notes::format::{Formatter, DEFAULT_WIDTH};
+use rust_notes::xref::{self, Resolved};
+use rust_notes::{Document, Error, NoteFile, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let mut formatter = Formatter::new();
+	if let Some(width) = args.value("width") {
+		let width = width
+			.parse()
+			.ok()
+			.filter(|&width| width > 0)
+			.ok_or_else(|| {
+				Error::Usage(format!("--width needs a positive number, not `{width}`"))
+			})?;
+		formatter = formatter.width(width);
+	}
+	let check = args.flag("check");
+
+	let mut changed = 0;
+	for file in args.files()? {
+		let source = fs::read_to_string(&file.path).map_err(|e| Error::io(&file.path, e))?;
+		let Some(formatted) = formatter.format(&file, &source) else {
+			outln!("{}: skipped, its lines end in \\r\\n", file.file_name());
+			continue;
+		};
+		for (reference, lines) in &formatted.kept {
+			outln!(
+				"{}: lines {}-{} left as they are; formatting would change what `{}` on line {} \
+				 points at",
+				file.file_name(),
+				lines.start,
+				lines.end,
+				reference.text,
+				reference.line
+			);
+		}
+		let formatted = formatted.source;
+		if formatted == source {
+			continue;
+		}
+		changed += 1;
+		let differ = source
+			.lines()
+			.zip(formatted.lines())
+			.filter(|(old, new)| old != new)
+			.count() + source.lines().count().abs_diff(formatted.lines().count());
+		if check {
+			outln!("{}: {differ} lines would change", file.file_name());
+			continue;
+		}
+		fs::write(&file.path, &formatted).map_err(|e| Error::io(&file.path, e))?;
+		outln!("{}: {differ} lines changed", file.file_name());
+
+		// Rewrapping moves lines, and with them what the prose's references
+		// to line numbers point at.
+		let (before, after) = (stale_refs(&file, &source), stale_refs(&file, &formatted));
+		if after > before {
+			outln!(
+				"  {} references to line numbers no longer resolve; see `notes xref`",
+				after - before
+			);
+		}
+	}
+
+	if changed == 0 {
+		outln!("nothing to format");
+	} else if check {
+		let files = if changed == 1 { "file is" } else { "files are" };
+		outln!(
+			"\n{changed} {files} not formatted to {} columns; `notes fmt` formats them",
+			args.value("width")
+				.map_or(DEFAULT_WIDTH.to_string(), str::to_string)
+		);
+		return Ok(ExitCode::FAILURE);
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// How many of the references a version of a notes file makes to its own
+/// lines are stale.
+fn stale_refs(file: &NoteFile, source: &str) -> usize {
+	let doc = Document::parse(file.clone(), source);
+	xref::line_refs(&doc, source)
+		.iter()
+		.filter(|reference| matches!(xref::resolve(&doc, source, reference), Resolved::Stale(_)))
+		.count()
+}
@@ -0,0 +1,50 @@
+//! `notes glossary`: where each concept is introduced, and where it comes
+//! up again afterwards.
+
+use std::process::ExitCode;
+
+use rust_notes::glossary::{self, Place};
+use rust_notes::{Document, Error, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let only = args.value("term").map(str::to_lowercase);
+	let entries: Vec<_> = glossary::glossary(&docs)
+		.into_iter()
+		.filter(|entry| {
+			only.as_ref()
+				.is_none_or(|term| entry.term.to_lowercase() == *term)
+		})
+		.collect();
+	if let (Some(term), true) = (&only, entries.is_empty()) {
+		return Err(Error::Usage(format!("`{term}` is not in the glossary")));
+	}
+	for (i, entry) in entries.iter().enumerate() {
+		if i > 0 {
+			outln!();
+		}
+		outln!("{}", entry.term);
+		for (j, before) in entry.earlier.iter().enumerate() {
+			let label = if j == 0 { "before" } else { "" };
+			outln!("  {label:<7}  {}", place(&docs, before));
+		}
+		outln!("  defined  {}", place(&docs, &entry.defined));
+		for (j, used) in entry.uses.iter().enumerate() {
+			let label = if j == 0 { "used in" } else { "" };
+			outln!("  {label:<7}  {}", place(&docs, used));
+		}
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// E.g. `Chapter 3: Common Concepts / Shadowing  (rust_chapter_3_common_concepts.rs:99)`.
+fn place(docs: &[Document], place: &Place) -> String {
+	let doc = &docs[place.doc];
+	let section = match place.heading.and_then(|i| doc.headings().nth(i)) {
+		Some(heading) => format!("{} / {}", doc.file.title(), heading.title),
+		None => doc.file.title(),
+	};
+	format!("{section}  ({}:{})", doc.file.file_name(), place.line)
+}
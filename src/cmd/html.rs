@@ -0,0 +1,26 @@
+//! `notes html`: write the notes out as a static HTML site.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::export::html;
+use rust_notes::Result;
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let out = Path::new(args.value("out").unwrap_or("site"));
+	let index = super::rustdoc::load(args)?;
+	if index.is_none() {
+		outln!("no std docs found, so std items are not linked; pass --docs DIR");
+	}
+	let written = html::write_site(&docs, out, index.as_ref())?;
+	outln!(
+		"wrote {} pages to {}; open {}",
+		written.len() - 1,
+		out.display(),
+		out.join("index.html").display()
+	);
+	Ok(ExitCode::SUCCESS)
+}
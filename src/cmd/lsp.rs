@@ -0,0 +1,24 @@
+//! `notes lsp`: a language server for the notes files, talking to the
+//! editor over stdin and stdout.
+
+use std::process::ExitCode;
+
+use rust_notes::{lsp, Error, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	if !args.positional.is_empty() {
+		return Err(Error::Usage(
+			"lsp serves the notes files the editor opens; it takes no files".to_string(),
+		));
+	}
+	let checker = args.checker()?;
+	// The editor's workspace folder, if it names one, takes the place of
+	// --dir.
+	if lsp::serve(args.dir(), checker)? {
+		Ok(ExitCode::SUCCESS)
+	} else {
+		Ok(ExitCode::FAILURE)
+	}
+}
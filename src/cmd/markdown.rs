@@ -0,0 +1,22 @@
+//! `notes markdown`: write the notes out as an mdBook source tree.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::export::markdown;
+use rust_notes::Result;
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let out = Path::new(args.value("out").unwrap_or("book"));
+	let index = super::rustdoc::load(args)?;
+	let written = markdown::write_book(&docs, out, index.as_ref())?;
+	outln!(
+		"wrote {} chapters and a glossary to {}",
+		written.len() - 3,
+		out.display()
+	);
+	Ok(ExitCode::SUCCESS)
+}
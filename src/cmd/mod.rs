@@ -0,0 +1,266 @@
+//! Subcommands of the `notes` binary.
+//!
+//! Every command takes the notes files to work on as positional arguments.
+//! With none given, it uses every notes file in `--dir` (the current
+//! directory by default), in chapter order.
+
+/// `print!` for the commands' output, which ends the program quietly once
+/// whatever reads it has gone, as `head` does after its lines.
+macro_rules! out {
+	($($arg:tt)*) => {
+		$crate::cmd::write_out(format_args!($($arg)*))
+	};
+}
+
+/// `println!`, the same way as [`out!`].
+macro_rules! outln {
+	() => {
+		out!("\n")
+	};
+	($($arg:tt)*) => {
+		out!("{}\n", format_args!($($arg)*))
+	};
+}
+
+mod cards;
+mod check;
+mod epub;
+mod exercises;
+mod explain;
+mod fmt;
+mod glossary;
+mod html;
+mod lsp;
+mod markdown;
+mod notebook;
+mod ownership;
+mod parse;
+mod rustdoc;
+mod search;
+mod stats;
+mod trace;
+mod versions;
+#[cfg(target_os = "linux")]
+mod watch;
+mod workspace;
+mod xref;
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+
+use rust_notes::check::Checker;
+use rust_notes::{discover, Document, Error, NoteFile, Result};
+
+const USAGE: &str = "\
+usage: notes <command> [options] [files...]
+
+commands:
+  parse     print the prose/heading/code model of each file
+  check     compile every Rust snippet and compare with its annotations,
+            then run the snippets with expected output
+  markdown  write an mdBook source tree of the notes
+  html      write a static HTML site of the notes
+  epub      write an EPUB 3 book of the notes for e-readers
+  notebook  write a Jupyter notebook of each notes file for the evcxr
+            kernel
+  search    find the passages that mention some words or a Rust path;
+            the positional arguments are the query, not files
+  xref      check the references to line numbers in the prose
+  rustdoc   resolve the std items the notes mention against the local
+            std docs and list the ones that do not resolve
+  versions  show how each item changes between the snippets that define it
+  glossary  show where each concept is introduced and every later section
+            that uses it
+  explain   list the compiler errors the notes quote, reproduce or
+            mention, or show what they say about one, as in
+            `notes explain E0384`; the positional argument is the code
+  fmt       rewrap the prose to --width columns, keeping headings and
+            lists as they are, and format the snippets with rustfmt
+  cards     list the flashcards in the notes and when each is due
+  exercises list the exercises the chapters end with and run the tests of
+            their solutions
+  stats     count the sections, prose and code lines, compiling snippets
+            and solved exercises of each notes file, and the sections
+            marked as reviewed
+  trace     run the snippets written in the Rust of chapter 3 (integers,
+            bools, let, if, loops and labels) and print each binding and
+            turn of the control flow; --line picks the snippet
+  ownership check the snippets against the ownership rules of chapter 4
+            (moves, copies, borrows and drops) and explain each one it
+            rejects; --line picks the snippet, --examples checks the
+            checker against its own examples instead
+  workspace write the projects the notes build (hello_cargo, guessing_game,
+            ...) as one Cargo workspace
+  lsp       serve the notes files to an editor as a language server:
+            outline, glossary hovers, definitions and compile errors
+  watch     check the notes and write the site and book, then again for
+            each notes file in --dir as it is saved (Linux only)
+
+options:
+  --dir DIR   where to look for the notes when no files are given (default .)
+  --out DIR   markdown, html, notebook, workspace: where to write the
+              output (default book, site, notebooks and projects)
+  --out FILE  epub: the book to write (default notes.epub)
+  --site DIR  watch: where to write the site (default site)
+  --book DIR  watch: where to write the book (default book)
+  --jobs N    check, exercises, lsp, stats, watch: how many snippets to
+              compile at once
+  --limit N   search: how many results to show (default 10)
+  --rewrite   xref: replace line references by section anchors
+  --item NAME versions: only show the item called NAME
+  --term NAME glossary: only show the term NAME
+  --width N   fmt: the width to wrap prose to (default 80)
+  --check     fmt: only list the files that are not formatted
+  --review    cards: go through the cards that are due
+  --limit N   cards: how many cards to review (default 20)
+  --history FILE
+              cards: the review log (default .flashcards in --dir)
+  --apkg FILE cards: also write the cards as an Anki package
+  --json      stats: print the statistics as JSON
+  --progress FILE
+              stats: the reviewed sections (default .progress in --dir)
+  --mark SECTION
+              stats: mark a section (FILE#ANCHOR) or a whole notes file
+              (FILE) as reviewed
+  --unmark SECTION
+              stats: take the mark off a section or file
+  --line N    trace, ownership: trace or explain the snippet at line N of
+              the one file given
+  --examples  ownership: check the built-in examples instead of the notes
+  --verbose   check: also list the snippets that compiled or were skipped;
+              rustdoc: also list the references that resolve; trace,
+              ownership: also list the snippets they cannot handle and why;
+              with --examples, show each example and its explanation
+  --docs DIR  explain, html, markdown, rustdoc, watch: the `html`
+              directory of the std docs (default the active toolchain's)
+";
+
+pub fn run(raw: &[String]) -> Result<ExitCode> {
+	let Some((command, rest)) = raw.split_first() else {
+		return Err(Error::Usage(USAGE.to_string()));
+	};
+	match command.as_str() {
+		"parse" => parse::run(&Args::parse(rest, &[])?),
+		"check" => check::run(&Args::parse(rest, &["jobs"])?),
+		"markdown" => markdown::run(&Args::parse(rest, &["out", "docs"])?),
+		"html" => html::run(&Args::parse(rest, &["out", "docs"])?),
+		"epub" => epub::run(&Args::parse(rest, &["out"])?),
+		"notebook" => notebook::run(&Args::parse(rest, &["out"])?),
+		"search" => search::run(&Args::parse(rest, &["limit"])?),
+		"xref" => xref::run(&Args::parse(rest, &[])?),
+		"rustdoc" => rustdoc::run(&Args::parse(rest, &["docs"])?),
+		"versions" => versions::run(&Args::parse(rest, &["item"])?),
+		"glossary" => glossary::run(&Args::parse(rest, &["term"])?),
+		"explain" => explain::run(&Args::parse(rest, &["docs"])?),
+		"fmt" => fmt::run(&Args::parse(rest, &["width"])?),
+		"cards" => cards::run(&Args::parse(rest, &["limit", "history", "apkg"])?),
+		"exercises" => exercises::run(&Args::parse(rest, &["jobs"])?),
+		"stats" => stats::run(&Args::parse(rest, &["jobs", "progress", "mark", "unmark"])?),
+		"trace" => trace::run(&Args::parse(rest, &["line"])?),
+		"ownership" => ownership::run(&Args::parse(rest, &["line"])?),
+		"workspace" => workspace::run(&Args::parse(rest, &["out"])?),
+		"lsp" => lsp::run(&Args::parse(rest, &["jobs"])?),
+		#[cfg(target_os = "linux")]
+		"watch" => watch::run(&Args::parse(rest, &["site", "book", "jobs", "docs"])?),
+		"help" | "--help" | "-h" => {
+			out!("{USAGE}");
+			Ok(ExitCode::SUCCESS)
+		}
+		other => Err(Error::Usage(format!(
+			"unknown command `{other}`\n\n{USAGE}"
+		))),
+	}
+}
+
+/// Writes `args` to a locked stdout for [`out!`]. A closed pipe exits with
+/// success, since the reader has all it wanted; any other error exits as a
+/// failed command would.
+pub fn write_out(args: std::fmt::Arguments) {
+	if let Err(e) = io::stdout().lock().write_fmt(args) {
+		if e.kind() == io::ErrorKind::BrokenPipe {
+			process::exit(0);
+		}
+		eprintln!("notes: writing to stdout: {e}");
+		process::exit(2);
+	}
+}
+
+/// Parsed command line: `--name value` options, `--flag`s and positionals.
+pub struct Args {
+	pub positional: Vec<String>,
+	options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+	/// `valued` names the options that take a value; `--dir` always does.
+	pub fn parse(raw: &[String], valued: &[&str]) -> Result<Args> {
+		let mut positional = Vec::new();
+		let mut options = Vec::new();
+		let mut iter = raw.iter();
+		while let Some(arg) = iter.next() {
+			let Some(name) = arg.strip_prefix("--") else {
+				positional.push(arg.clone());
+				continue;
+			};
+			if let Some((name, value)) = name.split_once('=') {
+				options.push((name.to_string(), Some(value.to_string())));
+			} else if name == "dir" || valued.contains(&name) {
+				let value = iter
+					.next()
+					.ok_or_else(|| Error::Usage(format!("--{name} needs a value")))?;
+				options.push((name.to_string(), Some(value.clone())));
+			} else {
+				options.push((name.to_string(), None));
+			}
+		}
+		Ok(Args {
+			positional,
+			options,
+		})
+	}
+
+	pub fn flag(&self, name: &str) -> bool {
+		self.options.iter().any(|(n, _)| n == name)
+	}
+
+	pub fn value(&self, name: &str) -> Option<&str> {
+		self.options
+			.iter()
+			.rev()
+			.find(|(n, _)| n == name)
+			.and_then(|(_, value)| value.as_deref())
+	}
+
+	pub fn dir(&self) -> &Path {
+		Path::new(self.value("dir").unwrap_or("."))
+	}
+
+	/// The notes files named on the command line, or all of them.
+	pub fn files(&self) -> Result<Vec<NoteFile>> {
+		if self.positional.is_empty() {
+			return discover(self.dir());
+		}
+		let mut files = Vec::new();
+		for arg in &self.positional {
+			let path = PathBuf::from(arg);
+			files.push(NoteFile::from_path(&path).ok_or(Error::NotNotes(path))?);
+		}
+		Ok(files)
+	}
+
+	pub fn documents(&self) -> Result<Vec<Document>> {
+		self.files()?.into_iter().map(Document::load).collect()
+	}
+
+	/// A checker compiling `--jobs` snippets at once, if given.
+	pub fn checker(&self) -> Result<Checker> {
+		let Some(jobs) = self.value("jobs") else {
+			return Ok(Checker::new());
+		};
+		let jobs = jobs
+			.parse()
+			.map_err(|_| Error::Usage(format!("--jobs needs a number, not `{jobs}`")))?;
+		Ok(Checker::new().jobs(jobs))
+	}
+}
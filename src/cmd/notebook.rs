@@ -0,0 +1,22 @@
+//! `notes notebook`: write each notes file out as a Jupyter notebook for the
+//! evcxr Rust kernel.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::export::notebook;
+use rust_notes::Result;
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let out = Path::new(args.value("out").unwrap_or("notebooks"));
+	let written = notebook::write_notebooks(&docs, out)?;
+	outln!(
+		"wrote {} notebooks to {}; open them with the evcxr Jupyter kernel",
+		written.len(),
+		out.display()
+	);
+	Ok(ExitCode::SUCCESS)
+}
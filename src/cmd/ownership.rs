@@ -0,0 +1,198 @@
+//! `notes ownership`: check the snippets against the ownership rules of
+//! chapter 4, or with `--examples` the checker against its own examples.
+
+use std::process::ExitCode;
+
+use rust_notes::check::Expectation;
+use rust_notes::mini::ownership::{self, Rejection, Verdict, EXAMPLES};
+use rust_notes::mini::{syntax, Problem};
+use rust_notes::{Code, Document, Error, Lang, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	if args.flag("examples") {
+		return Ok(examples(args.flag("verbose")));
+	}
+	let docs = args.documents()?;
+	let Some(line) = args.value("line") else {
+		return Ok(list(&docs, args.flag("verbose")));
+	};
+
+	let line: usize = line
+		.parse()
+		.map_err(|_| Error::Usage(format!("--line needs a line number, not `{line}`")))?;
+	let [doc] = &docs[..] else {
+		return Err(Error::Usage(
+			"--line needs the one notes file the line is in".to_string(),
+		));
+	};
+	let Some(code) = doc.code_blocks().find(|code| code.lines.contains(line)) else {
+		return Err(Error::Usage(format!(
+			"line {line} of {} is not in a snippet",
+			doc.file.file_name()
+		)));
+	};
+
+	outln!("{}:{}\n", doc.file.file_name(), code.lines);
+	match outcome(code) {
+		Ok(verdict) => Ok(show(&verdict)),
+		Err(problem) => {
+			outln!("{:>5}  {}", problem.line, problem.message);
+			Ok(ExitCode::FAILURE)
+		}
+	}
+}
+
+/// Prints what happens to the values and why the program is rejected, if
+/// it is.
+fn show(verdict: &Verdict) -> ExitCode {
+	for note in &verdict.notes {
+		outln!("{:>5}  {}", note.line, note.text);
+	}
+	let Some(rejection) = &verdict.rejection else {
+		outln!("\naccepted");
+		return ExitCode::SUCCESS;
+	};
+	outln!(
+		"{:>5}  rejected: error[{}]: {}",
+		rejection.line,
+		rejection.code,
+		rejection.message
+	);
+	outln!("\n{}", rejection.why);
+	outln!(
+		"\nRule (chapter {}): \"{}\"",
+		rejection.rule.chapter(),
+		rejection.rule.text()
+	);
+	ExitCode::FAILURE
+}
+
+/// Lists the snippets the checker understands with its verdict on each,
+/// and whether the annotation of the snippet agrees.
+fn list(docs: &[Document], verbose: bool) -> ExitCode {
+	let (mut checked, mut beyond, mut disagree) = (0, 0, 0);
+	for doc in docs {
+		for code in doc.code_blocks().filter(|code| code.lang() == Lang::Rust) {
+			let at = format!("{}:{}", doc.file.file_name(), code.lines);
+			let verdict = match outcome(code) {
+				Ok(verdict) => verdict,
+				Err(problem) => {
+					beyond += 1;
+					if verbose {
+						outln!("{at}  not checked: {problem}");
+					}
+					continue;
+				}
+			};
+			checked += 1;
+			let rejection = verdict.rejection.as_ref();
+			let mut text = match rejection {
+				Some(rejection) => format!("rejected, {}", summary(rejection)),
+				None => "accepted".to_string(),
+			};
+			let expectation = Expectation::of(code);
+			match agrees(&expectation, rejection) {
+				Some(true) => text.push_str(", as annotated"),
+				Some(false) => {
+					disagree += 1;
+					text.push_str(&format!(", but {}", annotated(&expectation)));
+				}
+				None => {}
+			}
+			outln!("{at}  {text}");
+		}
+	}
+	outln!(
+		"\n{checked} snippets checked, {beyond} beyond the checker, {disagree} disagreeing with \
+		 their annotations; `notes ownership FILE --line N` explains the one at line N"
+	);
+	if disagree > 0 {
+		ExitCode::FAILURE
+	} else {
+		ExitCode::SUCCESS
+	}
+}
+
+/// Checks the built-in examples, each of which should be accepted or
+/// rejected as `rustc` does.
+fn examples(verbose: bool) -> ExitCode {
+	let mut wrong = 0;
+	for example in &EXAMPLES {
+		let verdict =
+			match syntax::parse(example.source, 1).and_then(|program| ownership::check(&program)) {
+				Ok(verdict) => verdict,
+				Err(problem) => {
+					wrong += 1;
+					outln!("{}: not checked: {problem}", example.name);
+					continue;
+				}
+			};
+		let found = verdict.rejection.as_ref().map(|rejection| rejection.code);
+		let text = match &verdict.rejection {
+			Some(rejection) => format!("rejected, {}", summary(rejection)),
+			None => "accepted".to_string(),
+		};
+		if found == example.rejected_with {
+			outln!("{}: {text}", example.name);
+		} else {
+			wrong += 1;
+			let rustc = match example.rejected_with {
+				Some(code) => format!("rustc gives {code}"),
+				None => "rustc compiles it".to_string(),
+			};
+			outln!("{}: {text}, but {rustc}", example.name);
+		}
+		if verbose {
+			outln!();
+			for (number, line) in example.source.lines().enumerate() {
+				outln!("{:>5}  {}", number + 1, line.replace('\t', "    "));
+			}
+			outln!();
+			show(&verdict);
+			outln!();
+		}
+	}
+	if wrong > 0 {
+		outln!("\n{wrong} of {} examples checked wrongly", EXAMPLES.len());
+		ExitCode::FAILURE
+	} else {
+		outln!("\nall {} examples checked as rustc does", EXAMPLES.len());
+		ExitCode::SUCCESS
+	}
+}
+
+fn summary(rejection: &Rejection) -> String {
+	format!(
+		"line {}: {} {}",
+		rejection.line, rejection.code, rejection.message
+	)
+}
+
+/// Whether a verdict agrees with what the notes say, or `None` if they
+/// leave the snippet unchecked.
+fn agrees(expectation: &Expectation, rejection: Option<&Rejection>) -> Option<bool> {
+	match (expectation, rejection) {
+		(Expectation::Skip(_), _) => None,
+		(Expectation::Compiles, rejection) => Some(rejection.is_none()),
+		(Expectation::Fails(_), None) => Some(false),
+		(Expectation::Fails(codes), Some(rejection)) => {
+			Some(codes.is_empty() || codes.iter().any(|code| code == rejection.code))
+		}
+	}
+}
+
+fn annotated(expectation: &Expectation) -> String {
+	match expectation {
+		Expectation::Fails(codes) if codes.is_empty() => "the notes expect an error".to_string(),
+		Expectation::Fails(codes) => format!("the notes expect {}", codes.join(", ")),
+		_ => "the notes expect it to compile".to_string(),
+	}
+}
+
+/// The checker's verdict on a snippet, or why it cannot give one.
+fn outcome(code: &Code) -> std::result::Result<Verdict, Problem> {
+	let program = syntax::parse(&code.text, code.lines.start)?;
+	ownership::check(&program)
+}
@@ -0,0 +1,51 @@
+//! `notes parse`: show how a file splits into headings, prose and code.
+
+use std::process::ExitCode;
+
+use rust_notes::{Block, Lang, ProseStyle, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	for doc in args.documents()? {
+		outln!("{}  ({})", doc.file.file_name(), doc.file.title());
+		for block in &doc.blocks {
+			let (kind, text) = match block {
+				Block::Heading(heading) => {
+					(format!("heading {}", heading.level), heading.title.clone())
+				}
+				Block::Prose(prose) => {
+					let kind = match prose.style {
+						ProseStyle::Block => "prose",
+						ProseStyle::Line => "prose //",
+					};
+					(kind.to_string(), first_line(&prose.text))
+				}
+				Block::Code(code) => {
+					let text = match code.lang() {
+						Lang::Rust => first_line(&code.text),
+						lang => format!("[{}] {}", lang.name(), first_line(&code.text)),
+					};
+					(format!("code #{}", code.index), text)
+				}
+			};
+			outln!("  {:>9}  {:<10} {}", block.lines().to_string(), kind, text);
+		}
+		outln!();
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+fn first_line(text: &str) -> String {
+	let line = text
+		.lines()
+		.find(|l| !l.trim().is_empty())
+		.unwrap_or("")
+		.trim();
+	if line.chars().count() > 60 {
+		let cut: String = line.chars().take(57).collect();
+		format!("{cut}...")
+	} else {
+		line.to_string()
+	}
+}
@@ -0,0 +1,81 @@
+//! `notes rustdoc`: resolve the std items the notes mention against the
+//! local docs, and report the references that name nothing.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rust_notes::rustdoc::{self, Index, Resolution};
+use rust_notes::{Error, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let index = load(args)?.ok_or_else(|| {
+		Error::Usage(
+			"no std docs found; install them with `rustup component add rust-docs` or pass --docs DIR"
+				.to_string(),
+		)
+	})?;
+	let found = rustdoc::references(&index, &docs);
+	let mut counts = [0; 4];
+	for reference in &found {
+		let place = format!(
+			"{}:{}",
+			docs[reference.doc].file.file_name(),
+			reference.line
+		);
+		let what = if reference.in_code { "code" } else { "prose" };
+		match &reference.resolution {
+			Resolution::Linked { path, .. } => {
+				counts[0] += 1;
+				if args.flag("verbose") {
+					outln!("  {place:<44} {what:<5}  {} -> {path}", reference.path);
+				}
+			}
+			Resolution::Unresolved(reason) => {
+				counts[1] += 1;
+				outln!("  {place:<44} {what:<5}  {}: {reason}", reference.path);
+			}
+			Resolution::Ambiguous(candidates) => {
+				counts[2] += 1;
+				if args.flag("verbose") {
+					let mut shown = candidates[..candidates.len().min(4)].join(", ");
+					if candidates.len() > 4 {
+						shown += &format!(" and {} more", candidates.len() - 4);
+					}
+					outln!(
+						"  {place:<44} {what:<5}  {}: one of {shown}",
+						reference.path
+					);
+				}
+			}
+			Resolution::Elsewhere => counts[3] += 1,
+		}
+	}
+	outln!(
+		"\n{} references resolved against {}, {} do not resolve, {} ambiguous, {} to the notes' own code or other crates",
+		counts[0],
+		index.root().display(),
+		counts[1],
+		counts[2],
+		counts[3]
+	);
+	if counts[1] > 0 {
+		return Ok(ExitCode::FAILURE);
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// The std docs given with `--docs`, or else the active toolchain's, or
+/// `None` if neither is there.
+pub fn load(args: &Args) -> Result<Option<Index>> {
+	let dir = match args.value("docs") {
+		Some(dir) => PathBuf::from(dir),
+		None => match Index::locate() {
+			Some(dir) => dir,
+			None => return Ok(None),
+		},
+	};
+	Index::load(&dir).map(Some)
+}
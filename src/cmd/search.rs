@@ -0,0 +1,72 @@
+//! `notes search`: find where something is written about.
+
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use rust_notes::search::{Index, SnippetLine};
+use rust_notes::{discover, Document, Error, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let query = args.positional.join(" ");
+	if query.trim().is_empty() {
+		return Err(Error::Usage("search needs a query".to_string()));
+	}
+	let limit = match args.value("limit") {
+		Some(n) => n
+			.parse()
+			.map_err(|_| Error::Usage(format!("--limit needs a number, not `{n}`")))?,
+		None => 10,
+	};
+	let docs = discover(args.dir())?
+		.into_iter()
+		.map(Document::load)
+		.collect::<Result<Vec<_>>>()?;
+	let index = Index::new(&docs);
+	let hits = index.search(&query);
+	if hits.is_empty() {
+		outln!("nothing found for `{query}`");
+		return Ok(ExitCode::FAILURE);
+	}
+
+	let marks = if io::stdout().is_terminal() {
+		("\x1b[1;33m", "\x1b[0m")
+	} else {
+		("**", "**")
+	};
+	for (rank, hit) in hits.iter().take(limit).enumerate() {
+		let section = match hit.heading {
+			Some(heading) => format!("{} / {}", hit.doc.file.title(), heading.title),
+			None => hit.doc.file.title(),
+		};
+		outln!(
+			"{:>2}. {section}  ({}:{})",
+			rank + 1,
+			hit.doc.file.file_name(),
+			hit.line
+		);
+		for line in &hit.snippet {
+			outln!("      {:>4} | {}", line.line, highlight(line, marks));
+		}
+	}
+	if hits.len() > limit {
+		outln!("\n{} more; use --limit to see them", hits.len() - limit);
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// The line with its matches wrapped in `marks`, and without its indentation.
+fn highlight(line: &SnippetLine, (open, close): (&str, &str)) -> String {
+	let mut out = String::new();
+	let mut at = 0;
+	for range in &line.matches {
+		out.push_str(&line.text[at..range.start]);
+		out.push_str(open);
+		out.push_str(&line.text[range.clone()]);
+		out.push_str(close);
+		at = range.end;
+	}
+	out.push_str(&line.text[at..]);
+	out.trim().to_string()
+}
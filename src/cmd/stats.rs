@@ -0,0 +1,160 @@
+//! `notes stats`: what each notes file is made of and how far the reading
+//! has got, as a table or as JSON; or mark sections as reviewed.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rust_notes::check::Outcome;
+use rust_notes::date;
+use rust_notes::exercises;
+use rust_notes::json::Value;
+use rust_notes::stats::{self, Mark, Stats};
+use rust_notes::{Document, Error, Result};
+
+use super::Args;
+
+/// The progress file, in the notes directory unless `--progress` says
+/// otherwise.
+const PROGRESS: &str = ".progress";
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let progress = match args.value("progress") {
+		Some(path) => PathBuf::from(path),
+		None => args.dir().join(PROGRESS),
+	};
+	let docs = args.documents()?;
+	let mut marks = stats::read_progress(&progress)?;
+
+	if let Some(name) = args.value("mark") {
+		let sections = named_sections(&docs, name)?;
+		let today = date::today();
+		let mut added = 0;
+		for section in sections {
+			if !marks.iter().any(|mark| mark.section == section) {
+				marks.push(Mark {
+					day: today,
+					section,
+				});
+				added += 1;
+			}
+		}
+		stats::write_progress(&progress, &marks)?;
+		outln!("marked {} as reviewed", count(added, "section"));
+		return Ok(ExitCode::SUCCESS);
+	}
+	if let Some(name) = args.value("unmark") {
+		let sections = named_sections(&docs, name)?;
+		let before = marks.len();
+		marks.retain(|mark| !sections.contains(&mark.section));
+		stats::write_progress(&progress, &marks)?;
+		outln!("unmarked {}", count(before - marks.len(), "section"));
+		return Ok(ExitCode::SUCCESS);
+	}
+
+	let checker = args.checker()?;
+	let outcomes: Vec<Outcome> = checker
+		.check_all(&docs)?
+		.into_iter()
+		.map(|checked| checked.outcome)
+		.collect();
+	let linked = exercises::link(&docs);
+	let statuses = exercises::statuses(&checker, &linked)?;
+	let stats = stats::stats(&docs, &outcomes, &linked, &statuses, &marks);
+	let total = stats::total(&stats);
+
+	if args.flag("json") {
+		let json = Value::object([
+			("date", date::format(date::today()).into()),
+			(
+				"files",
+				stats.iter().map(Stats::to_json).collect::<Vec<_>>().into(),
+			),
+			("total", total.to_json()),
+		]);
+		outln!("{}", json.pretty());
+		return Ok(ExitCode::SUCCESS);
+	}
+
+	let width = stats
+		.iter()
+		.map(|stats| stats.title.len())
+		.max()
+		.unwrap_or(0)
+		.max(total.title.len());
+	outln!(
+		"{:width$}  sections  reviewed  prose   code  ratio  compiled  failing  skipped  solved",
+		""
+	);
+	for stats in &stats {
+		print_row(stats, width);
+	}
+	outln!();
+	print_row(&total, width);
+
+	// Marks for sections whose heading was renamed or removed count for
+	// nothing; say so, so that they can be made again.
+	if args.positional.is_empty() {
+		let known: Vec<String> = docs.iter().flat_map(stats::sections).collect();
+		let stale: Vec<&Mark> = marks
+			.iter()
+			.filter(|mark| !known.contains(&mark.section))
+			.collect();
+		if !stale.is_empty() {
+			outln!(
+				"\n{} marks in {} name sections that are gone:",
+				stale.len(),
+				progress.display()
+			);
+			for mark in stale {
+				outln!("  {}", mark.section);
+			}
+		}
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+fn print_row(stats: &Stats, width: usize) {
+	let ratio = stats
+		.ratio()
+		.map_or("-".to_string(), |ratio| format!("{ratio:.1}"));
+	let solved = if stats.exercises == 0 {
+		"-".to_string()
+	} else {
+		format!("{}/{}", stats.solved, stats.exercises)
+	};
+	outln!(
+		"{:width$}  {:>8}  {:>8}  {:>5}  {:>5}  {:>5}  {:>8}  {:>7}  {:>7}  {:>6}",
+		stats.title,
+		stats.sections,
+		stats.reviewed,
+		stats.prose_lines,
+		stats.code_lines,
+		ratio,
+		stats.compiled,
+		stats.failing,
+		stats.skipped,
+		solved
+	);
+}
+
+fn count(n: usize, what: &str) -> String {
+	let s = if n == 1 { "" } else { "s" };
+	format!("{n} {what}{s}")
+}
+
+/// The sections `--mark` or `--unmark` name, or a usage error.
+fn named_sections(docs: &[Document], name: &str) -> Result<Vec<String>> {
+	let sections = stats::find_sections(docs, name);
+	if sections.is_empty() {
+		let example = docs
+			.iter()
+			.flat_map(stats::sections)
+			.next()
+			.unwrap_or_else(|| "rust_chapter_3_common_concepts#variables".to_string());
+		return Err(Error::Usage(format!(
+			"no section `{name}`; name one as FILE#ANCHOR, such as `{example}`, or a whole \
+			 file as FILE"
+		)));
+	}
+	Ok(sections)
+}
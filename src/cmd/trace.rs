@@ -0,0 +1,104 @@
+//! `notes trace`: run the snippets written in the mini Rust of the early
+//! chapters and print what happens, step by step.
+
+use std::process::ExitCode;
+
+use rust_notes::mini::{syntax, trace, Problem};
+use rust_notes::{Code, Document, Error, Lang, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let Some(line) = args.value("line") else {
+		list(&docs, args.flag("verbose"));
+		return Ok(ExitCode::SUCCESS);
+	};
+
+	let line: usize = line
+		.parse()
+		.map_err(|_| Error::Usage(format!("--line needs a line number, not `{line}`")))?;
+	let [doc] = &docs[..] else {
+		return Err(Error::Usage(
+			"--line needs the one notes file the line is in".to_string(),
+		));
+	};
+	let Some(code) = doc.code_blocks().find(|code| code.lines.contains(line)) else {
+		return Err(Error::Usage(format!(
+			"line {line} of {} is not in a snippet",
+			doc.file.file_name()
+		)));
+	};
+
+	outln!("{}:{}\n", doc.file.file_name(), code.lines);
+	let parsed = syntax::parse(&code.text, code.lines.start)
+		.and_then(|program| trace::in_subset(&program).map(|()| program));
+	let program = match parsed {
+		Ok(program) => program,
+		Err(problem) => {
+			outln!("{:>5}  {}", problem.line, problem.message);
+			return Ok(ExitCode::FAILURE);
+		}
+	};
+	let trace = trace::run(&program);
+	for step in &trace.steps {
+		outln!("{:>5}  {}{}", step.line, "  ".repeat(step.depth), step.text);
+	}
+	match trace.problem {
+		Some(problem) => {
+			outln!("{:>5}  stops: {}", problem.line, problem.message);
+			Ok(ExitCode::FAILURE)
+		}
+		None => Ok(ExitCode::SUCCESS),
+	}
+}
+
+/// Lists the snippets the interpreter runs, and with `verbose` the ones it
+/// does not and why.
+fn list(docs: &[Document], verbose: bool) {
+	let (mut runs, mut beyond) = (0, 0);
+	for doc in docs {
+		for code in doc.code_blocks().filter(|code| code.lang() == Lang::Rust) {
+			let at = format!("{}:{}", doc.file.file_name(), code.lines);
+			match outcome(code) {
+				Ok((steps, None)) => {
+					runs += 1;
+					outln!("{at}  {}", count(steps, "step"));
+				}
+				Ok((steps, Some(problem))) => {
+					runs += 1;
+					outln!(
+						"{at}  {}, then stops at line {}: {}",
+						count(steps, "step"),
+						problem.line,
+						problem.message
+					);
+				}
+				Err(problem) => {
+					beyond += 1;
+					if verbose {
+						outln!("{at}  not run: {problem}");
+					}
+				}
+			}
+		}
+	}
+	outln!(
+		"\n{runs} snippets run, {beyond} beyond the subset; `notes trace FILE --line N` traces the \
+		 one at line N"
+	);
+}
+
+fn count(n: usize, what: &str) -> String {
+	let s = if n == 1 { "" } else { "s" };
+	format!("{n} {what}{s}")
+}
+
+/// How many steps a snippet takes and why it stops early, if it does; or
+/// why it cannot be run.
+fn outcome(code: &Code) -> std::result::Result<(usize, Option<Problem>), Problem> {
+	let program = syntax::parse(&code.text, code.lines.start)?;
+	trace::in_subset(&program)?;
+	let trace = trace::run(&program);
+	Ok((trace.steps.len(), trace.problem))
+}
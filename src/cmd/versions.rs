@@ -0,0 +1,205 @@
+//! `notes versions`: show how an item changes across the snippets that
+//! define it, one token-level diff per step.
+
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use rust_notes::versions::{self, Change, History};
+use rust_notes::Result;
+
+use super::Args;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+	Kept,
+	Added,
+	Removed,
+}
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let only = args.value("item");
+	let color = io::stdout().is_terminal();
+	for doc in args.documents()? {
+		let histories: Vec<History> = versions::histories(&doc)
+			.into_iter()
+			.filter(|history| only.is_none_or(|name| history.name == name))
+			.collect();
+		if histories.is_empty() {
+			continue;
+		}
+		outln!("{}", doc.file.file_name());
+		for history in &histories {
+			let lines: Vec<String> = history
+				.versions
+				.iter()
+				.map(|version| version.line.to_string())
+				.collect();
+			outln!(
+				"\n  {}: {} versions, at lines {}",
+				history.name,
+				history.versions.len(),
+				lines.join(", ")
+			);
+			for pair in history.versions.windows(2) {
+				let (old, new) = (&pair[0], &pair[1]);
+				let changes = versions::diff(&old.text, &new.text);
+				let added = count(&changes, Kind::Added);
+				let removed = count(&changes, Kind::Removed);
+				let label = if old.label == new.label {
+					String::new()
+				} else {
+					format!(", now {}", new.label)
+				};
+				outln!(
+					"\n    line {} -> line {}: +{added} -{removed} tokens{label}",
+					old.line,
+					new.line
+				);
+				print_diff(&changes, &old.text, color);
+			}
+		}
+		outln!();
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+fn count(changes: &[Change], kind: Kind) -> usize {
+	changes
+		.iter()
+		.filter(|change| kind_of(change) == kind)
+		.count()
+}
+
+fn kind_of(change: &Change) -> Kind {
+	match change {
+		Change::Kept(_) => Kind::Kept,
+		Change::Added(_) => Kind::Added,
+		Change::Removed(_) => Kind::Removed,
+	}
+}
+
+/// Prints the new version with its changes marked, `{+added+}` and
+/// `[-removed-]` (or in colour on a terminal), keeping only the lines with
+/// changes and a little context.
+fn print_diff(changes: &[Change], old: &str, color: bool) {
+	let lines = lines(&segments(changes, old));
+	let changed: Vec<bool> = lines
+		.iter()
+		.map(|line| {
+			line.iter()
+				.any(|(kind, text)| *kind != Kind::Kept && !text.trim().is_empty())
+		})
+		.collect();
+	let shown: Vec<bool> = (0..lines.len())
+		.map(|i| {
+			let from = i.saturating_sub(CONTEXT);
+			let to = (i + CONTEXT).min(lines.len() - 1);
+			changed[from..=to].iter().any(|&changed| changed)
+		})
+		.collect();
+	let mut skipped = false;
+	for (line, show) in lines.iter().zip(&shown) {
+		if !show {
+			skipped = true;
+			continue;
+		}
+		if skipped {
+			outln!("        ...");
+			skipped = false;
+		}
+		let mut out = String::new();
+		for (kind, text) in line {
+			let (open, close) = match (kind, color) {
+				(Kind::Kept, _) => ("", ""),
+				(Kind::Added, true) => ("\x1b[32m", "\x1b[0m"),
+				(Kind::Removed, true) => ("\x1b[31;9m", "\x1b[0m"),
+				(Kind::Added, false) => ("{+", "+}"),
+				(Kind::Removed, false) => ("[-", "-]"),
+			};
+			out.push_str(open);
+			out.push_str(text);
+			out.push_str(close);
+		}
+		outln!("        {out}");
+	}
+	if skipped {
+		outln!("        ...");
+	}
+}
+
+/// Runs of one kind. Whitespace and comments between two tokens of an
+/// added run belong to the run; a removed run is shown as it was written in
+/// `old`, on one line.
+fn segments(changes: &[Change], old: &str) -> Vec<(Kind, String)> {
+	let mut segments: Vec<(Kind, String)> = Vec::new();
+	// Where the last removed token ended in `old`.
+	let mut removed_end = None;
+	for (i, change) in changes.iter().enumerate() {
+		let (mut kind, text) = match change {
+			Change::Kept(token) => (Kind::Kept, token.text),
+			Change::Added(token) => (Kind::Added, token.text),
+			Change::Removed(token) => (Kind::Removed, token.text),
+		};
+		let is_trivia = matches!(change, Change::Kept(token) if token.is_trivia());
+		if is_trivia {
+			let before = segments.last().map(|(kind, _)| *kind);
+			let after = changes[i + 1..]
+				.iter()
+				.find(|change| !matches!(change, Change::Kept(token) if token.is_trivia()))
+				.map(kind_of);
+			if before == Some(Kind::Added) && after == Some(Kind::Added) {
+				kind = Kind::Added;
+			}
+		}
+		match segments.last_mut() {
+			Some((last, run)) if *last == kind => {
+				if let (Change::Removed(token), Some(end)) = (change, removed_end) {
+					let between = &old[end..token.start];
+					if !between.is_empty() {
+						run.push_str(&between.split_whitespace().collect::<Vec<_>>().join(" "));
+						if between.ends_with(char::is_whitespace) {
+							run.push(' ');
+						}
+					}
+				}
+				run.push_str(text);
+			}
+			_ => segments.push((kind, text.to_string())),
+		}
+		removed_end = match change {
+			Change::Removed(token) => Some(token.start + token.text.len()),
+			_ => None,
+		};
+	}
+	segments
+}
+
+/// Splits segments at line breaks, so each line can be printed on its own.
+/// The indentation of a line is never marked as a change.
+fn lines(segments: &[(Kind, String)]) -> Vec<Vec<(Kind, String)>> {
+	let mut lines = vec![Vec::new()];
+	for (kind, text) in segments {
+		for (i, part) in text.split('\n').enumerate() {
+			if i > 0 {
+				lines.push(Vec::new());
+			}
+			let line = lines.last_mut().unwrap();
+			let mut part = part;
+			if line.is_empty() && *kind != Kind::Kept {
+				let content = part.trim_start();
+				let indent = &part[..part.len() - content.len()];
+				if !indent.is_empty() {
+					line.push((Kind::Kept, indent.to_string()));
+				}
+				part = content;
+			}
+			if !part.is_empty() {
+				line.push((*kind, part.to_string()));
+			}
+		}
+	}
+	lines
+}
@@ -0,0 +1,142 @@
+//! `notes watch`: check the notes and write the exports, then do it again
+//! for each notes file as it is saved, only compiling the snippets that
+//! changed and only writing the pages that did.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::check::{Checked, Outcome};
+use rust_notes::export::{html, markdown};
+use rust_notes::run::expected_stdout;
+use rust_notes::rustdoc::Index;
+use rust_notes::watch::Watcher;
+use rust_notes::{discover, Document, Error, NoteFile, Result};
+
+use super::check::{print_entry, print_run};
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	if !args.positional.is_empty() {
+		return Err(Error::Usage(
+			"watch follows every notes file in --dir; it takes no files".to_string(),
+		));
+	}
+	let dir = args.dir();
+	let site = Path::new(args.value("site").unwrap_or("site"));
+	let book = Path::new(args.value("book").unwrap_or("book"));
+	let checker = args.checker()?;
+	let index = super::rustdoc::load(args)?;
+	// Watch first, so that nothing saved while the first check runs is missed.
+	let mut watcher = Watcher::new(dir)?;
+
+	let mut docs: Vec<Document> = discover(dir)?
+		.into_iter()
+		.map(Document::load)
+		.collect::<Result<_>>()?;
+	let all = checker.check_all(&docs)?;
+	let mut checked: Vec<Vec<Checked>> = Vec::new();
+	let mut rest = all.into_iter();
+	for doc in &docs {
+		checked.push(rest.by_ref().take(doc.code_blocks().count()).collect());
+	}
+	for (doc, results) in docs.iter().zip(&checked) {
+		report(doc, results);
+	}
+	write_all(&docs, site, book, index.as_ref())?;
+	outln!("watching {} for changes", dir.display());
+
+	loop {
+		for path in watcher.changes()? {
+			let Some(file) = NoteFile::from_path(&path) else {
+				continue;
+			};
+			let doc = match Document::load(file) {
+				Ok(doc) => doc,
+				Err(e) => {
+					outln!("{e}");
+					continue;
+				}
+			};
+			let existing = docs
+				.iter()
+				.position(|d| d.file.file_name() == doc.file.file_name());
+			// The results of the snippets as they were before the save.
+			let known: HashMap<String, Checked> = match existing {
+				Some(i) => docs[i]
+					.code_blocks()
+					.zip(&checked[i])
+					.map(|(code, result)| (code.text.clone(), result.clone()))
+					.collect(),
+				None => HashMap::new(),
+			};
+			let i = match existing {
+				Some(i) => {
+					docs[i] = doc;
+					i
+				}
+				None => {
+					let i = docs.partition_point(|d| d.file.order() < doc.file.order());
+					docs.insert(i, doc);
+					checked.insert(i, Vec::new());
+					i
+				}
+			};
+			let doc = &docs[i];
+			checked[i] = checker.recheck(doc, &known)?;
+
+			let changed: Vec<_> = doc
+				.code_blocks()
+				.zip(&checked[i])
+				.filter(|(code, _)| !known.contains_key(&code.text))
+				.collect();
+			outln!(
+				"\n{} saved: {} of {} snippets changed",
+				doc.file.file_name(),
+				changed.len(),
+				checked[i].len()
+			);
+			for (code, result) in changed {
+				print_entry(doc, code, &result.outcome, result.outcome.is_problem());
+				let expected = expected_stdout(code);
+				if !expected.is_empty() && result.outcome == Outcome::Compiled {
+					let run = checker.run(code, &result.context, &expected)?;
+					print_run(doc, code, &run);
+				}
+			}
+			report(doc, &checked[i]);
+
+			if existing.is_some() {
+				let mut written = html::write_page(&docs, i, site, index.as_ref())?;
+				written.extend(markdown::write_chapter(&docs, i, book, index.as_ref())?);
+				let names: Vec<String> = written.iter().map(|p| p.display().to_string()).collect();
+				outln!("  wrote {}", names.join(", "));
+			} else {
+				write_all(&docs, site, book, index.as_ref())?;
+			}
+		}
+	}
+}
+
+/// Prints how many of the snippets of `doc` are problems.
+fn report(doc: &Document, checked: &[Checked]) {
+	let problems = checked.iter().filter(|c| c.outcome.is_problem()).count();
+	outln!(
+		"  {}: {} snippets, {problems} problems",
+		doc.file.file_name(),
+		checked.len()
+	);
+}
+
+/// Writes the whole site and book, for a start or when the list of notes
+/// files changes.
+fn write_all(docs: &[Document], site: &Path, book: &Path, std: Option<&Index>) -> Result<()> {
+	html::write_site(docs, site, std)?;
+	markdown::write_book(docs, book, std)?;
+	outln!(
+		"  wrote the site to {} and the book to {}",
+		site.display(),
+		book.display()
+	);
+	Ok(())
+}
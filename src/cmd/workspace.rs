@@ -0,0 +1,40 @@
+//! `notes workspace`: write the projects the notes were written in out as
+//! one Cargo workspace.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_notes::projects;
+use rust_notes::Result;
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let docs = args.documents()?;
+	let out = Path::new(args.value("out").unwrap_or("projects"));
+	let members = projects::members(&docs);
+	let mut problems = 0;
+	for member in &members {
+		let source = docs[member.doc].file.file_name();
+		outln!("{}", member.name);
+		for file in &member.files {
+			outln!("  {:<30} {source}:{}", file.path, file.lines.0);
+		}
+		for problem in &member.problems {
+			outln!("  problem: {problem}");
+		}
+		problems += member.problems.len();
+	}
+	projects::write_workspace(&members, &docs, out)?;
+	outln!(
+		"\nwrote {} projects to {}; build them all with `cargo build --manifest-path {}`",
+		members.len(),
+		out.display(),
+		out.join("Cargo.toml").display()
+	);
+	if problems > 0 {
+		outln!("{problems} problems");
+		return Ok(ExitCode::FAILURE);
+	}
+	Ok(ExitCode::SUCCESS)
+}
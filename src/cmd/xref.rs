@@ -0,0 +1,81 @@
+//! `notes xref`: check the places where the notes refer to their own lines,
+//! and optionally replace them by section anchors.
+
+use std::fs;
+use std::process::ExitCode;
+
+use rust_notes::xref::{self, Resolved};
+use rust_notes::{Error, Result};
+
+use super::Args;
+
+pub fn run(args: &Args) -> Result<ExitCode> {
+	let rewrite = args.flag("rewrite");
+	let mut problems = 0;
+	let mut rewritten = 0;
+	let mut kept = 0;
+	for doc in args.documents()? {
+		let source =
+			fs::read_to_string(&doc.file.path).map_err(|e| Error::io(&doc.file.path, e))?;
+		let refs = xref::line_refs(&doc, &source);
+		let broken = xref::broken_anchors(&doc);
+		if refs.is_empty() && broken.is_empty() {
+			continue;
+		}
+		outln!("{}", doc.file.file_name());
+		for reference in &refs {
+			let what = match xref::resolve(&doc, &source, reference) {
+				Resolved::Section {
+					title, code, text, ..
+				} => {
+					let kind = if code { "code" } else { "prose" };
+					format!("{kind} in \"{title}\": {text}")
+				}
+				Resolved::OtherFile(file) => format!("a line of {file}, not checked"),
+				Resolved::Stale(why) => {
+					problems += 1;
+					format!("STALE: {why}")
+				}
+			};
+			outln!(
+				"  {:>5}  {} -> {what}",
+				reference.line,
+				reference
+					.text
+					.split_whitespace()
+					.collect::<Vec<_>>()
+					.join(" ")
+			);
+		}
+		for (line, anchor) in &broken {
+			problems += 1;
+			outln!("  {line:>5}  <#{anchor}> -> BROKEN: no heading has this anchor");
+		}
+
+		if rewrite {
+			let done = xref::rewrite(&doc, &source);
+			for (reference, why) in &done.kept {
+				outln!("  {:>5}  kept: {why}", reference.line);
+			}
+			if !done.done.is_empty() {
+				fs::write(&doc.file.path, &done.source)
+					.map_err(|e| Error::io(&doc.file.path, e))?;
+				for (reference, replacement) in &done.done {
+					outln!("  {:>5}  rewritten as: {replacement}", reference.line);
+				}
+				rewritten += done.done.len();
+			}
+			kept += done.kept.len();
+		}
+		outln!();
+	}
+
+	if rewrite {
+		outln!("{rewritten} references rewritten as section anchors, {kept} kept as line numbers");
+	}
+	if problems > 0 {
+		outln!("{problems} references need fixing by hand");
+		return Ok(ExitCode::FAILURE);
+	}
+	Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,67 @@
+//! Calendar days, for anything that has to remember when it happened.
+//!
+//! A day is counted from 1970-01-01 in UTC and written as `YYYY-MM-DD`. The
+//! conversions are Howard Hinnant's `days_from_civil` and `civil_from_days`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Days since 1970-01-01.
+pub type Day = i64;
+
+/// The current day in UTC.
+pub fn today() -> Day {
+	let seconds = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |elapsed| elapsed.as_secs());
+	(seconds / 86_400) as Day
+}
+
+/// Writes a day as `YYYY-MM-DD`.
+pub fn format(day: Day) -> String {
+	let z = day + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let d = doy - (153 * mp + 2) / 5 + 1;
+	let m = if mp < 10 { mp + 3 } else { mp - 9 };
+	let y = yoe + era * 400 + i64::from(m <= 2);
+	format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Reads a `YYYY-MM-DD` day.
+pub fn parse(text: &str) -> Option<Day> {
+	let mut parts = text.splitn(3, '-');
+	let y: i64 = parts.next()?.parse().ok()?;
+	let m: i64 = parts.next()?.parse().ok()?;
+	let d: i64 = parts.next()?.parse().ok()?;
+	if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+		return None;
+	}
+	let y = if m <= 2 { y - 1 } else { y };
+	let era = y.div_euclid(400);
+	let yoe = y.rem_euclid(400);
+	let mp = if m > 2 { m - 3 } else { m + 9 };
+	let doy = (153 * mp + 2) / 5 + d - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	Some(era * 146_097 + doe - 719_468)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn days_are_written_as_dates() {
+		assert_eq!(format(0), "1970-01-01");
+		assert_eq!(format(11_016), "2000-02-29");
+		assert_eq!(parse("2000-02-29"), Some(11_016));
+		assert_eq!(parse("1969-12-31"), Some(-1));
+		for day in [-1, 0, 59, 20_000, 2_000_000] {
+			assert_eq!(parse(&format(day)), Some(day));
+		}
+		assert_eq!(parse("2024-13-01"), None);
+		assert_eq!(parse("yesterday"), None);
+	}
+}
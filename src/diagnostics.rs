@@ -0,0 +1,349 @@
+//! A catalog of the compiler errors the notes deal with, keyed by error code.
+//!
+//! The notes meet an error in three ways: they quote what rustc printed,
+//!
+//! ```text
+//! error[E0384]: cannot assign twice to immutable variable `x`
+//!  --> src/main.rs:4:5
+//! ...
+//! ```
+//!
+//! they annotate a snippet with `// expect-error: E0384`, which `notes check`
+//! holds to failing with that error, and they mention the code in passing,
+//! as in "try `rustc --explain E0384`". Every section where one of these
+//! happens explains the error, and the annotated snippets reproduce it.
+//!
+//! The toolchain's own explanation of each code comes from the error index
+//! installed with the std docs, `html/error_codes/E0384.html`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::check::Expectation;
+use crate::model::{Block, Code, Document, Heading, Lines};
+use crate::parse::is_blank;
+use crate::prose::{self, Paragraph};
+use crate::text;
+
+/// A diagnostic quoted in the prose: its first line, up to the next blank
+/// line.
+#[derive(Debug, Clone)]
+pub struct Quote<'a> {
+	pub doc: &'a Document,
+	/// From the `error[...]` header to the line before the next blank one.
+	pub lines: Lines,
+	/// What follows `error[E0384]: `.
+	pub message: String,
+	/// The whole quote, dedented.
+	pub text: String,
+}
+
+/// A section that explains an error.
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'a> {
+	pub doc: &'a Document,
+	/// `None` for the text before a file's first heading.
+	pub heading: Option<&'a Heading>,
+}
+
+impl Section<'_> {
+	/// `Chapter 3: Common Concepts / Shadowing`.
+	pub fn title(&self) -> String {
+		match self.heading {
+			Some(heading) => format!("{} / {}", self.doc.file.title(), heading.title),
+			None => self.doc.file.title(),
+		}
+	}
+
+	/// The line the section starts on.
+	pub fn line(&self) -> usize {
+		self.heading.map_or(1, |heading| heading.lines.start)
+	}
+}
+
+/// Everything the notes have on one error code.
+#[derive(Debug, Clone)]
+pub struct Entry<'a> {
+	/// `E0384`.
+	pub code: String,
+	pub quotes: Vec<Quote<'a>>,
+	/// The snippets annotated to fail with the code.
+	pub snippets: Vec<(&'a Document, &'a Code)>,
+	/// The sections that quote, reproduce or mention the error, in reading
+	/// order.
+	pub sections: Vec<Section<'a>>,
+}
+
+impl Entry<'_> {
+	/// What the error says, as the first quote has it.
+	pub fn message(&self) -> Option<&str> {
+		self.quotes.first().map(|quote| quote.message.as_str())
+	}
+}
+
+/// The catalog of `docs`, ordered by code.
+pub fn catalog(docs: &[Document]) -> Vec<Entry<'_>> {
+	let mut entries: BTreeMap<String, Entry> = BTreeMap::new();
+	for doc in docs {
+		for block in &doc.blocks {
+			match block {
+				Block::Prose(prose) => {
+					for (lines, code, text) in quotes(&prose.text, prose.lines.start) {
+						let header = text.lines().next().unwrap_or_default();
+						let message =
+							header[header.find("]: ").map_or(0, |at| at + 3)..].to_string();
+						let entry = entry(&mut entries, &code);
+						add_section(&mut entry.sections, doc, lines.start);
+						entry.quotes.push(Quote {
+							doc,
+							lines,
+							message,
+							text,
+						});
+					}
+					for (i, line) in prose.text.lines().enumerate() {
+						for code in mentions(line) {
+							let entry = entry(&mut entries, code);
+							add_section(&mut entry.sections, doc, prose.lines.start + i);
+						}
+					}
+				}
+				Block::Code(code) => {
+					if let Expectation::Fails(codes) = Expectation::of(code) {
+						for name in codes {
+							let entry = entry(&mut entries, &name);
+							add_section(&mut entry.sections, doc, code.lines.start);
+							entry.snippets.push((doc, code));
+						}
+					}
+				}
+				Block::Heading(_) => {}
+			}
+		}
+	}
+	entries.into_values().collect()
+}
+
+/// The entry of `code`, made empty if it is not there yet.
+fn entry<'m, 'a>(entries: &'m mut BTreeMap<String, Entry<'a>>, code: &str) -> &'m mut Entry<'a> {
+	entries.entry(code.to_string()).or_insert_with(|| Entry {
+		code: code.to_string(),
+		quotes: Vec::new(),
+		snippets: Vec::new(),
+		sections: Vec::new(),
+	})
+}
+
+/// Adds the section holding `line` of `doc`, unless it is there already.
+fn add_section<'a>(sections: &mut Vec<Section<'a>>, doc: &'a Document, line: usize) {
+	let heading = doc.heading_for_line(line);
+	let seen = sections.iter().any(|section| {
+		std::ptr::eq(section.doc, doc)
+			&& section.heading.map(|h| h.lines) == heading.map(|h| h.lines)
+	});
+	if !seen {
+		sections.push(Section { doc, heading });
+	}
+}
+
+/// The diagnostics quoted in some prose: the lines of each, its code and
+/// its text.
+pub fn quotes(text: &str, first_line: usize) -> Vec<(Lines, String, String)> {
+	let lines: Vec<&str> = text.lines().collect();
+	let mut quotes = Vec::new();
+	let mut i = 0;
+	while i < lines.len() {
+		let Some(code) = header_code(lines[i]) else {
+			i += 1;
+			continue;
+		};
+		let start = i;
+		while i < lines.len() && !is_blank(lines[i]) {
+			i += 1;
+		}
+		quotes.push((
+			Lines::new(first_line + start, first_line + i - 1),
+			code.to_string(),
+			text::dedent(&lines[start..i].join("\n")),
+		));
+	}
+	quotes
+}
+
+/// The paragraph of prose that leads into a snippet: the last one of the
+/// prose right before it, if that is running text.
+pub fn lead_in(doc: &Document, code: &Code) -> Option<String> {
+	let at = doc
+		.blocks
+		.iter()
+		.position(|block| block.lines() == code.lines)?;
+	let Block::Prose(prose) = doc.blocks[..at].last()? else {
+		return None;
+	};
+	match prose::paragraphs(&prose.text).pop()? {
+		Paragraph::Text { text, .. } => Some(text),
+		_ => None,
+	}
+}
+
+/// What the notes say about a quote: the paragraph of running text right
+/// after it, if there is one before the next heading or snippet.
+pub fn commentary(quote: &Quote) -> Option<String> {
+	let prose = quote.doc.blocks.iter().find_map(|block| match block {
+		Block::Prose(prose) if prose.lines.contains(quote.lines.start) => Some(prose),
+		_ => None,
+	})?;
+	let rest: Vec<&str> = prose
+		.text
+		.lines()
+		.skip(quote.lines.end + 1 - prose.lines.start)
+		.collect();
+	prose::paragraphs(&rest.join("\n"))
+		.into_iter()
+		.find_map(|paragraph| match paragraph {
+			Paragraph::Text { text, .. } => Some(text),
+			_ => None,
+		})
+}
+
+/// The code of an `error[E0384]: ...` line.
+fn header_code(line: &str) -> Option<&str> {
+	let rest = line.trim_start().strip_prefix("error[")?;
+	let (code, rest) = rest.split_once(']')?;
+	(is_code(code) && rest.starts_with(':')).then_some(code)
+}
+
+/// The error codes a line of prose names.
+fn mentions(line: &str) -> impl Iterator<Item = &str> {
+	line.split(|c: char| !c.is_ascii_alphanumeric())
+		.filter(|word| is_code(word))
+}
+
+/// Whether `word` is an error code: `E` and four digits.
+pub fn is_code(word: &str) -> bool {
+	word.len() == 5 && word.starts_with('E') && word[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The page of the error index for `code`, under the `html` directory of
+/// the std docs, if it is there.
+pub fn official_page(html: &Path, code: &str) -> Option<PathBuf> {
+	let page = html.join("error_codes").join(format!("{code}.html"));
+	page.is_file().then_some(page)
+}
+
+/// The first paragraph of an error index page, as text: a sentence saying
+/// what the error is about.
+pub fn official_summary(page: &Path) -> Option<String> {
+	let html = fs::read_to_string(page).ok()?;
+	let main = &html[html.find("<main>")?..];
+	let start = main.find("<p>")? + 3;
+	let end = start + main[start..].find("</p>")?;
+	let mut text = String::new();
+	let mut in_tag = false;
+	for c in main[start..end].chars() {
+		match c {
+			'<' => in_tag = true,
+			'>' => in_tag = false,
+			c if !in_tag => text.push(c),
+			_ => {}
+		}
+	}
+	let text = text
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&amp;", "&");
+	Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::slice;
+
+	use super::*;
+	use crate::check;
+	use crate::source::NoteFile;
+
+	fn doc() -> Document {
+		Document::parse(
+			NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap(),
+			"/*
+Try rustc --explain E0308 for more.
+
+Mutability:
+
+	Assigning twice fails, as in
+
+		error[E0384]: cannot assign twice to immutable variable `x`
+		 --> src/main.rs:4:5
+
+	Make it mut to allow this.
+*/
+
+	// expect-error: E0384
+	let x = 5;
+	x = 6;
+",
+		)
+	}
+
+	#[test]
+	fn quotes_annotations_and_mentions_are_gathered_by_code() {
+		let doc = doc();
+		let catalog = catalog(slice::from_ref(&doc));
+		let codes: Vec<&str> = catalog.iter().map(|entry| entry.code.as_str()).collect();
+		assert_eq!(codes, ["E0308", "E0384"]);
+
+		let mentioned = &catalog[0];
+		assert!(mentioned.quotes.is_empty() && mentioned.snippets.is_empty());
+		assert_eq!(mentioned.sections[0].title(), "Chapter 3: Common Concepts");
+		assert_eq!(mentioned.sections[0].line(), 1);
+
+		let quoted = &catalog[1];
+		assert_eq!(
+			quoted.message(),
+			Some("cannot assign twice to immutable variable `x`")
+		);
+		assert_eq!(quoted.quotes[0].lines, Lines::new(8, 9));
+		assert_eq!(quoted.snippets.len(), 1);
+		// The quote and the snippet are in the same section.
+		assert_eq!(quoted.sections.len(), 1);
+		assert_eq!(
+			quoted.sections[0].title(),
+			"Chapter 3: Common Concepts / Mutability"
+		);
+		assert_eq!(
+			commentary(&quoted.quotes[0]).as_deref(),
+			Some("Make it mut to allow this.")
+		);
+	}
+
+	#[test]
+	fn codes_are_an_e_and_four_digits() {
+		assert!(is_code("E0384"));
+		assert!(!is_code("E038"));
+		assert!(!is_code("e0384"));
+		assert_eq!(header_code("error[E0502]: cannot borrow"), Some("E0502"));
+		assert_eq!(header_code("error: aborting"), None);
+	}
+
+	#[test]
+	fn the_official_summary_is_the_first_paragraph() {
+		let dir = check::temp_dir().unwrap();
+		let page = dir.join("E0384.html");
+		fs::write(
+			&page,
+			"<nav><p>Menu</p></nav><main><h1>E0384</h1><p>An immutable \
+			 <code>x</code> was\n  reassigned &amp; so on.</p><p>More.</p></main>",
+		)
+		.unwrap();
+		let summary = official_summary(&page);
+		let _ = fs::remove_dir_all(&dir);
+		assert_eq!(
+			summary.as_deref(),
+			Some("An immutable x was reassigned & so on.")
+		);
+	}
+}
@@ -0,0 +1,65 @@
+//! The error type shared by every part of the notes tooling.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+	/// Reading or writing a file failed.
+	Io { path: PathBuf, source: io::Error },
+	/// The command line could not be understood.
+	Usage(String),
+	/// A path does not look like one of the notes files.
+	NotNotes(PathBuf),
+	/// A file the tooling keeps for itself has a line it cannot read.
+	Malformed {
+		path: PathBuf,
+		line: usize,
+		message: String,
+	},
+	/// Two things that must differ do not, such as the rowids of a table.
+	Conflict(String),
+}
+
+impl Error {
+	pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+		Error::Io {
+			path: path.into(),
+			source,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+			Error::Usage(msg) => write!(f, "{msg}"),
+			Error::NotNotes(path) => {
+				write!(
+					f,
+					"{}: not a rust_chapter_N_*.rs notes file",
+					path.display()
+				)
+			}
+			Error::Malformed {
+				path,
+				line,
+				message,
+			} => write!(f, "{}:{line}: {message}", path.display()),
+			Error::Conflict(msg) => write!(f, "{msg}"),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,498 @@
+//! The exercises the chapters end with, their solutions, and the tests a
+//! solution has to pass.
+//!
+//! [`EXERCISES`] is the registry. Each exercise is found in its chapter's
+//! Conclusion by the words its statement opens with, and its solution is
+//! looked for in the chapter's `chapter_N_suggested_exercises.rs`, which has
+//! to define every function the exercise's tests call. The tests pin down
+//! the interface a solution is expected to have.
+//!
+//! A solution is built together with a `main` that runs one test, named by
+//! its first argument, so that a test that panics does not take the others
+//! down with it. A test passes if it does not panic and prints the
+//! fragments it lists, in order, case aside.
+
+use crate::cards;
+use crate::check::{self, Checker, Diagnostic};
+use crate::error::Result;
+use crate::model::{Block, Document};
+use crate::run::{self, Run};
+use crate::source::FileKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exercise {
+	/// A short name, e.g. `pig_latin`.
+	pub id: &'static str,
+	pub chapter: u32,
+	/// The words the statement opens with in the chapter's Conclusion.
+	pub opening: &'static str,
+	/// The functions a solution has to define.
+	pub functions: &'static [&'static str],
+	pub tests: &'static [Test],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Test {
+	pub name: &'static str,
+	/// Statements run in the solution's crate.
+	pub code: &'static str,
+	/// What the statements should print, in order, as parts of lines.
+	pub prints: &'static [&'static str],
+}
+
+pub const EXERCISES: [Exercise; 6] = [
+	Exercise {
+		id: "temperatures",
+		chapter: 3,
+		opening: "Convert temperatures",
+		functions: &["celsius_to_fahrenheit", "fahrenheit_to_celsius"],
+		tests: &[
+			Test {
+				name: "boiling",
+				code: "assert_eq!(celsius_to_fahrenheit(100), 212);
+					assert_eq!(fahrenheit_to_celsius(212), 100);",
+				prints: &[],
+			},
+			Test {
+				name: "freezing",
+				code: "assert_eq!(celsius_to_fahrenheit(0), 32);
+					assert_eq!(fahrenheit_to_celsius(32), 0);",
+				prints: &[],
+			},
+			Test {
+				name: "minus_forty",
+				code: "assert_eq!(celsius_to_fahrenheit(-40), -40);
+					assert_eq!(fahrenheit_to_celsius(-40), -40);",
+				prints: &[],
+			},
+		],
+	},
+	Exercise {
+		id: "fibonacci",
+		chapter: 3,
+		opening: "Generate the nth Fibonacci number",
+		functions: &["nth_fib"],
+		// Counted the way the solution's own `main` counts: the sequence
+		// starts 0, 1, 1, 2, so its 10th number is 34.
+		tests: &[
+			Test {
+				name: "starts_at_zero",
+				code: "assert_eq!(nth_fib(1), 0);
+					assert_eq!(nth_fib(2), 1);",
+				prints: &[],
+			},
+			Test {
+				name: "tenth",
+				code: "assert_eq!(nth_fib(3), 1);
+					assert_eq!(nth_fib(4), 2);
+					assert_eq!(nth_fib(10), 34);",
+				prints: &[],
+			},
+			Test {
+				name: "twentieth",
+				code: "assert_eq!(nth_fib(20), 4181);",
+				prints: &[],
+			},
+		],
+	},
+	Exercise {
+		id: "christmas_carol",
+		chapter: 3,
+		opening: "Print the lyrics",
+		functions: &["christmas_carol"],
+		tests: &[
+			Test {
+				name: "first_verse",
+				code: "christmas_carol();",
+				prints: &["first day", "partridge in a pear tree", "second day"],
+			},
+			Test {
+				name: "twelve_verses",
+				code: "christmas_carol();",
+				prints: &[
+					"first day",
+					"second day",
+					"third day",
+					"fourth day",
+					"fifth day",
+					"sixth day",
+					"seventh day",
+					"eighth day",
+					"ninth day",
+					"tenth day",
+					"eleventh day",
+					"twelfth day",
+					"twelve drummers drumming",
+					"partridge in a pear tree",
+				],
+			},
+		],
+	},
+	Exercise {
+		id: "median_mode",
+		chapter: 8,
+		opening: "Given a list of integers",
+		functions: &["median", "mode"],
+		tests: &[
+			Test {
+				name: "median_odd",
+				code: "assert_eq!(median(&vec![5, 1, 3]), 3.0);",
+				prints: &[],
+			},
+			Test {
+				name: "median_even",
+				code: "assert_eq!(median(&vec![4, 1, 3, 2]), 2.5);",
+				prints: &[],
+			},
+			Test {
+				name: "mode",
+				code: "assert_eq!(mode(&vec![1, 3, 3, 2, 1, 3]), 3);",
+				prints: &[],
+			},
+		],
+	},
+	Exercise {
+		id: "pig_latin",
+		chapter: 8,
+		opening: "Convert strings to pig latin",
+		functions: &["pig_latin"],
+		tests: &[
+			Test {
+				name: "consonant",
+				code: r#"assert_eq!(pig_latin("first"), "irst-fay");"#,
+				prints: &[],
+			},
+			Test {
+				name: "vowel",
+				code: r#"assert_eq!(pig_latin("apple"), "apple-hay");"#,
+				prints: &[],
+			},
+			Test {
+				name: "sentence",
+				code: r#"assert_eq!(pig_latin("first apple"), "irst-fay apple-hay");"#,
+				prints: &[],
+			},
+			Test {
+				name: "utf8",
+				code: r#"assert_eq!(pig_latin("naïve"), "aïve-nay");"#,
+				prints: &[],
+			},
+		],
+	},
+	Exercise {
+		id: "employees",
+		chapter: 8,
+		opening: "Using a hash map and vectors, create a text interface",
+		functions: &["add_employee", "department", "company"],
+		tests: &[
+			Test {
+				name: "department",
+				code: r#"let mut directory = std::collections::HashMap::new();
+					add_employee(&mut directory, "Add Sally to Engineering");
+					add_employee(&mut directory, "Add Amir to Sales");
+					add_employee(&mut directory, "Add Bob to Engineering");
+					assert_eq!(department(&directory, "Engineering"), ["Bob", "Sally"]);"#,
+				prints: &[],
+			},
+			Test {
+				name: "company",
+				code: r##"let mut directory = std::collections::HashMap::new();
+					add_employee(&mut directory, "Add Sally to Engineering");
+					add_employee(&mut directory, "Add Amir to Sales");
+					add_employee(&mut directory, "Add Bob to Engineering");
+					assert_eq!(
+						format!("{:?}", company(&directory)),
+						r#"[("Engineering", ["Bob", "Sally"]), ("Sales", ["Amir"])]"#
+					);"##,
+				prints: &[],
+			},
+			Test {
+				name: "not_a_command",
+				code: r#"let mut directory = std::collections::HashMap::new();
+					add_employee(&mut directory, "Hire Sally");
+					assert!(company(&directory).is_empty());"#,
+				prints: &[],
+			},
+		],
+	},
+];
+
+/// An exercise with what the notes have for it.
+#[derive(Debug, Clone)]
+pub struct Linked<'a> {
+	pub exercise: &'static Exercise,
+	/// The chapter's notes.
+	pub notes: &'a Document,
+	/// The line the statement starts on and its text, joined into one line;
+	/// `None` if the Conclusion no longer has it.
+	pub statement: Option<(usize, String)>,
+	/// The chapter's exercises file, if there is one.
+	pub solutions: Option<&'a Document>,
+}
+
+/// Every exercise of [`EXERCISES`] whose chapter's notes are among `docs`.
+pub fn link(docs: &[Document]) -> Vec<Linked<'_>> {
+	EXERCISES
+		.iter()
+		.filter_map(|exercise| {
+			let of_kind = |kind| {
+				docs.iter()
+					.find(|doc| doc.file.chapter == exercise.chapter && doc.file.kind == kind)
+			};
+			let notes = of_kind(FileKind::Notes)?;
+			Some(Linked {
+				exercise,
+				notes,
+				statement: statement(notes, exercise.opening),
+				solutions: of_kind(FileKind::Exercises),
+			})
+		})
+		.collect()
+}
+
+/// The statement in the Conclusion of `notes` that opens with `opening`.
+fn statement(notes: &Document, opening: &str) -> Option<(usize, String)> {
+	let section = notes
+		.sections()
+		.into_iter()
+		.find(|section| section.heading.is_some_and(|h| h.title == "Conclusion"))?;
+	section.blocks.iter().find_map(|block| match block {
+		Block::Prose(prose) => cards::pieces(&prose.text, prose.lines.start)
+			.into_iter()
+			.find(|(_, piece)| piece.starts_with(opening)),
+		_ => None,
+	})
+}
+
+/// Why a test failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+	pub test: &'static str,
+	pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+	/// There is no solution, or it lacks some of the functions.
+	Unsolved {
+		missing: Vec<&'static str>,
+	},
+	Solved,
+	/// The solution does not build, or fails some of its tests.
+	Failing(Vec<Failure>),
+}
+
+impl Status {
+	pub fn label(&self) -> &'static str {
+		match self {
+			Status::Unsolved { .. } => "unsolved",
+			Status::Solved => "solved",
+			Status::Failing(_) => "FAILING",
+		}
+	}
+}
+
+/// Builds the solution of every exercise and runs its tests, on the
+/// checker's threads.
+pub fn statuses(checker: &Checker, linked: &[Linked]) -> Result<Vec<Status>> {
+	checker
+		.parallel(linked, |linked| status(checker, linked))
+		.into_iter()
+		.collect()
+}
+
+fn status(checker: &Checker, linked: &Linked) -> Result<Status> {
+	let exercise = linked.exercise;
+	let items: Vec<check::Item> = linked
+		.solutions
+		.into_iter()
+		.flat_map(Document::code_blocks)
+		.flat_map(check::items)
+		.collect();
+	let missing: Vec<&'static str> = exercise
+		.functions
+		.iter()
+		.copied()
+		.filter(|name| !items.iter().any(|item| item.key == *name))
+		.collect();
+	if !missing.is_empty() {
+		return Ok(Status::Unsolved { missing });
+	}
+
+	let mut source = String::new();
+	for item in &items {
+		source.push_str(&item.text);
+		source.push_str("\n\n");
+	}
+	source.push_str("fn main() {\n\tmatch std::env::args().nth(1).as_deref() {\n");
+	for test in exercise.tests {
+		source.push_str(&format!(
+			"\t\tSome({:?}) => {{\n{}\n\t\t}}\n",
+			test.name, test.code
+		));
+	}
+	source.push_str("\t\t_ => {}\n\t}\n}\n");
+
+	let dir = check::temp_dir()?;
+	let result = run_tests(checker, &dir, &source, exercise.tests);
+	let _ = std::fs::remove_dir_all(&dir);
+	let failures = result?;
+	Ok(if failures.is_empty() {
+		Status::Solved
+	} else {
+		Status::Failing(failures)
+	})
+}
+
+fn run_tests(
+	checker: &Checker,
+	dir: &std::path::Path,
+	source: &str,
+	tests: &'static [Test],
+) -> Result<Vec<Failure>> {
+	let errors = checker.build(dir, source)?;
+	if let Some(error) = errors.first() {
+		return Ok(vec![Failure {
+			test: "build",
+			reason: format!("does not compile: {}", describe(error)),
+		}]);
+	}
+	let mut failures = Vec::new();
+	for test in tests {
+		let reason = match run::execute(dir, &[test.name])? {
+			Run::TimedOut => Some("timed out".to_string()),
+			Run::Finished { status, stderr, .. } if !status.success() => {
+				Some(panic_message(&stderr).unwrap_or_else(|| status.to_string()))
+			}
+			Run::Finished { stdout, .. } => missing_output(&stdout, test.prints)
+				.map(|fragment| format!("\"{fragment}\" is not in the output where expected")),
+		};
+		if let Some(reason) = reason {
+			failures.push(Failure {
+				test: test.name,
+				reason,
+			});
+		}
+	}
+	Ok(failures)
+}
+
+/// The first of `prints` that is not in `stdout` after the ones before it.
+fn missing_output(stdout: &str, prints: &[&'static str]) -> Option<&'static str> {
+	let lines: Vec<String> = stdout.lines().map(str::to_lowercase).collect();
+	let mut at = 0;
+	for fragment in prints {
+		let wanted = fragment.to_lowercase();
+		match lines[at..].iter().position(|line| line.contains(&wanted)) {
+			Some(i) => at += i + 1,
+			None => return Some(fragment),
+		}
+	}
+	None
+}
+
+/// The message of the panic reported in `stderr`, on one line.
+fn panic_message(stderr: &str) -> Option<String> {
+	let mut lines = stderr
+		.lines()
+		.skip_while(|line| !line.contains(" panicked at "));
+	lines.next()?;
+	let message: Vec<&str> = lines
+		.take_while(|line| !line.starts_with("note: ") && !line.starts_with("stack backtrace:"))
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
+		.collect();
+	Some(message.join("; "))
+}
+
+fn describe(error: &Diagnostic) -> String {
+	match &error.code {
+		Some(code) => format!("error[{code}]: {}", error.message),
+		None => error.message.clone(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(name: &str, source: &str) -> Document {
+		Document::parse(NoteFile::from_path(name).unwrap(), source)
+	}
+
+	#[test]
+	fn solutions_are_run_against_the_tests() {
+		let docs = [
+			doc(
+				"rust_chapter_3_common_concepts.rs",
+				"/*
+Conclusion:
+
+	- Convert temperatures between Fahrenheit
+	and Celsius.
+	- Generate the nth Fibonacci number.
+*/
+",
+			),
+			doc(
+				"chapter_3_suggested_exercises.rs",
+				"fn celsius_to_fahrenheit(c: i32) -> i32 { c * 9 / 5 + 32 }
+fn fahrenheit_to_celsius(f: i32) -> i32 { (f - 32) * 5 / 9 }
+
+fn nth_fib(n: u32) -> u64 { if n < 3 { 1 } else { nth_fib(n - 1) + nth_fib(n - 2) } }
+",
+			),
+		];
+		let linked = link(&docs);
+		let found: Vec<(&str, Option<usize>)> = linked
+			.iter()
+			.map(|linked| {
+				(
+					linked.exercise.id,
+					linked.statement.as_ref().map(|(line, _)| *line),
+				)
+			})
+			.collect();
+		assert_eq!(
+			found[..3],
+			[
+				("temperatures", Some(4)),
+				("fibonacci", Some(6)),
+				("christmas_carol", None)
+			]
+		);
+		assert_eq!(
+			linked[0].statement.as_ref().unwrap().1,
+			"Convert temperatures between Fahrenheit and Celsius."
+		);
+
+		let statuses = statuses(&Checker::new(), &linked[..3]).unwrap();
+		assert_eq!(statuses[0], Status::Solved);
+		let Status::Failing(failures) = &statuses[1] else {
+			panic!("the Fibonacci solution is off by one");
+		};
+		let failed: Vec<&str> = failures.iter().map(|failure| failure.test).collect();
+		assert_eq!(failed, ["starts_at_zero", "tenth", "twentieth"]);
+		assert_eq!(
+			failures[0].reason,
+			"assertion `left == right` failed; left: 1; right: 0"
+		);
+		assert_eq!(
+			statuses[2],
+			Status::Unsolved {
+				missing: vec!["christmas_carol"]
+			}
+		);
+	}
+
+	#[test]
+	fn output_is_looked_for_in_order() {
+		let stdout = "On the First day\na partridge\nOn the second day\n";
+		assert_eq!(missing_output(stdout, &["first day", "second day"]), None);
+		assert_eq!(
+			missing_output(stdout, &["second day", "first day"]),
+			Some("first day")
+		);
+	}
+}
@@ -0,0 +1,571 @@
+//! An Anki package of the flashcards: a zip holding the SQLite collection
+//! Anki imports, with one note per card under a note type of its own.
+//!
+//! ```text
+//! collection.anki2   the collection, in Anki's schema 11
+//! media              `{}`, as no card has pictures or sounds
+//! ```
+//!
+//! A note's GUID is the card's id, which is made from its file and question,
+//! so a package written after the notes change updates the notes imported
+//! from the last one, and their review history stays with them.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::cards::{self, Card};
+use crate::date::{self, Day};
+use crate::error::{Error, Result};
+use crate::json::Value as Json;
+use crate::model::Document;
+use crate::sqlite::{Database, Value};
+use crate::zip::Zip;
+
+use super::highlight;
+use super::html::escape;
+
+/// The note type's id. Anki recognises the note type of a later package by
+/// it, rather than adding a copy.
+const MODEL: i64 = 1_700_000_000_000;
+
+/// The deck the cards go into.
+const DECK: i64 = 1_700_000_000_001;
+const DECK_NAME: &str = "Rust Notes";
+
+const MODEL_NAME: &str = "Rust Notes";
+const FIELDS: [&str; 3] = ["Front", "Back", "Source"];
+
+const QUESTION: &str = "{{Front}}";
+const ANSWER: &str =
+	"{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}\n\n<div class=source>{{Source}}</div>";
+
+const STYLE: &str = "\
+.card { font-family: sans-serif; font-size: 20px; text-align: left; }
+code { font-family: monospace; font-size: 0.9em; }
+.source { margin-top: 1em; font-size: 0.7em; color: #59636e; }
+.kw { color: #cf222e; } .ty { color: #953800; } .fn { color: #8250df; }
+.mac { color: #0550ae; } .str { color: #0a3069; } .num { color: #0550ae; }
+.com { color: #59636e; font-style: italic; } .life { color: #953800; }
+.attr { color: #116329; }
+";
+
+/// Names from the prelude that are code wherever the prose uses them.
+const PRELUDE: [&str; 6] = ["Copy", "Err", "Ok", "Option", "Result", "self"];
+
+const SCHEMA: [(&str, &str); 5] = [
+	(
+		"col",
+		"CREATE TABLE col (id integer primary key, crt integer not null, \
+		 mod integer not null, scm integer not null, ver integer not null, \
+		 dty integer not null, usn integer not null, ls integer not null, \
+		 conf text not null, models text not null, decks text not null, \
+		 dconf text not null, tags text not null)",
+	),
+	(
+		"notes",
+		"CREATE TABLE notes (id integer primary key, guid text not null, \
+		 mid integer not null, mod integer not null, usn integer not null, \
+		 tags text not null, flds text not null, sfld integer not null, \
+		 csum integer not null, flags integer not null, data text not null)",
+	),
+	(
+		"cards",
+		"CREATE TABLE cards (id integer primary key, nid integer not null, \
+		 did integer not null, ord integer not null, mod integer not null, \
+		 usn integer not null, type integer not null, queue integer not null, \
+		 due integer not null, ivl integer not null, factor integer not null, \
+		 reps integer not null, lapses integer not null, left integer not null, \
+		 odue integer not null, odid integer not null, flags integer not null, \
+		 data text not null)",
+	),
+	(
+		"revlog",
+		"CREATE TABLE revlog (id integer primary key, cid integer not null, \
+		 usn integer not null, ease integer not null, ivl integer not null, \
+		 lastIvl integer not null, factor integer not null, time integer not null, \
+		 type integer not null)",
+	),
+	(
+		"graves",
+		"CREATE TABLE graves (usn integer not null, oid integer not null, \
+		 type integer not null)",
+	),
+];
+
+/// Writes the package for the cards in `docs` to the file `out`, and
+/// returns how many cards it holds.
+pub fn write_deck(docs: &[Document], out: &Path) -> Result<usize> {
+	let count = docs.iter().map(|doc| cards::cards(doc).len()).sum();
+	let deck = deck(docs, date::today())?;
+	if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+	}
+	fs::write(out, deck).map_err(|e| Error::io(out, e))?;
+	Ok(count)
+}
+
+/// The bytes of the package, with every note marked as changed on `day`.
+pub fn deck(docs: &[Document], day: Day) -> Result<Vec<u8>> {
+	let mut zip = Zip::new();
+	zip.add("collection.anki2", &collection(docs, day)?);
+	zip.add("media", b"{}");
+	Ok(zip.finish())
+}
+
+/// The SQLite collection.
+fn collection(docs: &[Document], day: Day) -> Result<Vec<u8>> {
+	let seconds = day * 86_400;
+	let mut notes = Vec::new();
+	let mut cards = Vec::new();
+	for (doc, card) in docs
+		.iter()
+		.flat_map(|doc| cards::cards(doc).into_iter().map(move |card| (doc, card)))
+	{
+		let id = note_id(&card);
+		let front = rich(&card.front());
+		let source = source(doc, &card);
+		let fields = [front.as_str(), &rich(&card.answer), &source].join("\x1f");
+		notes.push((
+			id,
+			vec![
+				Value::Null,
+				card.id.as_str().into(),
+				MODEL.into(),
+				seconds.into(),
+				(-1).into(),
+				format!(" {} ", doc.file.stem()).into(),
+				fields.into(),
+				plain(&front).into(),
+				checksum(&front).into(),
+				0.into(),
+				"".into(),
+			],
+		));
+		// New cards are shown in the order of their `due`.
+		let due = cards.len() as i64 + 1;
+		cards.push((
+			id,
+			vec![
+				Value::Null,
+				id.into(),
+				DECK.into(),
+				0.into(),
+				seconds.into(),
+				(-1).into(),
+				0.into(),
+				0.into(),
+				due.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				0.into(),
+				"".into(),
+			],
+		));
+	}
+	let col = vec![
+		Value::Null,
+		seconds.into(),
+		(seconds * 1000).into(),
+		(seconds * 1000).into(),
+		11.into(),
+		0.into(),
+		0.into(),
+		0.into(),
+		config(cards.len()).to_string().into(),
+		models(seconds).to_string().into(),
+		decks(seconds).to_string().into(),
+		deck_options().to_string().into(),
+		"{}".into(),
+	];
+
+	let mut rows = [vec![(1, col)], notes, cards, Vec::new(), Vec::new()].into_iter();
+	let mut database = Database::new();
+	for (name, sql) in SCHEMA {
+		database.table(name, sql, rows.next().unwrap_or_default())?;
+	}
+	Ok(database.finish())
+}
+
+/// The id of a card's note, and of the card itself: its id as a number,
+/// cut to the integers a JavaScript number holds exactly.
+fn note_id(card: &Card) -> i64 {
+	let hash = u64::from_str_radix(&card.id, 16).unwrap_or_default();
+	(hash & ((1 << 52) - 1)) as i64
+}
+
+/// Where a card comes from: `Chapter 9: Error Handling / Propagating Errors
+/// (rust_chapter_9_error_handling.rs:319)`.
+fn source(doc: &Document, card: &Card) -> String {
+	let mut source = doc.file.title();
+	if let Some(section) = &card.section {
+		let _ = write!(source, " / {section}");
+	}
+	let _ = write!(source, " ({}:{})", doc.file.file_name(), card.line);
+	escape(&source)
+}
+
+/// Escapes prose for a field, with the words that are code, such as
+/// `unwrap()`, `&mut self` or `From<io::Error>`, highlighted.
+fn rich(text: &str) -> String {
+	let words: Vec<&str> = text.split(' ').collect();
+	let mut out = String::new();
+	let mut i = 0;
+	while i < words.len() {
+		if i > 0 {
+			out.push(' ');
+		}
+		let (before, mut code, mut after) = split_punctuation(words[i]);
+		i += 1;
+		// `&mut` belongs with the word after it.
+		let joined;
+		if code == "&mut" && i < words.len() {
+			let (_, next, rest) = split_punctuation(words[i]);
+			joined = format!("&mut {next}");
+			(code, after) = (&joined, rest);
+			i += 1;
+		}
+		out.push_str(&escape(before));
+		if is_code(code) {
+			let _ = write!(out, "<code>{}</code>", highlight::rust(code));
+		} else {
+			out.push_str(&escape(code));
+		}
+		out.push_str(&escape(after));
+	}
+	out
+}
+
+/// A word without the brackets and punctuation around it, and them.
+fn split_punctuation(word: &str) -> (&str, &str, &str) {
+	if word == "?" {
+		return ("", word, "");
+	}
+	let start = word.len() - word.trim_start_matches('(').len();
+	let mut end = word.trim_end_matches(['.', ',', ';', ':', '?', '!']).len();
+	if start > 0 && word[..end].ends_with(')') && !word[..end].ends_with("()") {
+		end -= 1;
+	}
+	let end = end.max(start);
+	(&word[..start], &word[start..end], &word[end..])
+}
+
+fn is_code(word: &str) -> bool {
+	word == "?"
+		|| word.ends_with("()")
+		|| word.contains("::")
+		|| (word.starts_with('&') && word.len() > 1)
+		|| (word.contains('<') && word.ends_with('>'))
+		|| (word.contains('_') && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+		|| PRELUDE.contains(&word)
+}
+
+/// The checksum Anki finds duplicates by: the first 32 bits of the SHA-1
+/// of the first field, without its markup.
+fn checksum(field: &str) -> i64 {
+	let digest = sha1(plain(field).as_bytes());
+	i64::from(u32::from_be_bytes([
+		digest[0], digest[1], digest[2], digest[3],
+	]))
+}
+
+/// A field as text, which is what Anki sorts and compares notes by.
+fn plain(field: &str) -> String {
+	let mut text = String::new();
+	let mut in_tag = false;
+	for c in field.chars() {
+		match c {
+			'<' => in_tag = true,
+			'>' if in_tag => in_tag = false,
+			c if !in_tag => text.push(c),
+			_ => {}
+		}
+	}
+	text.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&amp;", "&")
+}
+
+/// The collection's settings, with new cards numbered after the `cards`
+/// already in it.
+fn config(cards: usize) -> Json {
+	Json::object([
+		("nextPos", (cards + 1).into()),
+		("estTimes", true.into()),
+		("activeDecks", vec![Json::from(1usize)].into()),
+		("sortType", "noteFld".into()),
+		("timeLim", 0usize.into()),
+		("sortBackwards", false.into()),
+		("addToCur", true.into()),
+		("curDeck", DECK.into()),
+		("newSpread", 0usize.into()),
+		("dueCounts", true.into()),
+		("curModel", MODEL.to_string().into()),
+		("collapseTime", 1200usize.into()),
+	])
+}
+
+/// The note type, keyed by its id.
+fn models(seconds: i64) -> Json {
+	let fields = FIELDS
+		.iter()
+		.enumerate()
+		.map(|(ord, name)| {
+			Json::object([
+				("name", (*name).into()),
+				("ord", ord.into()),
+				("sticky", false.into()),
+				("rtl", false.into()),
+				("font", "Arial".into()),
+				("size", 20usize.into()),
+				("media", Vec::new().into()),
+			])
+		})
+		.collect::<Vec<_>>();
+	let template = Json::object([
+		("name", "Card 1".into()),
+		("ord", 0usize.into()),
+		("qfmt", QUESTION.into()),
+		("afmt", ANSWER.into()),
+		("did", Json::Null),
+		("bqfmt", "".into()),
+		("bafmt", "".into()),
+	]);
+	let model = Json::object([
+		("id", MODEL.into()),
+		("name", MODEL_NAME.into()),
+		("type", 0usize.into()),
+		("mod", seconds.into()),
+		("usn", (-1i64).into()),
+		("sortf", 0usize.into()),
+		("did", DECK.into()),
+		("tmpls", vec![template].into()),
+		("flds", fields.into()),
+		("css", STYLE.into()),
+		("latexPre", LATEX_PRE.into()),
+		("latexPost", "\\end{document}".into()),
+		("latexsvg", false.into()),
+		// The one card needs the front to be filled in.
+		(
+			"req",
+			vec![Json::Array(vec![
+				0usize.into(),
+				"any".into(),
+				vec![Json::from(0usize)].into(),
+			])]
+			.into(),
+		),
+		("tags", Vec::new().into()),
+		("vers", Vec::new().into()),
+	]);
+	Json::Object(vec![(MODEL.to_string(), model)])
+}
+
+const LATEX_PRE: &str = "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\
+\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\
+\\setlength{\\parindent}{0in}\n\\begin{document}\n";
+
+/// The default deck every collection has, and the one for the cards.
+fn decks(seconds: i64) -> Json {
+	let deck = |id: i64, name: &str| {
+		Json::object([
+			("id", id.into()),
+			("name", name.into()),
+			("mod", seconds.into()),
+			("usn", (-1i64).into()),
+			("desc", "".into()),
+			("dyn", 0usize.into()),
+			("conf", 1usize.into()),
+			("collapsed", false.into()),
+			("newToday", vec![0usize.into(), 0usize.into()].into()),
+			("revToday", vec![0usize.into(), 0usize.into()].into()),
+			("lrnToday", vec![0usize.into(), 0usize.into()].into()),
+			("timeToday", vec![0usize.into(), 0usize.into()].into()),
+			("extendNew", 10usize.into()),
+			("extendRev", 50usize.into()),
+		])
+	};
+	Json::Object(vec![
+		("1".to_string(), deck(1, "Default")),
+		(DECK.to_string(), deck(DECK, DECK_NAME)),
+	])
+}
+
+/// Anki's default scheduling options, which both decks use.
+fn deck_options() -> Json {
+	let numbers = |items: &[f64]| Json::Array(items.iter().map(|&n| Json::Number(n)).collect());
+	let options = Json::object([
+		("id", 1usize.into()),
+		("name", "Default".into()),
+		(
+			"new",
+			Json::object([
+				("delays", numbers(&[1.0, 10.0])),
+				("ints", numbers(&[1.0, 4.0, 7.0])),
+				("initialFactor", 2500usize.into()),
+				("order", 1usize.into()),
+				("perDay", 20usize.into()),
+				("bury", true.into()),
+				("separate", true.into()),
+			]),
+		),
+		(
+			"rev",
+			Json::object([
+				("perDay", 200usize.into()),
+				("ease4", Json::Number(1.3)),
+				("ivlFct", 1usize.into()),
+				("maxIvl", 36500usize.into()),
+				("fuzz", Json::Number(0.05)),
+				("bury", true.into()),
+				("minSpace", 1usize.into()),
+			]),
+		),
+		(
+			"lapse",
+			Json::object([
+				("delays", numbers(&[10.0])),
+				("mult", 0usize.into()),
+				("minInt", 1usize.into()),
+				("leechFails", 8usize.into()),
+				("leechAction", 0usize.into()),
+			]),
+		),
+		("maxTaken", 60usize.into()),
+		("timer", 0usize.into()),
+		("autoplay", true.into()),
+		("replayq", true.into()),
+		("mod", 0usize.into()),
+		("usn", 0usize.into()),
+		("dyn", false.into()),
+	]);
+	Json::Object(vec![("1".to_string(), options)])
+}
+
+/// The SHA-1 digest of `data`.
+fn sha1(data: &[u8]) -> [u8; 20] {
+	let mut h: [u32; 5] = [
+		0x6745_2301,
+		0xEFCD_AB89,
+		0x98BA_DCFE,
+		0x1032_5476,
+		0xC3D2_E1F0,
+	];
+	let mut message = data.to_vec();
+	message.push(0x80);
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+	message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+	for block in message.chunks(64) {
+		let mut w = [0u32; 80];
+		for (i, word) in block.chunks(4).enumerate() {
+			w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+		}
+		for i in 16..80 {
+			w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+		}
+		let [mut a, mut b, mut c, mut d, mut e] = h;
+		for (i, &word) in w.iter().enumerate() {
+			let (f, k) = match i {
+				0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+				20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+				40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+				_ => (b ^ c ^ d, 0xCA62_C1D6),
+			};
+			let t = a
+				.rotate_left(5)
+				.wrapping_add(f)
+				.wrapping_add(e)
+				.wrapping_add(k)
+				.wrapping_add(word);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = t;
+		}
+		for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+			*h = h.wrapping_add(x);
+		}
+	}
+	let mut digest = [0; 20];
+	for (i, word) in h.iter().enumerate() {
+		digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+	}
+	digest
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn hex(digest: [u8; 20]) -> String {
+		digest.iter().map(|byte| format!("{byte:02x}")).collect()
+	}
+
+	#[test]
+	fn sha1_matches_the_standard_digests() {
+		assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+		assert_eq!(
+			hex(sha1(b"abc")),
+			"a9993e364706816aba3e25717850c26c9cd0d89d"
+		);
+		// Long enough to take two blocks.
+		assert_eq!(
+			hex(sha1(
+				b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+			)),
+			"84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+		);
+	}
+
+	#[test]
+	fn code_words_are_highlighted_and_punctuation_is_not() {
+		let field = rich("Call unwrap(), or use &mut self.");
+		assert!(field.starts_with("Call <code>"));
+		assert!(field.contains("</code>, or use <code>"));
+		assert!(field.ends_with("</code>."));
+		assert_eq!(plain(&field), "Call unwrap(), or use &mut self.");
+		assert_eq!(split_punctuation("(Option<T>)."), ("(", "Option<T>", ")."));
+		assert!(!is_code("Call"));
+	}
+
+	#[test]
+	fn notes_are_numbered_by_their_cards() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap(),
+			"/*\nStatements:\n\n\tQ: Do statements return values? A: No.\n*/\n",
+		);
+		let card = &cards::cards(&doc)[0];
+		let id = note_id(card);
+		assert!((0..1 << 52).contains(&id));
+		assert_eq!(
+			source(&doc, card),
+			"Chapter 3: Common Concepts / Statements (rust_chapter_3_common_concepts.rs:4)"
+		);
+
+		let deck = deck(&[doc], 20_000).unwrap();
+		assert_eq!(&deck[30..46], b"collection.anki2");
+		assert_eq!(&deck[46..62], b"SQLite format 3\0");
+	}
+
+	#[test]
+	fn a_question_asked_twice_makes_two_notes() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_4_ownership_rules.rs").unwrap(),
+			"/*\nMoves:\n\n\tQ: What is a move? A: Handing over ownership.\n\n\t\
+			 Q: What is a move? A: Passing ownership to a parameter.\n*/\n",
+		);
+		let cards = cards::cards(&doc);
+		assert_ne!(note_id(&cards[0]), note_id(&cards[1]));
+		assert!(deck(&[doc], 20_000).is_ok());
+	}
+}
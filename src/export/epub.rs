@@ -0,0 +1,226 @@
+//! An EPUB 3 book of the notes, for e-readers: one XHTML document per notes
+//! file in chapter order, a navigation document listing every section, and a
+//! stylesheet, packed into the zip container by [`crate::zip`].
+//!
+//! ```text
+//! mimetype
+//! META-INF/container.xml
+//! OEBPS/content.opf
+//! OEBPS/nav.xhtml
+//! OEBPS/style.css
+//! OEBPS/rust_chapter_1_project_setup.xhtml
+//! ...
+//! ```
+//!
+//! The chapters are the bodies of the HTML site's pages, so they read the
+//! same way; only the sidebar and pager are left to the reader's software.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::date;
+use crate::error::{Error, Result};
+use crate::model::Document;
+use crate::zip::Zip;
+
+use super::html::{self, escape};
+use super::{anchors, is_nested};
+
+const TITLE: &str = "Rust Notes";
+
+/// Stays the same from one edition of the book to the next, so a reader
+/// replaces the old copy rather than keeping both.
+const IDENTIFIER: &str = "urn:x-rust-notes:book";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE: &str = "\
+body { font-family: serif; line-height: 1.5; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.25; }
+h2 a, h3 a, h4 a, h5 a, h6 a { color: inherit; text-decoration: none; }
+pre, code { font-family: monospace; }
+pre {
+	font-size: 0.8em; line-height: 1.35; white-space: pre-wrap;
+	border: 1px solid #d0d7de; padding: 0.5em; margin: 1em 0;
+}
+pre.compile-fail { border-left: 4px solid #cf222e; }
+pre.ignore { border-left: 4px solid #9a6700; }
+.code-note, .side-notes { font-size: 0.85em; color: #59636e; }
+.note-ref { font-size: 0.7em; vertical-align: super; }
+.kw { color: #cf222e; } .ty { color: #953800; } .fn { color: #8250df; }
+.mac { color: #0550ae; } .str { color: #0a3069; } .num { color: #0550ae; }
+.com { color: #59636e; font-style: italic; } .life { color: #953800; }
+.attr { color: #116329; } .key { color: #0550ae; } .cmd { color: #116329; }
+";
+
+/// Writes the book for `docs` to the file `out`.
+pub fn write_book(docs: &[Document], out: &Path) -> Result<()> {
+	let book = book(docs, &date::format(date::today()));
+	if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+	}
+	fs::write(out, book).map_err(|e| Error::io(out, e))
+}
+
+/// The bytes of the book, marked as last modified on `day` (`YYYY-MM-DD`).
+pub fn book(docs: &[Document], day: &str) -> Vec<u8> {
+	let mut zip = Zip::new();
+	// The media type comes first and uncompressed, so the file can be
+	// recognised from its first bytes.
+	zip.add("mimetype", b"application/epub+zip");
+	zip.add("META-INF/container.xml", CONTAINER.as_bytes());
+	zip.add("OEBPS/content.opf", package(docs, day).as_bytes());
+	zip.add("OEBPS/nav.xhtml", nav(docs).as_bytes());
+	zip.add("OEBPS/style.css", STYLE.as_bytes());
+	for doc in docs {
+		let path = format!("OEBPS/{}", file_name(doc));
+		zip.add(&path, chapter(doc).as_bytes());
+	}
+	zip.finish()
+}
+
+/// The file name of a document's chapter, e.g. `rust_chapter_3_common_concepts.xhtml`.
+pub fn file_name(doc: &Document) -> String {
+	format!("{}.xhtml", doc.file.stem())
+}
+
+/// The package document: what the book is and which files it is made of,
+/// in reading order.
+fn package(docs: &[Document], day: &str) -> String {
+	let mut manifest = String::new();
+	let mut spine = String::new();
+	for (i, doc) in docs.iter().enumerate() {
+		let _ = writeln!(
+			manifest,
+			"    <item id=\"chapter-{i}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+			file_name(doc)
+		);
+		let _ = writeln!(spine, "    <itemref idref=\"chapter-{i}\"/>");
+	}
+	format!(
+		r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{IDENTIFIER}</dc:identifier>
+    <dc:title>{TITLE}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{day}T00:00:00Z</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#
+	)
+}
+
+/// The navigation document: every notes file, with its sections nested
+/// under it as deep as their headings go.
+fn nav(docs: &[Document]) -> String {
+	let mut entries = Vec::new();
+	for doc in docs {
+		let depth = usize::from(is_nested(doc, docs));
+		let file = file_name(doc);
+		entries.push((
+			depth,
+			format!("<a href=\"{file}\">{}</a>", escape(&doc.file.title())),
+		));
+		for (heading, anchor) in doc.headings().zip(anchors(doc)) {
+			entries.push((
+				depth + heading.level,
+				format!("<a href=\"{file}#{anchor}\">{}</a>", escape(&heading.title)),
+			));
+		}
+	}
+	let body = format!(
+		"<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n{}</nav>\n",
+		html::nested_list("ol", &entries)
+	);
+	xhtml(TITLE, &body)
+}
+
+/// A notes file as a chapter.
+fn chapter(doc: &Document) -> String {
+	xhtml(&doc.file.title(), &html::chapter(doc, None))
+}
+
+/// A complete XHTML document around `body`.
+fn xhtml(title: &str, body: &str) -> String {
+	format!(
+		r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="en" xml:lang="en">
+<head>
+<meta charset="utf-8"/>
+<title>{}</title>
+<link rel="stylesheet" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+		escape(title)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(name: &str, source: &str) -> Document {
+		Document::parse(NoteFile::from_path(name).unwrap(), source)
+	}
+
+	fn docs() -> Vec<Document> {
+		vec![
+			doc("rust_chapter_1_project_setup.rs", "fn main() {}\n"),
+			doc(
+				"rust_chapter_3_common_concepts.rs",
+				"/*\nVariables:\n\n\tMutability:\n\n\t\tText.\n*/\n",
+			),
+		]
+	}
+
+	#[test]
+	fn the_media_type_is_the_first_file() {
+		let book = book(&docs(), "2026-10-17");
+		assert_eq!(&book[..4], b"PK\x03\x04");
+		assert_eq!(&book[30..38], b"mimetype");
+		assert_eq!(&book[38..58], b"application/epub+zip");
+	}
+
+	#[test]
+	fn the_spine_lists_the_chapters_in_order() {
+		let package = package(&docs(), "2026-10-17");
+		assert!(package.contains("<meta property=\"dcterms:modified\">2026-10-17T00:00:00Z</meta>"));
+		assert!(package.contains(
+			"    <item id=\"chapter-1\" href=\"rust_chapter_3_common_concepts.xhtml\" \
+			 media-type=\"application/xhtml+xml\"/>\n"
+		));
+		assert!(package.contains(
+			"  <spine>\n    <itemref idref=\"chapter-0\"/>\n    <itemref idref=\"chapter-1\"/>\n  </spine>"
+		));
+	}
+
+	#[test]
+	fn sections_are_nested_under_their_chapter() {
+		let nav = nav(&docs());
+		let chapter = nav.find("rust_chapter_3_common_concepts.xhtml\">").unwrap();
+		let variables = nav.find("#variables\">Variables</a>").unwrap();
+		let mutability = nav.find("#mutability\">Mutability</a>").unwrap();
+		assert!(chapter < variables && variables < mutability);
+		assert_eq!(nav[chapter..variables].matches("<ol>").count(), 1);
+		assert_eq!(nav[variables..mutability].matches("<ol>").count(), 1);
+	}
+}
@@ -0,0 +1,235 @@
+//! Syntax highlighting as HTML `<span>`s, done at export time so the pages
+//! need no script to colour their code.
+//!
+//! The classes are short and shared with the stylesheet in [`super::html`]:
+//!
+//! | class  | for                                        |
+//! |--------|--------------------------------------------|
+//! | `kw`   | keywords                                   |
+//! | `ty`   | types: capitalised names and primitives    |
+//! | `fn`   | the name after `fn`, and called functions  |
+//! | `mac`  | macro names such as `println!`             |
+//! | `str`  | string and character literals              |
+//! | `num`  | numbers                                    |
+//! | `com`  | comments                                   |
+//! | `life` | lifetimes and loop labels                  |
+//! | `attr` | attributes such as `#[derive(Debug)]`      |
+//! | `key`  | TOML keys and table headers                |
+//! | `cmd`  | the prompt and command of a console line   |
+
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use crate::lex::{self, TokenKind};
+
+use super::html::escape;
+
+const PRIMITIVES: [&str; 17] = [
+	"i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+	"f64", "bool", "char", "str",
+];
+
+/// Highlights Rust code.
+pub fn rust(code: &str) -> String {
+	rust_linked(code, &[])
+}
+
+/// Highlights Rust code, with each range of `links` made a link to its URL.
+/// The ranges start and end on token boundaries and do not overlap.
+pub fn rust_linked(code: &str, links: &[(Range<usize>, String)]) -> String {
+	let tokens = lex::tokens(code);
+	let mut links = links.iter().peekable();
+	let mut linking = false;
+	let next_significant = |i: usize| tokens[i + 1..].iter().find(|token| !token.is_trivia());
+	let mut out = String::with_capacity(code.len() * 2);
+	let mut previous: Option<&str> = None;
+	let mut i = 0;
+	while i < tokens.len() {
+		let token = tokens[i];
+		// An attribute is coloured whole, up to its closing bracket.
+		if token.text == "#" && next_significant(i).is_some_and(|next| next.text == "[") {
+			let end = attribute_end(&tokens, i);
+			let text: String = tokens[i..end].iter().map(|token| token.text).collect();
+			span(&mut out, "attr", &text);
+			i = end;
+			previous = Some("]");
+			continue;
+		}
+		// A link that starts inside an attribute or a macro name is dropped.
+		while !linking
+			&& links
+				.peek()
+				.is_some_and(|(range, _)| range.start < token.start)
+		{
+			links.next();
+		}
+		if let Some((range, url)) = links.peek() {
+			if !linking && range.start == token.start {
+				let _ = write!(out, "<a class=\"std\" href=\"{}\">", escape(url));
+				linking = true;
+			}
+		}
+		let next = next_significant(i).map(|next| next.text);
+		let class = match token.kind {
+			TokenKind::Keyword => Some("kw"),
+			TokenKind::Str | TokenKind::Char => Some("str"),
+			TokenKind::Number => Some("num"),
+			TokenKind::Comment => Some("com"),
+			TokenKind::Lifetime => Some("life"),
+			TokenKind::Ident if next == Some("!") => Some("mac"),
+			TokenKind::Ident
+				if token.text.starts_with(char::is_uppercase)
+					|| PRIMITIVES.contains(&token.text) =>
+			{
+				Some("ty")
+			}
+			TokenKind::Ident if previous == Some("fn") || next == Some("(") => Some("fn"),
+			_ => None,
+		};
+		match class {
+			// The `!` belongs to the macro name.
+			Some("mac") => {
+				let bang = tokens[i + 1..]
+					.iter()
+					.position(|token| token.text == "!")
+					.map_or(i, |at| i + 1 + at);
+				let text: String = tokens[i..=bang].iter().map(|token| token.text).collect();
+				span(&mut out, "mac", &text);
+				i = bang;
+			}
+			Some(class) => span(&mut out, class, token.text),
+			None => out.push_str(&escape(token.text)),
+		}
+		if !token.is_trivia() {
+			previous = Some(token.text);
+		}
+		let end = tokens[i].start + tokens[i].text.len();
+		if linking && links.peek().is_some_and(|(range, _)| range.end <= end) {
+			out.push_str("</a>");
+			links.next();
+			linking = false;
+		}
+		i += 1;
+	}
+	out
+}
+
+/// The index just past the `]` that closes the attribute starting at `start`.
+fn attribute_end(tokens: &[lex::Token], start: usize) -> usize {
+	let mut depth = 0;
+	for (i, token) in tokens.iter().enumerate().skip(start) {
+		match token.text {
+			"[" => depth += 1,
+			"]" => {
+				depth -= 1;
+				if depth == 0 {
+					return i + 1;
+				}
+			}
+			_ => {}
+		}
+	}
+	tokens.len()
+}
+
+/// Highlights a `Cargo.toml` excerpt line by line: table headers, keys,
+/// strings and comments.
+pub fn toml(code: &str) -> String {
+	let mut out = String::new();
+	for (i, line) in code.lines().enumerate() {
+		if i > 0 {
+			out.push('\n');
+		}
+		let (content, comment) = match line.find('#') {
+			Some(at) if !line[..at].contains('"') => (&line[..at], &line[at..]),
+			_ => (line, ""),
+		};
+		let trimmed = content.trim();
+		if trimmed.starts_with('[') {
+			span(&mut out, "key", content);
+		} else if let Some((key, value)) = content.split_once('=') {
+			span(&mut out, "key", key);
+			out.push('=');
+			toml_value(&mut out, value);
+		} else {
+			out.push_str(&escape(content));
+		}
+		if !comment.is_empty() {
+			span(&mut out, "com", comment);
+		}
+	}
+	out
+}
+
+fn toml_value(out: &mut String, value: &str) {
+	let mut rest = value;
+	while let Some(open) = rest.find('"') {
+		out.push_str(&escape(&rest[..open]));
+		let after = &rest[open + 1..];
+		let close = after.find('"').map_or(after.len(), |at| at + 1);
+		span(out, "str", &rest[open..open + 1 + close]);
+		rest = &after[close..];
+	}
+	let trimmed = rest.trim();
+	if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_digit() || c == '.') {
+		let at = rest.find(trimmed).unwrap_or(0);
+		out.push_str(&escape(&rest[..at]));
+		span(out, "num", trimmed);
+		out.push_str(&escape(&rest[at + trimmed.len()..]));
+	} else {
+		out.push_str(&escape(rest));
+	}
+}
+
+/// Highlights a terminal session: lines starting with `$ ` are commands, the
+/// rest is output and left plain.
+pub fn console(text: &str) -> String {
+	let mut out = String::new();
+	for (i, line) in text.lines().enumerate() {
+		if i > 0 {
+			out.push('\n');
+		}
+		if line.starts_with("$ ") {
+			span(&mut out, "cmd", line);
+		} else {
+			out.push_str(&escape(line));
+		}
+	}
+	out
+}
+
+fn span(out: &mut String, class: &str, text: &str) {
+	out.push_str("<span class=\"");
+	out.push_str(class);
+	out.push_str("\">");
+	out.push_str(&escape(text));
+	out.push_str("</span>");
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rust_is_highlighted_by_role() {
+		assert_eq!(
+			rust("#[derive(Debug)]\nfn main() { let s: String = format!(\"{}\", 1); } // done"),
+			"<span class=\"attr\">#[derive(Debug)]</span>\n\
+			 <span class=\"kw\">fn</span> <span class=\"fn\">main</span>() { \
+			 <span class=\"kw\">let</span> s: <span class=\"ty\">String</span> = \
+			 <span class=\"mac\">format!</span>(<span class=\"str\">&quot;{}&quot;</span>, \
+			 <span class=\"num\">1</span>); } <span class=\"com\">// done</span>"
+		);
+	}
+
+	#[test]
+	fn toml_and_console_are_highlighted() {
+		let html = toml("[dependencies]\nrand = \"0.8.5\"");
+		assert!(html.starts_with("<span class=\"key\">[dependencies]</span>\n"));
+		assert!(html.ends_with("= <span class=\"str\">&quot;0.8.5&quot;</span>"));
+		assert_eq!(
+			console("$ cargo run\n   Compiling x"),
+			"<span class=\"cmd\">$ cargo run</span>\n   Compiling x"
+		);
+	}
+}
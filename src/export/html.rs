@@ -0,0 +1,631 @@
+//! A static HTML site: one page per notes file, an index, a glossary, and a
+//! stylesheet.
+//!
+//! Every page carries the whole table of contents in a sidebar, with the
+//! sections of the current page listed under it, and links to the pages
+//! before and after it. The code is highlighted when the pages are written
+//! and nothing is fetched from elsewhere, so the site can be opened straight
+//! from the directory it was written to.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::check::Expectation;
+use crate::error::Result;
+use crate::glossary::{self, Place};
+use crate::model::{Block, Code, Document, Lang};
+use crate::prose::{self, Paragraph, Preformatted};
+use crate::rustdoc::{self, Index, Resolution, Resolver};
+
+use super::{
+	anchors, by_document, highlight, is_nested, margin, section, titles, with_links, write,
+};
+
+const SITE_TITLE: &str = "Rust Notes";
+
+const GLOSSARY_PAGE: &str = "glossary.html";
+
+const STYLE: &str = "\
+:root {
+	--fg: #1f2328; --muted: #59636e; --bg: #ffffff; --side: #f6f8fa;
+	--border: #d1d9e0; --link: #0969da; --code: #f6f8fa;
+}
+* { box-sizing: border-box; }
+body {
+	margin: 0; color: var(--fg); background: var(--bg);
+	font: 16px/1.6 system-ui, -apple-system, \"Segoe UI\", sans-serif;
+}
+a { color: var(--link); text-decoration: none; }
+a:hover { text-decoration: underline; }
+.sidebar {
+	position: fixed; top: 0; bottom: 0; left: 0; width: 300px; overflow-y: auto;
+	padding: 1rem; background: var(--side); border-right: 1px solid var(--border);
+	font-size: 14px;
+}
+.sidebar .site-title { display: block; font-weight: 600; font-size: 18px; margin-bottom: .5rem; }
+.sidebar ol { list-style: none; margin: 0; padding-left: 1rem; }
+.sidebar > ol { padding-left: 0; }
+.sidebar li { margin: .2rem 0; }
+.sidebar .current > a { font-weight: 600; color: var(--fg); }
+.sidebar .glossary { margin-top: .75rem; }
+main { margin-left: 300px; padding: 1rem 2rem 3rem; max-width: calc(300px + 54rem); }
+h1, h2, h3, h4, h5, h6 { line-height: 1.25; margin: 1.5em 0 .5em; }
+h2 a, h3 a, h4 a, h5 a, h6 a { color: inherit; }
+pre {
+	background: var(--code); border: 1px solid var(--border); border-radius: 6px;
+	padding: .75rem 1rem; overflow-x: auto; font-size: 14px; line-height: 1.45;
+	tab-size: 4;
+}
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+pre.compile-fail { border-left: 4px solid #cf222e; }
+pre.ignore { border-left: 4px solid #9a6700; }
+.code-note { color: var(--muted); font-size: 13px; margin: -.5rem 0 1rem; }
+.annotated { display: grid; grid-template-columns: minmax(0, 1fr) 16rem; gap: 1rem;
+	align-items: start; }
+.side-notes { margin: 0; padding-left: 1.5rem; color: var(--muted); font-size: 13px; }
+.side-notes li { margin-bottom: .5rem; }
+.side-notes p { margin: 0 0 .25rem; }
+a.std { color: inherit; text-decoration: underline dotted; }
+.note-ref { font-size: 11px; vertical-align: super; margin-left: .25rem;
+	font-style: normal; }
+.pager { display: flex; justify-content: space-between; margin-top: 3rem;
+	padding-top: 1rem; border-top: 1px solid var(--border); }
+.kw { color: #cf222e; } .ty { color: #953800; } .fn { color: #8250df; }
+.mac { color: #0550ae; } .str { color: #0a3069; } .num { color: #0550ae; }
+.com { color: #59636e; font-style: italic; } .life { color: #953800; }
+.attr { color: #116329; } .key { color: #0550ae; } .cmd { color: #116329; }
+@media (max-width: 800px) {
+	.sidebar { position: static; width: auto; border-right: 0; }
+	main { margin-left: 0; }
+	.annotated { display: block; }
+}
+";
+
+/// Writes the site for `docs` under `out` and returns the files written.
+/// With the std docs given, the std items the notes mention link to them.
+pub fn write_site(docs: &[Document], out: &Path, std: Option<&Index>) -> Result<Vec<PathBuf>> {
+	let mut written = Vec::new();
+	let mut put = |path: PathBuf, contents: &str| -> Result<()> {
+		write(&path, contents)?;
+		written.push(path);
+		Ok(())
+	};
+	put(out.join("style.css"), STYLE)?;
+	put(out.join("index.html"), &index(docs))?;
+	for i in 0..docs.len() {
+		put(out.join(page_name(&docs[i])), &page(docs, i, std))?;
+	}
+	put(out.join(GLOSSARY_PAGE), &glossary_page(docs))?;
+	Ok(written)
+}
+
+/// Writes the page of `docs[i]` and the glossary, the pages that change
+/// when only that document does, and returns the files written.
+pub fn write_page(
+	docs: &[Document],
+	i: usize,
+	out: &Path,
+	std: Option<&Index>,
+) -> Result<Vec<PathBuf>> {
+	let page_path = out.join(page_name(&docs[i]));
+	write(&page_path, &page(docs, i, std))?;
+	let glossary_path = out.join(GLOSSARY_PAGE);
+	write(&glossary_path, &glossary_page(docs))?;
+	Ok(vec![page_path, glossary_path])
+}
+
+/// The file name of a document's page, e.g. `rust_chapter_3_common_concepts.html`.
+pub fn page_name(doc: &Document) -> String {
+	format!("{}.html", doc.file.stem())
+}
+
+/// The landing page: the list of chapters.
+pub fn index(docs: &[Document]) -> String {
+	let mut body = format!("<h1>{}</h1>\n<ol>\n", escape(SITE_TITLE));
+	for doc in docs {
+		let _ = writeln!(
+			body,
+			"<li><a href=\"{}\">{}</a></li>",
+			page_name(doc),
+			escape(&doc.file.title())
+		);
+	}
+	body.push_str("</ol>\n");
+	let _ = writeln!(
+		body,
+		"<p>See also the <a href=\"{GLOSSARY_PAGE}\">glossary</a>.</p>"
+	);
+	let next = docs.first().map(|doc| pager_link("next", doc));
+	layout(SITE_TITLE, &sidebar(docs, None), &body, [None, next])
+}
+
+/// The page of `docs[current]`.
+pub fn page(docs: &[Document], current: usize, std: Option<&Index>) -> String {
+	let doc = &docs[current];
+	let title = doc.file.title();
+	let prev = current.checked_sub(1).map(|i| pager_link("prev", &docs[i]));
+	let next = match docs.get(current + 1) {
+		Some(doc) => pager_link("next", doc),
+		None => ("next", GLOSSARY_PAGE.to_string(), "Glossary".to_string()),
+	};
+	layout(
+		&format!("{title} - {SITE_TITLE}"),
+		&sidebar(docs, Some(current)),
+		&chapter(doc, std),
+		[prev, Some(next)],
+	)
+}
+
+/// The glossary: every term with the section that introduces it and the
+/// sections that mention it before and after, grouped by chapter.
+pub fn glossary_page(docs: &[Document]) -> String {
+	let mut body = String::from("<h1>Glossary</h1>\n");
+	let entries = glossary::glossary(docs);
+	body.push_str("<p>");
+	for (i, entry) in entries.iter().enumerate() {
+		if i > 0 {
+			body.push_str(" · ");
+		}
+		let _ = write!(
+			body,
+			"<a href=\"#{}\">{}</a>",
+			super::slug(entry.term),
+			escape(entry.term)
+		);
+	}
+	body.push_str("</p>\n");
+	for entry in &entries {
+		let anchor = super::slug(entry.term);
+		let _ = writeln!(
+			body,
+			"<h2 id=\"{anchor}\"><a href=\"#{anchor}\">{}</a></h2>\n<p>Introduced {}.</p>",
+			escape(entry.term),
+			link(docs, &entry.defined)
+		);
+		for (label, places) in [
+			("Used again in", &entry.uses),
+			("Mentioned before in", &entry.earlier),
+		] {
+			if places.is_empty() {
+				continue;
+			}
+			let _ = writeln!(body, "<p>{label}:</p>\n<ul>");
+			for (doc, places) in by_document(places) {
+				let doc = &docs[doc];
+				let sections: Vec<String> = places
+					.iter()
+					.filter_map(|place| section(doc, place))
+					.map(|(anchor, title)| {
+						format!(
+							"<a href=\"{}#{anchor}\">{}</a>",
+							page_name(doc),
+							escape(&title)
+						)
+					})
+					.collect();
+				let _ = write!(
+					body,
+					"<li><a href=\"{}\">{}</a>",
+					page_name(doc),
+					escape(&doc.file.title())
+				);
+				if !sections.is_empty() {
+					let _ = write!(body, ": {}", sections.join(", "));
+				}
+				body.push_str("</li>\n");
+			}
+			body.push_str("</ul>\n");
+		}
+	}
+	let prev = docs.last().map(|doc| pager_link("prev", doc));
+	layout(
+		&format!("Glossary - {SITE_TITLE}"),
+		&sidebar(docs, Some(docs.len())),
+		&body,
+		[prev, None],
+	)
+}
+
+/// Where a glossary place is, as `under <a>Section</a> in <a>Chapter</a>`.
+fn link(docs: &[Document], place: &Place) -> String {
+	let doc = &docs[place.doc];
+	let chapter = format!(
+		"<a href=\"{}\">{}</a>",
+		page_name(doc),
+		escape(&doc.file.title())
+	);
+	match section(doc, place) {
+		Some((anchor, title)) => format!(
+			"under <a href=\"{}#{anchor}\">{}</a> in {chapter}",
+			page_name(doc),
+			escape(&title)
+		),
+		None => format!("in {chapter}"),
+	}
+}
+
+/// A pager link to the page of `doc`: its `rel`, address and title.
+fn pager_link(rel: &'static str, doc: &Document) -> (&'static str, String, String) {
+	(rel, page_name(doc), doc.file.title())
+}
+
+fn layout(
+	title: &str,
+	sidebar: &str,
+	body: &str,
+	pager: [Option<(&str, String, String)>; 2],
+) -> String {
+	let mut out = format!(
+		"<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+		 <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+		 <title>{}</title>\n<link rel=\"stylesheet\" href=\"style.css\">\n</head>\n<body>\n",
+		escape(title)
+	);
+	out.push_str(sidebar);
+	out.push_str("<main>\n");
+	out.push_str(body);
+	out.push_str("<nav class=\"pager\">\n");
+	for link in pager {
+		match link {
+			Some((rel, href, title)) => {
+				let arrow = if rel == "prev" { "← " } else { "" };
+				let after = if rel == "next" { " →" } else { "" };
+				let _ = writeln!(
+					out,
+					"<a rel=\"{rel}\" href=\"{href}\">{arrow}{}{after}</a>",
+					escape(&title)
+				);
+			}
+			None => out.push_str("<span></span>\n"),
+		}
+	}
+	out.push_str("</nav>\n</main>\n</body>\n</html>\n");
+	out
+}
+
+/// The table of contents. The sections of the current page are listed
+/// under its entry; `current` is `docs.len()` on the glossary, which comes
+/// after the last document.
+fn sidebar(docs: &[Document], current: Option<usize>) -> String {
+	let mut out = format!(
+		"<nav class=\"sidebar\">\n<a class=\"site-title\" href=\"index.html\">{}</a>\n",
+		escape(SITE_TITLE)
+	);
+	let mut entries = Vec::new();
+	for (i, doc) in docs.iter().enumerate() {
+		let depth = usize::from(is_nested(doc, docs));
+		let class = if current == Some(i) {
+			" class=\"current\""
+		} else {
+			""
+		};
+		let mut entry = format!(
+			"<a href=\"{}\">{}</a>",
+			page_name(doc),
+			escape(&doc.file.title())
+		);
+		if current == Some(i) {
+			let sections: Vec<(usize, String)> = doc
+				.headings()
+				.zip(anchors(doc))
+				.map(|(heading, anchor)| {
+					(
+						heading.level.saturating_sub(1),
+						format!("<a href=\"#{anchor}\">{}</a>", escape(&heading.title)),
+					)
+				})
+				.collect();
+			entry.push('\n');
+			entry.push_str(&nested_list("ol", &sections));
+		}
+		entries.push((depth, format!("<li{class}>{entry}")));
+	}
+	out.push_str(&nested_items("ol", &entries));
+	let class = if current == Some(docs.len()) {
+		"glossary current"
+	} else {
+		"glossary"
+	};
+	let _ = writeln!(
+		out,
+		"<div class=\"{class}\"><a href=\"{GLOSSARY_PAGE}\">Glossary</a></div>"
+	);
+	out.push_str("</nav>\n");
+	out
+}
+
+/// A list whose items are nested by depth, each an `<li>` of `html`.
+pub(super) fn nested_list(tag: &str, items: &[(usize, String)]) -> String {
+	let items: Vec<(usize, String)> = items
+		.iter()
+		.map(|(depth, html)| (*depth, format!("<li>{html}")))
+		.collect();
+	nested_items(tag, &items)
+}
+
+/// Opens and closes lists around already opened `<li>`s so that each sits at
+/// its depth. An item more than one level deeper than the one before it is
+/// pulled up to one level deeper.
+fn nested_items(tag: &str, items: &[(usize, String)]) -> String {
+	let mut out = String::new();
+	let mut open = 0;
+	for (depth, item) in items {
+		let depth = (*depth).min(open);
+		if depth == open {
+			let _ = writeln!(out, "<{tag}>");
+			open += 1;
+		} else {
+			out.push_str("</li>\n");
+			while open > depth + 1 {
+				let _ = writeln!(out, "</{tag}>\n</li>");
+				open -= 1;
+			}
+		}
+		out.push_str(item);
+	}
+	for _ in 0..open {
+		let _ = writeln!(out, "</li>\n</{tag}>");
+	}
+	out
+}
+
+/// The body of a page: the title and every block.
+pub fn chapter(doc: &Document, std: Option<&Index>) -> String {
+	let mut out = format!("<h1>{}</h1>\n", escape(&doc.file.title()));
+	let mut anchors = anchors(doc).into_iter();
+	let titles = titles(doc);
+	let resolver = std.map(|index| index.resolver(doc));
+	let resolver = resolver.as_ref();
+	let mut notes = 0;
+	for block in &doc.blocks {
+		match block {
+			Block::Heading(heading) => {
+				let level = (heading.level + 1).min(6);
+				let anchor = anchors.next().unwrap_or_default();
+				let _ = writeln!(
+					out,
+					"<h{level} id=\"{anchor}\"><a href=\"#{anchor}\">{}</a></h{level}>",
+					escape(&heading.title)
+				);
+			}
+			Block::Prose(prose) => paragraphs(&mut out, &prose.text, &titles, resolver),
+			Block::Code(code) => code_block(&mut out, code, &mut notes, resolver),
+		}
+	}
+	out
+}
+
+fn paragraphs(
+	out: &mut String,
+	text: &str,
+	titles: &HashMap<String, String>,
+	resolver: Option<&Resolver>,
+) {
+	let mut list: Vec<(usize, String)> = Vec::new();
+	for paragraph in prose::paragraphs(text) {
+		if let Paragraph::Item { depth, text, .. } = &paragraph {
+			list.push((*depth, inline(text, titles, resolver)));
+			continue;
+		}
+		out.push_str(&nested_list("ul", &list));
+		list.clear();
+		match paragraph {
+			Paragraph::Text { text, .. } => {
+				let _ = writeln!(out, "<p>{}</p>", inline(&text, titles, resolver));
+			}
+			Paragraph::Preformatted { kind, text } => {
+				let (class, html) = match kind {
+					Preformatted::Console => ("console", highlight::console(&text)),
+					Preformatted::Plain => ("plain", escape(&text)),
+				};
+				let _ = writeln!(out, "<pre class=\"{class}\"><code>{html}</code></pre>");
+			}
+			Paragraph::Item { .. } => unreachable!(),
+		}
+	}
+	out.push_str(&nested_list("ul", &list));
+}
+
+/// Writes a code block, with its margin comments beside it as notes
+/// numbered on from `notes`, the count so far on the page.
+fn code_block(out: &mut String, code: &Code, notes: &mut usize, resolver: Option<&Resolver>) {
+	let (text, side_notes) = margin::side_notes(code);
+	let lang = code.lang();
+	let mut html = match lang {
+		Lang::Rust => {
+			let links: Vec<(Range<usize>, String)> = resolver.map_or_else(Vec::new, |resolver| {
+				rustdoc::code_references(&text)
+					.into_iter()
+					.filter_map(|reference| match resolver.resolve(&reference) {
+						Resolution::Linked { url, .. } => Some((reference.range, url)),
+						_ => None,
+					})
+					.collect()
+			});
+			highlight::rust_linked(&text, &links)
+		}
+		Lang::Toml => highlight::toml(&text),
+		Lang::Console => highlight::console(&text),
+		Lang::Unknown => escape(&text),
+	};
+	let (extra, note) = match Expectation::of(code) {
+		_ if lang != Lang::Rust => ("", None),
+		Expectation::Fails(codes) if codes.is_empty() => {
+			(" compile-fail", Some("This does not compile.".to_string()))
+		}
+		Expectation::Fails(codes) => (
+			" compile-fail",
+			Some(format!("This does not compile: {}.", codes.join(", "))),
+		),
+		Expectation::Skip(_) => (" ignore", Some("Not checked on its own.".to_string())),
+		Expectation::Compiles => ("", None),
+	};
+	if !side_notes.is_empty() {
+		let mut lines: Vec<String> = html.split('\n').map(str::to_string).collect();
+		for (n, note) in (*notes + 1..).zip(&side_notes) {
+			let _ = write!(
+				lines[note.line],
+				"<a class=\"note-ref\" href=\"#code-note-{n}\">{n}</a>"
+			);
+		}
+		html = lines.join("\n");
+		out.push_str("<div class=\"annotated\">\n");
+	}
+	let _ = writeln!(
+		out,
+		"<pre class=\"{}{extra}\"><code>{html}</code></pre>",
+		lang.name()
+	);
+	if !side_notes.is_empty() {
+		let _ = writeln!(out, "<ol class=\"side-notes\" start=\"{}\">", *notes + 1);
+		for note in &side_notes {
+			*notes += 1;
+			let _ = write!(out, "<li id=\"code-note-{notes}\">");
+			for paragraph in &note.paragraphs {
+				let _ = write!(out, "<p>{}</p>", escape(paragraph));
+			}
+			out.push_str("</li>\n");
+		}
+		out.push_str("</ol>\n</div>\n");
+	}
+	if let Some(note) = note {
+		let _ = writeln!(out, "<p class=\"code-note\">{}</p>", escape(&note));
+	}
+}
+
+/// Escapes running text, turns bare `http(s)://` addresses into links, and
+/// `<#anchor>` references into links to their headings, and std items into
+/// links to their docs.
+fn inline(text: &str, titles: &HashMap<String, String>, resolver: Option<&Resolver>) -> String {
+	with_links(
+		text,
+		titles,
+		|plain| urls(plain, resolver),
+		|anchor, title| format!("<a href=\"#{anchor}\">{}</a>", escape(title)),
+	)
+}
+
+fn urls(text: &str, resolver: Option<&Resolver>) -> String {
+	let mut out = String::new();
+	for (i, word) in text.split(' ').enumerate() {
+		if i > 0 {
+			out.push(' ');
+		}
+		let url_end = word.trim_end_matches(['.', ',', ')', ';', ':']).len();
+		if word.starts_with("http://") || word.starts_with("https://") {
+			let (url, rest) = word.split_at(url_end);
+			let url = escape(url);
+			let _ = write!(out, "<a href=\"{url}\">{url}</a>{}", escape(rest));
+		} else if let Some(resolver) = resolver {
+			out.push_str(&std_links(word, resolver));
+		} else {
+			out.push_str(&escape(word));
+		}
+	}
+	out
+}
+
+/// Escapes a word of prose, with the std items in it linked to their docs.
+fn std_links(word: &str, resolver: &Resolver) -> String {
+	let mut out = String::new();
+	let mut at = 0;
+	for reference in rustdoc::prose_references(word) {
+		if let Resolution::Linked { path, url } = resolver.resolve(&reference) {
+			out.push_str(&escape(&word[at..reference.range.start]));
+			let _ = write!(
+				out,
+				"<a class=\"std\" href=\"{}\" title=\"{}\">{}</a>",
+				escape(&url),
+				escape(&path),
+				escape(&word[reference.range.clone()])
+			);
+			at = reference.range.end;
+		}
+	}
+	out.push_str(&escape(&word[at..]));
+	out
+}
+
+/// Escapes the characters that mean something in HTML text and attributes.
+pub fn escape(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => out.push_str("&amp;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			'"' => out.push_str("&quot;"),
+			_ => out.push(c),
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(name: &str, source: &str) -> Document {
+		Document::parse(NoteFile::from_path(name).unwrap(), source)
+	}
+
+	#[test]
+	fn a_chapter_links_its_headings_and_marks_failing_code() {
+		let doc = doc(
+			"rust_chapter_3_common_concepts.rs",
+			"/*
+Shadowing:
+
+	See https://doc.rust-lang.org/book/ch03-01-variables-and-mutability.html.
+*/
+
+	let x = 5;
+	x = 6; // expect-error: E0384
+
+	/*
+	Shadowing:
+
+		Again, with <T> & more.*/
+",
+		);
+		assert_eq!(
+			chapter(&doc, None),
+			"<h1>Chapter 3: Common Concepts</h1>
+<h2 id=\"shadowing\"><a href=\"#shadowing\">Shadowing</a></h2>
+<p>See <a href=\"https://doc.rust-lang.org/book/ch03-01-variables-and-mutability.html\">\
+https://doc.rust-lang.org/book/ch03-01-variables-and-mutability.html</a>.</p>
+<pre class=\"rust compile-fail\"><code><span class=\"kw\">let</span> x = <span class=\"num\">5</span>;
+x = <span class=\"num\">6</span>;</code></pre>
+<p class=\"code-note\">This does not compile: E0384.</p>
+<h3 id=\"shadowing-1\"><a href=\"#shadowing-1\">Shadowing</a></h3>
+<p>Again, with &lt;T&gt; &amp; more.</p>
+"
+		);
+	}
+
+	#[test]
+	fn the_sidebar_opens_the_current_page() {
+		let docs = [
+			doc(
+				"rust_chapter_1_project_setup.rs",
+				"/*\nSetup:\n\n\tText.\n*/\n",
+			),
+			doc(
+				"rust_chapter_2_guessing_game.rs",
+				"/*\nGuessing:\n\n\tText.\n*/\n",
+			),
+		];
+		let page = page(&docs, 1, None);
+		assert!(page.contains(
+			"<li class=\"current\"><a href=\"rust_chapter_2_guessing_game.html\">\
+			 Chapter 2: Guessing Game</a>\n<ol>\n<li><a href=\"#guessing\">Guessing</a>"
+		));
+		assert!(!page.contains("href=\"#setup\""));
+		assert!(page.contains(
+			"<a rel=\"prev\" href=\"rust_chapter_1_project_setup.html\">← Chapter 1: Project Setup</a>"
+		));
+	}
+}
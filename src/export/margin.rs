@@ -0,0 +1,237 @@
+//! Comments written in the margin of the code, lifted out as side notes.
+//!
+//! The notes often explain a statement with a comment that starts at the
+//! end of its line and carries on down the margin, one `//` line after
+//! another, lined up far to the right of the code:
+//!
+//! ```text
+//! let mut guess = String::new();  //Here, we create a String variable to
+//!                                 //store user input using let. All Rust
+//! ```
+//!
+//! A margin line is a line holding nothing but a `//` comment in words,
+//! indented more than a level past the code on either side of it. A run of
+//! them, blank lines included, belongs to the last line of code above it,
+//! together with that line's own trailing comment. The exporters show the run
+//! as a note beside or under the code, and the code without it. A lone
+//! trailing comment with nothing in the margin below stays in the code.
+
+use crate::lex::{self, TokenKind};
+use crate::model::{Code, Lang};
+
+use super::display_code;
+
+/// How much deeper than the code around it a comment has to be indented to
+/// be in the margin.
+const MARGIN: usize = 4;
+
+/// A run of margin comments, attached to the line of code it explains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideNote {
+	/// The line of the cleaned code the note belongs to, from 0.
+	pub line: usize,
+	/// The comment text, one paragraph per stretch between blank lines.
+	pub paragraphs: Vec<String>,
+}
+
+/// What a line of code holds, as far as comments go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+	Blank,
+	/// Only a `//` comment, starting at this column.
+	Comment(usize),
+	/// Code, maybe followed by a `//` comment starting at this byte.
+	Code(Option<usize>),
+}
+
+/// The code of a block as [`display_code`] gives it, with the margin
+/// comment runs taken out, and the runs as notes.
+pub fn side_notes(code: &Code) -> (String, Vec<SideNote>) {
+	let text = display_code(code);
+	if code.lang() != Lang::Rust {
+		return (text, Vec::new());
+	}
+	let lines: Vec<&str> = text.lines().collect();
+	let shapes = shapes(&text, &lines);
+	let indent = |i: usize| lines[i].len() - lines[i].trim_start().len();
+	let in_margin: Vec<bool> = (0..lines.len())
+		.map(|i| {
+			let Shape::Comment(column) = shapes[i] else {
+				return false;
+			};
+			if !is_prose(comment_text(lines[i].trim_start())) {
+				return false;
+			}
+			let Some(above) = first_code(&shapes, (0..i).rev()).map(indent) else {
+				return false;
+			};
+			let below = first_code(&shapes, i + 1..lines.len()).map_or(0, indent);
+			column > above.max(below) + MARGIN
+		})
+		.collect();
+
+	let mut out: Vec<String> = Vec::new();
+	let mut notes: Vec<SideNote> = Vec::new();
+	// The last line of code, in `lines` and in `out`.
+	let mut last_code: Option<(usize, usize)> = None;
+	// Whether the last note is still being read.
+	let mut open = false;
+	for (i, line) in lines.iter().enumerate() {
+		if in_margin[i] {
+			let comment = comment_text(line.trim_start());
+			if open {
+				let note = notes.last_mut().expect("an open run has a note");
+				let paragraph = note.paragraphs.last_mut().expect("a note has a paragraph");
+				if !paragraph.is_empty() {
+					paragraph.push(' ');
+				}
+				paragraph.push_str(comment);
+			} else {
+				let (anchor, written) = last_code.expect("a margin line has code above it");
+				let mut first = String::new();
+				if let Shape::Code(Some(at)) = shapes[anchor] {
+					first.push_str(comment_text(&lines[anchor][at..]));
+					first.push(' ');
+					out[written] = lines[anchor][..at].trim_end().to_string();
+				}
+				first.push_str(comment);
+				notes.push(SideNote {
+					line: written,
+					paragraphs: vec![first],
+				});
+				open = true;
+			}
+			continue;
+		}
+		match shapes[i] {
+			Shape::Blank if open => {
+				// A blank line between two stretches of one run starts a new
+				// paragraph; one after the run stays in the code.
+				let next = (i + 1..lines.len()).find(|&j| shapes[j] != Shape::Blank);
+				if next.is_some_and(|j| in_margin[j]) {
+					let note = notes.last_mut().expect("an open run has a note");
+					if note.paragraphs.last().is_some_and(|p| !p.is_empty()) {
+						note.paragraphs.push(String::new());
+					}
+					continue;
+				}
+			}
+			Shape::Blank => {}
+			Shape::Code(_) => {
+				last_code = Some((i, out.len()));
+				open = false;
+			}
+			Shape::Comment(_) => open = false,
+		}
+		out.push(line.to_string());
+	}
+	(out.join("\n"), notes)
+}
+
+/// The shape of every line of `text`, from its tokens, so that `//` inside
+/// a string is not taken for a comment.
+fn shapes(text: &str, lines: &[&str]) -> Vec<Shape> {
+	let mut starts = Vec::with_capacity(lines.len());
+	let mut at = 0;
+	for line in lines {
+		starts.push(at);
+		at += line.len() + 1;
+	}
+	let mut shapes: Vec<Shape> = lines
+		.iter()
+		.map(|line| {
+			if line.trim().is_empty() {
+				Shape::Blank
+			} else {
+				Shape::Code(None)
+			}
+		})
+		.collect();
+	for token in lex::tokens(text) {
+		if token.kind != TokenKind::Comment || !token.text.starts_with("//") {
+			continue;
+		}
+		let line = starts.partition_point(|&start| start <= token.start) - 1;
+		let column = token.start - starts[line];
+		shapes[line] = if lines[line][..column].trim().is_empty() {
+			Shape::Comment(column)
+		} else {
+			Shape::Code(Some(column))
+		};
+	}
+	shapes
+}
+
+/// The first line of `lines` that holds code.
+fn first_code(shapes: &[Shape], mut lines: impl Iterator<Item = usize>) -> Option<usize> {
+	lines.find(|&i| matches!(shapes[i], Shape::Code(_)))
+}
+
+/// Whether a comment is written in words, rather than being part of a
+/// diagram drawn down the side of the code like chapter 10's lifetimes.
+fn is_prose(comment: &str) -> bool {
+	let letters = comment.chars().filter(|c| c.is_alphabetic()).count();
+	let visible = comment.chars().filter(|c| !c.is_whitespace()).count();
+	letters * 2 >= visible && letters > 0
+}
+
+/// The words of a `//` comment.
+fn comment_text(comment: &str) -> &str {
+	comment.trim_start_matches('/').trim()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::Document;
+	use crate::source::NoteFile;
+
+	fn side_notes_of(code: &str) -> (String, Vec<SideNote>) {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_2_guessing_game.rs").unwrap(),
+			&format!("/*\nInput:*/\n\n{code}\n"),
+		);
+		let code = doc.code_blocks().next().unwrap();
+		side_notes(code)
+	}
+
+	#[test]
+	fn a_margin_run_becomes_a_note_on_the_line_above() {
+		let (code, notes) = side_notes_of(
+			"\tlet mut guess = String::new();\t\t\t\t//Here, we create a String variable to
+\t\t\t\t\t\t\t\t\t\t\t//store user input using let.
+\t\t\t\t\t\t\t\t\t\t\t
+\t\t\t\t\t\t\t\t\t\t\t//A second paragraph.
+\tlet x = 5; // a trailing comment that stays
+\tprintln!(\"// not a comment\");",
+		);
+		assert_eq!(
+			code,
+			"let mut guess = String::new();\nlet x = 5; // a trailing comment that stays\n\
+			 println!(\"// not a comment\");"
+		);
+		assert_eq!(
+			notes,
+			[SideNote {
+				line: 0,
+				paragraphs: vec![
+					"Here, we create a String variable to store user input using let.".to_string(),
+					"A second paragraph.".to_string(),
+				],
+			}]
+		);
+	}
+
+	#[test]
+	fn diagrams_and_comments_in_line_with_the_code_stay() {
+		let code = "\tfn main() {
+\t\tlet r;                \t\t\t\t// ---------+-- 'a
+\t\t                      \t\t\t\t//          |
+\t\t// An ordinary comment.
+\t\tr = 5;
+\t}";
+		let (text, notes) = side_notes_of(code);
+		assert!(notes.is_empty());
+		assert_eq!(text.lines().count(), 6);
+	}
+}
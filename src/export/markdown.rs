@@ -0,0 +1,445 @@
+//! An mdBook source tree: one Markdown chapter per notes file and a
+//! `SUMMARY.md` listing them in chapter order.
+//!
+//! ```text
+//! book.toml
+//! src/SUMMARY.md
+//! src/rust_chapter_1_project_setup.md
+//! ...
+//! src/glossary.md
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::check::Expectation;
+use crate::error::Result;
+use crate::glossary::{self, Place};
+use crate::model::{Block, Code, Document, Lang};
+use crate::prose::{self, Paragraph, Preformatted};
+use crate::rustdoc::{self, Index, Resolution, Resolver};
+
+use super::margin::{self, SideNote};
+use super::{by_document, is_nested, section, titles, with_links, write};
+
+const GLOSSARY_PAGE: &str = "glossary.md";
+
+const BOOK_TOML: &str = "\
+[book]
+title = \"Rust Notes\"
+language = \"en\"
+src = \"src\"
+";
+
+/// Writes the book for `docs` under `out` and returns the files written.
+/// With the `std` docs, the std items the prose mentions link to them.
+pub fn write_book(docs: &[Document], out: &Path, std: Option<&Index>) -> Result<Vec<PathBuf>> {
+	let mut written = Vec::new();
+	let mut put = |path: PathBuf, contents: &str| -> Result<()> {
+		write(&path, contents)?;
+		written.push(path);
+		Ok(())
+	};
+	put(out.join("book.toml"), BOOK_TOML)?;
+	put(out.join("src").join("SUMMARY.md"), &summary(docs))?;
+	for doc in docs {
+		put(out.join("src").join(page_name(doc)), &chapter(doc, std))?;
+	}
+	put(out.join("src").join(GLOSSARY_PAGE), &glossary(docs))?;
+	Ok(written)
+}
+
+/// Writes the chapter of `docs[i]` and the glossary, the pages that change
+/// when only that document does, and returns the files written.
+pub fn write_chapter(
+	docs: &[Document],
+	i: usize,
+	out: &Path,
+	std: Option<&Index>,
+) -> Result<Vec<PathBuf>> {
+	let chapter_path = out.join("src").join(page_name(&docs[i]));
+	write(&chapter_path, &chapter(&docs[i], std))?;
+	let glossary_path = out.join("src").join(GLOSSARY_PAGE);
+	write(&glossary_path, &glossary(docs))?;
+	Ok(vec![chapter_path, glossary_path])
+}
+
+/// The file name of a document's chapter, e.g. `rust_chapter_3_common_concepts.md`.
+pub fn page_name(doc: &Document) -> String {
+	format!("{}.md", doc.file.stem())
+}
+
+/// The table of contents, in the order of `docs`. Exercises are listed
+/// under the notes of their chapter.
+pub fn summary(docs: &[Document]) -> String {
+	let mut out = String::from("# Summary\n\n");
+	for doc in docs {
+		let indent = if is_nested(doc, docs) { "  " } else { "" };
+		let _ = writeln!(
+			out,
+			"{indent}- [{}]({})",
+			escape(&doc.file.title()),
+			page_name(doc)
+		);
+	}
+	let _ = writeln!(out, "- [Glossary]({GLOSSARY_PAGE})");
+	out
+}
+
+/// The glossary page: for every term, the section that introduces it and
+/// the sections that mention it before and after, grouped by chapter.
+pub fn glossary(docs: &[Document]) -> String {
+	let mut out = String::from("# Glossary\n");
+	for entry in glossary::glossary(docs) {
+		let _ = write!(
+			out,
+			"\n## {}\n\nIntroduced {}.\n",
+			escape(entry.term),
+			link(docs, &entry.defined)
+		);
+		for (label, places) in [
+			("Used again in", &entry.uses),
+			("Mentioned before in", &entry.earlier),
+		] {
+			if places.is_empty() {
+				continue;
+			}
+			let _ = writeln!(out, "\n{label}:\n");
+			for (doc, places) in by_document(places) {
+				let doc = &docs[doc];
+				let sections: Vec<String> = places
+					.iter()
+					.filter_map(|place| section(doc, place))
+					.map(|(anchor, title)| {
+						format!("[{}]({}#{anchor})", escape(&title), page_name(doc))
+					})
+					.collect();
+				let _ = write!(out, "- [{}]({})", escape(&doc.file.title()), page_name(doc));
+				if !sections.is_empty() {
+					let _ = write!(out, ": {}", sections.join(", "));
+				}
+				out.push('\n');
+			}
+		}
+	}
+	out
+}
+
+/// Where a glossary place is, as `under [Section](...) in [Chapter](...)`.
+fn link(docs: &[Document], place: &Place) -> String {
+	let doc = &docs[place.doc];
+	let chapter = format!("[{}]({})", escape(&doc.file.title()), page_name(doc));
+	match section(doc, place) {
+		Some((anchor, title)) => format!(
+			"under [{}]({}#{anchor}) in {chapter}",
+			escape(&title),
+			page_name(doc)
+		),
+		None => format!("in {chapter}"),
+	}
+}
+
+/// One notes file as a Markdown chapter.
+pub fn chapter(doc: &Document, std: Option<&Index>) -> String {
+	let mut out = format!("# {}\n", escape(&doc.file.title()));
+	let titles = titles(doc);
+	let resolver = std.map(|index| index.resolver(doc));
+	for block in &doc.blocks {
+		out.push('\n');
+		match block {
+			Block::Heading(heading) => {
+				let level = (heading.level + 1).min(6);
+				let _ = writeln!(out, "{} {}", "#".repeat(level), escape(&heading.title));
+			}
+			Block::Prose(prose) => paragraphs(&mut out, &prose.text, &titles, resolver.as_ref()),
+			Block::Code(code) => {
+				let (text, notes) = margin::side_notes(code);
+				let _ = write!(out, "```{}\n{text}\n```\n", fence_info(code));
+				side_notes(&mut out, &text, &notes);
+			}
+		}
+	}
+	out
+}
+
+pub(super) fn paragraphs(
+	out: &mut String,
+	text: &str,
+	titles: &HashMap<String, String>,
+	resolver: Option<&Resolver>,
+) {
+	let mut previous_item = false;
+	for (i, paragraph) in prose::paragraphs(text).iter().enumerate() {
+		let is_item = matches!(paragraph, Paragraph::Item { .. });
+		// Items of one list stay together; everything else is set apart.
+		if i > 0 && !(is_item && previous_item) {
+			out.push('\n');
+		}
+		previous_item = is_item;
+		match paragraph {
+			Paragraph::Text { text, .. } => {
+				let _ = writeln!(out, "{}", inline(text, titles, resolver));
+			}
+			Paragraph::Item { depth, text, .. } => {
+				let indent = "  ".repeat(*depth);
+				let _ = writeln!(out, "{indent}- {}", inline(text, titles, resolver));
+			}
+			Paragraph::Preformatted { kind, text } => {
+				let info = match kind {
+					Preformatted::Console => "console",
+					Preformatted::Plain => "text",
+				};
+				let _ = write!(out, "```{info}\n{text}\n```\n");
+			}
+		}
+	}
+}
+
+/// A piece of prose as Markdown, to be shown apart from its page: the
+/// references to headings stay as they are written.
+pub fn excerpt(text: &str) -> String {
+	let mut out = String::new();
+	paragraphs(&mut out, text, &HashMap::new(), None);
+	out.trim_end().to_string()
+}
+
+/// Escaped running text, with `<#anchor>` references and std items as
+/// links.
+fn inline(text: &str, titles: &HashMap<String, String>, resolver: Option<&Resolver>) -> String {
+	with_links(
+		text,
+		titles,
+		|plain| match resolver {
+			Some(resolver) => std_links(plain, resolver),
+			None => escape(plain),
+		},
+		|anchor, title| format!("[{}](#{anchor})", escape(title)),
+	)
+}
+
+/// Escapes prose, with the std items in it linked to their docs.
+fn std_links(text: &str, resolver: &Resolver) -> String {
+	let mut out = String::new();
+	let mut at = 0;
+	for reference in rustdoc::prose_references(text) {
+		if let Resolution::Linked { path, url } = resolver.resolve(&reference) {
+			out.push_str(&escape(&text[at..reference.range.start]));
+			let name = escape(&text[reference.range.clone()]);
+			let _ = write!(out, "[{name}]({url} \"{path}\")");
+			at = reference.range.end;
+		}
+	}
+	out.push_str(&escape(&text[at..]));
+	out
+}
+
+/// The margin comments of a code block, as a list after it: each note is
+/// headed by the line of code it explains.
+fn side_notes(out: &mut String, code: &str, notes: &[SideNote]) {
+	if !notes.is_empty() {
+		out.push('\n');
+	}
+	for note in notes {
+		let line = code.lines().nth(note.line).unwrap_or_default().trim();
+		for (i, paragraph) in note.paragraphs.iter().enumerate() {
+			if i == 0 {
+				let _ = writeln!(out, "- {} {}", code_span(line), escape(paragraph));
+			} else {
+				let _ = write!(out, "\n  {}\n", escape(paragraph));
+			}
+		}
+	}
+}
+
+/// `text` as inline code, with enough backticks around it to hold any it
+/// contains.
+fn code_span(text: &str) -> String {
+	let mut longest = 0;
+	let mut run = 0;
+	for c in text.chars() {
+		run = if c == '`' { run + 1 } else { 0 };
+		longest = longest.max(run);
+	}
+	let ticks = "`".repeat(longest + 1);
+	if longest > 0 {
+		format!("{ticks} {text} {ticks}")
+	} else {
+		format!("{ticks}{text}{ticks}")
+	}
+}
+
+/// The info string of a code fence, e.g. `rust,compile_fail` for a
+/// snippet annotated `expect-error`.
+fn fence_info(code: &Code) -> String {
+	let lang = code.lang();
+	let mut info = match lang {
+		Lang::Unknown => "text".to_string(),
+		_ => lang.name().to_string(),
+	};
+	if lang == Lang::Rust {
+		match Expectation::of(code) {
+			Expectation::Fails(_) => info.push_str(",compile_fail"),
+			Expectation::Skip(_) => info.push_str(",ignore"),
+			Expectation::Compiles => {}
+		}
+	}
+	info
+}
+
+/// Escapes the characters Markdown would otherwise read as markup.
+///
+/// An underscore inside a word, as in `add_to_waitlist`, is left alone
+/// since it cannot start emphasis there.
+pub fn escape(text: &str) -> String {
+	let chars: Vec<char> = text.chars().collect();
+	let mut out = String::with_capacity(text.len());
+	for (i, &c) in chars.iter().enumerate() {
+		let intraword = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+		let escape = match c {
+			'\\' | '`' | '*' | '[' | ']' | '<' | '>' | '|' => true,
+			'_' => !(i > 0 && intraword(chars.get(i - 1)) && intraword(chars.get(i + 1))),
+			'#' | '+' | '-' => i == 0,
+			// `1. ` at the start of a paragraph would begin a numbered list.
+			'.' | ')' => chars[..i].iter().all(char::is_ascii_digit) && i > 0,
+			_ => false,
+		};
+		if escape {
+			out.push('\\');
+		}
+		out.push(c);
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(name: &str, source: &str) -> Document {
+		Document::parse(NoteFile::from_path(name).unwrap(), source)
+	}
+
+	#[test]
+	fn a_chapter_keeps_headings_lists_and_fenced_code() {
+		let doc = doc(
+			"rust_chapter_3_common_concepts.rs",
+			"/*
+Variables:
+
+	Variables are immutable by
+	default, so *this* fails:
+
+	- a bullet
+		- a nested one
+*/
+
+	fn main() {
+		let x = 5;
+		x = 6; // expect-error: E0384
+	}
+",
+		);
+		assert_eq!(
+			chapter(&doc, None),
+			"# Chapter 3: Common Concepts
+
+## Variables
+
+Variables are immutable by default, so \\*this\\* fails:
+
+- a bullet
+  - a nested one
+
+```rust,compile_fail
+fn main() {
+    let x = 5;
+    x = 6;
+}
+```
+"
+		);
+	}
+
+	#[test]
+	fn exercises_are_listed_under_their_chapter_and_the_glossary_last() {
+		let docs = [
+			doc("rust_chapter_3_common_concepts.rs", ""),
+			doc("chapter_3_suggested_exercises.rs", ""),
+			doc("rust_chapter_4_ownership_rules.rs", ""),
+		];
+		assert_eq!(
+			summary(&docs),
+			"# Summary
+
+- [Chapter 3: Common Concepts](rust_chapter_3_common_concepts.md)
+  - [Chapter 3: Suggested Exercises](chapter_3_suggested_exercises.md)
+- [Chapter 4: Ownership Rules](rust_chapter_4_ownership_rules.md)
+- [Glossary](glossary.md)
+"
+		);
+	}
+
+	#[test]
+	fn margin_comments_follow_the_code_as_a_list() {
+		let doc = doc(
+			"rust_chapter_2_guessing_game.rs",
+			"/*
+Input:*/
+
+	let mut guess = String::new();\t\t\t\t//Here, we create a String
+\t\t\t\t\t\t\t\t\t\t\t//variable to store user input.
+	let `odd` = 1;\t\t\t\t\t\t//a name in `backticks`
+\t\t\t\t\t\t\t\t\t\t\t//is not Rust.
+",
+		);
+		assert!(chapter(&doc, None).ends_with(
+			"```rust
+let mut guess = String::new();
+let `odd` = 1;
+```
+
+- `let mut guess = String::new();` Here, we create a String variable to store user input.
+- `` let `odd` = 1; `` a name in \\`backticks\\` is not Rust.
+"
+		));
+	}
+
+	#[test]
+	fn escape_leaves_underscores_inside_words() {
+		assert_eq!(escape("add_to_waitlist"), "add_to_waitlist");
+		assert_eq!(escape("_private and <T>"), "\\_private and \\<T\\>");
+		assert_eq!(escape("1. not a list"), "1\\. not a list");
+		assert_eq!(escape("# not a heading"), "\\# not a heading");
+	}
+
+	#[test]
+	fn std_items_in_the_prose_link_to_their_docs() {
+		let html = crate::check::temp_dir().unwrap();
+		let std = html.join("std");
+		std::fs::create_dir_all(std.join("collections")).unwrap();
+		std::fs::write(
+			std.join("all.html"),
+			"<li><a href=\"collections/struct.HashMap.html\">collections::HashMap</a></li>",
+		)
+		.unwrap();
+		std::fs::write(
+			std.join("collections/struct.HashMap.html"),
+			"<section id=\"method.entry\"></section>",
+		)
+		.unwrap();
+		let index = Index::load(&html).unwrap();
+		let _ = std::fs::remove_dir_all(&html);
+
+		let doc = doc(
+			"rust_chapter_8_common_collections.rs",
+			"/*\nCounting:\n\n\tHashMap::entry finds a_key, and gen_range() is not std.\n*/\n",
+		);
+		assert!(chapter(&doc, Some(&index)).contains(
+			"[HashMap::entry](https://doc.rust-lang.org/std/collections/struct.HashMap.html\
+			 #method.entry \"std::collections::HashMap::entry\") finds a_key, and gen_range() \
+			 is not std."
+		));
+	}
+}
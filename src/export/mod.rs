@@ -0,0 +1,133 @@
+//! Turning the notes into formats meant for reading.
+
+pub mod anki;
+pub mod epub;
+pub mod highlight;
+pub mod html;
+pub mod margin;
+pub mod markdown;
+pub mod notebook;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::check::strip_annotation;
+use crate::error::{Error, Result};
+use crate::glossary::Place;
+use crate::model::{Code, Document};
+use crate::source::FileKind;
+use crate::text;
+use crate::xref::{self, Piece};
+
+/// Writes `contents` to `path`, creating its directory first.
+pub fn write(path: &Path, contents: &str) -> Result<()> {
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+	}
+	fs::write(path, contents).map_err(|e| Error::io(path, e))
+}
+
+/// The code of a block as a reader should see it: dedented, with tabs
+/// expanded and without the harness annotations.
+pub fn display_code(code: &Code) -> String {
+	let stripped: Vec<&str> = code.text.lines().map(strip_annotation).collect();
+	text::dedent(&stripped.join("\n"))
+}
+
+/// Whether `doc` is an exercises file listed under the notes of its chapter
+/// in a table of contents, rather than on its own.
+pub fn is_nested(doc: &Document, docs: &[Document]) -> bool {
+	doc.file.kind == FileKind::Exercises
+		&& docs.iter().any(|other| {
+			other.file.kind == FileKind::Notes && other.file.chapter == doc.file.chapter
+		})
+}
+
+/// The anchor of every heading of `doc`, in order.
+///
+/// These are the ids mdBook gives the same headings, so a link written for
+/// one export works in the other: lowercase, spaces become `-`, other
+/// punctuation is dropped, and repeats get `-1`, `-2`, ... appended.
+pub fn anchors(doc: &Document) -> Vec<String> {
+	let mut seen: Vec<String> = Vec::new();
+	let mut anchors = Vec::new();
+	for heading in doc.headings() {
+		let base = slug(&heading.title);
+		let repeats = seen.iter().filter(|id| **id == base).count();
+		seen.push(base.clone());
+		anchors.push(if repeats == 0 {
+			base
+		} else {
+			format!("{base}-{repeats}")
+		});
+	}
+	anchors
+}
+
+/// The anchor of a heading titled `title`, before repeats are numbered.
+pub fn slug(title: &str) -> String {
+	title
+		.chars()
+		.filter_map(|c| {
+			if c.is_alphanumeric() || c == '_' || c == '-' {
+				Some(c.to_ascii_lowercase())
+			} else if c.is_whitespace() {
+				Some('-')
+			} else {
+				None
+			}
+		})
+		.collect()
+}
+
+/// The title of every heading of `doc`, by anchor.
+pub fn titles(doc: &Document) -> HashMap<String, String> {
+	anchors(doc)
+		.into_iter()
+		.zip(doc.headings().map(|heading| heading.title.clone()))
+		.collect()
+}
+
+/// Renders prose that may hold `<#anchor>` references: `text` renders the
+/// plain parts, and `link` gets the anchor and title of every reference to
+/// a heading in `titles`. A reference to no known heading is kept as text.
+pub fn with_links(
+	prose: &str,
+	titles: &HashMap<String, String>,
+	text: impl Fn(&str) -> String,
+	link: impl Fn(&str, &str) -> String,
+) -> String {
+	let mut out = String::new();
+	for piece in xref::pieces(prose) {
+		match piece {
+			Piece::Text(plain) => out.push_str(&text(plain)),
+			Piece::Anchor(anchor) => match titles.get(anchor) {
+				Some(title) => out.push_str(&link(anchor, title)),
+				None => out.push_str(&text(&format!("<#{anchor}>"))),
+			},
+		}
+	}
+	out
+}
+
+/// The anchor and title of the section of `doc` a glossary place is in, or
+/// `None` for the part before its first heading.
+pub fn section(doc: &Document, place: &Place) -> Option<(String, String)> {
+	let index = place.heading?;
+	let anchor = anchors(doc).into_iter().nth(index)?;
+	let title = doc.headings().nth(index)?.title.clone();
+	Some((anchor, title))
+}
+
+/// Glossary places grouped by the document they are in, in order.
+pub fn by_document(places: &[Place]) -> Vec<(usize, Vec<&Place>)> {
+	let mut groups: Vec<(usize, Vec<&Place>)> = Vec::new();
+	for place in places {
+		match groups.last_mut() {
+			Some((doc, group)) if *doc == place.doc => group.push(place),
+			_ => groups.push((place.doc, vec![place])),
+		}
+	}
+	groups
+}
@@ -0,0 +1,278 @@
+//! Jupyter notebooks for the evcxr Rust kernel, one per notes file, so the
+//! snippets of a chapter can be run one cell at a time.
+//!
+//! Headings and prose become Markdown cells and Rust snippets code cells.
+//! evcxr runs a cell's statements as they come, so a snippet wrapped in
+//! `fn main() { ... }` is unwrapped to its body; the items beside it stay.
+//! A `Cargo.toml` excerpt is shown as it is, and its dependencies are added
+//! to the kernel with a `:dep` cell after it.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::check::{code_part, top_level_items, Expectation};
+use crate::error::Result;
+use crate::json;
+use crate::model::{Block, Code, Document, Lang};
+use crate::text;
+use crate::toml;
+
+use super::markdown::{self, escape};
+use super::{display_code, titles, write};
+
+/// Writes one notebook per document under `out` and returns the files
+/// written.
+pub fn write_notebooks(docs: &[Document], out: &Path) -> Result<Vec<PathBuf>> {
+	let mut written = Vec::new();
+	for doc in docs {
+		let path = out.join(file_name(doc));
+		write(&path, &notebook(doc))?;
+		written.push(path);
+	}
+	Ok(written)
+}
+
+/// The file name of a document's notebook, e.g. `rust_chapter_2_guessing_game.ipynb`.
+pub fn file_name(doc: &Document) -> String {
+	format!("{}.ipynb", doc.file.stem())
+}
+
+/// A cell of a notebook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+	Markdown(String),
+	Code(String),
+}
+
+/// The cells of a document, in order. Neighbouring headings and prose share
+/// a Markdown cell.
+pub fn cells(doc: &Document) -> Vec<Cell> {
+	let titles = titles(doc);
+	let mut cells = Vec::new();
+	let mut text = format!("# {}\n", escape(&doc.file.title()));
+	let flush = |text: &mut String, cells: &mut Vec<Cell>| {
+		if !text.trim().is_empty() {
+			let text = text.trim_start_matches('\n').trim_end();
+			cells.push(Cell::Markdown(text.to_string()));
+		}
+		text.clear();
+	};
+	for block in &doc.blocks {
+		match block {
+			Block::Heading(heading) => {
+				let level = (heading.level + 1).min(6);
+				let _ = write!(text, "\n{} {}\n", "#".repeat(level), escape(&heading.title));
+			}
+			Block::Prose(prose) => {
+				text.push('\n');
+				markdown::paragraphs(&mut text, &prose.text, &titles, None);
+			}
+			Block::Code(code) if code.lang() == Lang::Rust => {
+				flush(&mut text, &mut cells);
+				cells.push(Cell::Code(runnable(code)));
+			}
+			Block::Code(code) => {
+				let info = match code.lang() {
+					Lang::Unknown => "text",
+					lang => lang.name(),
+				};
+				let _ = write!(text, "\n```{info}\n{}\n```\n", display_code(code));
+				if let Some(deps) = dependencies(code) {
+					flush(&mut text, &mut cells);
+					cells.push(Cell::Code(deps));
+				}
+			}
+		}
+	}
+	flush(&mut text, &mut cells);
+	cells
+}
+
+/// A snippet as a code cell: unwrapped from `fn main`, and headed by a
+/// comment if it is not meant to compile.
+fn runnable(code: &Code) -> String {
+	let text = unwrap_main(&display_code(code));
+	match Expectation::of(code) {
+		Expectation::Fails(codes) if codes.is_empty() => {
+			format!("// This does not compile.\n{text}")
+		}
+		Expectation::Fails(codes) => {
+			format!("// This does not compile: {}.\n{text}", codes.join(", "))
+		}
+		_ => text,
+	}
+}
+
+/// `text` with a top-level `fn main() { ... }` replaced by its dedented
+/// body; attributes on `main` go with it. A `main` that returns something, or whose braces share a line with
+/// its body, is kept and called at the end instead.
+pub fn unwrap_main(text: &str) -> String {
+	let Some(main) = top_level_items(text)
+		.into_iter()
+		.find(|item| item.key == "main")
+	else {
+		return text.to_string();
+	};
+	let lines: Vec<&str> = main.text.lines().collect();
+	let header = lines
+		.iter()
+		.position(|line| code_part(line).contains("fn main"));
+	let unwrappable = |line: &str| {
+		let line = code_part(line).trim_end();
+		line.ends_with('{') && !line.contains("->")
+	};
+	let body = match header {
+		Some(header) if unwrappable(lines[header]) => {
+			let last = lines.len() - 1;
+			(last > header && code_part(lines[last]).trim() == "}").then(|| {
+				text::dedent(&lines[header + 1..last].join("\n"))
+					.trim_matches('\n')
+					.to_string()
+			})
+		}
+		_ => None,
+	};
+	match body {
+		Some(body) => {
+			// The item takes the blank lines after it along.
+			let blank = &main.text[main.text.trim_end_matches('\n').len()..];
+			text.replacen(&main.text, &format!("{body}{blank}"), 1)
+		}
+		None => format!("{}\nmain()", text.trim_end()),
+	}
+}
+
+/// A `:dep` line for each dependency of a `Cargo.toml` excerpt, or `None` if
+/// it has none.
+fn dependencies(code: &Code) -> Option<String> {
+	if code.lang() != Lang::Toml {
+		return None;
+	}
+	let manifest = toml::parse(&text::dedent(&code.text)).ok()?;
+	let deps = manifest.get("dependencies")?.as_table()?;
+	let lines: Vec<String> = deps
+		.entries
+		.iter()
+		.map(|entry| format!(":dep {} = {}", toml::format_key(&entry.key), entry.value))
+		.collect();
+	(!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The notebook of a document, as nbformat 4 JSON.
+pub fn notebook(doc: &Document) -> String {
+	let mut out = String::from("{\n \"cells\": [");
+	for (i, cell) in cells(doc).iter().enumerate() {
+		let (kind, source, extra) = match cell {
+			Cell::Markdown(source) => ("markdown", source, ""),
+			Cell::Code(source) => (
+				"code",
+				source,
+				"   \"execution_count\": null,\n   \"outputs\": [],\n",
+			),
+		};
+		let lines: Vec<String> = source.split_inclusive('\n').map(json::string).collect();
+		let _ = write!(
+			out,
+			"{}\n  {{\n   \"cell_type\": \"{kind}\",\n{extra}   \"metadata\": {{}},\n   \"source\": [{}]\n  }}",
+			if i == 0 { "" } else { "," },
+			lines.join(", ")
+		);
+	}
+	out.push_str(
+		"\n ],
+ \"metadata\": {
+  \"kernelspec\": {
+   \"display_name\": \"Rust\",
+   \"language\": \"rust\",
+   \"name\": \"rust\"
+  },
+  \"language_info\": {
+   \"codemirror_mode\": \"rust\",
+   \"file_extension\": \".rs\",
+   \"mimetype\": \"text/rust\",
+   \"name\": \"Rust\",
+   \"pygment_lexer\": \"rust\",
+   \"version\": \"\"
+  }
+ },
+ \"nbformat\": 4,
+ \"nbformat_minor\": 4
+}
+",
+	);
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(source: &str) -> Document {
+		Document::parse(
+			NoteFile::from_path("rust_chapter_2_guessing_game.rs").unwrap(),
+			source,
+		)
+	}
+
+	#[test]
+	fn snippets_become_cells_run_as_statements() {
+		let doc = doc("/*
+Setup:
+
+	Add rand to Cargo.toml:*/
+
+	[dependencies]
+	rand = \"0.8.5\"
+
+	/*Then guess:*/
+
+	use std::io;
+
+	fn main() {
+		let guess = 5;
+		println!(\"{guess}\");
+	}
+
+	/*But not twice:*/
+
+	let x = 5;
+	x = 6; // expect-error: E0384
+");
+		assert_eq!(
+			cells(&doc),
+			[
+				Cell::Markdown(
+					"# Chapter 2: Guessing Game\n\n## Setup\n\nAdd rand to Cargo.toml:\n\n\
+					 ```toml\n[dependencies]\nrand = \"0.8.5\"\n```"
+						.to_string()
+				),
+				Cell::Code(":dep rand = \"0.8.5\"".to_string()),
+				Cell::Markdown("## Then guess".to_string()),
+				Cell::Code("use std::io;\n\nlet guess = 5;\nprintln!(\"{guess}\");".to_string()),
+				Cell::Markdown("## But not twice".to_string()),
+				Cell::Code("// This does not compile: E0384.\nlet x = 5;\nx = 6;".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn a_main_that_returns_is_called_instead() {
+		let text = "fn main() -> Result<(), String> {\n    Ok(())\n}";
+		assert_eq!(unwrap_main(text), format!("{text}\nmain()"));
+		assert_eq!(unwrap_main("fn other() {}"), "fn other() {}");
+	}
+
+	#[test]
+	fn notebooks_are_nbformat_4() {
+		let notebook = notebook(&doc("/*\nSetup:\n\n\tSay \"hi\".\n*/\n\n\tlet x = 1;\n"));
+		assert!(notebook.contains(
+			"   \"cell_type\": \"markdown\",\n   \"metadata\": {},\n   \"source\": \
+			 [\"# Chapter 2: Guessing Game\\n\", \"\\n\", \"## Setup\\n\", \"\\n\", \"Say \\\"hi\\\".\"]"
+		));
+		assert!(notebook.contains(
+			"   \"cell_type\": \"code\",\n   \"execution_count\": null,\n   \"outputs\": [],\n"
+		));
+		assert!(notebook.ends_with(" \"nbformat\": 4,\n \"nbformat_minor\": 4\n}\n"));
+	}
+}
@@ -0,0 +1,497 @@
+//! Formatting a notes file: prose rewrapped to a width, code laid out the
+//! way `rustfmt` lays it out.
+//!
+//! Only what can change without changing what the file says is touched. A
+//! paragraph of running text in a `/* ... */` comment is rewrapped at its
+//! own indentation. Headings, bullets, compiler output, anything laid out in
+//! columns and paragraphs whose lines are indented differently keep their
+//! lines, and so does a paragraph ending in a colon that is, or would come
+//! out, short enough to be read as a title.
+//!
+//! Each snippet goes through `rustfmt` (`$RUSTFMT` if set), as it is or, for
+//! a run of statements, inside a `fn main`, and is put back at the
+//! indentation it had. An annotation such as `// expect-error: E0384` that
+//! rustfmt moves off the `{` line it was on is put back there. A snippet
+//! rustfmt cannot parse only has its indentation made tabs. A snippet with
+//! a run of comments down its margin, as [`margin`] finds them, is left as
+//! it is: rustfmt would pull the run in to the code.
+//!
+//! Rewrapping and rustfmt move lines, so the prose's references to lines of
+//! its own file, as [`xref`] finds them, are renumbered to where their
+//! targets went. A paragraph or snippet that would change a line a
+//! reference points at, so that there is no telling where it went, keeps its
+//! lines instead.
+//!
+//! A file with `\r\n` line endings is not formatted at all.
+//!
+//! Formatting a formatted file changes nothing.
+
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::check::{annotation, ANNOTATIONS};
+use crate::export::margin;
+use crate::model::{Block, Document, Lines, ProseStyle};
+use crate::parse::{indent_of, is_blank};
+use crate::prose::{self, Paragraph};
+use crate::source::NoteFile;
+use crate::text::{self, TAB_WIDTH};
+use crate::xref::{self, LineRef};
+
+/// The width prose is wrapped to unless told otherwise.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// The width code is formatted to, as rustfmt's own default.
+const CODE_WIDTH: usize = 100;
+
+/// What [`Formatter::format`] made of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatted {
+	pub source: String,
+	/// The references that kept a paragraph or snippet, the lines of which
+	/// are given, from being formatted.
+	pub kept: Vec<(LineRef, Lines)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Formatter {
+	width: usize,
+	rustfmt: PathBuf,
+}
+
+impl Default for Formatter {
+	fn default() -> Formatter {
+		Formatter::new()
+	}
+}
+
+impl Formatter {
+	pub fn new() -> Formatter {
+		Formatter {
+			width: DEFAULT_WIDTH,
+			rustfmt: env::var_os("RUSTFMT").map_or_else(|| PathBuf::from("rustfmt"), PathBuf::from),
+		}
+	}
+
+	/// Wraps prose to `width` columns, counting a tab as four.
+	pub fn width(mut self, width: usize) -> Formatter {
+		self.width = width;
+		self
+	}
+
+	/// The formatted text of `file`, which reads `source`; `None` if the
+	/// file has `\r\n` line endings.
+	pub fn format(&self, file: &NoteFile, source: &str) -> Option<Formatted> {
+		if source.contains('\r') {
+			return None;
+		}
+		let doc = Document::parse(file.clone(), source);
+		let refs: Vec<LineRef> = xref::line_refs(&doc, source)
+			.into_iter()
+			.filter(|reference| reference.other_file.is_none())
+			.collect();
+		let lines: Vec<&str> = source.split('\n').collect();
+		let mut out: Vec<String> = Vec::with_capacity(lines.len());
+		// The line each line went to, counted from 1.
+		let mut moved: Vec<usize> = Vec::with_capacity(lines.len());
+		let mut kept = Vec::new();
+		// The next line to copy as it is, counted from 0.
+		let mut next = 0;
+		for block in &doc.blocks {
+			let (range, new) = match block {
+				Block::Prose(prose) if prose.style == ProseStyle::Block => {
+					(prose.lines, Some(self.prose(slice(&lines, prose.lines))))
+				}
+				Block::Code(code) => {
+					let old = slice(&lines, code.lines);
+					// A snippet sharing a line with a comment delimiter is
+					// left alone.
+					let whole = old.join("\n") == code.text;
+					// rustfmt would pull a run of margin comments in to the
+					// code, and the exports would lose the run's side note.
+					let margin = !margin::side_notes(code).1.is_empty();
+					(
+						code.lines,
+						(whole && !margin).then(|| self.code(old)).flatten(),
+					)
+				}
+				_ => continue,
+			};
+			let Some(new) = new else {
+				continue;
+			};
+			let first = out.len() + range.start - next;
+			let places = match places(slice(&lines, range), &new, range, &refs) {
+				Ok(places) => places,
+				Err(reference) => {
+					kept.push((reference.clone(), range));
+					continue;
+				}
+			};
+			for line in &lines[next..range.start - 1] {
+				out.push(line.to_string());
+				moved.push(out.len());
+			}
+			moved.extend(places.into_iter().map(|place| first + place));
+			out.extend(new);
+			next = range.end;
+		}
+		for line in &lines[next..] {
+			out.push(line.to_string());
+			moved.push(out.len());
+		}
+		Some(Formatted {
+			source: xref::renumber(&doc, &out.join("\n"), &moved),
+			kept,
+		})
+	}
+
+	/// The lines of a prose block with its running text rewrapped.
+	fn prose(&self, lines: &[&str]) -> Vec<String> {
+		let mut out = Vec::new();
+		let mut i = 0;
+		while i < lines.len() {
+			if is_blank(lines[i]) {
+				out.push(lines[i].to_string());
+				i += 1;
+				continue;
+			}
+			// A paragraph ends at a blank line, or where an `A:` answer
+			// starts under its question.
+			let start = i;
+			i += 1;
+			while i < lines.len() && !is_blank(lines[i]) && !lines[i].trim_start().starts_with("A:")
+			{
+				i += 1;
+			}
+			let paragraph = &lines[start..i];
+			match self.reflow(paragraph) {
+				Some(new) => out.extend(new),
+				None => out.extend(paragraph.iter().map(|line| line.to_string())),
+			}
+		}
+		out
+	}
+
+	/// A paragraph rewrapped, or `None` if it has to keep its lines.
+	pub(crate) fn reflow(&self, paragraph: &[&str]) -> Option<Vec<String>> {
+		let last = paragraph.last()?.trim_end();
+		// The comment may close at the end of the paragraph, as in
+		// "...won't work:*/"; anywhere else it stays where it is.
+		let (last, close) = match last.strip_suffix("*/") {
+			Some(body) => (
+				body.trim_end(),
+				&paragraph[paragraph.len() - 1][body.trim_end().len()..],
+			),
+			None => (last, ""),
+		};
+		let mut body: Vec<&str> = paragraph.to_vec();
+		*body.last_mut()? = last;
+		if body
+			.iter()
+			.any(|line| line.contains("/*") || line.contains("*/"))
+		{
+			return None;
+		}
+		let indent = leading(body[0]);
+		if body.iter().any(|line| leading(line) != indent) {
+			return None;
+		}
+		let paragraphs = prose::paragraphs(&body.join("\n"));
+		let [Paragraph::Text { text, .. }] = &paragraphs[..] else {
+			return None;
+		};
+
+		let indent = "\t".repeat(indent_of(indent));
+		let room = self
+			.width
+			.saturating_sub(text::expand_tabs(&indent).len())
+			.max(1);
+		let mut new: Vec<String> = text::wrap(text, room)
+			.into_iter()
+			.map(|line| format!("{indent}{line}"))
+			.collect();
+		// A title is at most two lines ending in a colon; a paragraph that is
+		// or could become one keeps its lines.
+		if text.ends_with(':') && paragraph.len().min(new.len()) <= 2 {
+			return None;
+		}
+		new.last_mut()?.push_str(close.trim_end());
+		Some(new)
+	}
+
+	/// A snippet formatted, or `None` to leave it as it is.
+	fn code(&self, lines: &[&str]) -> Option<Vec<String>> {
+		let base = lines
+			.iter()
+			.filter(|line| !is_blank(line))
+			.map(|line| indent_of(line))
+			.min()?;
+		let code: Vec<String> = lines.iter().map(|line| strip_levels(line, base)).collect();
+		let code = code.join("\n") + "\n";
+		let width = CODE_WIDTH.saturating_sub(base * TAB_WIDTH);
+
+		let formatted = match self.rustfmt(&code, width) {
+			Some(formatted) => formatted,
+			None => match self.rustfmt(&format!("fn main() {{\n{code}}}\n"), width + TAB_WIDTH) {
+				Some(wrapped) => {
+					let inner: Vec<&str> = wrapped.lines().collect();
+					let inner = inner.get(1..inner.len().checked_sub(1)?)?;
+					inner
+						.iter()
+						.map(|line| strip_levels(line, 1))
+						.collect::<Vec<_>>()
+						.join("\n")
+				}
+				None => code
+					.lines()
+					.map(tabs_for_spaces)
+					.collect::<Vec<_>>()
+					.join("\n"),
+			},
+		};
+
+		let trailing = trailing_annotations(&code);
+		let mut out: Vec<String> = Vec::new();
+		for line in formatted.lines() {
+			let comment = line.trim_start();
+			if let Some(previous) = out.last_mut() {
+				if previous.trim_end().ends_with('{') && trailing.iter().any(|t| t == comment) {
+					previous.push(' ');
+					previous.push_str(comment);
+					continue;
+				}
+			}
+			out.push(if is_blank(line) {
+				String::new()
+			} else {
+				format!("{}{line}", "\t".repeat(base))
+			});
+		}
+		Some(out)
+	}
+
+	/// Runs rustfmt over `code`; `None` if it cannot parse it or is not
+	/// installed, or if what it gives back is not to be trusted: rustfmt
+	/// outdents a comment alone in a `match` inside a `loop`, with the
+	/// braces after it, and formats its own output differently.
+	fn rustfmt(&self, code: &str, width: usize) -> Option<String> {
+		let once = self.rustfmt_once(code, width)?;
+		(braces_line_up(&once) && self.rustfmt_once(&once, width)? == once).then_some(once)
+	}
+
+	fn rustfmt_once(&self, code: &str, width: usize) -> Option<String> {
+		let mut child = Command::new(&self.rustfmt)
+			.args([
+				"--edition",
+				"2021",
+				"--emit",
+				"stdout",
+				"--quiet",
+				"--config",
+			])
+			// Reordered imports would leave a comment on the line of another.
+			.arg(format!(
+				"hard_tabs=true,max_width={width},reorder_imports=false,reorder_modules=false"
+			))
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.spawn()
+			.ok()?;
+		child.stdin.take()?.write_all(code.as_bytes()).ok()?;
+		let output = child.wait_with_output().ok()?;
+		if !output.status.success() {
+			return None;
+		}
+		String::from_utf8(output.stdout).ok()
+	}
+}
+
+/// Where each of the `old` lines of `range` is among the `new` lines it was
+/// formatted to, counted from 0. A line that was changed is put where its
+/// place in `old` would be, unless one of `refs` points at it: that
+/// reference is given back instead.
+fn places<'a>(
+	old: &[&str],
+	new: &[String],
+	range: Lines,
+	refs: &'a [LineRef],
+) -> Result<Vec<usize>, &'a LineRef> {
+	let mut places = Vec::with_capacity(old.len());
+	// Where to look for the next line that was kept.
+	let mut from = 0;
+	for (offset, line) in old.iter().enumerate() {
+		let found = (!is_blank(line))
+			.then(|| {
+				new[from..]
+					.iter()
+					.position(|other| other.trim() == line.trim())
+			})
+			.flatten();
+		if let Some(at) = found {
+			from += at + 1;
+			places.push(from - 1);
+			continue;
+		}
+		let number = range.start + offset;
+		if let Some(reference) = refs
+			.iter()
+			.find(|reference| [reference.target.start, reference.target.end - 1].contains(&number))
+		{
+			return Err(reference);
+		}
+		places.push(offset.min(new.len().saturating_sub(1)));
+	}
+	Ok(places)
+}
+
+/// The lines of `range`, counted from 1, out of `lines`.
+fn slice<'a>(lines: &'a [&'a str], range: Lines) -> &'a [&'a str] {
+	&lines[range.start - 1..range.end]
+}
+
+/// The whitespace `line` starts with.
+fn leading(line: &str) -> &str {
+	&line[..line.len() - line.trim_start().len()]
+}
+
+/// `line` without `levels` levels of indentation, a tab or four spaces each.
+fn strip_levels(line: &str, levels: usize) -> String {
+	let mut rest = line;
+	for _ in 0..levels {
+		rest = match rest.strip_prefix('\t') {
+			Some(rest) => rest,
+			None => rest.strip_prefix("    ").unwrap_or(rest.trim_start()),
+		};
+	}
+	rest.to_string()
+}
+
+/// `line` with its indentation written as tabs, and spaces only for what is
+/// left over, and without trailing whitespace.
+fn tabs_for_spaces(line: &str) -> String {
+	let body = line.trim();
+	if body.is_empty() {
+		return String::new();
+	}
+	let columns = text::expand_tabs(leading(line)).len();
+	format!(
+		"{}{}{body}",
+		"\t".repeat(columns / TAB_WIDTH),
+		" ".repeat(columns % TAB_WIDTH)
+	)
+}
+
+/// Whether every line that starts with `}` is indented like the line ending
+/// in the `{` it closes.
+fn braces_line_up(code: &str) -> bool {
+	let mut open: Vec<&str> = Vec::new();
+	for line in code.lines() {
+		let body = line.trim();
+		if body.starts_with('}') && open.pop() != Some(leading(line)) {
+			return false;
+		}
+		if body.ends_with('{') {
+			open.push(leading(line));
+		}
+	}
+	true
+}
+
+/// The annotation comments of `code` that follow code on their line, such
+/// as the `// expect-error: E0384` of `fn main() { // expect-error: E0384`.
+fn trailing_annotations(code: &str) -> Vec<String> {
+	let mut found = Vec::new();
+	for line in code.lines() {
+		let body = line.trim_start();
+		if body.starts_with("//") {
+			continue;
+		}
+		for (at, _) in line.match_indices("//") {
+			let comment = &line[at..];
+			if ANNOTATIONS
+				.iter()
+				.any(|name| annotation(comment, name).is_some())
+			{
+				found.push(comment.trim_end().to_string());
+				break;
+			}
+		}
+	}
+	found
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::export::margin::SideNote;
+
+	const CHAPTER_2: &str = include_str!("../rust_chapter_2_guessing_game.rs");
+
+	fn format(source: &str) -> String {
+		let file = NoteFile::from_path("rust_chapter_2_guessing_game.rs").unwrap();
+		Formatter::new().format(&file, source).unwrap().source
+	}
+
+	fn side_notes(source: &str) -> Vec<Vec<SideNote>> {
+		let file = NoteFile::from_path("rust_chapter_2_guessing_game.rs").unwrap();
+		Document::parse(file, source)
+			.code_blocks()
+			.map(|code| margin::side_notes(code).1)
+			.collect()
+	}
+
+	#[test]
+	fn formatting_keeps_the_side_notes() {
+		let before = side_notes(CHAPTER_2);
+		assert!(before.iter().any(|notes| !notes.is_empty()));
+		assert_eq!(side_notes(&format(CHAPTER_2)), before);
+	}
+
+	#[test]
+	fn formatting_keeps_the_imports_in_order() {
+		// Each import with the words of its comment.
+		let imports = |source: &str| -> Vec<Vec<String>> {
+			source
+				.lines()
+				.filter(|line| line.trim_start().starts_with("use "))
+				.map(|line| line.split_whitespace().map(str::to_string).collect())
+				.collect()
+		};
+		let formatted = format(CHAPTER_2);
+		assert_eq!(imports(&formatted), imports(CHAPTER_2));
+	}
+
+	#[test]
+	fn references_to_lines_follow_them() {
+		let file = NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap();
+		let source =
+			"/*\nBasics:\n\n\tOne two three four five six seven eight nine ten eleven.\n\n\
+		              \tThe code on line 9 prints.\n*/\n\n\tprintln!(\"hi\");\n";
+		let formatted = Formatter::new().width(40).format(&file, source).unwrap();
+		assert!(formatted.kept.is_empty());
+		let lines: Vec<&str> = formatted.source.lines().collect();
+		assert_eq!(lines[6], "\tThe code on line 10 prints.");
+		assert_eq!(lines[9], "\tprintln!(\"hi\");");
+
+		// A reference to a line that rewrapping would change keeps its
+		// paragraph as it is.
+		let source = source.replace("line 9", "line 4");
+		let formatted = Formatter::new().width(40).format(&file, &source).unwrap();
+		assert_eq!(formatted.source, source);
+		assert_eq!(formatted.kept.len(), 1);
+		assert_eq!(formatted.kept[0].1, Lines::new(4, 6));
+	}
+
+	#[test]
+	fn files_with_crlf_line_endings_are_skipped() {
+		let file = NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap();
+		assert_eq!(
+			Formatter::new().format(&file, "/*\r\nNotes\r\n*/\r\n"),
+			None
+		);
+	}
+}
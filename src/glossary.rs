@@ -0,0 +1,393 @@
+//! A glossary of the concepts the notes introduce, with backlinks.
+//!
+//! Each term of [`TERMS`] is traced through the notes in chapter order. Its
+//! definition is the first heading that names it, such as "Shadowing" or
+//! "The match Control Flow Construct"; failing that, a `Term: definition`
+//! line; failing that, the first paragraph that mentions it. Every section
+//! after the definition that mentions the term is a use of it. Mentions
+//! before the definition, like the `String`s of the early chapters, are
+//! kept apart.
+//!
+//! Type and keyword names like `Result` or `match` are matched exactly, in
+//! prose and code. Concepts like "trait object" are matched in the prose
+//! and in comments, in any case, in the plural too, and across line breaks.
+
+use std::ops::Range;
+
+use crate::cards;
+use crate::lex::{self, TokenKind};
+use crate::model::{Block, Document};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermKind {
+	/// A name from the language or the standard library.
+	Code,
+	/// An idea, written in plain words.
+	Concept,
+}
+
+/// The terms the glossary follows: the name shown, how it is matched, and
+/// other forms of it found in the notes.
+pub const TERMS: &[(&str, TermKind, &[&str])] = &[
+	("Result", TermKind::Code, &[]),
+	("Option", TermKind::Code, &[]),
+	("match", TermKind::Code, &[]),
+	("if let", TermKind::Code, &[]),
+	("String", TermKind::Code, &[]),
+	("Vec", TermKind::Code, &[]),
+	("HashMap", TermKind::Code, &[]),
+	("Copy", TermKind::Code, &[]),
+	("Clone", TermKind::Code, &[]),
+	("Drop", TermKind::Code, &[]),
+	("impl", TermKind::Code, &[]),
+	("unwrap", TermKind::Code, &[]),
+	("expect", TermKind::Code, &[]),
+	("array", TermKind::Concept, &[]),
+	("associated function", TermKind::Concept, &[]),
+	("blanket implementation", TermKind::Concept, &[]),
+	("borrow checker", TermKind::Concept, &[]),
+	("borrowing", TermKind::Concept, &["borrow", "borrowed"]),
+	("closure", TermKind::Concept, &[]),
+	("crate", TermKind::Concept, &[]),
+	("dangling reference", TermKind::Concept, &[]),
+	("deref coercion", TermKind::Concept, &[]),
+	("enum", TermKind::Concept, &["enumeration"]),
+	("expression", TermKind::Concept, &[]),
+	("generics", TermKind::Concept, &["generic"]),
+	("hash map", TermKind::Concept, &[]),
+	("heap", TermKind::Concept, &[]),
+	("iterator", TermKind::Concept, &[]),
+	("lifetime", TermKind::Concept, &[]),
+	("lifetime elision", TermKind::Concept, &[]),
+	("method", TermKind::Concept, &[]),
+	("module", TermKind::Concept, &[]),
+	("ownership", TermKind::Concept, &[]),
+	("panic", TermKind::Concept, &[]),
+	("shadowing", TermKind::Concept, &["shadow", "shadowed"]),
+	("slice", TermKind::Concept, &[]),
+	("statement", TermKind::Concept, &[]),
+	("struct", TermKind::Concept, &[]),
+	("trait", TermKind::Concept, &[]),
+	("trait bound", TermKind::Concept, &[]),
+	("trait object", TermKind::Concept, &[]),
+	("tuple", TermKind::Concept, &[]),
+];
+
+/// A section of one of the documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Place {
+	/// Index into the documents the glossary was built from.
+	pub doc: usize,
+	/// Index of the section's heading among the document's headings; `None`
+	/// before the first one.
+	pub heading: Option<usize>,
+	/// The first line in the section that mentions the term.
+	pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+	pub term: &'static str,
+	pub defined: Place,
+	/// The sections before the definition that mention the term in passing.
+	pub earlier: Vec<Place>,
+	/// The later sections that mention the term, in order.
+	pub uses: Vec<Place>,
+}
+
+/// The glossary of `docs`, which should be in chapter order, sorted by
+/// term. Terms the notes never mention are left out.
+pub fn glossary(docs: &[Document]) -> Vec<Entry> {
+	let mut entries: Vec<Entry> = TERMS
+		.iter()
+		.filter_map(|&(term, kind, other)| entry(docs, term, kind, other))
+		.collect();
+	entries.sort_by_key(|entry| entry.term.to_lowercase());
+	entries
+}
+
+/// How a section mentions a term, from the strongest claim to define it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Mention {
+	Heading,
+	Definition,
+	Prose,
+	Code,
+}
+
+fn entry(docs: &[Document], term: &'static str, kind: TermKind, other: &[&str]) -> Option<Entry> {
+	let spellings: Vec<Vec<&str>> = std::iter::once(term)
+		.chain(other.iter().copied())
+		.map(|spelling| spelling.split(' ').collect())
+		.collect();
+	let matches = |text: &str| -> Option<usize> {
+		spellings
+			.iter()
+			.filter_map(|words| find(text, words, kind))
+			.min()
+	};
+
+	// Every section that mentions the term, with its strongest mention.
+	let mut sections: Vec<(Place, Mention)> = Vec::new();
+	for (doc_index, doc) in docs.iter().enumerate() {
+		let mut heading = None;
+		let mut current: Option<(Place, Mention)> = None;
+		for block in &doc.blocks {
+			let found = match block {
+				Block::Heading(h) => {
+					sections.extend(current.take());
+					heading = Some(heading.map_or(0, |i| i + 1));
+					matches(&h.title).map(|_| (h.lines.start, Mention::Heading))
+				}
+				Block::Prose(prose) => {
+					let definition = prose.text.lines().enumerate().find(|(_, line)| {
+						cards::definition(line.trim_start_matches([' ', '\t', '-']))
+							.is_some_and(|(name, _)| matches(name).is_some())
+					});
+					match definition {
+						Some((i, _)) => Some((prose.lines.start + i, Mention::Definition)),
+						None => matches(&prose.text).map(|at| {
+							let line = prose.lines.start + prose.text[..at].matches('\n').count();
+							(line, Mention::Prose)
+						}),
+					}
+				}
+				Block::Code(code) if kind == TermKind::Code => matches(&code.text).map(|at| {
+					let line = code.lines.start + code.text[..at].matches('\n').count();
+					(line, Mention::Code)
+				}),
+				Block::Code(code) => lex::tokens(&code.text)
+					.into_iter()
+					.filter(|token| token.kind == TokenKind::Comment)
+					.find_map(|token| matches(token.text).map(|at| token.start + at))
+					.map(|at| {
+						let line = code.lines.start + code.text[..at].matches('\n').count();
+						(line, Mention::Code)
+					}),
+			};
+			let Some((line, mention)) = found else {
+				continue;
+			};
+			let place = Place {
+				doc: doc_index,
+				heading,
+				line,
+			};
+			// A section is placed at its strongest mention, or at its first
+			// one if they are all alike.
+			match current {
+				Some((_, strongest)) if strongest <= mention => {}
+				_ => current = Some((place, mention)),
+			}
+		}
+		sections.extend(current);
+	}
+
+	let defined = [
+		Mention::Heading,
+		Mention::Definition,
+		Mention::Prose,
+		Mention::Code,
+	]
+	.iter()
+	.find_map(|&wanted| sections.iter().position(|(_, mention)| *mention == wanted))?;
+	Some(Entry {
+		term,
+		defined: sections[defined].0,
+		earlier: sections[..defined]
+			.iter()
+			.map(|(place, _)| *place)
+			.collect(),
+		uses: sections[defined + 1..]
+			.iter()
+			.map(|(place, _)| *place)
+			.collect(),
+	})
+}
+
+/// The byte offset of the first place `words` appear in `text` one after
+/// the other, whatever the whitespace between them.
+fn find(text: &str, words: &[&str], kind: TermKind) -> Option<usize> {
+	occurrences(text, words, kind)
+		.first()
+		.map(|range| range.start)
+}
+
+/// Every place `words` appear in `text` one after the other.
+fn occurrences(text: &str, words: &[&str], kind: TermKind) -> Vec<Range<usize>> {
+	let found: Vec<(usize, &str)> = split_words(text);
+	found
+		.windows(words.len())
+		.filter(|window| {
+			window
+				.iter()
+				.zip(words)
+				.enumerate()
+				.all(|(i, (&(_, word), wanted))| {
+					let last = i + 1 == words.len();
+					match kind {
+						TermKind::Code => word == *wanted,
+						TermKind::Concept => is_form_of(word, wanted, last),
+					}
+				})
+		})
+		.map(|window| {
+			let (start, _) = window[0];
+			let (at, word) = window[window.len() - 1];
+			start..at + word.len()
+		})
+		.collect()
+}
+
+/// The term `text` spells out at the byte offset `at`. The longest wins,
+/// so that "trait object" is found rather than "trait".
+pub fn term_at(text: &str, at: usize) -> Option<&'static str> {
+	TERMS
+		.iter()
+		.flat_map(|&(term, kind, other)| {
+			std::iter::once(term)
+				.chain(other.iter().copied())
+				.flat_map(move |spelling| {
+					let words: Vec<&str> = spelling.split(' ').collect();
+					occurrences(text, &words, kind)
+				})
+				.map(move |range| (term, range))
+		})
+		.filter(|(_, range)| range.start <= at && at <= range.end)
+		.max_by_key(|(_, range)| range.len())
+		.map(|(term, _)| term)
+}
+
+/// The paragraph that introduces the term of `entry`: the one its
+/// definition is in, or the first one under the heading that names it.
+/// `None` if the term is first seen in code.
+pub fn introduction<'a>(docs: &'a [Document], entry: &Entry) -> Option<&'a str> {
+	let doc = &docs[entry.defined.doc];
+	let line = entry.defined.line;
+	let at = doc
+		.blocks
+		.iter()
+		.position(|block| block.lines().contains(line))?;
+	let (prose, line) = match &doc.blocks[at] {
+		Block::Prose(prose) => (prose, line),
+		Block::Heading(_) => match doc.blocks.get(at + 1) {
+			Some(Block::Prose(prose)) => (prose, prose.lines.start),
+			_ => return None,
+		},
+		Block::Code(_) => return None,
+	};
+	// The paragraph is the run of lines around `line` between blank ones.
+	let lines: Vec<&str> = prose.text.split_inclusive('\n').collect();
+	let i = (line - prose.lines.start).min(lines.len().checked_sub(1)?);
+	let blank = |line: &&str| line.trim().is_empty();
+	let i = (i..lines.len()).find(|&i| !blank(&lines[i]))?;
+	let start = lines[..i].iter().rposition(blank).map_or(0, |j| j + 1);
+	let end = lines[i..]
+		.iter()
+		.position(blank)
+		.map_or(lines.len(), |j| i + j);
+	let from: usize = lines[..start].iter().map(|line| line.len()).sum();
+	let to: usize = lines[..end].iter().map(|line| line.len()).sum();
+	Some(prose.text[from..to].trim_end())
+}
+
+/// Whether `word` is `wanted` in any case, or its plural for the last word
+/// of a term.
+fn is_form_of(word: &str, wanted: &str, last: bool) -> bool {
+	let word = word.to_lowercase();
+	if word == wanted {
+		return true;
+	}
+	last && [wanted.to_string() + "s", wanted.to_string() + "es"].contains(&word)
+}
+
+/// The words of `text` with their byte offsets: runs of letters, digits
+/// and underscores.
+fn split_words(text: &str) -> Vec<(usize, &str)> {
+	let mut words = Vec::new();
+	let mut start = None;
+	for (i, c) in text.char_indices() {
+		let in_word = c.is_alphanumeric() || c == '_';
+		match (in_word, start) {
+			(true, None) => start = Some(i),
+			(false, Some(at)) => {
+				words.push((at, &text[at..i]));
+				start = None;
+			}
+			_ => {}
+		}
+	}
+	if let Some(at) = start {
+		words.push((at, &text[at..]));
+	}
+	words
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn doc(name: &str, source: &str) -> Document {
+		Document::parse(NoteFile::from_path(name).unwrap(), source)
+	}
+
+	fn place(doc: usize, heading: Option<usize>, line: usize) -> Place {
+		Place { doc, heading, line }
+	}
+
+	#[test]
+	fn a_term_is_defined_where_it_is_introduced() {
+		let docs = [
+			doc(
+				"rust_chapter_3_common_concepts.rs",
+				"/*
+Variables:
+
+	Later chapters use trait
+	objects, but not yet.
+
+	Shadowing: declaring a new variable with the name of an old one.
+*/
+
+	let x = 5;
+	let x = x + 1; // shadowed
+",
+			),
+			doc(
+				"rust_chapter_17_oop.rs",
+				"/*
+Trait Objects:
+
+	A trait object points to a value.
+
+Once More:
+
+	We shadow it once more.
+*/
+
+	fn parse() -> Result<u8, String> { Ok(1) }
+",
+			),
+		];
+		let glossary = glossary(&docs);
+		let entry = |term: &str| glossary.iter().find(|entry| entry.term == term).unwrap();
+
+		let objects = entry("trait object");
+		assert_eq!(objects.defined, place(1, Some(0), 2));
+		assert_eq!(objects.earlier, [place(0, Some(0), 4)]);
+		assert!(objects.uses.is_empty());
+
+		let shadowing = entry("shadowing");
+		assert_eq!(shadowing.defined, place(0, Some(0), 7));
+		assert_eq!(shadowing.uses, [place(1, Some(1), 8)]);
+
+		let result = entry("Result");
+		assert_eq!(result.defined, place(1, Some(1), 11));
+		assert!(glossary.iter().all(|entry| entry.term != "Vec"));
+		let terms: Vec<&str> = glossary.iter().map(|entry| entry.term).collect();
+		assert!(terms
+			.windows(2)
+			.all(|pair| pair[0].to_lowercase() <= pair[1].to_lowercase()));
+	}
+}
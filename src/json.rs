@@ -0,0 +1,460 @@
+//! A small JSON reader and writer, for the language server's messages and
+//! the files some exports are written as.
+//!
+//! Objects keep their keys in the order they were written, and numbers are
+//! read as `f64`, which holds every integer the protocols here send.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Null,
+	Boolean(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+impl Value {
+	/// An object of `entries`, in order.
+	pub fn object<const N: usize>(entries: [(&str, Value); N]) -> Value {
+		Value::Object(
+			entries
+				.into_iter()
+				.map(|(key, value)| (key.to_string(), value))
+				.collect(),
+		)
+	}
+
+	/// The value of `key`, if this is an object that has it.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(text) => Some(text),
+			_ => None,
+		}
+	}
+
+	/// The value as an index or a count, if it is a whole number that fits.
+	pub fn as_usize(&self) -> Option<usize> {
+		match *self {
+			Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= usize::MAX as f64 => {
+				Some(n as usize)
+			}
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[Value]> {
+		match self {
+			Value::Array(items) => Some(items),
+			_ => None,
+		}
+	}
+
+	/// The value written over several lines, indented by two spaces a level,
+	/// for files people read and diff.
+	pub fn pretty(&self) -> String {
+		let mut out = String::new();
+		self.write_pretty(&mut out, 0);
+		out
+	}
+
+	fn write_pretty(&self, out: &mut String, depth: usize) {
+		let indent = |out: &mut String, depth: usize| out.push_str(&"  ".repeat(depth));
+		match self {
+			Value::Array(items) if !items.is_empty() => {
+				out.push_str("[\n");
+				for (i, item) in items.iter().enumerate() {
+					indent(out, depth + 1);
+					item.write_pretty(out, depth + 1);
+					out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+				}
+				indent(out, depth);
+				out.push(']');
+			}
+			Value::Object(entries) if !entries.is_empty() => {
+				out.push_str("{\n");
+				for (i, (key, value)) in entries.iter().enumerate() {
+					indent(out, depth + 1);
+					out.push_str(&string(key));
+					out.push_str(": ");
+					value.write_pretty(out, depth + 1);
+					out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+				}
+				indent(out, depth);
+				out.push('}');
+			}
+			_ => out.push_str(&self.to_string()),
+		}
+	}
+}
+
+impl From<bool> for Value {
+	fn from(b: bool) -> Value {
+		Value::Boolean(b)
+	}
+}
+
+impl From<usize> for Value {
+	fn from(n: usize) -> Value {
+		Value::Number(n as f64)
+	}
+}
+
+impl From<i64> for Value {
+	fn from(n: i64) -> Value {
+		Value::Number(n as f64)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(text: &str) -> Value {
+		Value::String(text.to_string())
+	}
+}
+
+impl From<String> for Value {
+	fn from(text: String) -> Value {
+		Value::String(text)
+	}
+}
+
+impl From<Vec<Value>> for Value {
+	fn from(items: Vec<Value>) -> Value {
+		Value::Array(items)
+	}
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+	fn from(value: Option<T>) -> Value {
+		value.map_or(Value::Null, Into::into)
+	}
+}
+
+/// Writes the value on one line, without spaces.
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Null => write!(f, "null"),
+			Value::Boolean(b) => write!(f, "{b}"),
+			// JSON has no infinities, and integers read better without a
+			// fraction.
+			Value::Number(n) if !n.is_finite() => write!(f, "null"),
+			Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+			Value::Number(n) => write!(f, "{n}"),
+			Value::String(text) => write!(f, "{}", string(text)),
+			Value::Array(items) => {
+				write!(f, "[")?;
+				for (i, item) in items.iter().enumerate() {
+					let comma = if i == 0 { "" } else { "," };
+					write!(f, "{comma}{item}")?;
+				}
+				write!(f, "]")
+			}
+			Value::Object(entries) => {
+				write!(f, "{{")?;
+				for (i, (key, value)) in entries.iter().enumerate() {
+					let comma = if i == 0 { "" } else { "," };
+					write!(f, "{comma}{}:{value}", string(key))?;
+				}
+				write!(f, "}}")
+			}
+		}
+	}
+}
+
+/// `text` as a JSON string literal.
+pub fn string(text: &str) -> String {
+	let mut out = String::with_capacity(text.len() + 2);
+	out.push('"');
+	for c in text.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// Why some text is not JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	/// The byte offset the problem was found at.
+	pub offset: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "at byte {}: {}", self.offset, self.message)
+	}
+}
+
+/// Reads a JSON document: one value, with nothing but whitespace around it.
+pub fn parse(text: &str) -> Result<Value, ParseError> {
+	let mut parser = Parser { text, at: 0 };
+	let value = parser.value()?;
+	parser.skip_whitespace();
+	if parser.at < text.len() {
+		return Err(parser.error("text after the value"));
+	}
+	Ok(value)
+}
+
+struct Parser<'a> {
+	text: &'a str,
+	at: usize,
+}
+
+impl Parser<'_> {
+	fn error(&self, message: &str) -> ParseError {
+		ParseError {
+			offset: self.at,
+			message: message.to_string(),
+		}
+	}
+
+	fn peek(&self) -> Option<u8> {
+		self.text.as_bytes().get(self.at).copied()
+	}
+
+	fn skip_whitespace(&mut self) {
+		while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+			self.at += 1;
+		}
+	}
+
+	/// Consumes `expected`, or fails.
+	fn eat(&mut self, expected: u8) -> Result<(), ParseError> {
+		if self.peek() == Some(expected) {
+			self.at += 1;
+			Ok(())
+		} else {
+			Err(self.error(&format!("expected `{}`", expected as char)))
+		}
+	}
+
+	fn value(&mut self) -> Result<Value, ParseError> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(b'{') => self.object(),
+			Some(b'[') => self.array(),
+			Some(b'"') => self.string().map(Value::String),
+			Some(b'-' | b'0'..=b'9') => self.number(),
+			Some(_) => {
+				for (word, value) in [
+					("null", Value::Null),
+					("true", Value::Boolean(true)),
+					("false", Value::Boolean(false)),
+				] {
+					if self.text[self.at..].starts_with(word) {
+						self.at += word.len();
+						return Ok(value);
+					}
+				}
+				Err(self.error("expected a value"))
+			}
+			None => Err(self.error("unexpected end of text")),
+		}
+	}
+
+	fn object(&mut self) -> Result<Value, ParseError> {
+		self.eat(b'{')?;
+		let mut entries = Vec::new();
+		self.skip_whitespace();
+		if self.peek() == Some(b'}') {
+			self.at += 1;
+			return Ok(Value::Object(entries));
+		}
+		loop {
+			self.skip_whitespace();
+			if self.peek() != Some(b'"') {
+				return Err(self.error("expected a key"));
+			}
+			let key = self.string()?;
+			self.skip_whitespace();
+			self.eat(b':')?;
+			let value = self.value()?;
+			entries.push((key, value));
+			self.skip_whitespace();
+			match self.peek() {
+				Some(b',') => self.at += 1,
+				Some(b'}') => {
+					self.at += 1;
+					return Ok(Value::Object(entries));
+				}
+				_ => return Err(self.error("expected `,` or `}`")),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Value, ParseError> {
+		self.eat(b'[')?;
+		let mut items = Vec::new();
+		self.skip_whitespace();
+		if self.peek() == Some(b']') {
+			self.at += 1;
+			return Ok(Value::Array(items));
+		}
+		loop {
+			items.push(self.value()?);
+			self.skip_whitespace();
+			match self.peek() {
+				Some(b',') => self.at += 1,
+				Some(b']') => {
+					self.at += 1;
+					return Ok(Value::Array(items));
+				}
+				_ => return Err(self.error("expected `,` or `]`")),
+			}
+		}
+	}
+
+	fn number(&mut self) -> Result<Value, ParseError> {
+		let start = self.at;
+		while matches!(
+			self.peek(),
+			Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+		) {
+			self.at += 1;
+		}
+		self.text[start..self.at]
+			.parse()
+			.map(Value::Number)
+			.map_err(|_| ParseError {
+				offset: start,
+				message: "not a number".to_string(),
+			})
+	}
+
+	fn string(&mut self) -> Result<String, ParseError> {
+		self.eat(b'"')?;
+		let mut out = String::new();
+		loop {
+			let rest = &self.text[self.at..];
+			let Some(end) = rest.find(['"', '\\']) else {
+				return Err(self.error("unterminated string"));
+			};
+			out.push_str(&rest[..end]);
+			self.at += end + 1;
+			if rest.as_bytes()[end] == b'"' {
+				return Ok(out);
+			}
+			let escaped = match self.peek() {
+				Some(b'"') => '"',
+				Some(b'\\') => '\\',
+				Some(b'/') => '/',
+				Some(b'b') => '\u{8}',
+				Some(b'f') => '\u{c}',
+				Some(b'n') => '\n',
+				Some(b'r') => '\r',
+				Some(b't') => '\t',
+				Some(b'u') => {
+					self.at += 1;
+					let unit = self.hex4()?;
+					// Characters outside the basic plane come as a pair of
+					// surrogates.
+					let c = if (0xD800..0xDC00).contains(&unit)
+						&& self.text[self.at..].starts_with("\\u")
+					{
+						self.at += 2;
+						let low = self.hex4()?;
+						(0xDC00..0xE000)
+							.contains(&low)
+							.then(|| {
+								char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+							})
+							.flatten()
+					} else {
+						char::from_u32(unit)
+					};
+					out.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+					continue;
+				}
+				_ => return Err(self.error("unknown escape")),
+			};
+			out.push(escaped);
+			self.at += 1;
+		}
+	}
+
+	fn hex4(&mut self) -> Result<u32, ParseError> {
+		let digits = self
+			.text
+			.get(self.at..self.at + 4)
+			.ok_or_else(|| self.error("short \\u escape"))?;
+		let unit = u32::from_str_radix(digits, 16).map_err(|_| self.error("bad \\u escape"))?;
+		self.at += 4;
+		Ok(unit)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn surrogate_pairs_make_one_character() {
+		assert_eq!(
+			parse(r#""🦀 crab""#),
+			Ok(Value::String("\u{1F980} crab".to_string()))
+		);
+		assert_eq!(parse(r#""é""#), Ok(Value::String("é".to_string())));
+	}
+
+	#[test]
+	fn lone_surrogates_become_replacement_characters() {
+		assert_eq!(
+			parse(r#""\ud83e""#),
+			Ok(Value::String("\u{FFFD}".to_string()))
+		);
+		assert_eq!(
+			parse(r#""\ud83eA""#),
+			Ok(Value::String("\u{FFFD}A".to_string()))
+		);
+		assert_eq!(
+			parse(r#""\udd80""#),
+			Ok(Value::String("\u{FFFD}".to_string()))
+		);
+	}
+
+	#[test]
+	fn values_read_back_as_written() {
+		let value = Value::object([
+			("id", Value::from(3usize)),
+			("text", Value::from("tab\there \"quoted\" \u{1} \u{1F980}")),
+			("list", Value::from(vec![Value::Null, Value::from(true)])),
+			("none", Value::from(None::<i64>)),
+		]);
+		assert_eq!(parse(&value.to_string()), Ok(value));
+	}
+
+	#[test]
+	fn errors_give_their_offset() {
+		let error = parse(r#"{"a": 1,}"#).unwrap_err();
+		assert_eq!(
+			(error.offset, error.message.as_str()),
+			(8, "expected a key")
+		);
+		let error = parse("[1] 2").unwrap_err();
+		assert_eq!(error.message, "text after the value");
+		let error = parse(r#""\u12zz""#).unwrap_err();
+		assert_eq!(error.message, "bad \\u escape");
+		let error = parse(r#""\u12""#).unwrap_err();
+		assert_eq!(error.message, "short \\u escape");
+	}
+}
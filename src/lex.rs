@@ -0,0 +1,298 @@
+//! A lexer for the Rust in the snippets.
+//!
+//! It never fails: the snippets are not always complete programs, and text
+//! it does not understand comes out as [`TokenKind::Unknown`]. Concatenating
+//! the tokens gives back the input exactly.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+	Whitespace,
+	/// A `//` comment up to the end of its line, or a `/* ... */` comment.
+	Comment,
+	Ident,
+	Keyword,
+	/// `'a`, and also the label in `'outer: loop`.
+	Lifetime,
+	/// A string or byte string literal, raw or not.
+	Str,
+	Char,
+	Number,
+	Punct,
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+	pub kind: TokenKind,
+	pub text: &'a str,
+	/// Byte offset of the token in the input.
+	pub start: usize,
+}
+
+impl Token<'_> {
+	pub fn is_trivia(&self) -> bool {
+		matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+	}
+}
+
+/// Strict and reserved keywords of the 2021 edition.
+pub const KEYWORDS: [&str; 38] = [
+	"as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+	"false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+	"ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+	"unsafe", "use", "where", "while",
+];
+
+/// Operators made of more than one character, longest first.
+/// Shifts are left out so that the `>>` closing `Vec<Vec<i32>>` stays two
+/// tokens.
+const OPERATORS: [&str; 18] = [
+	"..=", "...", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=",
+	"%=", "^=", "..",
+];
+
+/// Splits `source` into tokens.
+pub fn tokens(source: &str) -> Vec<Token<'_>> {
+	let mut lexer = Lexer { source, at: 0 };
+	let mut tokens = Vec::new();
+	while lexer.at < source.len() {
+		let start = lexer.at;
+		let kind = lexer.next_kind();
+		tokens.push(Token {
+			kind,
+			text: &source[start..lexer.at],
+			start,
+		});
+	}
+	tokens
+}
+
+/// The tokens of `source` that are neither whitespace nor comments.
+pub fn significant(source: &str) -> Vec<Token<'_>> {
+	tokens(source)
+		.into_iter()
+		.filter(|token| !token.is_trivia())
+		.collect()
+}
+
+pub fn is_ident_start(c: char) -> bool {
+	c.is_alphabetic() || c == '_'
+}
+
+pub fn is_ident_continue(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+struct Lexer<'a> {
+	source: &'a str,
+	at: usize,
+}
+
+impl<'a> Lexer<'a> {
+	fn rest(&self) -> &'a str {
+		&self.source[self.at..]
+	}
+
+	fn peek(&self, n: usize) -> Option<char> {
+		self.rest().chars().nth(n)
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let c = self.peek(0)?;
+		self.at += c.len_utf8();
+		Some(c)
+	}
+
+	fn eat_while(&mut self, f: impl Fn(char) -> bool) {
+		while self.peek(0).is_some_and(&f) {
+			self.bump();
+		}
+	}
+
+	/// Lexes one token starting at `self.at` and returns its kind.
+	fn next_kind(&mut self) -> TokenKind {
+		let rest = self.rest();
+		let c = self.peek(0).unwrap();
+		if c.is_whitespace() {
+			self.eat_while(char::is_whitespace);
+			return TokenKind::Whitespace;
+		}
+		if rest.starts_with("//") {
+			self.at += rest.find('\n').unwrap_or(rest.len());
+			return TokenKind::Comment;
+		}
+		if rest.starts_with("/*") {
+			self.block_comment();
+			return TokenKind::Comment;
+		}
+		if let Some(kind) = self.prefixed_literal() {
+			return kind;
+		}
+		if c == '"' {
+			self.bump();
+			self.quoted('"');
+			return TokenKind::Str;
+		}
+		if c == '\'' {
+			return self.quote();
+		}
+		if c.is_ascii_digit() {
+			self.number();
+			return TokenKind::Number;
+		}
+		if is_ident_start(c) {
+			let raw = rest.starts_with("r#") && rest[2..].starts_with(is_ident_start);
+			if raw {
+				self.at += 2;
+			}
+			let start = self.at;
+			self.eat_while(is_ident_continue);
+			let word = &self.source[start..self.at];
+			return if !raw && KEYWORDS.contains(&word) {
+				TokenKind::Keyword
+			} else {
+				TokenKind::Ident
+			};
+		}
+		if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+			self.at += op.len();
+			return TokenKind::Punct;
+		}
+		self.bump();
+		if c.is_ascii_punctuation() {
+			TokenKind::Punct
+		} else {
+			TokenKind::Unknown
+		}
+	}
+
+	/// `/* ... */`, which nests like everywhere else in the notes.
+	fn block_comment(&mut self) {
+		let mut depth = 0;
+		while !self.rest().is_empty() {
+			if self.rest().starts_with("/*") {
+				depth += 1;
+				self.at += 2;
+			} else if self.rest().starts_with("*/") {
+				depth -= 1;
+				self.at += 2;
+				if depth == 0 {
+					return;
+				}
+			} else {
+				self.bump();
+			}
+		}
+	}
+
+	/// `b"..."`, `b'x'`, `r"..."`, `r#"..."#` and `br"..."`.
+	fn prefixed_literal(&mut self) -> Option<TokenKind> {
+		let rest = self.rest();
+		let after_b = rest.strip_prefix('b').unwrap_or(rest);
+		let byte = after_b.len() < rest.len();
+		if let Some(raw) = after_b.strip_prefix('r') {
+			let hashes = raw.len() - raw.trim_start_matches('#').len();
+			if raw[hashes..].starts_with('"') {
+				self.at += rest.len() - raw.len() + hashes + 1;
+				let close = format!("\"{}", "#".repeat(hashes));
+				match self.rest().find(&close) {
+					Some(end) => self.at += end + close.len(),
+					None => self.at = self.source.len(),
+				}
+				return Some(TokenKind::Str);
+			}
+		}
+		if byte && after_b.starts_with('"') {
+			self.at += 2;
+			self.quoted('"');
+			return Some(TokenKind::Str);
+		}
+		if byte && after_b.starts_with('\'') {
+			self.at += 2;
+			self.quoted('\'');
+			return Some(TokenKind::Char);
+		}
+		None
+	}
+
+	/// The rest of a quoted literal, after its opening quote.
+	fn quoted(&mut self, quote: char) {
+		while let Some(c) = self.bump() {
+			if c == '\\' {
+				self.bump();
+			} else if c == quote {
+				return;
+			}
+		}
+	}
+
+	/// A `'` starts either a character literal or a lifetime.
+	fn quote(&mut self) -> TokenKind {
+		self.bump();
+		match (self.peek(0), self.peek(1)) {
+			(Some('\\'), _) => {
+				self.quoted('\'');
+				TokenKind::Char
+			}
+			(Some(c), Some('\'')) if c != '\'' => {
+				self.bump();
+				self.bump();
+				TokenKind::Char
+			}
+			(Some(c), _) if is_ident_start(c) => {
+				self.eat_while(is_ident_continue);
+				TokenKind::Lifetime
+			}
+			_ => TokenKind::Punct,
+		}
+	}
+
+	/// Digits, a suffix such as `u32`, and a fractional part; `1..5` stays a
+	/// range.
+	fn number(&mut self) {
+		self.eat_while(is_ident_continue);
+		if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+			self.bump();
+			self.eat_while(is_ident_continue);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+		significant(source)
+			.iter()
+			.map(|token| (token.kind, token.text))
+			.collect()
+	}
+
+	#[test]
+	fn tokens_give_back_the_input() {
+		let source = "fn longest<'a>(x: &'a str) -> &'a str { /* a /* nested */ comment */ x }\n\
+		              let c = '{'; let s = r#\"raw \"str\"\"#; 1_000u32 + 0x1f; ¤";
+		let joined: String = tokens(source).iter().map(|token| token.text).collect();
+		assert_eq!(joined, source);
+	}
+
+	#[test]
+	fn lifetimes_and_chars_are_told_apart() {
+		assert_eq!(
+			kinds("'outer: loop { let c = 'x'; }"),
+			[
+				(TokenKind::Lifetime, "'outer"),
+				(TokenKind::Punct, ":"),
+				(TokenKind::Keyword, "loop"),
+				(TokenKind::Punct, "{"),
+				(TokenKind::Keyword, "let"),
+				(TokenKind::Ident, "c"),
+				(TokenKind::Punct, "="),
+				(TokenKind::Char, "'x'"),
+				(TokenKind::Punct, ";"),
+				(TokenKind::Punct, "}"),
+			]
+		);
+	}
+}
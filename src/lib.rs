@@ -0,0 +1,43 @@
+//! Tooling for the `rust_chapter_*.rs` notes.
+//!
+//! The notes are Rust files in name only: prose lives in `/* ... */` comments
+//! and the code between them is a series of snippets, often several versions
+//! of the same `fn main()`. The [`parse`] module turns a file into a
+//! [`Document`], an ordered list of headings, prose and code blocks that each
+//! remember the lines they came from. Everything else is built on that model.
+
+pub mod cards;
+pub mod check;
+pub mod date;
+pub mod diagnostics;
+pub mod error;
+pub mod exercises;
+pub mod export;
+pub mod format;
+pub mod glossary;
+pub mod json;
+pub mod lex;
+pub mod lsp;
+pub mod mini;
+pub mod model;
+pub mod parse;
+pub mod projects;
+pub mod prose;
+pub mod run;
+pub mod rustdoc;
+pub mod search;
+pub mod source;
+pub mod sqlite;
+pub mod stats;
+pub mod text;
+pub mod toml;
+pub mod validate;
+pub mod versions;
+#[cfg(target_os = "linux")]
+pub mod watch;
+pub mod xref;
+pub mod zip;
+
+pub use error::{Error, Result};
+pub use model::{Block, Code, Document, Heading, Lang, Lines, Prose, ProseStyle, Section};
+pub use source::{discover, FileKind, NoteFile};
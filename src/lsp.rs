@@ -0,0 +1,871 @@
+//! A language server for the notes files, so that an editor finds its way
+//! around them instead of reading them as broken Rust.
+//!
+//! It gives the headings as a file's outline, the glossary's introduction
+//! of a term on hover, the definitions of the notes' own types and functions
+//! on go-to-definition, and the compiler's errors in the snippets as
+//! diagnostics. Only the snippets are ever compiled, each as `notes check`
+//! would, so the prose raises no errors; a snippet that is meant to fail is
+//! marked with a hint rather than an error.
+//!
+//! Messages are JSON-RPC over stdin and stdout, each behind a
+//! `Content-Length` header. Files are synced whole on every change, and
+//! compiled when they are opened or saved: on a thread of their own, so the
+//! editor is not kept waiting, and only the snippets that changed since the
+//! last check.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use crate::check::{self, Checked, Checker, Outcome};
+use crate::error::{Error, Result};
+use crate::export::markdown;
+use crate::glossary::{self, Entry};
+use crate::json::{self, Value};
+use crate::lex;
+use crate::model::{Document, Lang};
+use crate::source::{discover, NoteFile};
+use crate::validate;
+use crate::versions;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// How serious a diagnostic is, as the protocol numbers it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+	Error = 1,
+	Warning = 2,
+	Hint = 4,
+}
+
+/// Something to show on a line of a notes file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub line: usize,
+	pub severity: Severity,
+	/// The `E0384` style code of a compiler error.
+	pub code: Option<String>,
+	pub message: String,
+}
+
+/// The diagnostics of `doc`, given the results of its snippets in order.
+pub fn diagnostics(doc: &Document, checked: &[Checked]) -> Vec<Diagnostic> {
+	let mut found = Vec::new();
+	for (code, checked) in doc.code_blocks().zip(checked) {
+		let start = code.lines.start;
+		let from_compiler = |severity: Severity, prefix: &str, found: &mut Vec<Diagnostic>| {
+			for diagnostic in checked.outcome.diagnostics() {
+				found.push(Diagnostic {
+					line: diagnostic.line.unwrap_or(start),
+					severity,
+					code: diagnostic.code.clone(),
+					message: format!("{prefix}{}", diagnostic.message),
+				});
+			}
+		};
+		match &checked.outcome {
+			Outcome::Accidental(_) => from_compiler(Severity::Error, "", &mut found),
+			Outcome::FailedAsExpected(_) => {
+				from_compiler(Severity::Hint, "fails as expected: ", &mut found)
+			}
+			Outcome::UnexpectedSuccess => found.push(Diagnostic {
+				line: start,
+				severity: Severity::Warning,
+				code: None,
+				message: "annotated expect-error, but it compiles".to_string(),
+			}),
+			Outcome::WrongErrors { expected, .. } => {
+				found.push(Diagnostic {
+					line: start,
+					severity: Severity::Warning,
+					code: None,
+					message: format!("expected {}, but fails otherwise", expected.join(", ")),
+				});
+				from_compiler(Severity::Warning, "", &mut found);
+			}
+			Outcome::Compiled | Outcome::Skipped(_) => {}
+		}
+	}
+	for code in doc.code_blocks().filter(|code| code.lang() != Lang::Rust) {
+		for problem in validate::problems(code) {
+			found.push(Diagnostic {
+				line: problem.line,
+				severity: Severity::Warning,
+				code: None,
+				message: problem.message,
+			});
+		}
+	}
+	found
+}
+
+/// A heading in the outline of a file, with the ones under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+	pub title: String,
+	pub line: usize,
+	/// The last line of the section, before the next heading at its level
+	/// or above.
+	pub end: usize,
+	pub children: Vec<Symbol>,
+}
+
+/// The outline of `doc`: its headings, nested by level.
+pub fn symbols(doc: &Document) -> Vec<Symbol> {
+	let last = doc.blocks.last().map_or(1, |block| block.lines().end);
+	let headings: Vec<_> = doc.headings().collect();
+	// Built from the end, so that each heading's children are done first.
+	let mut stack: Vec<(usize, Symbol)> = Vec::new();
+	for (i, heading) in headings.iter().enumerate().rev() {
+		let end = headings[i + 1..]
+			.iter()
+			.find(|next| next.level <= heading.level)
+			.map_or(last, |next| next.lines.start - 1);
+		let mut children = Vec::new();
+		while stack
+			.last()
+			.is_some_and(|(level, _)| *level > heading.level)
+		{
+			children.push(stack.pop().unwrap().1);
+		}
+		stack.push((
+			heading.level,
+			Symbol {
+				title: heading.title.clone(),
+				line: heading.lines.start,
+				end,
+				children,
+			},
+		));
+	}
+	stack.into_iter().rev().map(|(_, symbol)| symbol).collect()
+}
+
+/// The glossary text for the term at byte `at` of `line`, as Markdown.
+pub fn hover(docs: &[Document], entries: &[Entry], line: &str, at: usize) -> Option<String> {
+	let term = glossary::term_at(line, at)?;
+	let entry = entries.iter().find(|entry| entry.term == term)?;
+	let doc = &docs[entry.defined.doc];
+	let section = match entry.defined.heading.and_then(|i| doc.headings().nth(i)) {
+		Some(heading) => format!(
+			"*{}* in {}",
+			markdown::escape(&heading.title),
+			doc.file.title()
+		),
+		None => doc.file.title(),
+	};
+	let mut text = format!("**{}**\n\n", markdown::escape(term));
+	if let Some(introduction) = glossary::introduction(docs, entry) {
+		text.push_str(&markdown::excerpt(introduction));
+		text.push_str("\n\n");
+	}
+	text.push_str(&format!(
+		"Introduced under {section} ({}:{}).",
+		doc.file.file_name(),
+		entry.defined.line
+	));
+	Some(text)
+}
+
+/// Where the notes define `name`, as a document index and line: the latest
+/// definition at or before `line` of `docs[current]`, or failing that the
+/// first one after it, or the latest one in an earlier file, or the first
+/// in a later one.
+pub fn definition(
+	docs: &[Document],
+	current: usize,
+	line: usize,
+	name: &str,
+) -> Option<(usize, usize)> {
+	let lines = |doc: &Document| -> Vec<usize> {
+		doc.code_blocks()
+			.flat_map(versions::definitions)
+			.filter(|(key, _)| key == name)
+			.map(|(_, version)| name_line(doc, version.line, name))
+			.collect()
+	};
+	let here = lines(&docs[current]);
+	if let Some(&found) = here.iter().rev().find(|&&l| l <= line).or(here.first()) {
+		return Some((current, found));
+	}
+	let earlier = (0..current)
+		.rev()
+		.find_map(|i| Some((i, *lines(&docs[i]).last()?)));
+	earlier.or_else(|| (current + 1..docs.len()).find_map(|i| Some((i, *lines(&docs[i]).first()?))))
+}
+
+/// The line at or after `start` that names the item, past its attributes
+/// and doc comments.
+fn name_line(doc: &Document, start: usize, name: &str) -> usize {
+	(start..start + 20)
+		.find(|&l| {
+			line_text(doc, l).is_some_and(|text| find_word(check::code_part(&text), name).is_some())
+		})
+		.unwrap_or(start)
+}
+
+/// The text of line `line` of a code block of `doc`.
+fn line_text(doc: &Document, line: usize) -> Option<String> {
+	let code = doc.code_blocks().find(|code| code.lines.contains(line))?;
+	code.text
+		.lines()
+		.nth(line - code.lines.start)
+		.map(str::to_string)
+}
+
+/// The byte offset of `word` in `text` as a whole identifier.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+	text.match_indices(word).map(|(at, _)| at).find(|&at| {
+		let before = text[..at].chars().next_back();
+		let after = text[at + word.len()..].chars().next();
+		!before.is_some_and(lex::is_ident_continue) && !after.is_some_and(lex::is_ident_continue)
+	})
+}
+
+/// The identifier around byte `at` of `text`, if there is one.
+fn word_at(text: &str, at: usize) -> Option<&str> {
+	let start = text[..at]
+		.char_indices()
+		.rev()
+		.take_while(|&(_, c)| lex::is_ident_continue(c))
+		.last()
+		.map_or(at, |(i, _)| i);
+	let end = text[at..]
+		.char_indices()
+		.find(|&(_, c)| !lex::is_ident_continue(c))
+		.map_or(text.len(), |(i, _)| at + i);
+	let word = &text[start..end];
+	word.starts_with(lex::is_ident_start).then_some(word)
+}
+
+/// What the main loop waits on.
+enum Event {
+	Message(Value),
+	/// A message that was not JSON.
+	Unreadable(String),
+	/// The editor closed the connection.
+	Closed,
+	/// The snippets of a file were checked, as they were in `doc`.
+	Checked {
+		doc: Box<Document>,
+		results: Result<Vec<Checked>>,
+	},
+}
+
+/// Serves the notes in `dir` over stdin and stdout until the editor says to
+/// exit. Returns whether it was asked to shut down first, as it should be.
+pub fn serve(dir: &Path, checker: Checker) -> Result<bool> {
+	let (events, receiver) = mpsc::channel();
+	let reader = events.clone();
+	thread::spawn(move || read_messages(io::stdin().lock(), &reader));
+	let mut server = Server {
+		dir: dir.to_path_buf(),
+		docs: Vec::new(),
+		sources: HashMap::new(),
+		known: HashMap::new(),
+		checking: HashSet::new(),
+		pending: HashSet::new(),
+		glossary: None,
+		checker,
+		events,
+		out: io::stdout(),
+		shut_down: false,
+	};
+	for event in receiver {
+		match event {
+			Event::Message(message) => {
+				if !server.message(&message)? {
+					break;
+				}
+			}
+			Event::Unreadable(problem) => {
+				server.send(&Value::object([
+					("jsonrpc", "2.0".into()),
+					("id", Value::Null),
+					(
+						"error",
+						Value::object([("code", PARSE_ERROR.into()), ("message", problem.into())]),
+					),
+				]))?;
+			}
+			Event::Closed => break,
+			Event::Checked { doc, results } => server.checked(*doc, results)?,
+		}
+	}
+	Ok(server.shut_down)
+}
+
+/// Reads framed messages until the input ends.
+fn read_messages(input: impl Read, events: &Sender<Event>) {
+	let mut input = BufReader::new(input);
+	loop {
+		let mut length = None;
+		let mut header = String::new();
+		loop {
+			header.clear();
+			match input.read_line(&mut header) {
+				Ok(0) | Err(_) => {
+					let _ = events.send(Event::Closed);
+					return;
+				}
+				Ok(_) => {}
+			}
+			let header = header.trim_end();
+			if header.is_empty() {
+				break;
+			}
+			if let Some((name, value)) = header.split_once(':') {
+				if name.eq_ignore_ascii_case("content-length") {
+					length = value.trim().parse::<usize>().ok();
+				}
+			}
+		}
+		let Some(length) = length else {
+			let _ = events.send(Event::Unreadable("no Content-Length header".to_string()));
+			continue;
+		};
+		let mut body = vec![0; length];
+		if input.read_exact(&mut body).is_err() {
+			let _ = events.send(Event::Closed);
+			return;
+		}
+		let event = match String::from_utf8(body).map_err(|e| e.to_string()) {
+			Ok(text) => {
+				json::parse(&text).map_or_else(|e| Event::Unreadable(e.to_string()), Event::Message)
+			}
+			Err(e) => Event::Unreadable(e),
+		};
+		if events.send(event).is_err() {
+			return;
+		}
+	}
+}
+
+struct Server {
+	dir: PathBuf,
+	/// Every notes file in chapter order, as the editor has it if it is open.
+	docs: Vec<Document>,
+	/// The text of the files open in the editor.
+	sources: HashMap<PathBuf, String>,
+	/// The last results of each file's snippets, by the snippet's text.
+	known: HashMap<PathBuf, HashMap<String, Checked>>,
+	checking: HashSet<PathBuf>,
+	/// Files saved again while they were being checked.
+	pending: HashSet<PathBuf>,
+	glossary: Option<Vec<Entry>>,
+	checker: Checker,
+	events: Sender<Event>,
+	out: io::Stdout,
+	shut_down: bool,
+}
+
+/// A request that cannot be answered: a JSON-RPC error code and message.
+type Refusal = (i64, String);
+
+impl Server {
+	/// Handles one message; `false` once the editor says to exit.
+	fn message(&mut self, message: &Value) -> Result<bool> {
+		let Some(method) = message.get("method").and_then(Value::as_str) else {
+			// A response; the server sends no requests, so there are none
+			// to expect.
+			return Ok(true);
+		};
+		let params = message.get("params").unwrap_or(&Value::Null);
+		let Some(id) = message.get("id") else {
+			return self.notification(method, params);
+		};
+		let answer = if self.shut_down {
+			Err((INVALID_REQUEST, "the server has been shut down".to_string()))
+		} else {
+			self.request(method, params)
+		};
+		let reply = match answer {
+			Ok(result) => Value::object([
+				("jsonrpc", "2.0".into()),
+				("id", id.clone()),
+				("result", result),
+			]),
+			Err((code, text)) => Value::object([
+				("jsonrpc", "2.0".into()),
+				("id", id.clone()),
+				(
+					"error",
+					Value::object([("code", code.into()), ("message", text.into())]),
+				),
+			]),
+		};
+		self.send(&reply)?;
+		Ok(true)
+	}
+
+	fn request(&mut self, method: &str, params: &Value) -> std::result::Result<Value, Refusal> {
+		match method {
+			"initialize" => Ok(self.initialize(params)),
+			"shutdown" => {
+				self.shut_down = true;
+				Ok(Value::Null)
+			}
+			"textDocument/documentSymbol" => {
+				let doc = self.document(params)?;
+				let Some(doc) = doc else {
+					return Ok(Value::Null);
+				};
+				Ok(Value::Array(
+					symbols(&self.docs[doc]).iter().map(symbol_value).collect(),
+				))
+			}
+			"textDocument/hover" => {
+				let Some((_, line, text, at)) = self.position(params)? else {
+					return Ok(Value::Null);
+				};
+				let entries = self
+					.glossary
+					.get_or_insert_with(|| glossary::glossary(&self.docs));
+				Ok(
+					hover(&self.docs, entries, &text, at).map_or(Value::Null, |text| {
+						Value::object([
+							(
+								"contents",
+								Value::object([
+									("kind", "markdown".into()),
+									("value", text.into()),
+								]),
+							),
+							("range", line_range(line, line)),
+						])
+					}),
+				)
+			}
+			"textDocument/definition" => {
+				let Some((doc, line, text, at)) = self.position(params)? else {
+					return Ok(Value::Null);
+				};
+				let Some(name) = word_at(&text, at) else {
+					return Ok(Value::Null);
+				};
+				let Some((target, line)) = definition(&self.docs, doc, line, name) else {
+					return Ok(Value::Null);
+				};
+				let target = &self.docs[target];
+				let text = line_text(target, line).unwrap_or_default();
+				let column = find_word(check::code_part(&text), name).unwrap_or(0);
+				let start = utf16_len(&text[..column]);
+				let position = |character: usize| {
+					Value::object([("line", (line - 1).into()), ("character", character.into())])
+				};
+				Ok(Value::object([
+					("uri", uri_of(&target.file.path).into()),
+					(
+						"range",
+						Value::object([
+							("start", position(start)),
+							("end", position(start + utf16_len(name))),
+						]),
+					),
+				]))
+			}
+			_ => Err((METHOD_NOT_FOUND, format!("{method} is not supported"))),
+		}
+	}
+
+	fn notification(&mut self, method: &str, params: &Value) -> Result<bool> {
+		let document = params.get("textDocument");
+		let path = document
+			.and_then(|document| document.get("uri"))
+			.and_then(Value::as_str)
+			.and_then(path_of);
+		match (method, path) {
+			("exit", _) => return Ok(false),
+			("textDocument/didOpen", Some(path)) => {
+				let text = document.and_then(|d| d.get("text")).and_then(Value::as_str);
+				if let Some(text) = text {
+					self.update(&path, text.to_string());
+					self.check(&path);
+				}
+			}
+			("textDocument/didChange", Some(path)) => {
+				// Files are synced whole, so the last change is the file.
+				let text = params
+					.get("contentChanges")
+					.and_then(Value::as_array)
+					.and_then(|changes| changes.last())
+					.and_then(|change| change.get("text"))
+					.and_then(Value::as_str);
+				if let Some(text) = text {
+					self.update(&path, text.to_string());
+				}
+			}
+			("textDocument/didSave", Some(path)) => self.check(&path),
+			("textDocument/didClose", Some(path)) => {
+				// Back to the file as it is on disk.
+				if let Ok(text) = fs::read_to_string(&path) {
+					self.update(&path, text);
+				}
+				self.sources.remove(&path);
+				self.publish(&path, &[])?;
+			}
+			_ => {}
+		}
+		Ok(true)
+	}
+
+	fn initialize(&mut self, params: &Value) -> Value {
+		let root = params
+			.get("rootUri")
+			.and_then(Value::as_str)
+			.and_then(path_of)
+			.or_else(|| {
+				params
+					.get("rootPath")
+					.and_then(Value::as_str)
+					.map(PathBuf::from)
+			});
+		if let Some(root) = root {
+			self.dir = root;
+		}
+		self.dir = fs::canonicalize(&self.dir).unwrap_or_else(|_| self.dir.clone());
+		self.docs = discover(&self.dir)
+			.unwrap_or_default()
+			.into_iter()
+			.filter_map(|file| Document::load(file).ok())
+			.collect();
+		Value::object([
+			(
+				"capabilities",
+				Value::object([
+					(
+						"textDocumentSync",
+						Value::object([
+							("openClose", true.into()),
+							("change", 1usize.into()),
+							("save", true.into()),
+						]),
+					),
+					("documentSymbolProvider", true.into()),
+					("hoverProvider", true.into()),
+					("definitionProvider", true.into()),
+				]),
+			),
+			(
+				"serverInfo",
+				Value::object([
+					("name", "notes".into()),
+					("version", env!("CARGO_PKG_VERSION").into()),
+				]),
+			),
+		])
+	}
+
+	/// Takes the editor's text for `path`, if it is a notes file.
+	fn update(&mut self, path: &Path, text: String) {
+		let Some(file) = NoteFile::from_path(path) else {
+			return;
+		};
+		let doc = Document::parse(file, &text);
+		match self.docs.iter().position(|d| d.file.path == path) {
+			Some(i) => self.docs[i] = doc,
+			None => {
+				let i = self
+					.docs
+					.partition_point(|d| d.file.order() < doc.file.order());
+				self.docs.insert(i, doc);
+			}
+		}
+		self.sources.insert(path.to_path_buf(), text);
+		self.glossary = None;
+	}
+
+	/// Starts checking the snippets of `path` as they are now, or notes to
+	/// do it again if a check is already running.
+	fn check(&mut self, path: &Path) {
+		let Some(doc) = self.docs.iter().find(|d| d.file.path == path) else {
+			return;
+		};
+		if !self.checking.insert(path.to_path_buf()) {
+			self.pending.insert(path.to_path_buf());
+			return;
+		}
+		let doc = doc.clone();
+		let known = self.known.get(path).cloned().unwrap_or_default();
+		let checker = self.checker.clone();
+		let events = self.events.clone();
+		thread::spawn(move || {
+			let results = checker.recheck(&doc, &known);
+			let _ = events.send(Event::Checked {
+				doc: Box::new(doc),
+				results,
+			});
+		});
+	}
+
+	fn checked(&mut self, doc: Document, results: Result<Vec<Checked>>) -> Result<()> {
+		let path = doc.file.path.clone();
+		self.checking.remove(&path);
+		match results {
+			Ok(results) => {
+				self.publish(&path, &diagnostics(&doc, &results))?;
+				self.known.insert(
+					path.clone(),
+					doc.code_blocks()
+						.map(|code| code.text.clone())
+						.zip(results)
+						.collect(),
+				);
+			}
+			Err(e) => eprintln!("notes lsp: {e}"),
+		}
+		if self.pending.remove(&path) {
+			self.check(&path);
+		}
+		Ok(())
+	}
+
+	fn publish(&mut self, path: &Path, diagnostics: &[Diagnostic]) -> Result<()> {
+		let diagnostics = diagnostics.iter().map(diagnostic_json).collect();
+		self.send(&Value::object([
+			("jsonrpc", "2.0".into()),
+			("method", "textDocument/publishDiagnostics".into()),
+			(
+				"params",
+				Value::object([
+					("uri", uri_of(path).into()),
+					("diagnostics", Value::Array(diagnostics)),
+				]),
+			),
+		]))
+	}
+
+	fn send(&mut self, message: &Value) -> Result<()> {
+		let body = message.to_string();
+		let mut out = self.out.lock();
+		write!(out, "Content-Length: {}\r\n\r\n{body}", body.len())
+			.and_then(|()| out.flush())
+			.map_err(|e| Error::io("stdout", e))
+	}
+
+	/// The index of the notes file a request is about, or `None` if it is
+	/// not one.
+	fn document(&self, params: &Value) -> std::result::Result<Option<usize>, Refusal> {
+		let uri = params
+			.get("textDocument")
+			.and_then(|document| document.get("uri"))
+			.and_then(Value::as_str)
+			.ok_or((INVALID_PARAMS, "no textDocument.uri".to_string()))?;
+		Ok(path_of(uri).and_then(|path| self.docs.iter().position(|d| d.file.path == path)))
+	}
+
+	/// The file, 1-based line, line text and byte offset in it that a
+	/// request points at.
+	fn position(
+		&self,
+		params: &Value,
+	) -> std::result::Result<Option<(usize, usize, String, usize)>, Refusal> {
+		let Some(doc) = self.document(params)? else {
+			return Ok(None);
+		};
+		let position = params.get("position");
+		let number = |key: &str| position.and_then(|p| p.get(key)).and_then(Value::as_usize);
+		let (Some(line), Some(character)) = (number("line"), number("character")) else {
+			return Err((INVALID_PARAMS, "no position".to_string()));
+		};
+		let path = &self.docs[doc].file.path;
+		let source = match self.sources.get(path) {
+			Some(source) => source.clone(),
+			None => fs::read_to_string(path).unwrap_or_default(),
+		};
+		let Some(text) = source.lines().nth(line) else {
+			return Ok(None);
+		};
+		let at = byte_offset(text, character);
+		Ok(Some((doc, line + 1, text.to_string(), at)))
+	}
+}
+
+/// The protocol's form of an outline entry.
+fn symbol_value(symbol: &Symbol) -> Value {
+	Value::object([
+		("name", symbol.title.clone().into()),
+		// A string, as Markdown headings are.
+		("kind", 15usize.into()),
+		("range", line_range(symbol.line, symbol.end)),
+		("selectionRange", line_range(symbol.line, symbol.line)),
+		(
+			"children",
+			Value::Array(symbol.children.iter().map(symbol_value).collect()),
+		),
+	])
+}
+
+/// A diagnostic as LSP has it. LSP has no null code: a diagnostic without
+/// one leaves it out.
+fn diagnostic_json(diagnostic: &Diagnostic) -> Value {
+	let mut entries = vec![
+		("range", line_range(diagnostic.line, diagnostic.line)),
+		("severity", (diagnostic.severity as usize).into()),
+	];
+	if let Some(code) = &diagnostic.code {
+		entries.push(("code", code.as_str().into()));
+	}
+	entries.push(("source", "notes".into()));
+	entries.push(("message", diagnostic.message.clone().into()));
+	Value::Object(
+		entries
+			.into_iter()
+			.map(|(key, value)| (key.to_string(), value))
+			.collect(),
+	)
+}
+
+/// The whole of the 1-based lines `start` to `end`.
+fn line_range(start: usize, end: usize) -> Value {
+	let position =
+		|line: usize| Value::object([("line", line.into()), ("character", 0usize.into())]);
+	Value::object([("start", position(start - 1)), ("end", position(end))])
+}
+
+/// Positions count UTF-16 units, as the protocol has them by default.
+fn utf16_len(text: &str) -> usize {
+	text.encode_utf16().count()
+}
+
+/// The byte offset of a UTF-16 position in `text`.
+fn byte_offset(text: &str, character: usize) -> usize {
+	let mut units = 0;
+	for (i, c) in text.char_indices() {
+		if units >= character {
+			return i;
+		}
+		units += c.len_utf16();
+	}
+	text.len()
+}
+
+/// The path of a `file:` URI.
+fn path_of(uri: &str) -> Option<PathBuf> {
+	let path = uri.strip_prefix("file://")?;
+	let bytes = path.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		let hex = path
+			.get(i + 1..i + 3)
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match (bytes[i], hex) {
+			(b'%', Some(byte)) => {
+				decoded.push(byte);
+				i += 3;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+/// The `file:` URI of `path`.
+fn uri_of(path: &Path) -> String {
+	let mut uri = String::from("file://");
+	for &byte in path.to_string_lossy().as_bytes() {
+		if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+			uri.push(byte as char);
+		} else {
+			uri.push_str(&format!("%{byte:02X}"));
+		}
+	}
+	uri
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The events `read_messages` makes of `input`, as text.
+	fn events(input: &str) -> Vec<String> {
+		let (sender, receiver) = mpsc::channel();
+		read_messages(input.as_bytes(), &sender);
+		drop(sender);
+		receiver
+			.into_iter()
+			.map(|event| match event {
+				Event::Message(message) => message.to_string(),
+				Event::Unreadable(problem) => format!("unreadable: {problem}"),
+				Event::Closed => "closed".to_string(),
+				Event::Checked { .. } => "checked".to_string(),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn messages_are_framed_by_their_length() {
+		let body = r#"{"id":1,"text":"é"}"#;
+		let input = format!(
+			"Content-Length: {}\r\nContent-Type: utf-8\r\n\r\n{body}content-length: 2\r\n\r\n[]",
+			body.len()
+		);
+		assert_eq!(events(&input), [body, "[]", "closed"]);
+	}
+
+	#[test]
+	fn bad_frames_are_reported() {
+		assert_eq!(
+			events("X-Other: 1\r\n\r\nContent-Length: 3\r\n\r\n{x}"),
+			[
+				"unreadable: no Content-Length header",
+				"unreadable: at byte 1: expected a key",
+				"closed"
+			]
+		);
+		assert_eq!(events("Content-Length: 10\r\n\r\n{}"), ["closed"]);
+	}
+
+	#[test]
+	fn positions_count_utf16_units() {
+		let text = "let 🦀 = é;";
+		assert_eq!(utf16_len(text), 11);
+		assert_eq!(byte_offset(text, 4), 4);
+		assert_eq!(byte_offset(text, 6), 8);
+		assert_eq!(&text[byte_offset(text, 9)..], "é;");
+		assert_eq!(byte_offset(text, 99), text.len());
+	}
+
+	#[test]
+	fn uris_and_paths_convert_both_ways() {
+		let path = Path::new("/notes/my notes/rust_chapter_1_é.rs");
+		let uri = uri_of(path);
+		assert_eq!(uri, "file:///notes/my%20notes/rust_chapter_1_%C3%A9.rs");
+		assert_eq!(path_of(&uri).as_deref(), Some(path));
+		assert_eq!(path_of("untitled:1"), None);
+	}
+
+	#[test]
+	fn words_are_whole_identifiers() {
+		assert_eq!(word_at("let guess_count = 1;", 6), Some("guess_count"));
+		assert_eq!(word_at("let x = 42;", 9), None);
+		assert_eq!(find_word("guesses guess", "guess"), Some(8));
+	}
+
+	#[test]
+	fn a_diagnostic_has_a_code_only_if_it_has_one() {
+		let mut diagnostic = Diagnostic {
+			line: 3,
+			severity: Severity::Error,
+			code: Some("E0384".to_string()),
+			message: "cannot assign twice".to_string(),
+		};
+		let json = diagnostic_json(&diagnostic);
+		assert_eq!(json.get("code"), Some(&Value::from("E0384")));
+		diagnostic.code = None;
+		let json = diagnostic_json(&diagnostic);
+		assert_eq!(json.get("code"), None);
+		assert!(!json.to_string().contains("null"));
+	}
+}
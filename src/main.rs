@@ -0,0 +1,17 @@
+//! `notes`: the command line front end to the notes tooling.
+
+mod cmd;
+
+use std::env;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	match cmd::run(&args) {
+		Ok(code) => code,
+		Err(err) => {
+			eprintln!("notes: {err}");
+			ExitCode::from(2)
+		}
+	}
+}
@@ -0,0 +1,38 @@
+//! A mini Rust: the part of the language the early chapters walk through by
+//! hand.
+//!
+//! [`syntax`] parses it from a snippet; [`trace`] runs it, recording every
+//! binding and every turn the control flow takes, so that a walkthrough such
+//! as the one of the `'counting_up` loop in chapter 3 comes out of the code
+//! instead of being written next to it. [`ownership`] checks it against the
+//! ownership and borrowing rules of chapter 4 instead of running it. What a
+//! snippet uses beyond the subset is reported rather than guessed at.
+
+use std::fmt;
+
+pub mod ownership;
+pub mod syntax;
+pub mod trace;
+
+/// Why a snippet cannot be parsed or run, and the line of the snippet's
+/// notes file where that happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+	pub line: usize,
+	pub message: String,
+}
+
+impl Problem {
+	pub fn new(line: usize, message: impl Into<String>) -> Problem {
+		Problem {
+			line,
+			message: message.into(),
+		}
+	}
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
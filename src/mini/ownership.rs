@@ -0,0 +1,1711 @@
+//! Checking a mini Rust program against the ownership rules of chapter 4.
+//!
+//! The checker knows what the notes state and nothing more: every value has
+//! one owner, assigning or passing a `String` moves it while the `Copy`
+//! types (integers, `bool`, floats, `char`, `&str`, shared references and
+//! tuples of these) are copied, a `&mut` is the only reference to its value
+//! while it is in use, a `&` cannot change what it points at, and a value is
+//! dropped when its owner goes out of scope. A borrow lasts until the last
+//! use of the reference holding it, not to the end of its scope, which is
+//! what lets `r3 = &mut s` follow `r1` and `r2` in the notes.
+//!
+//! A program that breaks a rule is rejected at the first place it does so,
+//! with the code and message `rustc` gives the same mistake, the [`Rule`]
+//! broken and what happened in plain terms. Along the way the checker keeps
+//! [`Note`]s of every move, copy, borrow and drop, like the comments of the
+//! `takes_ownership` example.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::syntax::{BinaryOp, Block, Expr, ExprKind, Function, Program, Stmt, Type};
+use super::Problem;
+
+/// A rule of the notes a program can break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+	OneOwner,
+	Dropped,
+	Exclusive,
+	ReadOnly,
+	Immutable,
+}
+
+impl Rule {
+	/// The rule in the words of the notes.
+	pub fn text(self) -> &'static str {
+		match self {
+			Rule::OneOwner => "There can only be one owner at a time for a given value",
+			Rule::Dropped => "When the owner exits scope, the value is dropped.",
+			Rule::Exclusive => {
+				"If a mutable reference to an object exists, then it must be the single, unique \
+				 reference to that object"
+			}
+			Rule::ReadOnly => "we cannot change borrowed values",
+			Rule::Immutable => "All variables are immutable by default.",
+		}
+	}
+
+	/// The chapter whose notes state the rule.
+	pub fn chapter(self) -> u32 {
+		match self {
+			Rule::Immutable => 3,
+			_ => 4,
+		}
+	}
+}
+
+/// Why a program is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+	pub line: usize,
+	/// The code `rustc` gives the same mistake, such as `E0382`.
+	pub code: &'static str,
+	/// The message `rustc` gives with it.
+	pub message: String,
+	pub rule: Rule,
+	/// What the program does that breaks the rule.
+	pub why: String,
+}
+
+/// Something that happens to a value: a move, a copy, a borrow or a drop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+	pub line: usize,
+	pub text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Verdict {
+	/// What happens to the values, up to the rejection if there is one.
+	pub notes: Vec<Note>,
+	pub rejection: Option<Rejection>,
+}
+
+/// A program the checker should accept or reject, to show and test the
+/// rules on.
+#[derive(Debug, Clone, Copy)]
+pub struct Example {
+	pub name: &'static str,
+	pub source: &'static str,
+	/// The code `rustc` rejects the program with, `None` if it compiles.
+	pub rejected_with: Option<&'static str>,
+}
+
+/// The examples of chapter 4, each with the mistake it is about or the
+/// fix for it.
+pub const EXAMPLES: [Example; 18] = [
+	Example {
+		name: "takes_ownership",
+		source: "fn main() {
+	let s = String::from(\"hello\");
+	takes_ownership(s);
+	println!(\"{}\", s);
+}
+
+fn takes_ownership(some_string: String) {
+	println!(\"{}\", some_string);
+}
+",
+		rejected_with: Some("E0382"),
+	},
+	Example {
+		name: "makes_copy",
+		source: "fn main() {
+	let x = 5;
+	makes_copy(x);
+	println!(\"{}\", x);
+}
+
+fn makes_copy(some_integer: i32) {
+	println!(\"{}\", some_integer);
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "change(&s)",
+		source: "fn main() {
+	let s = String::from(\"hello\");
+	change(&s);
+}
+
+fn change(some_string: &String) {
+	some_string.push_str(\", world\");
+}
+",
+		rejected_with: Some("E0596"),
+	},
+	Example {
+		name: "change(&mut s)",
+		source: "fn main() {
+	let mut s = String::from(\"hello\");
+	change(&mut s);
+	println!(\"{}\", s);
+}
+
+fn change(some_string: &mut String) {
+	some_string.push_str(\", world\");
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "r1 and r2 end before r3",
+		source: "fn main() {
+	let mut s = String::from(\"hello\");
+	let r1 = &s;
+	let r2 = &s;
+	println!(\"{} and {}\", r1, r2);
+	let r3 = &mut s;
+	println!(\"{}\", r3);
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "r1 and r2 used after r3",
+		source: "fn main() {
+	let mut s = String::from(\"hello\");
+	let r1 = &s;
+	let r2 = &s;
+	let r3 = &mut s;
+	println!(\"{}, {} and {}\", r1, r2, r3);
+}
+",
+		rejected_with: Some("E0502"),
+	},
+	Example {
+		name: "s2 = s1",
+		source: "fn main() {
+	let s1 = String::from(\"hello\");
+	let s2 = s1;
+	println!(\"{}, world!\", s1);
+}
+",
+		rejected_with: Some("E0382"),
+	},
+	Example {
+		name: "s2 = s1.clone()",
+		source: "fn main() {
+	let s1 = String::from(\"hello\");
+	let s2 = s1.clone();
+	println!(\"s1 = {}, s2 = {}\", s1, s2);
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "two &mut",
+		source: "fn main() {
+	let mut s = String::from(\"hello\");
+	let r1 = &mut s;
+	let r2 = &mut s;
+	println!(\"{}, {}\", r1, r2);
+}
+",
+		rejected_with: Some("E0499"),
+	},
+	Example {
+		name: "push_str without mut",
+		source: "fn main() {
+	let s = String::from(\"hello\");
+	s.push_str(\", world!\");
+}
+",
+		rejected_with: Some("E0596"),
+	},
+	Example {
+		name: "dangle",
+		source: "fn main() {
+	let reference_to_nothing = dangle();
+}
+
+fn dangle() -> &String {
+	let s = String::from(\"hello\");
+	&s
+}
+",
+		rejected_with: Some("E0106"),
+	},
+	Example {
+		name: "no_dangle",
+		source: "fn main() {
+	let s = no_dangle();
+	println!(\"{}\", s);
+}
+
+fn no_dangle() -> String {
+	let s = String::from(\"hello\");
+	s
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "borrow outlives its owner",
+		source: "fn main() {
+	let s1 = String::from(\"hello\");
+	let mut r = &s1;
+	{
+		let s2 = String::from(\"world\");
+		r = &s2;
+	}
+	println!(\"{}\", r);
+}
+",
+		rejected_with: Some("E0597"),
+	},
+	Example {
+		name: "move in a loop",
+		source: "fn main() {
+	let s = String::from(\"hello\");
+	for _ in 0..3 {
+		takes_ownership(s);
+	}
+}
+
+fn takes_ownership(some_string: String) {
+	println!(\"{}\", some_string);
+}
+",
+		rejected_with: Some("E0382"),
+	},
+	Example {
+		name: "assign while borrowed",
+		source: "fn main() {
+	let mut s = String::from(\"hello\");
+	let r = &s;
+	s = String::from(\"world\");
+	println!(\"{}\", r);
+}
+",
+		rejected_with: Some("E0506"),
+	},
+	Example {
+		name: "move while borrowed",
+		source: "fn main() {
+	let s1 = String::from(\"hello\");
+	let r = &s1;
+	let s2 = s1;
+	println!(\"{} {}\", r, s2);
+}
+",
+		rejected_with: Some("E0505"),
+	},
+	Example {
+		name: "takes_and_gives_back",
+		source: "fn main() {
+	let s1 = gives_ownership();
+	let s2 = String::from(\"hello\");
+	let s3 = takes_and_gives_back(s2);
+	println!(\"{} {}\", s1, s3);
+}
+
+fn gives_ownership() -> String {
+	let some_string = String::from(\"yours\");
+	some_string
+}
+
+fn takes_and_gives_back(a_string: String) -> String {
+	a_string
+}
+",
+		rejected_with: None,
+	},
+	Example {
+		name: "assign twice",
+		source: "fn main() {
+	let x = 5;
+	println!(\"The value of x is: {x}\");
+	x = 6;
+	println!(\"The value of x is: {x}\");
+}
+",
+		rejected_with: Some("E0384"),
+	},
+];
+
+/// Checks every function of `program`, each against the signatures of the
+/// others, and stops at the first that breaks a rule. A [`Problem`] means
+/// the program uses something the checker does not model.
+pub fn check(program: &Program) -> Result<Verdict, Problem> {
+	for (at, function) in program.functions.iter().enumerate() {
+		if let Some(earlier) = program.functions[..at]
+			.iter()
+			.find(|earlier| earlier.name == function.name)
+		{
+			return Err(Problem::new(
+				function.line,
+				format!(
+					"`{}` is defined twice, at lines {} and {}",
+					function.name, earlier.line, function.line
+				),
+			));
+		}
+	}
+
+	// A first pass finds the last use of every binding, which is how long
+	// a borrow it holds lasts; the second then checks with that known.
+	let mut last_uses = Vec::new();
+	for function in &program.functions {
+		let mut checker = Checker::new(program, None);
+		match checker.function(function) {
+			Err(Stop::Problem(problem)) => return Err(problem),
+			Ok(()) | Err(Stop::Rejected(_)) => last_uses.push(checker.uses),
+		}
+	}
+
+	let mut verdict = Verdict::default();
+	for (function, last_use) in program.functions.iter().zip(last_uses) {
+		let mut checker = Checker::new(program, Some(last_use));
+		let result = checker.function(function);
+		verdict.notes.append(&mut checker.notes);
+		match result {
+			Ok(()) => {}
+			Err(Stop::Rejected(rejection)) => {
+				verdict.rejection = Some(rejection);
+				break;
+			}
+			Err(Stop::Problem(problem)) => return Err(problem),
+		}
+	}
+	Ok(verdict)
+}
+
+/// The type of a value, as far as ownership goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Ty {
+	/// An integer, float, `bool` or `char`.
+	Scalar(String),
+	String,
+	/// `&str`.
+	Str,
+	Ref {
+		mutable: bool,
+		to: Box<Ty>,
+	},
+	/// `()` when empty.
+	Tuple(Vec<Ty>),
+}
+
+impl Ty {
+	fn unit() -> Ty {
+		Ty::Tuple(Vec::new())
+	}
+
+	fn of(ty: &Type, line: usize) -> Result<Ty, Problem> {
+		match ty {
+			Type::Named(name) if name == "String" => Ok(Ty::String),
+			Type::Named(name) if is_scalar(name) => Ok(Ty::Scalar(name.clone())),
+			Type::Ref { mutable: false, to } if **to == Type::Named("str".to_string()) => {
+				Ok(Ty::Str)
+			}
+			Type::Ref { mutable, to } => Ok(Ty::Ref {
+				mutable: *mutable,
+				to: Box::new(Ty::of(to, line)?),
+			}),
+			Type::Tuple(types) => types
+				.iter()
+				.map(|ty| Ty::of(ty, line))
+				.collect::<Result<_, _>>()
+				.map(Ty::Tuple),
+			Type::Named(_) => Err(Problem::new(
+				line,
+				format!("the type `{ty}` is beyond the checker"),
+			)),
+		}
+	}
+
+	fn is_copy(&self) -> bool {
+		match self {
+			Ty::Scalar(_) | Ty::Str => true,
+			Ty::String => false,
+			Ty::Ref { mutable, .. } => !mutable,
+			Ty::Tuple(types) => types.iter().all(Ty::is_copy),
+		}
+	}
+
+	fn has_ref(&self) -> bool {
+		match self {
+			Ty::Scalar(_) | Ty::String => false,
+			Ty::Str | Ty::Ref { .. } => true,
+			Ty::Tuple(types) => types.iter().any(Ty::has_ref),
+		}
+	}
+
+	/// Whether a value of type `found` can be given where `self` is
+	/// expected. Numbers of one kind are taken to fit each other: which
+	/// integer type a literal is has nothing to do with ownership.
+	fn fits(&self, found: &Ty) -> bool {
+		match (self, found) {
+			(Ty::Scalar(expected), Ty::Scalar(found)) => {
+				scalar_kind(expected) == scalar_kind(found)
+			}
+			(
+				Ty::Ref { mutable, to },
+				Ty::Ref {
+					mutable: found_mutable,
+					to: found_to,
+				},
+			) => (*found_mutable || !mutable) && to.fits(found_to),
+			(Ty::Str, Ty::Ref { to, .. }) => **to == Ty::String,
+			(Ty::Tuple(expected), Ty::Tuple(found)) => {
+				expected.len() == found.len()
+					&& expected
+						.iter()
+						.zip(found)
+						.all(|(expected, found)| expected.fits(found))
+			}
+			(expected, found) => expected == found,
+		}
+	}
+}
+
+impl fmt::Display for Ty {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Ty::Scalar(name) => f.write_str(name),
+			Ty::String => f.write_str("String"),
+			Ty::Str => f.write_str("&str"),
+			Ty::Ref { mutable: true, to } => write!(f, "&mut {to}"),
+			Ty::Ref { mutable: false, to } => write!(f, "&{to}"),
+			Ty::Tuple(types) => {
+				let types: Vec<String> = types.iter().map(Ty::to_string).collect();
+				match &types[..] {
+					[one] => write!(f, "({one},)"),
+					_ => write!(f, "({})", types.join(", ")),
+				}
+			}
+		}
+	}
+}
+
+fn is_scalar(name: &str) -> bool {
+	matches!(
+		name,
+		"i8" | "i16"
+			| "i32" | "i64"
+			| "i128" | "isize"
+			| "u8" | "u16"
+			| "u32" | "u64"
+			| "u128" | "usize"
+			| "f32" | "f64"
+			| "bool" | "char"
+	)
+}
+
+/// Integers and floats by kind, other scalars by name.
+fn scalar_kind(name: &str) -> &str {
+	match name.as_bytes()[0] {
+		b'i' | b'u' => "integer",
+		b'f' => "float",
+		_ => name,
+	}
+}
+
+/// A value being checked: its type and the loans it holds, if it is or
+/// contains a reference.
+#[derive(Debug, Clone)]
+struct Val {
+	ty: Ty,
+	loans: Vec<usize>,
+}
+
+impl Val {
+	fn of(ty: Ty) -> Val {
+		Val {
+			ty,
+			loans: Vec::new(),
+		}
+	}
+}
+
+struct Binding {
+	name: String,
+	/// Which binding this is, counting from 0 in the order they are made,
+	/// the same in both passes.
+	id: usize,
+	mutable: bool,
+	ty: Ty,
+	moved: Option<Moved>,
+}
+
+/// Where a value went and on which line.
+#[derive(Debug, Clone)]
+struct Moved {
+	line: usize,
+	to: String,
+}
+
+/// A borrow of a binding.
+struct Loan {
+	of: usize,
+	name: String,
+	mutable: bool,
+	line: usize,
+	/// The `&s` or `&mut s` that made it.
+	text: String,
+	/// The bindings the reference has been stored in.
+	holders: Vec<usize>,
+}
+
+/// How an expression's value is used.
+#[derive(Debug, Clone, Copy)]
+enum Use<'a> {
+	/// Read or borrowed in place.
+	Read,
+	/// Moved or copied somewhere, described as in "moves into `f`".
+	Move(&'a str),
+}
+
+enum Stop {
+	Rejected(Rejection),
+	Problem(Problem),
+}
+
+impl From<Problem> for Stop {
+	fn from(problem: Problem) -> Stop {
+		Stop::Problem(problem)
+	}
+}
+
+type Check<T> = Result<T, Stop>;
+
+fn beyond<T>(line: usize, message: impl Into<String>) -> Check<T> {
+	Err(Stop::Problem(Problem::new(line, message)))
+}
+
+struct Checker<'p> {
+	program: &'p Program,
+	/// The last use of each binding by id, as the sequence number and line
+	/// of the use; `None` in the first pass, which finds them.
+	last_use: Option<HashMap<usize, (usize, usize)>>,
+	/// The uses the first pass finds.
+	uses: HashMap<usize, (usize, usize)>,
+	/// How many uses have been seen so far.
+	seq: usize,
+	names: Vec<String>,
+	scopes: Vec<Vec<Binding>>,
+	loans: Vec<Loan>,
+	/// The loans of the statement being checked that no binding holds.
+	temporaries: Vec<usize>,
+	/// Whether the code just checked leaves by `return`, `break` or
+	/// `continue`, so that what it moves does not count after it.
+	diverged: bool,
+	/// Nonzero while going over a loop body again, which only checks.
+	quiet: usize,
+	notes: Vec<Note>,
+	/// The function being checked.
+	current: Option<&'p Function>,
+}
+
+impl<'p> Checker<'p> {
+	fn new(program: &'p Program, last_use: Option<HashMap<usize, (usize, usize)>>) -> Self {
+		Checker {
+			program,
+			last_use,
+			uses: HashMap::new(),
+			seq: 0,
+			names: Vec::new(),
+			scopes: Vec::new(),
+			loans: Vec::new(),
+			temporaries: Vec::new(),
+			diverged: false,
+			quiet: 0,
+			notes: Vec::new(),
+			current: None,
+		}
+	}
+
+	fn note(&mut self, line: usize, text: String) {
+		if self.last_use.is_some() && self.quiet == 0 {
+			self.notes.push(Note { line, text });
+		}
+	}
+
+	/// Rejects the program in the second pass; the first goes on, to see
+	/// every use.
+	fn reject(
+		&self,
+		line: usize,
+		code: &'static str,
+		message: String,
+		rule: Rule,
+		why: String,
+	) -> Check<()> {
+		if self.last_use.is_none() {
+			return Ok(());
+		}
+		Err(Stop::Rejected(Rejection {
+			line,
+			code,
+			message,
+			rule,
+			why,
+		}))
+	}
+
+	fn function(&mut self, function: &'p Function) -> Check<()> {
+		self.current = Some(function);
+		let mut params = Vec::new();
+		for param in &function.params {
+			params.push((param, Ty::of(&param.ty, function.line)?));
+		}
+		let ret = match &function.ret {
+			Some(ty) => Ty::of(ty, function.line)?,
+			None => Ty::unit(),
+		};
+		// The reference a function returns borrows from the one it is given;
+		// with none or several, which is for a lifetime annotation to say.
+		let given = params.iter().filter(|(_, ty)| ty.has_ref()).count();
+		if ret.has_ref() && given != 1 {
+			let why = if given == 0 {
+				format!(
+					"`{}` returns a reference but is given none, so it could only point at a \
+					 value of the function's own, and those are dropped when it returns.",
+					function.name
+				)
+			} else {
+				format!(
+					"`{}` returns a reference and is given {given}, so nothing says which of \
+					 them it borrows from, nor so how long it may be used before that value is \
+					 dropped.",
+					function.name
+				)
+			};
+			self.reject(
+				function.line,
+				"E0106",
+				"missing lifetime specifier".to_string(),
+				Rule::Dropped,
+				why,
+			)?;
+		}
+
+		self.scopes.push(Vec::new());
+		for (param, ty) in params {
+			let text = if ty.is_copy() || ty.has_ref() {
+				format!("{} comes into scope", param.name)
+			} else {
+				format!(
+					"{} comes into scope, owning the {ty} it is given",
+					param.name
+				)
+			};
+			self.note(function.line, text);
+			self.declare(&param.name, param.mutable, ty);
+		}
+		let value = self.block(&function.body, Use::Move("to the caller"), true)?;
+		self.returns(&value, &ret, function.body.lines.end)?;
+		self.temporaries.clear();
+		self.end_scope(function.body.lines.end, None)
+	}
+
+	/// Checks what a function gives back, from its body or a `return`.
+	fn returns(&mut self, value: &Val, ret: &Ty, line: usize) -> Check<()> {
+		let Some(function) = self.current else {
+			return Ok(());
+		};
+		if self.diverged {
+			return Ok(());
+		}
+		if !ret.fits(&value.ty) {
+			return beyond(
+				line,
+				format!(
+					"mismatched types: `{}` returns `{ret}`, but this is `{}`",
+					function.name, value.ty
+				),
+			);
+		}
+		if let Some(&index) = value.loans.first() {
+			let loan = &self.loans[index];
+			self.reject(
+				line,
+				"E0515",
+				format!("cannot return reference to local variable `{}`", loan.name),
+				Rule::Dropped,
+				format!(
+					"{} belongs to `{}` and is dropped when it returns, so `{}` would point at \
+					 nothing.",
+					loan.name, function.name, loan.text
+				),
+			)?;
+		}
+		Ok(())
+	}
+
+	fn declare(&mut self, name: &str, mutable: bool, ty: Ty) -> usize {
+		let id = self.names.len();
+		self.names.push(name.to_string());
+		self.scopes
+			.last_mut()
+			.expect("a block is being checked")
+			.push(Binding {
+				name: name.to_string(),
+				id,
+				mutable,
+				ty,
+				moved: None,
+			});
+		id
+	}
+
+	fn lookup(&self, name: &str) -> Option<&Binding> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.iter().rev().find(|binding| binding.name == name))
+	}
+
+	fn binding_mut(&mut self, id: usize) -> &mut Binding {
+		self.scopes
+			.iter_mut()
+			.flatten()
+			.find(|binding| binding.id == id)
+			.expect("the binding is in scope")
+	}
+
+	/// Records a use of a binding, which keeps the loans it holds alive up
+	/// to here.
+	fn touch(&mut self, id: usize, line: usize) {
+		self.uses.insert(id, (self.seq, line));
+		self.seq += 1;
+	}
+
+	/// The line a binding is used on after this point, the last such use.
+	fn used_later(&self, id: usize) -> Option<usize> {
+		let (seq, line) = *self.last_use.as_ref()?.get(&id)?;
+		(seq >= self.seq).then_some(line)
+	}
+
+	/// Who keeps a loan alive here, as in "r1, which borrows s at line 3
+	/// and is used again at line 6", or `None` if no one does.
+	fn borrower(&self, index: usize) -> Option<String> {
+		let loan = &self.loans[index];
+		let holder = loan
+			.holders
+			.iter()
+			.find_map(|&id| Some((id, self.used_later(id)?)));
+		if let Some((id, line)) = holder {
+			return Some(format!(
+				"{}, which borrows {} at line {} and is used again at line {line}",
+				self.names[id], loan.name, loan.line
+			));
+		}
+		self.temporaries.contains(&index).then(|| {
+			format!(
+				"`{}` at line {}, which is still in use in the same statement",
+				loan.text, loan.line
+			)
+		})
+	}
+
+	/// The first live loan of a binding, if any, that `conflicts` with
+	/// whether it is mutable, and who holds it.
+	fn live_loan(&self, id: usize, conflicts: impl Fn(bool) -> bool) -> Option<(bool, String)> {
+		self.loans
+			.iter()
+			.enumerate()
+			.filter(|(_, loan)| loan.of == id && conflicts(loan.mutable))
+			.find_map(|(index, loan)| Some((loan.mutable, self.borrower(index)?)))
+	}
+
+	/// Ends the innermost scope, dropping its bindings, last made first.
+	/// `value` is what the block gives, which may not borrow from them.
+	fn end_scope(&mut self, line: usize, value: Option<&Val>) -> Check<()> {
+		let scope = self.scopes.pop().unwrap_or_default();
+		for binding in scope.iter().rev() {
+			let given = value.and_then(|value| {
+				value
+					.loans
+					.iter()
+					.find(|&&index| self.loans[index].of == binding.id)
+			});
+			let borrower = match given {
+				Some(&index) => Some(format!(
+					"`{}` at line {}, which the block gives as its value",
+					self.loans[index].text, self.loans[index].line
+				)),
+				None => self.live_loan(binding.id, |_| true).map(|(_, who)| who),
+			};
+			if let Some(borrower) = borrower {
+				self.reject(
+					line,
+					"E0597",
+					format!("`{}` does not live long enough", binding.name),
+					Rule::Dropped,
+					format!(
+						"{} is dropped at the end of its block on line {line}, too early for \
+						 {borrower}: that reference would point at nothing.",
+						binding.name
+					),
+				)?;
+			}
+			let text = match (&binding.moved, binding.ty.is_copy() || binding.ty.has_ref()) {
+				(Some(moved), _) => format!(
+					"{} goes out of scope, but its value moved {} at line {}, so nothing is \
+					 dropped",
+					binding.name, moved.to, moved.line
+				),
+				(None, true) => format!("{} goes out of scope", binding.name),
+				(None, false) => format!(
+					"{} goes out of scope and its {} is dropped",
+					binding.name, binding.ty
+				),
+			};
+			self.note(line, text);
+		}
+		Ok(())
+	}
+
+	/// Checks a block in a scope of its own, or for a function's `body`
+	/// in the scope of its parameters, which the caller ends.
+	fn block(&mut self, block: &Block, how: Use<'_>, body: bool) -> Check<Val> {
+		let outer = self.temporaries.len();
+		if !body {
+			self.scopes.push(Vec::new());
+		}
+		for stmt in &block.stmts {
+			let temporaries = self.temporaries.len();
+			match stmt {
+				Stmt::Let {
+					name,
+					mutable,
+					ty,
+					init,
+					line,
+				} => {
+					let Some(init) = init else {
+						return beyond(*line, "a `let` without a value is beyond the checker");
+					};
+					let to = format!("to {name}");
+					let value = self.expr(init, Use::Move(&to))?;
+					let ty = match ty {
+						Some(ty) => {
+							let ty = Ty::of(ty, *line)?;
+							if !ty.fits(&value.ty) {
+								return beyond(
+									*line,
+									format!(
+										"mismatched types: expected `{ty}`, found `{}`",
+										value.ty
+									),
+								);
+							}
+							ty
+						}
+						None => value.ty,
+					};
+					self.bind(name, *mutable, ty, value.loans, *line);
+				}
+				Stmt::Expr(expr) => {
+					self.expr(expr, Use::Move("into a statement, which drops it"))?;
+				}
+			}
+			self.temporaries.truncate(temporaries);
+		}
+		let value = match &block.tail {
+			Some(tail) => self.expr(tail, how)?,
+			None => Val::of(Ty::unit()),
+		};
+		if !body {
+			// The borrows the block's statements made end with it, except
+			// the ones in its value.
+			self.temporaries.truncate(outer);
+			self.end_scope(block.lines.end, Some(&value))?;
+			self.temporaries.extend(&value.loans);
+		}
+		Ok(value)
+	}
+
+	fn bind(&mut self, name: &str, mutable: bool, ty: Ty, loans: Vec<usize>, line: usize) {
+		let shadows = self.lookup(name).is_some();
+		let owns = !ty.is_copy() && !ty.has_ref();
+		let id = self.declare(name, mutable, ty.clone());
+		let mut text = format!("{name} comes into scope");
+		if owns {
+			text.push_str(&format!(", owning a {ty}"));
+		}
+		if shadows {
+			text.push_str(&format!(", shadowing the earlier {name}"));
+		}
+
+		let mut borrowed = Vec::new();
+		for index in loans {
+			let loan = &mut self.loans[index];
+			loan.holders.push(id);
+			let how = if loan.mutable { " mutably" } else { "" };
+			borrowed.push(format!("{}{how}", loan.name));
+		}
+		if !borrowed.is_empty() {
+			let last_use = self
+				.last_use
+				.as_ref()
+				.and_then(|uses| uses.get(&id))
+				.map(|&(_, line)| line);
+			text.push_str(&format!(", borrowing {}", borrowed.join(" and ")));
+			text.push_str(&match last_use {
+				Some(last) => format!(" until its last use, on line {last}"),
+				None => ", but is never used, so the borrow ends at once".to_string(),
+			});
+		}
+		self.note(line, text);
+	}
+
+	fn expr(&mut self, expr: &Expr, how: Use<'_>) -> Check<Val> {
+		let line = expr.line;
+		match &expr.kind {
+			ExprKind::Int { suffix, .. } => Ok(Val::of(Ty::Scalar(
+				suffix.clone().unwrap_or_else(|| "i32".to_string()),
+			))),
+			ExprKind::Float(text) => {
+				let ty = if text.ends_with("f32") { "f32" } else { "f64" };
+				Ok(Val::of(Ty::Scalar(ty.to_string())))
+			}
+			ExprKind::Bool(_) => Ok(Val::of(Ty::Scalar("bool".to_string()))),
+			ExprKind::Char(_) => Ok(Val::of(Ty::Scalar("char".to_string()))),
+			ExprKind::Str(_) => Ok(Val::of(Ty::Str)),
+			ExprKind::Tuple(items) => {
+				let mut tuple = Val::of(Ty::unit());
+				let mut types = Vec::new();
+				for item in items {
+					let item = self.expr(item, how)?;
+					types.push(item.ty);
+					tuple.loans.extend(item.loans);
+				}
+				tuple.ty = Ty::Tuple(types);
+				Ok(tuple)
+			}
+			ExprKind::Var(name) => self.var(name, line, how),
+			ExprKind::Unary(_, operand) => self.expr(operand, Use::Read),
+			ExprKind::Ref { mutable, value } => self.borrow(expr, *mutable, value),
+			ExprKind::Deref(value) => {
+				let value = self.expr(value, Use::Read)?;
+				match value.ty {
+					Ty::Ref { to, .. } => Ok(Val {
+						ty: *to,
+						loans: Vec::new(),
+					}),
+					Ty::Str => beyond(line, "a `str` on its own is beyond the checker"),
+					ty => beyond(line, format!("`{ty}` cannot be dereferenced")),
+				}
+			}
+			ExprKind::Binary(op, left, right) => self.binary(*op, left, right),
+			ExprKind::Assign { op, target, value } => self.assign(*op, target, value, line),
+			ExprKind::Range { start, end, .. } => {
+				self.expr(end, Use::Read)?;
+				self.expr(start, Use::Read)
+			}
+			ExprKind::Block(block) => self.block(block, how, false),
+			ExprKind::If {
+				cond,
+				then,
+				otherwise,
+			} => self.if_expr(cond, then, otherwise.as_deref(), how),
+			ExprKind::Loop { body, .. } => {
+				self.looped(|checker| checker.block(body, Use::Read, false).map(drop))?;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::While { cond, body, .. } => {
+				self.looped(|checker| {
+					checker.expr(cond, Use::Read)?;
+					checker.block(body, Use::Read, false).map(drop)
+				})?;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::For {
+				var, iter, body, ..
+			} => {
+				let range = match &iter.kind {
+					ExprKind::Method {
+						receiver,
+						name,
+						args,
+					} if name == "rev" && args.is_empty() => receiver,
+					_ => iter,
+				};
+				if !matches!(range.kind, ExprKind::Range { .. }) {
+					return beyond(
+						line,
+						"a `for` loop other than over a range is beyond the checker",
+					);
+				}
+				let ty = self.expr(range, Use::Read)?.ty;
+				self.looped(|checker| {
+					checker.scopes.push(Vec::new());
+					checker.declare(var, false, ty.clone());
+					checker.block(body, Use::Read, false)?;
+					checker.quiet += 1;
+					let end = checker.end_scope(line, None);
+					checker.quiet -= 1;
+					end
+				})?;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::Break { value, .. } => {
+				if let Some(value) = value {
+					self.expr(value, Use::Move("out of the loop"))?;
+				}
+				self.diverged = true;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::Continue { .. } => {
+				self.diverged = true;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::Return(value) => {
+				let value = match value {
+					Some(value) => self.expr(value, Use::Move("to the caller"))?,
+					None => Val::of(Ty::unit()),
+				};
+				if let Some(function) = self.current {
+					let ret = match &function.ret {
+						Some(ty) => Ty::of(ty, function.line)?,
+						None => Ty::unit(),
+					};
+					self.returns(&value, &ret, line)?;
+				}
+				self.diverged = true;
+				Ok(Val::of(Ty::unit()))
+			}
+			ExprKind::Call { name, args } => self.call(name, args, line),
+			ExprKind::Method {
+				receiver,
+				name,
+				args,
+			} => self.method(receiver, name, args, line),
+			ExprKind::Print { format, args, .. } => {
+				for name in implicit_args(format) {
+					self.var(name, line, Use::Read)?;
+				}
+				for arg in args {
+					self.expr(arg, Use::Read)?;
+				}
+				Ok(Val::of(Ty::unit()))
+			}
+		}
+	}
+
+	fn var(&mut self, name: &str, line: usize, how: Use<'_>) -> Check<Val> {
+		let Some(binding) = self.lookup(name) else {
+			return beyond(line, format!("cannot find value `{name}` in the snippet"));
+		};
+		let (id, ty, moved) = (binding.id, binding.ty.clone(), binding.moved.clone());
+		self.touch(id, line);
+		let loans: Vec<usize> = (0..self.loans.len())
+			.filter(|&index| self.loans[index].holders.contains(&id))
+			.collect();
+
+		if let Some(moved) = moved {
+			let code = match how {
+				Use::Read => "borrow",
+				Use::Move(_) => "use",
+			};
+			let when = if moved.line >= line {
+				" in an earlier pass of the loop"
+			} else {
+				""
+			};
+			self.reject(
+				line,
+				"E0382",
+				format!("{code} of moved value: `{name}`"),
+				Rule::OneOwner,
+				format!(
+					"{name} moved {} at line {}{when}; its value has a new owner, so {name} \
+					 holds nothing to use any more.",
+					moved.to, moved.line
+				),
+			)?;
+		}
+
+		if ty.is_copy() || matches!(how, Use::Read) {
+			if let Some((_, who)) = self.live_loan(id, |mutable| mutable) {
+				let (code, message) = if ty.is_copy() {
+					(
+						"E0503",
+						format!("cannot use `{name}` because it was mutably borrowed"),
+					)
+				} else {
+					(
+						"E0502",
+						format!(
+							"cannot borrow `{name}` as immutable because it is also borrowed as \
+							 mutable"
+						),
+					)
+				};
+				self.reject(
+					line,
+					code,
+					message,
+					Rule::Exclusive,
+					format!(
+						"There is {who}; while that `&mut` is in use nothing else may get at \
+						 {name}, even to read it."
+					),
+				)?;
+			}
+			if let Use::Move(to) = how {
+				self.note(line, format!("{name} is copied {to}: {ty} is Copy"));
+			}
+			return Ok(Val { ty, loans });
+		}
+
+		let Use::Move(to) = how else {
+			unreachable!("reads return above")
+		};
+		if let Some((_, who)) = self.live_loan(id, |_| true) {
+			self.reject(
+				line,
+				"E0505",
+				format!("cannot move out of `{name}` because it is borrowed"),
+				Rule::OneOwner,
+				format!(
+					"There is {who}; moving {name} would hand its value to an owner that could \
+					 drop it while that borrow still points at it."
+				),
+			)?;
+		}
+		self.note(
+			line,
+			format!("{name} moves {to}, and can no longer be used"),
+		);
+		self.binding_mut(id).moved = Some(Moved {
+			line,
+			to: to.to_string(),
+		});
+		// The references a moved `&mut` holds go with it.
+		for &index in &loans {
+			self.loans[index].holders.retain(|&holder| holder != id);
+		}
+		Ok(Val { ty, loans })
+	}
+
+	/// Checks `&value` or `&mut value`.
+	fn borrow(&mut self, expr: &Expr, mutable: bool, value: &Expr) -> Check<Val> {
+		let line = expr.line;
+		let ExprKind::Var(name) = &value.kind else {
+			let inner = self.expr(value, Use::Read)?;
+			return Ok(Val {
+				ty: Ty::Ref {
+					mutable,
+					to: Box::new(inner.ty),
+				},
+				loans: inner.loans,
+			});
+		};
+		let Some(binding) = self.lookup(name) else {
+			return beyond(line, format!("cannot find value `{name}` in the snippet"));
+		};
+		let (id, ty) = (binding.id, binding.ty.clone());
+		self.touch(id, line);
+		self.lend(id, mutable, line)?;
+		let index = self.loans.len();
+		self.loans.push(Loan {
+			of: id,
+			name: name.clone(),
+			mutable,
+			line,
+			text: expr.text.clone(),
+			holders: Vec::new(),
+		});
+		self.temporaries.push(index);
+		Ok(Val {
+			ty: Ty::Ref {
+				mutable,
+				to: Box::new(ty),
+			},
+			loans: vec![index],
+		})
+	}
+
+	/// Checks that the binding `id` can be lent out, mutably or not, on
+	/// `line`.
+	fn lend(&self, id: usize, mutable: bool, line: usize) -> Check<()> {
+		let binding = self
+			.scopes
+			.iter()
+			.flatten()
+			.find(|binding| binding.id == id)
+			.expect("the binding is in scope");
+		let (name, ty) = (&binding.name, &binding.ty);
+		if let Some(moved) = &binding.moved {
+			self.reject(
+				line,
+				"E0382",
+				format!("borrow of moved value: `{name}`"),
+				Rule::OneOwner,
+				format!(
+					"{name} moved {} at line {}; its value has a new owner, so there is nothing \
+					 left in {name} to borrow.",
+					moved.to, moved.line
+				),
+			)?;
+		}
+		if mutable && !binding.mutable {
+			self.reject(
+				line,
+				"E0596",
+				format!("cannot borrow `{name}` as mutable, as it is not declared as mutable"),
+				Rule::Immutable,
+				format!(
+					"{name} is not declared `mut`, so its {ty} cannot be changed, nor lent out to \
+					 be changed; `let mut {name}` would allow it."
+				),
+			)?;
+		}
+		let conflict = self.live_loan(id, |other| mutable || other);
+		if let Some((other_mutable, who)) = conflict {
+			let (code, message) = match (mutable, other_mutable) {
+				(true, true) => (
+					"E0499",
+					format!("cannot borrow `{name}` as mutable more than once at a time"),
+				),
+				(true, false) => (
+					"E0502",
+					format!(
+						"cannot borrow `{name}` as mutable because it is also borrowed as \
+						 immutable"
+					),
+				),
+				_ => (
+					"E0502",
+					format!(
+						"cannot borrow `{name}` as immutable because it is also borrowed as \
+						 mutable"
+					),
+				),
+			};
+			let why = if mutable {
+				format!(
+					"There is {who}, so a `&mut` to {name} here would not be the only \
+					 reference to it."
+				)
+			} else {
+				format!(
+					"There is {who}, and while that `&mut` is in use it must be the only \
+					 reference to {name}."
+				)
+			};
+			self.reject(line, code, message, Rule::Exclusive, why)?;
+		}
+		Ok(())
+	}
+
+	fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr) -> Check<Val> {
+		// `s1 + &s2` takes `s1` over and gives it back with `s2` added.
+		let concatenates = op == BinaryOp::Add
+			&& matches!(&left.kind, ExprKind::Var(name)
+				if self.lookup(name).is_some_and(|binding| binding.ty == Ty::String));
+		let how = if concatenates {
+			Use::Move("into `+`")
+		} else {
+			Use::Read
+		};
+		let left = self.expr(left, how)?;
+		self.expr(right, Use::Read)?;
+		Ok(match op {
+			BinaryOp::Eq
+			| BinaryOp::Ne
+			| BinaryOp::Lt
+			| BinaryOp::Le
+			| BinaryOp::Gt
+			| BinaryOp::Ge
+			| BinaryOp::And
+			| BinaryOp::Or => Val::of(Ty::Scalar("bool".to_string())),
+			_ => Val::of(left.ty),
+		})
+	}
+
+	fn assign(
+		&mut self,
+		op: Option<BinaryOp>,
+		target: &str,
+		value: &Expr,
+		line: usize,
+	) -> Check<Val> {
+		let to = format!("to {target}");
+		let how = match op {
+			Some(_) => Use::Read,
+			None => Use::Move(&to),
+		};
+		let value = self.expr(value, how)?;
+		let Some(binding) = self.lookup(target) else {
+			return beyond(line, format!("cannot find value `{target}` in the snippet"));
+		};
+		let (id, ty, moved, mutable) = (
+			binding.id,
+			binding.ty.clone(),
+			binding.moved.clone(),
+			binding.mutable,
+		);
+		if op.is_none() && !ty.fits(&value.ty) {
+			return beyond(
+				line,
+				format!("mismatched types: expected `{ty}`, found `{}`", value.ty),
+			);
+		}
+		if !mutable {
+			self.reject(
+				line,
+				"E0384",
+				format!("cannot assign twice to immutable variable `{target}`"),
+				Rule::Immutable,
+				format!(
+					"{target} is not declared `mut`, so once it has a value it keeps it; `let mut \
+					 {target}` would allow the assignment."
+				),
+			)?;
+		}
+		if let Some((_, who)) = self.live_loan(id, |_| true) {
+			self.reject(
+				line,
+				"E0506",
+				format!("cannot assign to `{target}` because it is borrowed"),
+				Rule::ReadOnly,
+				format!(
+					"There is {who}, and assigning to {target} would change the value that \
+					 borrow points at while it is in use."
+				),
+			)?;
+		}
+		if op.is_some() {
+			if let Some(moved) = &moved {
+				self.reject(
+					line,
+					"E0382",
+					format!("use of moved value: `{target}`"),
+					Rule::OneOwner,
+					format!(
+						"{target} moved {} at line {}, so there is nothing in it to add to.",
+						moved.to, moved.line
+					),
+				)?;
+			}
+			return Ok(Val::of(Ty::unit()));
+		}
+
+		if !ty.is_copy() && !ty.has_ref() {
+			let text = match &moved {
+				Some(_) => format!("{target} is given a new {ty}"),
+				None => format!("{target} is given a new {ty}, and its old one is dropped"),
+			};
+			self.note(line, text);
+		}
+		self.binding_mut(id).moved = None;
+		for loan in &mut self.loans {
+			loan.holders.retain(|&holder| holder != id);
+		}
+		for index in value.loans {
+			self.loans[index].holders.push(id);
+			let text = format!("{target} now borrows {}", self.loans[index].name);
+			self.note(line, text);
+		}
+		Ok(Val::of(Ty::unit()))
+	}
+
+	fn if_expr(
+		&mut self,
+		cond: &Expr,
+		then: &Block,
+		otherwise: Option<&Expr>,
+		how: Use<'_>,
+	) -> Check<Val> {
+		self.expr(cond, Use::Read)?;
+		let before = self.moves();
+		self.diverged = false;
+		let value = self.block(then, how, false)?;
+		let (then_moves, then_diverged) = (self.moves(), self.diverged);
+
+		self.restore(&before);
+		self.diverged = false;
+		let value = match otherwise {
+			Some(otherwise) => {
+				let other = self.expr(otherwise, how)?;
+				Val {
+					ty: value.ty,
+					loans: value.loans.into_iter().chain(other.loans).collect(),
+				}
+			}
+			None => Val::of(Ty::unit()),
+		};
+		let else_diverged = self.diverged;
+
+		// What either branch moves is moved after the `if`, unless that
+		// branch never gets there.
+		if else_diverged && !then_diverged {
+			self.restore(&then_moves);
+		} else if !then_diverged {
+			for (id, moved) in then_moves {
+				if let Some(moved) = moved {
+					let binding = self.binding_mut(id);
+					binding.moved.get_or_insert(moved);
+				}
+			}
+		}
+		self.diverged = then_diverged && else_diverged;
+		Ok(value)
+	}
+
+	/// Where every binding in scope stands, moved or not.
+	fn moves(&self) -> Vec<(usize, Option<Moved>)> {
+		self.scopes
+			.iter()
+			.flatten()
+			.map(|binding| (binding.id, binding.moved.clone()))
+			.collect()
+	}
+
+	fn restore(&mut self, moves: &[(usize, Option<Moved>)]) {
+		for binding in self.scopes.iter_mut().flatten() {
+			if let Some((_, moved)) = moves.iter().find(|(id, _)| *id == binding.id) {
+				binding.moved = moved.clone();
+			}
+		}
+	}
+
+	/// Checks a loop body twice, so that what one pass moves is seen to be
+	/// gone in the next; notes are kept from the first only.
+	fn looped(&mut self, mut body: impl FnMut(&mut Self) -> Check<()>) -> Check<()> {
+		body(self)?;
+		self.diverged = false;
+		self.quiet += 1;
+		let again = body(self);
+		self.quiet -= 1;
+		self.diverged = false;
+		again
+	}
+
+	fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Check<Val> {
+		match name {
+			"String::from" | "String::new" => {
+				for arg in args {
+					self.expr(arg, Use::Read)?;
+				}
+				return Ok(Val::of(Ty::String));
+			}
+			_ if name.contains("::") => {
+				return beyond(line, format!("the function `{name}` is beyond the checker"))
+			}
+			_ => {}
+		}
+		let program = self.program;
+		let Some(function) = program.function(name) else {
+			return beyond(
+				line,
+				format!("cannot find function `{name}` in the snippet"),
+			);
+		};
+		if args.len() != function.params.len() {
+			return beyond(
+				line,
+				format!(
+					"`{name}` takes {} arguments but {} were given",
+					function.params.len(),
+					args.len()
+				),
+			);
+		}
+
+		let into = format!("into `{name}`");
+		let mut loans = Vec::new();
+		for (param, arg) in function.params.iter().zip(args) {
+			let expected = Ty::of(&param.ty, function.line)?;
+			let by_ref = matches!(expected, Ty::Ref { .. } | Ty::Str);
+			// A `&mut` given to a function is lent to it again rather than
+			// moved.
+			let how = if by_ref { Use::Read } else { Use::Move(&into) };
+			let value = self.expr(arg, how)?;
+			if !expected.fits(&value.ty) {
+				if let (Ty::Ref { mutable: true, .. }, Ty::Ref { mutable: false, .. }) =
+					(&expected, &value.ty)
+				{
+					self.reject(
+						line,
+						"E0308",
+						format!(
+							"mismatched types: expected `{expected}`, found `{}`",
+							value.ty
+						),
+						Rule::ReadOnly,
+						format!(
+							"`{name}` takes a `{expected}` to change what it points at, but \
+							 `{}` is a shared reference, which cannot change it.",
+							arg.text
+						),
+					)?;
+				} else {
+					return beyond(
+						line,
+						format!(
+							"mismatched types: expected `{expected}`, found `{}`",
+							value.ty
+						),
+					);
+				}
+			}
+			if let ExprKind::Ref {
+				mutable,
+				value: borrowed,
+			} = &arg.kind
+			{
+				if let ExprKind::Var(borrowed) = &borrowed.kind {
+					let how = if *mutable { " mutably" } else { "" };
+					self.note(
+						line,
+						format!("`{name}` borrows {borrowed}{how} for the call, and gives it back"),
+					);
+				}
+			}
+			loans.extend(value.loans);
+		}
+		let ret = match &function.ret {
+			Some(ty) => Ty::of(ty, function.line)?,
+			None => Ty::unit(),
+		};
+		if !ret.has_ref() {
+			loans.clear();
+		}
+		Ok(Val { ty: ret, loans })
+	}
+
+	fn method(&mut self, receiver: &Expr, name: &str, args: &[Expr], line: usize) -> Check<Val> {
+		let needs_mut = match name {
+			"push_str" | "push" | "clear" => true,
+			"len" | "is_empty" | "clone" | "to_string" => false,
+			_ => {
+				return beyond(
+					line,
+					format!("the method `.{name}()` is beyond the checker"),
+				)
+			}
+		};
+
+		let ty = match &receiver.kind {
+			ExprKind::Var(var) => {
+				let Some(binding) = self.lookup(var) else {
+					return beyond(line, format!("cannot find value `{var}` in the snippet"));
+				};
+				let (id, ty) = (binding.id, binding.ty.clone());
+				match &ty {
+					Ty::Ref { mutable, to } => {
+						self.var(var, line, Use::Read)?;
+						if needs_mut && !mutable {
+							self.reject(
+								line,
+								"E0596",
+								format!(
+									"cannot borrow `*{var}` as mutable, as it is behind a `&` \
+									 reference"
+								),
+								Rule::ReadOnly,
+								format!(
+									"{var} is a `{ty}`, a shared reference: the {to} it points \
+									 at can be read through it but not changed, and `.{name}()` \
+									 changes it. Taking `&mut {to}` instead would allow it."
+								),
+							)?;
+						}
+						(**to).clone()
+					}
+					_ => {
+						self.touch(id, line);
+						self.lend(id, needs_mut, line)?;
+						ty
+					}
+				}
+			}
+			_ => self.expr(receiver, Use::Read)?.ty,
+		};
+		let ty = match ty {
+			Ty::Ref { to, .. } => *to,
+			ty => ty,
+		};
+		let fits = match name {
+			"push_str" | "push" | "clear" => ty == Ty::String,
+			"len" | "is_empty" | "to_string" => matches!(ty, Ty::String | Ty::Str),
+			_ => true,
+		};
+		if !fits {
+			return beyond(
+				line,
+				format!("`.{name}()` on a `{ty}` is beyond the checker"),
+			);
+		}
+
+		let into = format!("into `.{name}()`");
+		for arg in args {
+			self.expr(arg, Use::Move(&into))?;
+		}
+		Ok(Val::of(match name {
+			"len" => Ty::Scalar("usize".to_string()),
+			"is_empty" => Ty::Scalar("bool".to_string()),
+			"to_string" => Ty::String,
+			"clone" => ty,
+			_ => Ty::unit(),
+		}))
+	}
+}
+
+/// The names a format string uses inline, as the `x` of `"{x}"` or
+/// `"{x:?}"`.
+fn implicit_args(format: &str) -> Vec<&str> {
+	let mut names = Vec::new();
+	let mut rest = format;
+	while let Some(at) = rest.find(['{', '}']) {
+		let after = &rest[at + 1..];
+		if rest[at..].starts_with("{{") || rest[at..].starts_with("}}") {
+			rest = &after[1..];
+			continue;
+		}
+		if rest[at..].starts_with('}') {
+			rest = after;
+			continue;
+		}
+		let end = after.find('}').unwrap_or(after.len());
+		let name = after[..end].split(':').next().unwrap_or("");
+		if name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+			names.push(name);
+		}
+		rest = &after[end..];
+	}
+	names
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mini::syntax;
+
+	fn verdict(name: &str) -> Verdict {
+		let example = EXAMPLES
+			.iter()
+			.find(|example| example.name == name)
+			.unwrap();
+		check(&syntax::parse(example.source, 1).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn examples_are_checked_as_rustc_does() {
+		for example in &EXAMPLES {
+			let verdict = verdict(example.name);
+			assert_eq!(
+				verdict.rejection.map(|rejection| rejection.code),
+				example.rejected_with,
+				"{}",
+				example.name
+			);
+		}
+	}
+
+	#[test]
+	fn examples_cite_the_rule_they_break() {
+		let rule = |name| verdict(name).rejection.map(|rejection| rejection.rule);
+		assert_eq!(rule("takes_ownership"), Some(Rule::OneOwner));
+		assert_eq!(rule("change(&s)"), Some(Rule::ReadOnly));
+		assert_eq!(rule("makes_copy"), None);
+	}
+}
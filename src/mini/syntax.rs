@@ -0,0 +1,967 @@
+//! The syntax of the mini Rust, and its parser.
+//!
+//! A snippet is either a list of functions, one of them `main`, or a run of
+//! statements, which is taken as the body of `main`. Inside a function there
+//! are `let` bindings with or without `mut` and a type, assignments, integer
+//! and boolean arithmetic, blocks, `if`, `loop`, `while` and `for` over a
+//! range, loop labels, `break` with or without a value, `continue`,
+//! `return`, calls and `println!`; and for chapter 4, strings made with
+//! `String::from`, method calls, references and the other `Copy` values:
+//! floats, `char`s and tuples. Anything else is a [`Problem`] naming what
+//! is beyond the subset.
+
+use std::fmt;
+
+use crate::lex::{self, Token, TokenKind};
+use crate::model::Lines;
+
+use super::Problem;
+
+#[derive(Debug, Clone)]
+pub struct Program {
+	pub functions: Vec<Function>,
+}
+
+impl Program {
+	pub fn function(&self, name: &str) -> Option<&Function> {
+		self.functions.iter().find(|function| function.name == name)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+	pub name: String,
+	pub params: Vec<Param>,
+	/// `None` for a function that returns `()`.
+	pub ret: Option<Type>,
+	pub body: Block,
+	pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+	pub name: String,
+	pub mutable: bool,
+	pub ty: Type,
+}
+
+/// A type as written: the interpreter decides which ones it knows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+	Named(String),
+	Ref {
+		mutable: bool,
+		to: Box<Type>,
+	},
+	/// `()` when empty.
+	Tuple(Vec<Type>),
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Type::Named(name) => f.write_str(name),
+			Type::Ref { mutable: true, to } => write!(f, "&mut {to}"),
+			Type::Ref { mutable: false, to } => write!(f, "&{to}"),
+			Type::Tuple(types) => {
+				let types: Vec<String> = types.iter().map(Type::to_string).collect();
+				match &types[..] {
+					[one] => write!(f, "({one},)"),
+					_ => write!(f, "({})", types.join(", ")),
+				}
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+	pub stmts: Vec<Stmt>,
+	/// The expression the block ends with, which gives its value.
+	pub tail: Option<Box<Expr>>,
+	/// From the `{` to the `}`.
+	pub lines: Lines,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+	Let {
+		name: String,
+		mutable: bool,
+		ty: Option<Type>,
+		init: Option<Expr>,
+		line: usize,
+	},
+	Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+	pub kind: ExprKind,
+	pub line: usize,
+	/// The source of the expression, on one line.
+	pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+	Int {
+		value: i128,
+		/// The type of a literal such as `5u8`.
+		suffix: Option<String>,
+	},
+	/// A floating-point literal, as written.
+	Float(String),
+	Bool(bool),
+	Char(char),
+	Str(String),
+	/// `()` when empty.
+	Tuple(Vec<Expr>),
+	Var(String),
+	Unary(UnaryOp, Box<Expr>),
+	/// `&value` or `&mut value`.
+	Ref {
+		mutable: bool,
+		value: Box<Expr>,
+	},
+	/// `*reference`.
+	Deref(Box<Expr>),
+	Binary(BinaryOp, Box<Expr>, Box<Expr>),
+	/// `x = value`, or `x += value` and the like with the operator.
+	Assign {
+		op: Option<BinaryOp>,
+		target: String,
+		value: Box<Expr>,
+	},
+	Range {
+		start: Box<Expr>,
+		end: Box<Expr>,
+		inclusive: bool,
+	},
+	Block(Block),
+	If {
+		cond: Box<Expr>,
+		then: Block,
+		/// A block, or the `if` of an `else if`.
+		otherwise: Option<Box<Expr>>,
+	},
+	Loop {
+		label: Option<String>,
+		body: Block,
+	},
+	While {
+		label: Option<String>,
+		cond: Box<Expr>,
+		body: Block,
+	},
+	For {
+		label: Option<String>,
+		var: String,
+		iter: Box<Expr>,
+		body: Block,
+	},
+	Break {
+		label: Option<String>,
+		value: Option<Box<Expr>>,
+	},
+	Continue {
+		label: Option<String>,
+	},
+	Return(Option<Box<Expr>>),
+	/// A call of a function or of a path such as `String::from`.
+	Call {
+		name: String,
+		args: Vec<Expr>,
+	},
+	Method {
+		receiver: Box<Expr>,
+		name: String,
+		args: Vec<Expr>,
+	},
+	/// `println!` or `print!`.
+	Print {
+		newline: bool,
+		format: String,
+		args: Vec<Expr>,
+	},
+}
+
+impl ExprKind {
+	/// Whether the expression ends in a block, and so can be a statement
+	/// without a `;`.
+	fn is_block_like(&self) -> bool {
+		matches!(
+			self,
+			ExprKind::Block(_)
+				| ExprKind::If { .. }
+				| ExprKind::Loop { .. }
+				| ExprKind::While { .. }
+				| ExprKind::For { .. }
+		)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+	Neg,
+	Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Rem,
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	And,
+	Or,
+}
+
+impl BinaryOp {
+	pub fn symbol(self) -> &'static str {
+		match self {
+			BinaryOp::Add => "+",
+			BinaryOp::Sub => "-",
+			BinaryOp::Mul => "*",
+			BinaryOp::Div => "/",
+			BinaryOp::Rem => "%",
+			BinaryOp::Eq => "==",
+			BinaryOp::Ne => "!=",
+			BinaryOp::Lt => "<",
+			BinaryOp::Le => "<=",
+			BinaryOp::Gt => ">",
+			BinaryOp::Ge => ">=",
+			BinaryOp::And => "&&",
+			BinaryOp::Or => "||",
+		}
+	}
+}
+
+/// Binary operators from the loosest binding to the tightest.
+const PRECEDENCE: [&[BinaryOp]; 5] = [
+	&[BinaryOp::Or],
+	&[BinaryOp::And],
+	&[
+		BinaryOp::Eq,
+		BinaryOp::Ne,
+		BinaryOp::Lt,
+		BinaryOp::Le,
+		BinaryOp::Gt,
+		BinaryOp::Ge,
+	],
+	&[BinaryOp::Add, BinaryOp::Sub],
+	&[BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem],
+];
+
+/// Parses a snippet whose first line is `first_line` of its notes file.
+pub fn parse(source: &str, first_line: usize) -> Result<Program, Problem> {
+	let mut parser = Parser {
+		source,
+		tokens: lex::significant(source),
+		at: 0,
+		line_starts: line_starts(source),
+		first_line,
+	};
+	let mut functions = Vec::new();
+	if parser.peek() == "fn" {
+		while !parser.at_end() {
+			if parser.peek() != "fn" {
+				return Err(parser.beyond(&format!("`{}` at the top level", parser.peek())));
+			}
+			functions.push(parser.function()?);
+		}
+	} else {
+		let body = parser.statements(first_line)?;
+		functions.push(Function {
+			name: "main".to_string(),
+			params: Vec::new(),
+			ret: None,
+			body,
+			line: first_line,
+		});
+	}
+	Ok(Program { functions })
+}
+
+struct Parser<'a> {
+	source: &'a str,
+	tokens: Vec<Token<'a>>,
+	at: usize,
+	line_starts: Vec<usize>,
+	first_line: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn at_end(&self) -> bool {
+		self.at >= self.tokens.len()
+	}
+
+	/// The text of the next token, or `""` at the end.
+	fn peek(&self) -> &'a str {
+		self.tokens.get(self.at).map_or("", |token| token.text)
+	}
+
+	fn peek_kind(&self) -> Option<TokenKind> {
+		self.tokens.get(self.at).map(|token| token.kind)
+	}
+
+	fn eat(&mut self, text: &str) -> bool {
+		let found = !self.at_end() && self.peek() == text;
+		if found {
+			self.at += 1;
+		}
+		found
+	}
+
+	fn expect(&mut self, text: &str) -> Result<(), Problem> {
+		if self.eat(text) {
+			Ok(())
+		} else {
+			Err(self.error(&format!("expected `{text}`")))
+		}
+	}
+
+	fn ident(&mut self) -> Result<String, Problem> {
+		match self.tokens.get(self.at) {
+			Some(token) if token.kind == TokenKind::Ident => {
+				self.at += 1;
+				Ok(token.text.to_string())
+			}
+			_ => Err(self.error("expected a name")),
+		}
+	}
+
+	/// The line of the notes file byte `offset` of the snippet is on.
+	fn line_of(&self, offset: usize) -> usize {
+		self.first_line + self.line_starts.partition_point(|&start| start <= offset) - 1
+	}
+
+	/// The line of the next token, or of the last one at the end.
+	fn line(&self) -> usize {
+		let token = self.tokens.get(self.at).or(self.tokens.last());
+		token.map_or(self.first_line, |token| self.line_of(token.start))
+	}
+
+	fn error(&self, message: &str) -> Problem {
+		match self.tokens.get(self.at) {
+			Some(token) => Problem::new(self.line(), format!("{message}, found `{}`", token.text)),
+			None => Problem::new(
+				self.line(),
+				format!("{message}, found the end of the snippet"),
+			),
+		}
+	}
+
+	fn beyond(&self, what: &str) -> Problem {
+		Problem::new(self.line(), format!("{what} is beyond the subset"))
+	}
+
+	/// An expression made of the tokens from `start` up to here.
+	fn node(&self, start: usize, kind: ExprKind) -> Expr {
+		let first = &self.tokens[start];
+		let last = &self.tokens[self.at - 1];
+		let text = &self.source[first.start..last.start + last.text.len()];
+		Expr {
+			kind,
+			line: self.line_of(first.start),
+			text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+		}
+	}
+
+	fn function(&mut self) -> Result<Function, Problem> {
+		let line = self.line();
+		self.expect("fn")?;
+		let name = self.ident()?;
+		if self.peek() == "<" {
+			return Err(self.beyond("a generic function"));
+		}
+		self.expect("(")?;
+		let mut params = Vec::new();
+		while !self.eat(")") {
+			let mutable = self.eat("mut");
+			let name = self.ident()?;
+			self.expect(":")?;
+			params.push(Param {
+				name,
+				mutable,
+				ty: self.ty()?,
+			});
+			if !self.eat(",") {
+				self.expect(")")?;
+				break;
+			}
+		}
+		let ret = if self.eat("->") {
+			Some(self.ty()?)
+		} else {
+			None
+		};
+		Ok(Function {
+			name,
+			params,
+			ret,
+			body: self.block()?,
+			line,
+		})
+	}
+
+	fn ty(&mut self) -> Result<Type, Problem> {
+		if self.eat("&") {
+			let mutable = self.eat("mut");
+			return Ok(Type::Ref {
+				mutable,
+				to: Box::new(self.ty()?),
+			});
+		}
+		if self.eat("(") {
+			let mut types = Vec::new();
+			while !self.eat(")") {
+				types.push(self.ty()?);
+				if !self.eat(",") {
+					self.expect(")")?;
+					break;
+				}
+			}
+			return Ok(Type::Tuple(types));
+		}
+		if self.peek() == "[" {
+			return Err(self.beyond("an array type"));
+		}
+		let name = self.ident()?;
+		if matches!(self.peek(), "<" | "::") {
+			return Err(self.beyond(&format!("the type `{name}{}...`", self.peek())));
+		}
+		Ok(Type::Named(name))
+	}
+
+	fn block(&mut self) -> Result<Block, Problem> {
+		let start = self.line();
+		self.expect("{")?;
+		let mut block = self.statements(start)?;
+		block.lines.end = self.line();
+		self.expect("}")?;
+		Ok(block)
+	}
+
+	/// Statements up to a `}` or the end of the snippet, whichever comes
+	/// first; neither is consumed.
+	fn statements(&mut self, start: usize) -> Result<Block, Problem> {
+		let mut stmts = Vec::new();
+		let mut tail = None;
+		while !self.at_end() && self.peek() != "}" {
+			if self.eat(";") {
+				continue;
+			}
+			if self.peek() == "let" {
+				stmts.push(self.let_stmt()?);
+				continue;
+			}
+			let expr = self.statement_expr()?;
+			if self.eat(";") {
+				stmts.push(Stmt::Expr(expr));
+			} else if self.at_end() || self.peek() == "}" {
+				tail = Some(Box::new(expr));
+			} else if expr.kind.is_block_like() {
+				stmts.push(Stmt::Expr(expr));
+			} else {
+				return Err(self.error(&format!("expected `;` after `{}`", expr.text)));
+			}
+		}
+		Ok(Block {
+			stmts,
+			tail,
+			lines: Lines::new(start, self.line()),
+		})
+	}
+
+	/// An expression in statement position: one that starts with a block,
+	/// such as an `if`, ends with it, as in Rust.
+	fn statement_expr(&mut self) -> Result<Expr, Problem> {
+		let starts_block = matches!(self.peek(), "{" | "if" | "loop" | "while" | "for")
+			|| self.peek_kind() == Some(TokenKind::Lifetime);
+		if starts_block {
+			let expr = self.primary()?;
+			if !matches!(self.peek(), "." | "?") {
+				return Ok(expr);
+			}
+			return Err(self.beyond("calling a method on a block"));
+		}
+		self.expr()
+	}
+
+	fn let_stmt(&mut self) -> Result<Stmt, Problem> {
+		let line = self.line();
+		self.expect("let")?;
+		let mutable = self.eat("mut");
+		if self.peek_kind() != Some(TokenKind::Ident) {
+			return Err(self.beyond("a pattern other than a name"));
+		}
+		let name = self.ident()?;
+		let ty = if self.eat(":") {
+			Some(self.ty()?)
+		} else {
+			None
+		};
+		let init = if self.eat("=") {
+			Some(self.expr()?)
+		} else {
+			None
+		};
+		if self.peek() == "else" {
+			return Err(self.beyond("`let ... else`"));
+		}
+		self.expect(";")?;
+		Ok(Stmt::Let {
+			name,
+			mutable,
+			ty,
+			init,
+			line,
+		})
+	}
+
+	fn expr(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		let target = self.range()?;
+		let op = match self.peek() {
+			"=" => None,
+			"+=" => Some(BinaryOp::Add),
+			"-=" => Some(BinaryOp::Sub),
+			"*=" => Some(BinaryOp::Mul),
+			"/=" => Some(BinaryOp::Div),
+			"%=" => Some(BinaryOp::Rem),
+			_ => return Ok(target),
+		};
+		let ExprKind::Var(name) = target.kind else {
+			return Err(self.beyond(&format!("assigning to `{}`", target.text)));
+		};
+		self.at += 1;
+		let value = self.expr()?;
+		Ok(self.node(
+			start,
+			ExprKind::Assign {
+				op,
+				target: name,
+				value: Box::new(value),
+			},
+		))
+	}
+
+	fn range(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		let from = self.binary(0)?;
+		let inclusive = match self.peek() {
+			".." => false,
+			"..=" => true,
+			_ => return Ok(from),
+		};
+		self.at += 1;
+		let to = self.binary(0)?;
+		Ok(self.node(
+			start,
+			ExprKind::Range {
+				start: Box::new(from),
+				end: Box::new(to),
+				inclusive,
+			},
+		))
+	}
+
+	fn binary(&mut self, level: usize) -> Result<Expr, Problem> {
+		let Some(ops) = PRECEDENCE.get(level) else {
+			return self.unary();
+		};
+		let start = self.at;
+		let mut left = self.binary(level + 1)?;
+		while let Some(&op) = ops.iter().find(|op| op.symbol() == self.peek()) {
+			self.at += 1;
+			let right = self.binary(level + 1)?;
+			left = self.node(start, ExprKind::Binary(op, Box::new(left), Box::new(right)));
+		}
+		Ok(left)
+	}
+
+	fn unary(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		let op = match self.peek() {
+			"-" => UnaryOp::Neg,
+			"!" => UnaryOp::Not,
+			"&" => {
+				self.at += 1;
+				let mutable = self.eat("mut");
+				let value = Box::new(self.unary()?);
+				return Ok(self.node(start, ExprKind::Ref { mutable, value }));
+			}
+			"*" => {
+				self.at += 1;
+				let value = Box::new(self.unary()?);
+				return Ok(self.node(start, ExprKind::Deref(value)));
+			}
+			"&&" => return Err(self.beyond("a reference to a reference")),
+			_ => return self.postfix(),
+		};
+		self.at += 1;
+		let operand = self.unary()?;
+		Ok(self.node(start, ExprKind::Unary(op, Box::new(operand))))
+	}
+
+	fn postfix(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		let mut expr = self.primary()?;
+		loop {
+			match self.peek() {
+				"." => {
+					self.at += 1;
+					let name = self.ident()?;
+					if self.peek() != "(" {
+						return Err(self.beyond(&format!("the field `{name}`")));
+					}
+					let args = self.args()?;
+					expr = self.node(
+						start,
+						ExprKind::Method {
+							receiver: Box::new(expr),
+							name,
+							args,
+						},
+					);
+				}
+				"?" => return Err(self.beyond("the `?` operator")),
+				"[" => return Err(self.beyond("indexing")),
+				_ => return Ok(expr),
+			}
+		}
+	}
+
+	/// A parenthesized, comma-separated list of expressions.
+	fn args(&mut self) -> Result<Vec<Expr>, Problem> {
+		self.expect("(")?;
+		let mut args = Vec::new();
+		while !self.eat(")") {
+			args.push(self.expr()?);
+			if !self.eat(",") {
+				self.expect(")")?;
+				break;
+			}
+		}
+		Ok(args)
+	}
+
+	fn primary(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		let Some(&token) = self.tokens.get(self.at) else {
+			return Err(self.error("expected an expression"));
+		};
+		let kind = match (token.kind, token.text) {
+			(TokenKind::Number, text) => {
+				self.at += 1;
+				number(text).ok_or_else(|| {
+					Problem::new(
+						self.line_of(token.start),
+						format!("the number `{text}` is beyond the subset"),
+					)
+				})?
+			}
+			(TokenKind::Keyword, "true" | "false") => {
+				self.at += 1;
+				ExprKind::Bool(token.text == "true")
+			}
+			(TokenKind::Str, text) => {
+				self.at += 1;
+				ExprKind::Str(unescape(text).ok_or_else(|| {
+					Problem::new(
+						self.line_of(token.start),
+						format!("the string {text} is beyond the subset"),
+					)
+				})?)
+			}
+			(TokenKind::Lifetime, label) => {
+				self.at += 1;
+				self.expect(":")?;
+				return self.looping(start, Some(label.to_string()));
+			}
+			(TokenKind::Keyword, "loop" | "while" | "for") => {
+				return self.looping(start, None);
+			}
+			(TokenKind::Keyword, "if") => return self.if_expr(),
+			(TokenKind::Keyword, "break") => {
+				self.at += 1;
+				let label = self.label();
+				let value = if self.ends_expr() {
+					None
+				} else {
+					Some(Box::new(self.expr()?))
+				};
+				ExprKind::Break { label, value }
+			}
+			(TokenKind::Keyword, "continue") => {
+				self.at += 1;
+				ExprKind::Continue {
+					label: self.label(),
+				}
+			}
+			(TokenKind::Keyword, "return") => {
+				self.at += 1;
+				if self.ends_expr() {
+					ExprKind::Return(None)
+				} else {
+					ExprKind::Return(Some(Box::new(self.expr()?)))
+				}
+			}
+			(TokenKind::Punct, "{") => ExprKind::Block(self.block()?),
+			(TokenKind::Punct, "(") => {
+				self.at += 1;
+				if self.eat(")") {
+					return Ok(self.node(start, ExprKind::Tuple(Vec::new())));
+				}
+				let inner = self.expr()?;
+				if self.eat(",") {
+					let mut items = vec![inner];
+					while !self.eat(")") {
+						items.push(self.expr()?);
+						if !self.eat(",") {
+							self.expect(")")?;
+							break;
+						}
+					}
+					return Ok(self.node(start, ExprKind::Tuple(items)));
+				}
+				self.expect(")")?;
+				// The node takes in the parentheses, so that the text of
+				// `(1..4).rev()` is all of it.
+				return Ok(self.node(start, inner.kind));
+			}
+			(TokenKind::Ident, name) => {
+				self.at += 1;
+				if self.peek() == "!" {
+					return self.macro_call(start, name);
+				}
+				let mut path = name.to_string();
+				while self.eat("::") {
+					path.push_str("::");
+					path.push_str(&self.ident()?);
+				}
+				if self.peek() == "(" {
+					ExprKind::Call {
+						name: path,
+						args: self.args()?,
+					}
+				} else if path.contains("::") {
+					return Err(self.beyond(&format!("the path `{path}`")));
+				} else {
+					ExprKind::Var(path)
+				}
+			}
+			(TokenKind::Keyword, keyword) => return Err(self.beyond(&format!("`{keyword}`"))),
+			(TokenKind::Char, text) => {
+				self.at += 1;
+				let inner = &text[1..text.len() - 1];
+				let c = if inner.starts_with('\\') {
+					unescape(&format!("\"{inner}\"")).and_then(|text| text.chars().next())
+				} else {
+					inner.chars().next()
+				};
+				let c = c.ok_or_else(|| {
+					Problem::new(
+						self.line_of(token.start),
+						format!("the character {text} is beyond the subset"),
+					)
+				})?;
+				ExprKind::Char(c)
+			}
+			(TokenKind::Punct, "[") => return Err(self.beyond("an array")),
+			(TokenKind::Punct, "|" | "||") => return Err(self.beyond("a closure")),
+			_ => return Err(self.error("expected an expression")),
+		};
+		Ok(self.node(start, kind))
+	}
+
+	/// Whether the next token ends an expression, as after a bare `break`.
+	fn ends_expr(&self) -> bool {
+		self.at_end() || matches!(self.peek(), ";" | "}" | ")" | ",")
+	}
+
+	fn label(&mut self) -> Option<String> {
+		if self.peek_kind() != Some(TokenKind::Lifetime) {
+			return None;
+		}
+		self.at += 1;
+		Some(self.tokens[self.at - 1].text.to_string())
+	}
+
+	fn looping(&mut self, start: usize, label: Option<String>) -> Result<Expr, Problem> {
+		let kind = if self.eat("loop") {
+			ExprKind::Loop {
+				label,
+				body: self.block()?,
+			}
+		} else if self.eat("while") {
+			if self.peek() == "let" {
+				return Err(self.beyond("`while let`"));
+			}
+			ExprKind::While {
+				label,
+				cond: Box::new(self.expr()?),
+				body: self.block()?,
+			}
+		} else if self.eat("for") {
+			let var = match self.peek() {
+				"_" => {
+					self.at += 1;
+					"_".to_string()
+				}
+				_ if self.peek_kind() == Some(TokenKind::Ident) => self.ident()?,
+				_ => return Err(self.beyond("a pattern other than a name")),
+			};
+			self.expect("in")?;
+			ExprKind::For {
+				label,
+				var,
+				iter: Box::new(self.expr()?),
+				body: self.block()?,
+			}
+		} else {
+			return Err(self.error("expected `loop`, `while` or `for`"));
+		};
+		Ok(self.node(start, kind))
+	}
+
+	fn if_expr(&mut self) -> Result<Expr, Problem> {
+		let start = self.at;
+		self.expect("if")?;
+		if self.peek() == "let" {
+			return Err(self.beyond("`if let`"));
+		}
+		let cond = self.expr()?;
+		let then = self.block()?;
+		let otherwise = if self.eat("else") {
+			let else_start = self.at;
+			Some(Box::new(if self.peek() == "if" {
+				self.if_expr()?
+			} else {
+				let block = self.block()?;
+				self.node(else_start, ExprKind::Block(block))
+			}))
+		} else {
+			None
+		};
+		Ok(self.node(
+			start,
+			ExprKind::If {
+				cond: Box::new(cond),
+				then,
+				otherwise,
+			},
+		))
+	}
+
+	fn macro_call(&mut self, start: usize, name: &str) -> Result<Expr, Problem> {
+		let newline = match name {
+			"println" => true,
+			"print" => false,
+			_ => return Err(self.beyond(&format!("the `{name}!` macro"))),
+		};
+		self.expect("!")?;
+		let mut args = self.args()?;
+		let format = if args.is_empty() {
+			String::new()
+		} else {
+			match args.remove(0).kind {
+				ExprKind::Str(format) => format,
+				_ => {
+					return Err(Problem::new(
+						self.line(),
+						format!("`{name}!` needs a format string first"),
+					))
+				}
+			}
+		};
+		Ok(self.node(
+			start,
+			ExprKind::Print {
+				newline,
+				format,
+				args,
+			},
+		))
+	}
+}
+
+/// An integer literal such as `1_000`, `0xff` or `5u8`, or a float such as
+/// `2.0` or `1e3`.
+fn number(text: &str) -> Option<ExprKind> {
+	let text = text.replace('_', "");
+	// The `e` of `usize` is not an exponent.
+	let is_float = !text.starts_with("0x")
+		&& !text.contains(['i', 'u'])
+		&& (text.contains(['.', 'e', 'E']) || text.ends_with("f32") || text.ends_with("f64"));
+	if is_float {
+		let value = text.trim_end_matches("f32").trim_end_matches("f64");
+		value.parse::<f64>().ok()?;
+		return Some(ExprKind::Float(text));
+	}
+	let (radix, digits) = match text.get(..2) {
+		Some("0x") => (16, &text[2..]),
+		Some("0o") => (8, &text[2..]),
+		Some("0b") => (2, &text[2..]),
+		_ => (10, &text[..]),
+	};
+	let at = digits.find(['i', 'u']).unwrap_or(digits.len());
+	let (digits, suffix) = digits.split_at(at);
+	Some(ExprKind::Int {
+		value: i128::from_str_radix(digits, radix).ok()?,
+		suffix: (!suffix.is_empty()).then(|| suffix.to_string()),
+	})
+}
+
+/// The value of a string literal, for the escapes the snippets use.
+fn unescape(literal: &str) -> Option<String> {
+	if let Some(raw) = literal.strip_prefix('r') {
+		let hashes = raw.len() - raw.trim_start_matches('#').len();
+		return Some(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+	}
+	let inner = literal.strip_prefix('"')?.strip_suffix('"')?;
+	let mut out = String::with_capacity(inner.len());
+	let mut chars = inner.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
+		match chars.next()? {
+			'n' => out.push('\n'),
+			't' => out.push('\t'),
+			'r' => out.push('\r'),
+			'0' => out.push('\0'),
+			'\\' => out.push('\\'),
+			'"' => out.push('"'),
+			'\'' => out.push('\''),
+			'\n' => {
+				// A backslash at the end of a line skips the line break and
+				// the indentation after it.
+				let rest = chars.as_str().trim_start();
+				chars = rest.chars();
+			}
+			_ => return None,
+		}
+	}
+	Some(out)
+}
+
+fn line_starts(source: &str) -> Vec<usize> {
+	let mut starts = vec![0];
+	starts.extend(
+		source
+			.char_indices()
+			.filter(|&(_, c)| c == '\n')
+			.map(|(i, _)| i + 1),
+	);
+	starts
+}
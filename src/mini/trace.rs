@@ -0,0 +1,1468 @@
+//! Running a mini Rust program, step by step.
+//!
+//! Every binding, assignment, condition, pass of a loop, `break`,
+//! `continue`, call and line printed becomes a [`Step`] on the line of the
+//! notes file it happens on. Steps inside a loop or a call are nested one
+//! level deeper, so that printed with indentation they read like the
+//! walkthroughs in the notes.
+//!
+//! Integers are checked as a debug build checks them: overflowing the type
+//! of a value, or `i32` when nothing says otherwise, stops the program, as
+//! does dividing by zero. Some mistakes the compiler would catch first,
+//! such as assigning twice to an immutable variable, stop it too, with the
+//! compiler's message.
+
+use std::fmt;
+
+use super::syntax::{BinaryOp, Block, Expr, ExprKind, Function, Program, Stmt, Type, UnaryOp};
+use super::Problem;
+
+/// How many steps a program may take before it is taken not to terminate.
+pub const MAX_STEPS: usize = 10_000;
+
+/// The integer types, with their smallest and largest values.
+const INTEGERS: [(&str, i128, i128); 12] = [
+	("i8", i8::MIN as i128, i8::MAX as i128),
+	("i16", i16::MIN as i128, i16::MAX as i128),
+	("i32", i32::MIN as i128, i32::MAX as i128),
+	("i64", i64::MIN as i128, i64::MAX as i128),
+	("i128", i128::MIN, i128::MAX),
+	("isize", i64::MIN as i128, i64::MAX as i128),
+	("u8", 0, u8::MAX as i128),
+	("u16", 0, u16::MAX as i128),
+	("u32", 0, u32::MAX as i128),
+	("u64", 0, u64::MAX as i128),
+	("u128", 0, i128::MAX),
+	("usize", 0, u64::MAX as i128),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+	/// An integer and its type, `None` while nothing has fixed it, as for
+	/// a literal without a suffix.
+	Int(i128, Option<&'static str>),
+	Bool(bool),
+	Unit,
+}
+
+impl Value {
+	fn type_name(self) -> &'static str {
+		match self {
+			Value::Int(_, Some(ty)) => ty,
+			Value::Int(_, None) => "integer",
+			Value::Bool(_) => "bool",
+			Value::Unit => "()",
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Int(value, _) => write!(f, "{value}"),
+			Value::Bool(value) => write!(f, "{value}"),
+			Value::Unit => f.write_str("()"),
+		}
+	}
+}
+
+/// One thing the program did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+	pub line: usize,
+	/// How many loops and calls deep it happened, `main` being 0.
+	pub depth: usize,
+	pub text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+	pub steps: Vec<Step>,
+	/// What the program printed, line by line.
+	pub output: Vec<String>,
+	/// Why the program stopped early, if it did.
+	pub problem: Option<Problem>,
+}
+
+/// Checks that `program` keeps to what the interpreter runs: the syntax
+/// also has strings, method calls and paths, which it does not, except for
+/// a string to print and `.rev()` on a range to loop over.
+pub fn in_subset(program: &Program) -> Result<(), Problem> {
+	if program.function("main").is_none() {
+		let line = program
+			.functions
+			.first()
+			.map_or(1, |function| function.line);
+		return Err(Problem::new(line, "there is no `fn main`"));
+	}
+	for function in &program.functions {
+		for param in &function.params {
+			Kind::of(&param.ty, function.line)?;
+		}
+		if let Some(ty) = &function.ret {
+			Kind::of(ty, function.line)?;
+		}
+		block_in_subset(program, &function.body)?;
+	}
+	Ok(())
+}
+
+fn block_in_subset(program: &Program, block: &Block) -> Result<(), Problem> {
+	for stmt in &block.stmts {
+		match stmt {
+			Stmt::Let { ty, init, line, .. } => {
+				if let Some(ty) = ty {
+					Kind::of(ty, *line)?;
+				}
+				if let Some(init) = init {
+					expr_in_subset(program, init)?;
+				}
+			}
+			Stmt::Expr(expr) => expr_in_subset(program, expr)?,
+		}
+	}
+	match &block.tail {
+		Some(tail) => expr_in_subset(program, tail),
+		None => Ok(()),
+	}
+}
+
+fn expr_in_subset(program: &Program, expr: &Expr) -> Result<(), Problem> {
+	let beyond = |what: String| {
+		Err(Problem::new(
+			expr.line,
+			format!("{what} is beyond the subset"),
+		))
+	};
+	match &expr.kind {
+		ExprKind::Int {
+			suffix: Some(suffix),
+			..
+		} if int_type(suffix).is_none() => beyond(format!("the type `{suffix}`")),
+		ExprKind::Int { .. } | ExprKind::Bool(_) | ExprKind::Var(_) => Ok(()),
+		ExprKind::Continue { .. }
+		| ExprKind::Return(None)
+		| ExprKind::Break { value: None, .. } => Ok(()),
+		ExprKind::Str(text) => beyond(format!("the string {text:?}, other than to print,")),
+		ExprKind::Float(text) => beyond(format!("the float `{text}`")),
+		ExprKind::Char(_) => beyond("a `char`".to_string()),
+		ExprKind::Tuple(_) => beyond("a tuple".to_string()),
+		ExprKind::Ref { .. } | ExprKind::Deref(_) => beyond("a reference".to_string()),
+		ExprKind::Unary(_, operand) => expr_in_subset(program, operand),
+		ExprKind::Binary(_, left, right) => {
+			expr_in_subset(program, left)?;
+			expr_in_subset(program, right)
+		}
+		ExprKind::Assign { value, .. } => expr_in_subset(program, value),
+		ExprKind::Range { .. } => beyond("a range other than one to loop over".to_string()),
+		ExprKind::Block(block) | ExprKind::Loop { body: block, .. } => {
+			block_in_subset(program, block)
+		}
+		ExprKind::If {
+			cond,
+			then,
+			otherwise,
+		} => {
+			expr_in_subset(program, cond)?;
+			block_in_subset(program, then)?;
+			match otherwise {
+				Some(otherwise) => expr_in_subset(program, otherwise),
+				None => Ok(()),
+			}
+		}
+		ExprKind::While { cond, body, .. } => {
+			expr_in_subset(program, cond)?;
+			block_in_subset(program, body)
+		}
+		ExprKind::For { iter, body, .. } => {
+			let range = match &iter.kind {
+				ExprKind::Method {
+					receiver,
+					name,
+					args,
+				} if name == "rev" && args.is_empty() => receiver,
+				_ => iter,
+			};
+			let ExprKind::Range { start, end, .. } = &range.kind else {
+				return beyond(format!("looping over `{}`", iter.text));
+			};
+			expr_in_subset(program, start)?;
+			expr_in_subset(program, end)?;
+			block_in_subset(program, body)
+		}
+		ExprKind::Break {
+			value: Some(value), ..
+		}
+		| ExprKind::Return(Some(value)) => expr_in_subset(program, value),
+		ExprKind::Call { name, args } => {
+			if name.contains("::") {
+				return beyond(format!("the function `{name}`"));
+			}
+			if program.function(name).is_none() {
+				return Err(Problem::new(
+					expr.line,
+					format!("cannot find function `{name}` in the snippet"),
+				));
+			}
+			args.iter().try_for_each(|arg| expr_in_subset(program, arg))
+		}
+		ExprKind::Method { name, .. } => beyond(format!("the method `{name}`")),
+		ExprKind::Print { args, .. } => {
+			args.iter().try_for_each(|arg| expr_in_subset(program, arg))
+		}
+	}
+}
+
+/// Runs `main`, which should be [`in_subset`]. A program whose `if` gives
+/// values of two types, which rustc would not compile, is not run at all,
+/// even when the arm that would not type-check is never taken.
+pub fn run(program: &Program) -> Trace {
+	if let Err(problem) = Types::check(program) {
+		return Trace {
+			problem: Some(problem),
+			..Trace::default()
+		};
+	}
+	let mut interpreter = Interpreter {
+		program,
+		frames: Vec::new(),
+		depth: 0,
+		trace: Trace::default(),
+		printing: None,
+	};
+	let outcome = match program.function("main") {
+		Some(main) => interpreter.call(main, Vec::new(), main.line),
+		None => fail(1, "there is no `fn main`"),
+	};
+	if let Some(rest) = interpreter.printing.take() {
+		interpreter.trace.output.push(rest);
+	}
+	interpreter.trace.problem = match outcome {
+		Ok(_) => None,
+		Err(Unwind::Fail(problem)) => Some(problem),
+		Err(Unwind::Break { line, .. } | Unwind::Continue { line, .. }) => Some(Problem::new(
+			line,
+			"`break` or `continue` outside of a loop",
+		)),
+		Err(Unwind::Return(_)) => None,
+	};
+	interpreter.trace
+}
+
+struct Binding {
+	name: String,
+	mutable: bool,
+	/// `None` until a `let x;` is assigned.
+	value: Option<Value>,
+	/// The type it was declared with, which values assigned to it must have.
+	ty: Option<Kind>,
+}
+
+/// A type the interpreter knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+	Int(&'static str),
+	Bool,
+}
+
+impl Kind {
+	fn of(ty: &Type, line: usize) -> Result<Kind, Problem> {
+		match ty {
+			Type::Named(name) if name == "bool" => Ok(Kind::Bool),
+			Type::Named(name) => int_type(name).map(Kind::Int).ok_or_else(|| {
+				Problem::new(line, format!("the type `{name}` is beyond the subset"))
+			}),
+			Type::Ref { .. } | Type::Tuple(_) => Err(Problem::new(
+				line,
+				format!("the type `{ty}` is beyond the subset"),
+			)),
+		}
+	}
+}
+
+fn int_type(name: &str) -> Option<&'static str> {
+	INTEGERS
+		.iter()
+		.find(|(ty, ..)| *ty == name)
+		.map(|(ty, ..)| *ty)
+}
+
+/// How evaluation leaves the normal flow.
+enum Unwind {
+	Break {
+		label: Option<String>,
+		value: Value,
+		line: usize,
+	},
+	Continue {
+		label: Option<String>,
+		line: usize,
+	},
+	Return(Value),
+	Fail(Problem),
+}
+
+impl From<Problem> for Unwind {
+	fn from(problem: Problem) -> Unwind {
+		Unwind::Fail(problem)
+	}
+}
+
+type Flow<T> = Result<T, Unwind>;
+
+fn fail<T>(line: usize, message: impl Into<String>) -> Flow<T> {
+	Err(Unwind::Fail(Problem::new(line, message)))
+}
+
+/// The numbers a `for` loop goes through.
+type Numbers = Box<dyn Iterator<Item = i128>>;
+
+struct Interpreter<'p> {
+	program: &'p Program,
+	/// The scopes of each function being called, innermost last.
+	frames: Vec<Vec<Vec<Binding>>>,
+	depth: usize,
+	trace: Trace,
+	/// A line `print!` has started and nothing has ended yet.
+	printing: Option<String>,
+}
+
+impl Interpreter<'_> {
+	fn step(&mut self, line: usize, text: String) -> Flow<()> {
+		if self.trace.steps.len() == MAX_STEPS {
+			return fail(
+				line,
+				format!("gave up after {MAX_STEPS} steps; the program may never stop"),
+			);
+		}
+		self.trace.steps.push(Step {
+			line,
+			depth: self.depth,
+			text,
+		});
+		Ok(())
+	}
+
+	fn scopes(&mut self) -> &mut Vec<Vec<Binding>> {
+		self.frames.last_mut().expect("a function is running")
+	}
+
+	fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+		self.scopes()
+			.iter_mut()
+			.rev()
+			.find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name))
+	}
+
+	fn call(&mut self, function: &Function, args: Vec<Value>, line: usize) -> Flow<Value> {
+		if args.len() != function.params.len() {
+			return fail(
+				line,
+				format!(
+					"`{}` takes {} arguments but {} were given",
+					function.name,
+					function.params.len(),
+					args.len()
+				),
+			);
+		}
+		let mut scope = Vec::new();
+		for (param, value) in function.params.iter().zip(args) {
+			let kind = Kind::of(&param.ty, function.line)?;
+			scope.push(Binding {
+				name: param.name.clone(),
+				mutable: param.mutable,
+				value: Some(coerce(value, kind, line)?),
+				ty: Some(kind),
+			});
+		}
+		let is_main = self.frames.is_empty();
+		if !is_main {
+			let args = scope
+				.iter()
+				.map(|binding| {
+					format!(
+						"{} = {}",
+						binding.name,
+						binding.value.unwrap_or(Value::Unit)
+					)
+				})
+				.collect::<Vec<_>>()
+				.join(", ");
+			self.step(line, format!("call {}({args})", function.name))?;
+			self.depth += 1;
+		}
+		self.frames.push(vec![scope]);
+		let result = self.block(&function.body);
+		self.frames.pop();
+		let value = match result {
+			Ok(value) | Err(Unwind::Return(value)) => value,
+			Err(unwind) => return Err(unwind),
+		};
+		let value = match &function.ret {
+			Some(ty) => coerce(value, Kind::of(ty, function.line)?, function.body.lines.end)?,
+			None if value == Value::Unit => value,
+			None => {
+				return fail(
+					function.body.lines.end,
+					format!(
+						"mismatched types: `{}` returns `()`, but its body gives {}",
+						function.name,
+						value.type_name()
+					),
+				)
+			}
+		};
+		if !is_main {
+			self.depth -= 1;
+			self.step(
+				function.body.lines.end,
+				format!("{} returns {value}", function.name),
+			)?;
+		}
+		Ok(value)
+	}
+
+	fn block(&mut self, block: &Block) -> Flow<Value> {
+		self.scopes().push(Vec::new());
+		let result = self.block_body(block);
+		let scope = self.scopes().pop().unwrap_or_default();
+		let value = result?;
+		// Leaving a block makes the names it shadowed visible again.
+		for binding in scope.iter().rev() {
+			let shadowed = self
+				.lookup(&binding.name)
+				.and_then(|outer| outer.value)
+				.map(|value| format!("{} is {value} again", binding.name));
+			if let Some(shadowed) = shadowed {
+				self.step(block.lines.end, format!("end of block: {shadowed}"))?;
+			}
+		}
+		Ok(value)
+	}
+
+	fn block_body(&mut self, block: &Block) -> Flow<Value> {
+		for stmt in &block.stmts {
+			match stmt {
+				Stmt::Let {
+					name,
+					mutable,
+					ty,
+					init,
+					line,
+				} => self.bind(name, *mutable, ty.as_ref(), init.as_ref(), *line)?,
+				Stmt::Expr(expr) => {
+					self.eval(expr)?;
+				}
+			}
+		}
+		match &block.tail {
+			Some(tail) => self.eval(tail),
+			None => Ok(Value::Unit),
+		}
+	}
+
+	fn bind(
+		&mut self,
+		name: &str,
+		mutable: bool,
+		ty: Option<&Type>,
+		init: Option<&Expr>,
+		line: usize,
+	) -> Flow<()> {
+		let kind = ty.map(|ty| Kind::of(ty, line)).transpose()?;
+		let value = match init {
+			Some(init) => {
+				let value = self.eval(init)?;
+				Some(match kind {
+					Some(kind) => coerce(value, kind, init.line)?,
+					None => value,
+				})
+			}
+			None => None,
+		};
+		let shadowed = self.lookup(name).map(|old| match old.value {
+			Some(value) => format!(", shadowing {name} = {value}"),
+			None => format!(", shadowing the earlier {name}"),
+		});
+		let mut text = format!("let {}{name}", if mutable { "mut " } else { "" });
+		if let Some(ty) = ty {
+			text.push_str(&format!(": {ty}"));
+		}
+		match (init, value) {
+			(Some(init), Some(value)) if shows_work(init, value) => {
+				text.push_str(&format!(" = {} = {value}", init.text));
+			}
+			(_, Some(value)) => text.push_str(&format!(" = {value}")),
+			_ => text.push_str(", not yet given a value"),
+		}
+		text.push_str(&shadowed.unwrap_or_default());
+		self.step(line, text)?;
+		self.scopes()
+			.last_mut()
+			.expect("a block is running")
+			.push(Binding {
+				name: name.to_string(),
+				mutable,
+				value,
+				ty: kind,
+			});
+		Ok(())
+	}
+
+	fn eval(&mut self, expr: &Expr) -> Flow<Value> {
+		let line = expr.line;
+		match &expr.kind {
+			ExprKind::Int { value, suffix } => {
+				let ty = match suffix {
+					Some(suffix) => Some(int_type(suffix).ok_or_else(|| {
+						Problem::new(line, format!("the type `{suffix}` is beyond the subset"))
+					})?),
+					None => None,
+				};
+				Ok(checked(*value, ty, line, "literal out of range for")?)
+			}
+			ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+			ExprKind::Str(_) => fail(line, "a string is beyond the subset"),
+			ExprKind::Float(_)
+			| ExprKind::Char(_)
+			| ExprKind::Tuple(_)
+			| ExprKind::Ref { .. }
+			| ExprKind::Deref(_) => fail(line, format!("`{}` is beyond the subset", expr.text)),
+			ExprKind::Var(name) => match self.lookup(name) {
+				Some(Binding {
+					value: Some(value), ..
+				}) => Ok(*value),
+				Some(_) => fail(line, format!("used binding `{name}` isn't initialized")),
+				None => fail(line, format!("cannot find value `{name}` in this scope")),
+			},
+			ExprKind::Unary(op, operand) => {
+				let value = self.eval(operand)?;
+				match (op, value) {
+					(UnaryOp::Neg, Value::Int(n, ty)) => {
+						if ty.is_some_and(|ty| ty.starts_with('u')) {
+							return fail(
+								line,
+								format!(
+									"cannot apply unary operator `-` to type `{}`",
+									value.type_name()
+								),
+							);
+						}
+						Ok(checked(-n, ty, line, "attempt to negate with overflow in")?)
+					}
+					(UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+					_ => fail(
+						line,
+						format!(
+							"`{}` on {} is beyond the subset",
+							if *op == UnaryOp::Neg { "-" } else { "!" },
+							value.type_name()
+						),
+					),
+				}
+			}
+			ExprKind::Binary(op, left, right) => self.binary(*op, left, right, line),
+			ExprKind::Assign { op, target, value } => self.assign(*op, target, value, expr),
+			ExprKind::Range { .. } => fail(
+				line,
+				"a range is only in the subset as what a `for` loops over",
+			),
+			ExprKind::Block(block) => self.block(block),
+			ExprKind::If { .. } => self.if_expr(expr, false),
+			ExprKind::Loop { label, body } => {
+				for pass in 1usize.. {
+					self.step(line, format!("{}loop, pass {pass}", labeled(label)))?;
+					self.depth += 1;
+					let result = self.block(body);
+					self.depth -= 1;
+					match self.looped(result, label)? {
+						Some(value) => {
+							let with = if value == Value::Unit {
+								String::new()
+							} else {
+								format!(" with {value}")
+							};
+							self.step(line, format!("{}loop ends{with}", labeled(label)))?;
+							return Ok(value);
+						}
+						None => continue,
+					}
+				}
+				unreachable!("a loop only ends by breaking")
+			}
+			ExprKind::While { label, cond, body } => {
+				loop {
+					let holds = self.condition(cond)?;
+					self.step(
+						line,
+						format!("{}while {}: {holds}", labeled(label), cond.text),
+					)?;
+					if !holds {
+						break;
+					}
+					self.depth += 1;
+					let result = self.block(body);
+					self.depth -= 1;
+					if self.looped(result, label)?.is_some() {
+						self.step(line, format!("{}while loop ends", labeled(label)))?;
+						break;
+					}
+				}
+				Ok(Value::Unit)
+			}
+			ExprKind::For {
+				label,
+				var,
+				iter,
+				body,
+			} => {
+				let (numbers, ty) = self.range(iter)?;
+				for n in numbers {
+					self.step(line, format!("{}for {var} = {n}", labeled(label)))?;
+					self.depth += 1;
+					self.scopes().push(vec![Binding {
+						name: var.clone(),
+						mutable: false,
+						value: Some(Value::Int(n, ty)),
+						ty: None,
+					}]);
+					let result = self.block(body);
+					self.scopes().pop();
+					self.depth -= 1;
+					if self.looped(result, label)?.is_some() {
+						break;
+					}
+				}
+				self.step(line, format!("{}for loop ends", labeled(label)))?;
+				Ok(Value::Unit)
+			}
+			ExprKind::Break { label, value } => {
+				let value = match value {
+					Some(value) => Some((value, self.eval(value)?)),
+					None => None,
+				};
+				let mut text = format!(
+					"break{}",
+					label
+						.as_ref()
+						.map_or(String::new(), |label| format!(" {label}"))
+				);
+				if let Some((expr, value)) = value {
+					if shows_work(expr, value) {
+						text.push_str(&format!(" with {} = {value}", expr.text));
+					} else {
+						text.push_str(&format!(" with {value}"));
+					}
+				}
+				self.step(line, text)?;
+				Err(Unwind::Break {
+					label: label.clone(),
+					value: value.map_or(Value::Unit, |(_, value)| value),
+					line,
+				})
+			}
+			ExprKind::Continue { label } => {
+				self.step(
+					line,
+					format!(
+						"continue{}",
+						label
+							.as_ref()
+							.map_or(String::new(), |label| format!(" {label}"))
+					),
+				)?;
+				Err(Unwind::Continue {
+					label: label.clone(),
+					line,
+				})
+			}
+			ExprKind::Return(value) => {
+				let value = match value {
+					Some(value) => self.eval(value)?,
+					None => Value::Unit,
+				};
+				Err(Unwind::Return(value))
+			}
+			ExprKind::Call { name, args } => {
+				let program = self.program;
+				let Some(function) = program.function(name) else {
+					return fail(
+						line,
+						format!("cannot find function `{name}` in the snippet"),
+					);
+				};
+				let args = args
+					.iter()
+					.map(|arg| self.eval(arg))
+					.collect::<Flow<Vec<_>>>()?;
+				self.call(function, args, line)
+			}
+			ExprKind::Method { name, .. } => {
+				fail(line, format!("the method `{name}` is beyond the subset"))
+			}
+			ExprKind::Print {
+				newline,
+				format,
+				args,
+			} => {
+				let text = self.format(format, args, line)?;
+				self.step(line, format!("prints {text:?}"))?;
+				let mut current = self.printing.take().unwrap_or_default();
+				current.push_str(&text);
+				let mut lines: Vec<&str> = current.split('\n').collect();
+				let last = lines.pop().unwrap_or_default().to_string();
+				self.trace
+					.output
+					.extend(lines.into_iter().map(str::to_string));
+				if *newline {
+					self.trace.output.push(last);
+				} else {
+					self.printing = Some(last);
+				}
+				Ok(Value::Unit)
+			}
+		}
+	}
+
+	/// What the body of a loop ended with: `Some` value if it broke out of
+	/// this loop, `None` to go round again.
+	fn looped(&mut self, result: Flow<Value>, label: &Option<String>) -> Flow<Option<Value>> {
+		match result {
+			Ok(_) => Ok(None),
+			Err(Unwind::Break {
+				label: target,
+				value,
+				..
+			}) if target.is_none() || target == *label => Ok(Some(value)),
+			Err(Unwind::Continue { label: target, .. }) if target.is_none() || target == *label => {
+				Ok(None)
+			}
+			Err(unwind) => Err(unwind),
+		}
+	}
+
+	fn condition(&mut self, cond: &Expr) -> Flow<bool> {
+		match self.eval(cond)? {
+			Value::Bool(holds) => Ok(holds),
+			value => fail(
+				cond.line,
+				format!(
+					"mismatched types: expected `bool`, found {}",
+					value.type_name()
+				),
+			),
+		}
+	}
+
+	fn if_expr(&mut self, expr: &Expr, is_else: bool) -> Flow<Value> {
+		let ExprKind::If {
+			cond,
+			then,
+			otherwise,
+		} = &expr.kind
+		else {
+			return self.eval(expr);
+		};
+		let holds = self.condition(cond)?;
+		let keyword = if is_else { "else if" } else { "if" };
+		self.step(cond.line, format!("{keyword} {}: {holds}", cond.text))?;
+		if holds {
+			return self.block(then);
+		}
+		match otherwise {
+			Some(otherwise) if matches!(otherwise.kind, ExprKind::If { .. }) => {
+				self.if_expr(otherwise, true)
+			}
+			Some(otherwise) => self.eval(otherwise),
+			None => Ok(Value::Unit),
+		}
+	}
+
+	fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr, line: usize) -> Flow<Value> {
+		let a = self.eval(left)?;
+		// `&&` and `||` only look at their right side when they need to.
+		match (op, a) {
+			(BinaryOp::And, Value::Bool(false)) => return Ok(a),
+			(BinaryOp::Or, Value::Bool(true)) => return Ok(a),
+			_ => {}
+		}
+		let b = self.eval(right)?;
+		Ok(operate(op, a, b, line)?)
+	}
+
+	fn assign(
+		&mut self,
+		op: Option<BinaryOp>,
+		target: &str,
+		value: &Expr,
+		expr: &Expr,
+	) -> Flow<Value> {
+		let line = expr.line;
+		let new = self.eval(value)?;
+		let Some(binding) = self.lookup(target) else {
+			return fail(line, format!("cannot find value `{target}` in this scope"));
+		};
+		if !binding.mutable && binding.value.is_some() {
+			return fail(
+				line,
+				format!("cannot assign twice to immutable variable `{target}`"),
+			);
+		}
+		let new = match (op, binding.value) {
+			(Some(op), Some(old)) => operate(op, old, new, line)?,
+			(Some(_), None) => {
+				return fail(line, format!("used binding `{target}` isn't initialized"))
+			}
+			(None, _) => new,
+		};
+		// A value keeps the type the variable was declared with or first
+		// given.
+		let kind = binding.ty.or(match binding.value {
+			Some(Value::Int(_, Some(ty))) => Some(Kind::Int(ty)),
+			Some(Value::Int(_, None)) if matches!(new, Value::Int(..)) => None,
+			Some(Value::Int(..)) => Some(Kind::Int("i32")),
+			Some(Value::Bool(_)) => Some(Kind::Bool),
+			_ => None,
+		});
+		let new = match kind {
+			Some(kind) => coerce(new, kind, line)?,
+			None => new,
+		};
+		binding.value = Some(new);
+		let text = match op {
+			Some(_) => format!("{}, now {target} = {new}", expr.text),
+			None if shows_work(value, new) => format!("{target} = {} = {new}", value.text),
+			None => format!("{target} = {new}"),
+		};
+		self.step(line, text)?;
+		Ok(Value::Unit)
+	}
+
+	/// The numbers a `for` loop goes through, and their type.
+	fn range(&mut self, iter: &Expr) -> Flow<(Numbers, Option<&'static str>)> {
+		let (range, reversed) = match &iter.kind {
+			ExprKind::Method {
+				receiver,
+				name,
+				args,
+			} if name == "rev" && args.is_empty() => (&**receiver, true),
+			_ => (iter, false),
+		};
+		let ExprKind::Range {
+			start,
+			end,
+			inclusive,
+		} = &range.kind
+		else {
+			return fail(
+				iter.line,
+				format!(
+					"looping over `{}` is beyond the subset; only ranges are in it",
+					iter.text
+				),
+			);
+		};
+		let (Value::Int(from, a), Value::Int(to, b)) = (self.eval(start)?, self.eval(end)?) else {
+			return fail(iter.line, "a range needs integers at both ends");
+		};
+		let ty = unify(a, b, iter.line)?;
+		let to = if *inclusive { to } else { to - 1 };
+		let numbers: Numbers = if reversed {
+			Box::new((from..=to).rev())
+		} else {
+			Box::new(from..=to)
+		};
+		Ok((numbers, ty))
+	}
+
+	/// The text `println!` prints for `format` and its arguments.
+	fn format(&mut self, format: &str, args: &[Expr], line: usize) -> Flow<String> {
+		let mut out = String::new();
+		let mut next = 0;
+		let mut chars = format.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'{' if chars.peek() == Some(&'{') => {
+					chars.next();
+					out.push('{');
+				}
+				'}' if chars.peek() == Some(&'}') => {
+					chars.next();
+					out.push('}');
+				}
+				'{' => {
+					let mut spec = String::new();
+					for c in chars.by_ref() {
+						if c == '}' {
+							break;
+						}
+						spec.push(c);
+					}
+					let (argument, style) = spec.split_once(':').unwrap_or((&spec, ""));
+					if !style.is_empty() && style != "?" {
+						return fail(
+							line,
+							format!("the format `{{{spec}}}` is beyond the subset"),
+						);
+					}
+					let value = if argument.is_empty() {
+						next += 1;
+						self.argument(args, next - 1, line)?
+					} else if let Ok(index) = argument.parse::<usize>() {
+						self.argument(args, index, line)?
+					} else {
+						match self.lookup(argument).and_then(|binding| binding.value) {
+							Some(value) => value,
+							None => {
+								return fail(
+									line,
+									format!("cannot find value `{argument}` in this scope"),
+								)
+							}
+						}
+					};
+					out.push_str(&value.to_string());
+				}
+				c => out.push(c),
+			}
+		}
+		Ok(out)
+	}
+
+	fn argument(&mut self, args: &[Expr], index: usize, line: usize) -> Flow<Value> {
+		match args.get(index) {
+			Some(arg) => self.eval(arg),
+			None => fail(
+				line,
+				format!("the format string wants more than {} arguments", args.len()),
+			),
+		}
+	}
+}
+
+/// The type of an expression, as far as the type check before running
+/// needs to know it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+	Int(Option<&'static str>),
+	Bool,
+	Unit,
+	/// `break`, `continue` and `return`, which give no value at all.
+	Never,
+	/// Whatever the check does not work out, such as the value of a `loop`.
+	Unknown,
+}
+
+impl Ty {
+	fn of(kind: Kind) -> Ty {
+		match kind {
+			Kind::Int(ty) => Ty::Int(Some(ty)),
+			Kind::Bool => Ty::Bool,
+		}
+	}
+
+	/// Whether a value of one type can stand where the other is expected.
+	fn agrees(self, other: Ty) -> bool {
+		match (self, other) {
+			(Ty::Never | Ty::Unknown, _) | (_, Ty::Never | Ty::Unknown) => true,
+			(Ty::Int(Some(a)), Ty::Int(Some(b))) => a == b,
+			(Ty::Int(_), Ty::Int(_)) => true,
+			(a, b) => a == b,
+		}
+	}
+
+	/// The type of an `if` whose arms are `self` and `other`.
+	fn join(self, other: Ty) -> Ty {
+		match (self, other) {
+			(Ty::Never, ty) | (ty, Ty::Never) => ty,
+			(Ty::Int(None), Ty::Int(b)) => Ty::Int(b),
+			(a, _) => a,
+		}
+	}
+}
+
+impl fmt::Display for Ty {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Ty::Int(Some(ty)) => write!(f, "`{ty}`"),
+			Ty::Int(None) => f.write_str("integer"),
+			Ty::Bool => f.write_str("`bool`"),
+			Ty::Unit => f.write_str("`()`"),
+			Ty::Never => f.write_str("`!`"),
+			Ty::Unknown => f.write_str("a value"),
+		}
+	}
+}
+
+/// The type check rustc makes of every `if`, arms not taken included: both
+/// arms give a value of the same type, and one without an `else` gives
+/// `()`. The interpreter only looks at the arm it takes.
+struct Types<'p> {
+	program: &'p Program,
+	scopes: Vec<Vec<(String, Ty)>>,
+}
+
+impl Types<'_> {
+	fn check(program: &Program) -> Result<(), Problem> {
+		let mut types = Types {
+			program,
+			scopes: Vec::new(),
+		};
+		for function in &program.functions {
+			let mut scope = Vec::new();
+			for param in &function.params {
+				let ty = Kind::of(&param.ty, function.line).map_or(Ty::Unknown, Ty::of);
+				scope.push((param.name.clone(), ty));
+			}
+			types.scopes = vec![scope];
+			types.block(&function.body)?;
+		}
+		Ok(())
+	}
+
+	fn block(&mut self, block: &Block) -> Result<Ty, Problem> {
+		self.scopes.push(Vec::new());
+		let ty = self.block_body(block);
+		self.scopes.pop();
+		ty
+	}
+
+	fn block_body(&mut self, block: &Block) -> Result<Ty, Problem> {
+		let mut last = Ty::Unit;
+		for stmt in &block.stmts {
+			last = match stmt {
+				Stmt::Let { name, ty, init, .. } => {
+					let init = init.as_ref().map(|init| self.expr(init)).transpose()?;
+					let declared = ty
+						.as_ref()
+						.and_then(|ty| Kind::of(ty, block.lines.start).ok())
+						.map(Ty::of);
+					let ty = declared.or(init).unwrap_or(Ty::Unknown);
+					self.scopes
+						.last_mut()
+						.expect("a block is being checked")
+						.push((name.clone(), ty));
+					Ty::Unit
+				}
+				Stmt::Expr(expr) => self.expr(expr)?,
+			};
+		}
+		match &block.tail {
+			Some(tail) => self.expr(tail),
+			// A block that ends in `return` or `break` gives no value.
+			None if last == Ty::Never => Ok(Ty::Never),
+			None => Ok(Ty::Unit),
+		}
+	}
+
+	fn expr(&mut self, expr: &Expr) -> Result<Ty, Problem> {
+		Ok(match &expr.kind {
+			ExprKind::Int { suffix, .. } => Ty::Int(suffix.as_deref().and_then(int_type)),
+			ExprKind::Bool(_) => Ty::Bool,
+			ExprKind::Var(name) => self
+				.scopes
+				.iter()
+				.rev()
+				.find_map(|scope| scope.iter().rev().find(|(known, _)| known == name))
+				.map_or(Ty::Unknown, |&(_, ty)| ty),
+			ExprKind::Unary(UnaryOp::Neg, operand) => self.expr(operand)?,
+			ExprKind::Unary(UnaryOp::Not, operand) => {
+				self.expr(operand)?;
+				Ty::Bool
+			}
+			ExprKind::Binary(op, left, right) => {
+				let (a, b) = (self.expr(left)?, self.expr(right)?);
+				match op {
+					BinaryOp::Add
+					| BinaryOp::Sub
+					| BinaryOp::Mul
+					| BinaryOp::Div
+					| BinaryOp::Rem => a.join(b),
+					_ => Ty::Bool,
+				}
+			}
+			ExprKind::Assign { value, .. } => {
+				self.expr(value)?;
+				Ty::Unit
+			}
+			ExprKind::Block(block) => self.block(block)?,
+			ExprKind::If {
+				cond,
+				then,
+				otherwise,
+			} => {
+				self.expr(cond)?;
+				let then_ty = self.block(then)?;
+				match otherwise {
+					Some(otherwise) => {
+						let else_ty = self.expr(otherwise)?;
+						if !then_ty.agrees(else_ty) {
+							return Err(Problem::new(
+								value_line(otherwise),
+								format!(
+									"`if` and `else` have incompatible types: expected \
+									 {then_ty}, found {else_ty}"
+								),
+							));
+						}
+						then_ty.join(else_ty)
+					}
+					None if then_ty.agrees(Ty::Unit) => Ty::Unit,
+					None => {
+						return Err(Problem::new(
+							then.tail.as_ref().map_or(then.lines.end, |tail| tail.line),
+							format!(
+								"`if` may be missing an `else` clause: expected `()`, found \
+								 {then_ty}"
+							),
+						))
+					}
+				}
+			}
+			ExprKind::Loop { body, .. } => {
+				self.block(body)?;
+				Ty::Unknown
+			}
+			ExprKind::While { cond, body, .. } => {
+				self.expr(cond)?;
+				self.block(body)?;
+				Ty::Unit
+			}
+			ExprKind::For { var, body, .. } => {
+				self.scopes.push(vec![(var.clone(), Ty::Int(None))]);
+				let body = self.block(body);
+				self.scopes.pop();
+				body?;
+				Ty::Unit
+			}
+			ExprKind::Break { value, .. } | ExprKind::Return(value) => {
+				if let Some(value) = value {
+					self.expr(value)?;
+				}
+				Ty::Never
+			}
+			ExprKind::Continue { .. } => Ty::Never,
+			ExprKind::Call { name, args } => {
+				for arg in args {
+					self.expr(arg)?;
+				}
+				match self.program.function(name) {
+					Some(function) => match &function.ret {
+						Some(ty) => Kind::of(ty, function.line).map_or(Ty::Unknown, Ty::of),
+						None => Ty::Unit,
+					},
+					None => Ty::Unknown,
+				}
+			}
+			ExprKind::Print { args, .. } => {
+				for arg in args {
+					self.expr(arg)?;
+				}
+				Ty::Unit
+			}
+			_ => Ty::Unknown,
+		})
+	}
+}
+
+/// The line an expression's value comes from: for a block, that of its
+/// tail, and for an `else if` chain, that of its last arm.
+fn value_line(expr: &Expr) -> usize {
+	match &expr.kind {
+		ExprKind::Block(block) => block
+			.tail
+			.as_ref()
+			.map_or(block.lines.end, |tail| value_line(tail)),
+		ExprKind::If {
+			otherwise: Some(otherwise),
+			..
+		} => value_line(otherwise),
+		ExprKind::If { then, .. } => then
+			.tail
+			.as_ref()
+			.map_or(then.lines.end, |tail| value_line(tail)),
+		_ => expr.line,
+	}
+}
+
+/// `'label: ` for a labeled loop.
+fn labeled(label: &Option<String>) -> String {
+	label
+		.as_ref()
+		.map_or(String::new(), |label| format!("{label}: "))
+}
+
+/// Whether a step should show the expression a value came from as well as
+/// the value: it should for `x + 1`, not for `5` or for a block, whose
+/// steps come before.
+fn shows_work(expr: &Expr, value: Value) -> bool {
+	matches!(
+		expr.kind,
+		ExprKind::Var(_) | ExprKind::Unary(..) | ExprKind::Binary(..) | ExprKind::Call { .. }
+	) && expr.text != value.to_string()
+}
+
+/// The type of an operation on integers of types `a` and `b`.
+fn unify(
+	a: Option<&'static str>,
+	b: Option<&'static str>,
+	line: usize,
+) -> Result<Option<&'static str>, Problem> {
+	match (a, b) {
+		(Some(a), Some(b)) if a != b => Err(Problem::new(
+			line,
+			format!("mismatched types: expected `{a}`, found `{b}`"),
+		)),
+		_ => Ok(a.or(b)),
+	}
+}
+
+/// `value` as an integer of type `ty`, or `i32` while it has none, or the
+/// overflow `what` describes.
+fn checked(
+	value: i128,
+	ty: Option<&'static str>,
+	line: usize,
+	what: &str,
+) -> Result<Value, Problem> {
+	let name = ty.unwrap_or("i32");
+	let (_, min, max) = INTEGERS
+		.iter()
+		.find(|(ty, ..)| *ty == name)
+		.expect("integer types come from the table");
+	if (*min..=*max).contains(&value) {
+		Ok(Value::Int(value, ty))
+	} else {
+		Err(Problem::new(line, format!("{what} `{name}`")))
+	}
+}
+
+fn operate(op: BinaryOp, a: Value, b: Value, line: usize) -> Result<Value, Problem> {
+	let mismatch = || {
+		Problem::new(
+			line,
+			format!(
+				"cannot apply `{}` to {} and {}",
+				op.symbol(),
+				a.type_name(),
+				b.type_name()
+			),
+		)
+	};
+	match (a, b) {
+		(Value::Int(x, ta), Value::Int(y, tb)) => {
+			let ty = unify(ta, tb, line)?;
+			let (result, verb) = match op {
+				BinaryOp::Add => (x.checked_add(y), "add"),
+				BinaryOp::Sub => (x.checked_sub(y), "subtract"),
+				BinaryOp::Mul => (x.checked_mul(y), "multiply"),
+				BinaryOp::Div if y == 0 => {
+					return Err(Problem::new(line, "attempt to divide by zero"))
+				}
+				BinaryOp::Div => (x.checked_div(y), "divide"),
+				BinaryOp::Rem if y == 0 => {
+					return Err(Problem::new(
+						line,
+						"attempt to calculate the remainder with a divisor of zero",
+					))
+				}
+				BinaryOp::Rem => (x.checked_rem(y), "calculate the remainder"),
+				BinaryOp::Eq => return Ok(Value::Bool(x == y)),
+				BinaryOp::Ne => return Ok(Value::Bool(x != y)),
+				BinaryOp::Lt => return Ok(Value::Bool(x < y)),
+				BinaryOp::Le => return Ok(Value::Bool(x <= y)),
+				BinaryOp::Gt => return Ok(Value::Bool(x > y)),
+				BinaryOp::Ge => return Ok(Value::Bool(x >= y)),
+				BinaryOp::And | BinaryOp::Or => return Err(mismatch()),
+			};
+			let what = format!("attempt to {verb} with overflow in");
+			let overflow = || Problem::new(line, format!("{what} `{}`", ty.unwrap_or("i32")));
+			checked(result.ok_or_else(overflow)?, ty, line, &what)
+		}
+		(Value::Bool(x), Value::Bool(y)) => match op {
+			BinaryOp::And => Ok(Value::Bool(x && y)),
+			BinaryOp::Or => Ok(Value::Bool(x || y)),
+			BinaryOp::Eq => Ok(Value::Bool(x == y)),
+			BinaryOp::Ne => Ok(Value::Bool(x != y)),
+			_ => Err(mismatch()),
+		},
+		_ => Err(mismatch()),
+	}
+}
+
+/// `value` as a value of type `kind`, or why it cannot be one.
+fn coerce(value: Value, kind: Kind, line: usize) -> Result<Value, Problem> {
+	match (value, kind) {
+		(Value::Int(n, None), Kind::Int(ty)) => {
+			checked(n, Some(ty), line, "literal out of range for")
+		}
+		(Value::Int(_, Some(a)), Kind::Int(b)) if a == b => Ok(value),
+		(Value::Bool(_), Kind::Bool) => Ok(value),
+		(_, Kind::Int(ty)) => Err(Problem::new(
+			line,
+			format!(
+				"mismatched types: expected `{ty}`, found {}",
+				value.type_name()
+			),
+		)),
+		(_, Kind::Bool) => Err(Problem::new(
+			line,
+			format!(
+				"mismatched types: expected `bool`, found {}",
+				value.type_name()
+			),
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mini::syntax;
+
+	fn trace(source: &str) -> Trace {
+		let program = syntax::parse(source, 1).unwrap();
+		in_subset(&program).unwrap();
+		run(&program)
+	}
+
+	#[test]
+	fn loops_break_with_their_label() {
+		let trace = trace(
+			"fn main() {
+	let mut count = 0;
+	'counting_up: loop {
+		let mut remaining = 10;
+		loop {
+			if remaining == 9 {
+				break;
+			}
+			if count == 2 {
+				break 'counting_up;
+			}
+			remaining -= 1;
+		}
+		count += 1;
+	}
+	println!(\"End count = {count}\");
+}",
+		);
+		assert_eq!(trace.problem, None);
+		assert_eq!(trace.output, ["End count = 2"]);
+		assert!(trace
+			.steps
+			.iter()
+			.any(|step| step.line == 10 && step.depth == 2));
+	}
+
+	#[test]
+	fn overflow_stops_the_program() {
+		let trace = trace(
+			"fn main() {
+	let mut x: u8 = 255;
+	x += 1;
+	println!(\"{x}\");
+}",
+		);
+		assert_eq!(trace.problem.map(|problem| problem.line), Some(3));
+		assert!(trace.output.is_empty());
+	}
+
+	#[test]
+	fn functions_return_their_tail() {
+		let trace = trace(
+			"fn plus_one(x: i32) -> i32 {
+	x + 1
+}
+
+fn main() {
+	let five = 5;
+	let six = plus_one(five);
+	println!(\"{six}\");
+}",
+		);
+		assert_eq!(trace.problem, None);
+		assert_eq!(trace.output, ["6"]);
+	}
+
+	#[test]
+	fn assigning_twice_to_an_immutable_variable_stops_it() {
+		let trace = trace(
+			"fn main() {
+	let x = 5;
+	x = 6;
+}",
+		);
+		assert_eq!(trace.problem.map(|problem| problem.line), Some(3));
+	}
+
+	/// The problem `run` stops `source` with before it takes a step.
+	fn rejected(source: &str) -> Option<Problem> {
+		let trace = trace(source);
+		assert!(trace.steps.is_empty());
+		trace.problem
+	}
+
+	#[test]
+	fn both_arms_of_an_if_are_type_checked() {
+		let problem = rejected(
+			"fn main() {
+	let x = if true { 5 } else { false };
+}",
+		);
+		assert_eq!(
+			problem,
+			Some(Problem::new(
+				2,
+				"`if` and `else` have incompatible types: expected integer, found `bool`"
+			))
+		);
+		let problem = rejected(
+			"fn main() {
+	let n: u8 = 3;
+	let x = if n > 2 {
+		n
+	} else if n == 0 {
+		1
+	} else {
+		1u16
+	};
+}",
+		);
+		assert_eq!(problem.map(|problem| problem.line), Some(8));
+	}
+
+	#[test]
+	fn an_if_without_else_gives_unit() {
+		let problem = rejected(
+			"fn main() {
+	let x = if true { 5 };
+}",
+		);
+		assert_eq!(
+			problem,
+			Some(Problem::new(
+				2,
+				"`if` may be missing an `else` clause: expected `()`, found integer"
+			))
+		);
+	}
+
+	#[test]
+	fn arms_that_agree_are_run() {
+		let trace = trace(
+			"fn main() {
+	let condition = false;
+	let number = if condition { 5 } else { return; };
+	let other = if condition { number } else { 6i64 };
+	if condition {
+		println!(\"{number}\");
+	}
+	println!(\"{other}\");
+}",
+		);
+		assert_eq!(trace.problem, None);
+		assert!(trace.output.is_empty());
+	}
+}
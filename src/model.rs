@@ -0,0 +1,258 @@
+//! The ordered prose/code model of a notes file.
+//!
+//! A [`Document`] is a flat list of [`Block`]s in source order. Every block
+//! remembers the lines it was taken from, so anything built on the model can
+//! point back into the original file.
+
+use std::fmt;
+use std::fs;
+
+use crate::error::{Error, Result};
+use crate::lex::{self, TokenKind};
+use crate::parse;
+use crate::source::NoteFile;
+use crate::toml;
+
+/// An inclusive, 1-based range of source lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lines {
+	pub start: usize,
+	pub end: usize,
+}
+
+impl Lines {
+	pub fn new(start: usize, end: usize) -> Lines {
+		Lines { start, end }
+	}
+
+	pub fn contains(&self, line: usize) -> bool {
+		self.start <= line && line <= self.end
+	}
+}
+
+impl fmt::Display for Lines {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.start == self.end {
+			write!(f, "{}", self.start)
+		} else {
+			write!(f, "{}-{}", self.start, self.end)
+		}
+	}
+}
+
+/// A `Title:` line that opens a section of the notes.
+///
+/// Unindented titles are level 1; titles indented by one tab are level 2, and
+/// so on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+	pub level: usize,
+	/// The title without its trailing colon.
+	pub title: String,
+	pub lines: Lines,
+}
+
+/// Where a piece of prose was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProseStyle {
+	/// Inside a `/* ... */` comment.
+	Block,
+	/// A paragraph of `//` comments standing between two code snippets.
+	Line,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prose {
+	pub style: ProseStyle,
+	pub lines: Lines,
+	/// The prose with comment markers removed but indentation kept.
+	pub text: String,
+}
+
+/// A run of code outside the comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Code {
+	/// Position among the code blocks of the document, from 0.
+	pub index: usize,
+	pub lines: Lines,
+	/// The code exactly as written, including its indentation.
+	pub text: String,
+}
+
+/// The language a code block is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+	Rust,
+	/// A `Cargo.toml` excerpt such as `[dependencies]`.
+	Toml,
+	/// Shell commands written as `$ cargo ...`.
+	Console,
+	/// Text that is none of the above, such as prose that ended up outside
+	/// its comment.
+	Unknown,
+}
+
+impl Lang {
+	pub fn name(self) -> &'static str {
+		match self {
+			Lang::Rust => "rust",
+			Lang::Toml => "toml",
+			Lang::Console => "console",
+			Lang::Unknown => "unknown",
+		}
+	}
+}
+
+impl Code {
+	/// Works out the language of the block from its text.
+	///
+	/// A block is a console session if it starts with a `$ ` prompt, and
+	/// TOML if it starts with a table header or is made of nothing but
+	/// `key = value` lines that read as TOML. Anything else is Rust, unless
+	/// it has characters Rust has no use for or reads like a sentence.
+	pub fn lang(&self) -> Lang {
+		let lines: Vec<&str> = self
+			.text
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.collect();
+		let first = lines.first().copied().unwrap_or("");
+		let is_table = first.len() > 2
+			&& first.starts_with('[')
+			&& first.ends_with(']')
+			&& first[1..first.len() - 1]
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || "_-.[]".contains(c));
+		let is_key_values = !lines.is_empty()
+			&& lines.iter().all(|line| {
+				line.starts_with('#') || (line.contains(" = ") && !line.ends_with(';'))
+			}) && toml::parse(&self.text).is_ok();
+		if first.starts_with("$ ") {
+			Lang::Console
+		} else if is_table || is_key_values {
+			Lang::Toml
+		} else if is_rust(&self.text) {
+			Lang::Rust
+		} else {
+			Lang::Unknown
+		}
+	}
+}
+
+/// Whether text could be Rust: every character lexes, and no line has a run
+/// of plain words the way a sentence does.
+fn is_rust(text: &str) -> bool {
+	const SENTENCE_WORDS: usize = 3;
+	let tokens = lex::tokens(text);
+	if tokens.iter().any(|token| token.kind == TokenKind::Unknown) {
+		return false;
+	}
+	let mut run = 0;
+	for token in &tokens {
+		match token.kind {
+			TokenKind::Ident => {
+				run += 1;
+				if run >= SENTENCE_WORDS {
+					return false;
+				}
+			}
+			TokenKind::Whitespace if !token.text.contains('\n') => {}
+			_ => run = 0,
+		}
+	}
+	true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+	Heading(Heading),
+	Prose(Prose),
+	Code(Code),
+}
+
+impl Block {
+	pub fn lines(&self) -> Lines {
+		match self {
+			Block::Heading(heading) => heading.lines,
+			Block::Prose(prose) => prose.lines,
+			Block::Code(code) => code.lines,
+		}
+	}
+}
+
+/// The blocks that follow one heading, up to the next heading.
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'a> {
+	/// `None` for the preamble before the first heading.
+	pub heading: Option<&'a Heading>,
+	pub blocks: &'a [Block],
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+	pub file: NoteFile,
+	pub blocks: Vec<Block>,
+}
+
+impl Document {
+	/// Reads and parses a notes file.
+	pub fn load(file: NoteFile) -> Result<Document> {
+		let source = fs::read_to_string(&file.path).map_err(|e| Error::io(&file.path, e))?;
+		Ok(Document::parse(file, &source))
+	}
+
+	pub fn parse(file: NoteFile, source: &str) -> Document {
+		Document {
+			blocks: parse::blocks(source),
+			file,
+		}
+	}
+
+	pub fn headings(&self) -> impl Iterator<Item = &Heading> {
+		self.blocks.iter().filter_map(|block| match block {
+			Block::Heading(heading) => Some(heading),
+			_ => None,
+		})
+	}
+
+	pub fn code_blocks(&self) -> impl Iterator<Item = &Code> {
+		self.blocks.iter().filter_map(|block| match block {
+			Block::Code(code) => Some(code),
+			_ => None,
+		})
+	}
+
+	/// Splits the document at every heading, whatever its level.
+	pub fn sections(&self) -> Vec<Section<'_>> {
+		let mut sections = Vec::new();
+		let mut start = 0;
+		let mut heading = None;
+		for (i, block) in self.blocks.iter().enumerate() {
+			if let Block::Heading(next) = block {
+				if heading.is_some() || i > start {
+					sections.push(Section {
+						heading,
+						blocks: &self.blocks[start..i],
+					});
+				}
+				heading = Some(next);
+				start = i + 1;
+			}
+		}
+		if heading.is_some() || start < self.blocks.len() {
+			sections.push(Section {
+				heading,
+				blocks: &self.blocks[start..],
+			});
+		}
+		sections
+	}
+
+	/// The nearest heading at or above `line`.
+	pub fn heading_for_line(&self, line: usize) -> Option<&Heading> {
+		self.headings()
+			.take_while(|heading| heading.lines.start <= line)
+			.last()
+	}
+}
@@ -0,0 +1,514 @@
+//! Splitting a notes file into headings, prose and code.
+//!
+//! The files follow Rust's own comment rules: `/* ... */` comments nest, and
+//! a `/*` inside a string literal or after `//` in code does not open one.
+//! Everything inside a comment is prose and everything outside is code.
+//!
+//! Two more things are recovered on top of that split. Inside prose, a short
+//! paragraph ending in a colon and followed by more prose is a `Title:`
+//! heading. Inside code, a paragraph of `//` comments standing between blank
+//! lines at the top level separates two snippets, and is kept as prose.
+
+use crate::model::{Block, Code, Heading, Lines, Prose, ProseStyle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionKind {
+	Comment,
+	Code,
+}
+
+/// A stretch of the file that is either all comment or all code.
+struct Region<'a> {
+	kind: RegionKind,
+	first_line: usize,
+	/// The text between the outermost comment delimiters, or the code.
+	text: &'a str,
+}
+
+/// Parses a whole notes file into blocks, in source order.
+pub fn blocks(source: &str) -> Vec<Block> {
+	let mut builder = Builder::default();
+	for region in regions(source) {
+		let lines: Vec<(usize, &str)> = region
+			.text
+			.split('\n')
+			.enumerate()
+			.map(|(i, line)| {
+				(
+					region.first_line + i,
+					line.strip_suffix('\r').unwrap_or(line),
+				)
+			})
+			.collect();
+		match region.kind {
+			RegionKind::Comment => builder.prose(&lines),
+			RegionKind::Code => builder.code(&lines),
+		}
+	}
+	builder.blocks
+}
+
+fn regions(source: &str) -> Vec<Region<'_>> {
+	let bytes = source.as_bytes();
+	let mut regions = Vec::new();
+	let mut kind = RegionKind::Code;
+	let mut start = 0;
+	let mut start_line = 1;
+	let mut line = 1;
+	let mut depth = 0;
+	let mut i = 0;
+
+	while i < bytes.len() {
+		let rest = &bytes[i..];
+		match kind {
+			RegionKind::Code => {
+				if rest.starts_with(b"/*") {
+					regions.push(Region {
+						kind,
+						first_line: start_line,
+						text: &source[start..i],
+					});
+					kind = RegionKind::Comment;
+					depth = 1;
+					i += 2;
+					start = i;
+					start_line = line;
+					continue;
+				}
+				if rest.starts_with(b"//") {
+					i += rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+					continue;
+				}
+				if let Some(len) = literal_len(source, i) {
+					line += source[i..i + len].matches('\n').count();
+					i += len;
+					continue;
+				}
+			}
+			RegionKind::Comment => {
+				if rest.starts_with(b"/*") {
+					depth += 1;
+					i += 2;
+					continue;
+				}
+				if rest.starts_with(b"*/") {
+					depth -= 1;
+					if depth == 0 {
+						regions.push(Region {
+							kind,
+							first_line: start_line,
+							text: &source[start..i],
+						});
+						kind = RegionKind::Code;
+						start = i + 2;
+						start_line = line;
+					}
+					i += 2;
+					continue;
+				}
+			}
+		}
+		if bytes[i] == b'\n' {
+			line += 1;
+		}
+		i += 1;
+	}
+
+	regions.push(Region {
+		kind,
+		first_line: start_line,
+		text: &source[start..],
+	});
+	regions
+}
+
+/// The length of the string or char literal starting at byte `at`, if any.
+///
+/// A quote that is never closed is not treated as a literal, so a stray `"`
+/// in sloppy code cannot swallow the rest of the file.
+fn literal_len(source: &str, at: usize) -> Option<usize> {
+	let bytes = source.as_bytes();
+	match bytes[at] {
+		b'"' => {
+			let mut i = at + 1;
+			while i < bytes.len() {
+				match bytes[i] {
+					b'\\' => i += 2,
+					b'"' => return Some(i + 1 - at),
+					_ => i += 1,
+				}
+			}
+			None
+		}
+		b'\'' => {
+			let rest = &source[at + 1..];
+			let mut chars = rest.char_indices();
+			let (_, first) = chars.next()?;
+			if first == '\\' {
+				let close = rest[1..].find('\'')?;
+				return Some(close + 3);
+			}
+			// `'a'` is a char; `'a` followed by anything else is a lifetime or
+			// a loop label.
+			let (close, second) = chars.next()?;
+			(second == '\'').then_some(close + 2)
+		}
+		b'r' => {
+			let previous = at.checked_sub(1).map(|p| bytes[p]);
+			if previous.is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_') {
+				return None;
+			}
+			let hashes = bytes[at + 1..].iter().take_while(|&&b| b == b'#').count();
+			if bytes.get(at + 1 + hashes) != Some(&b'"') {
+				return None;
+			}
+			let body = at + 2 + hashes;
+			let close = format!("\"{}", "#".repeat(hashes));
+			let end = source[body..].find(&close)?;
+			Some(body + end + close.len() - at)
+		}
+		_ => None,
+	}
+}
+
+#[derive(Default)]
+struct Builder {
+	blocks: Vec<Block>,
+	code_count: usize,
+}
+
+impl Builder {
+	fn prose(&mut self, lines: &[(usize, &str)]) {
+		let mut pending: Vec<(usize, &str)> = Vec::new();
+		let mut i = 0;
+		while i < lines.len() {
+			let starts_paragraph = !is_blank(lines[i].1) && (i == 0 || is_blank(lines[i - 1].1));
+			if starts_paragraph {
+				let end = paragraph_end(lines, i);
+				if let Some(heading) = heading(lines, i, end) {
+					self.push_prose(ProseStyle::Block, &pending);
+					pending.clear();
+					self.blocks.push(Block::Heading(heading));
+					i = end;
+					continue;
+				}
+			}
+			pending.push(lines[i]);
+			i += 1;
+		}
+		self.push_prose(ProseStyle::Block, &pending);
+	}
+
+	fn code(&mut self, lines: &[(usize, &str)]) {
+		let text: Vec<&str> = lines.iter().map(|&(_, line)| line).collect();
+		let depths = nesting_depths(&text);
+		let mut start = 0;
+		let mut i = 0;
+		while i < lines.len() {
+			let opens_interlude = depths[i] == 0
+				&& is_line_comment(lines[i].1)
+				&& (i == 0 || is_blank(lines[i - 1].1));
+			if opens_interlude {
+				let end = i + lines[i..]
+					.iter()
+					.take_while(|(_, line)| is_line_comment(line))
+					.count();
+				if end == lines.len() || is_blank(lines[end].1) {
+					self.push_code(&lines[start..i]);
+					let prose: Vec<(usize, String)> = lines[i..end]
+						.iter()
+						.map(|&(number, line)| (number, strip_line_comment(line)))
+						.collect();
+					self.push_prose(ProseStyle::Line, &prose);
+					start = end;
+					i = end;
+					continue;
+				}
+			}
+			i += 1;
+		}
+		self.push_code(&lines[start..]);
+	}
+
+	fn push_prose<S: AsRef<str>>(&mut self, style: ProseStyle, lines: &[(usize, S)]) {
+		if let Some((lines, text)) = join_trimmed(lines) {
+			self.blocks.push(Block::Prose(Prose { style, lines, text }));
+		}
+	}
+
+	fn push_code(&mut self, lines: &[(usize, &str)]) {
+		if let Some((lines, text)) = join_trimmed(lines) {
+			self.blocks.push(Block::Code(Code {
+				index: self.code_count,
+				lines,
+				text,
+			}));
+			self.code_count += 1;
+		}
+	}
+}
+
+/// Drops blank lines from both ends and joins the rest.
+fn join_trimmed<S: AsRef<str>>(lines: &[(usize, S)]) -> Option<(Lines, String)> {
+	let first = lines
+		.iter()
+		.position(|(_, line)| !is_blank(line.as_ref()))?;
+	let last = lines
+		.iter()
+		.rposition(|(_, line)| !is_blank(line.as_ref()))?;
+	let kept = &lines[first..=last];
+	let text = kept
+		.iter()
+		.map(|(_, line)| line.as_ref())
+		.collect::<Vec<_>>()
+		.join("\n");
+	Some((Lines::new(kept[0].0, kept[kept.len() - 1].0), text))
+}
+
+fn paragraph_end(lines: &[(usize, &str)], start: usize) -> usize {
+	start
+		+ lines[start..]
+			.iter()
+			.take_while(|(_, line)| !is_blank(line))
+			.count()
+}
+
+/// Recognises the paragraph `lines[start..end]` as a `Title:` heading.
+fn heading(lines: &[(usize, &str)], start: usize, end: usize) -> Option<Heading> {
+	let paragraph = &lines[start..end];
+	let indent = indent_of(paragraph[0].1);
+	if paragraph.len() > 2 || paragraph.iter().any(|(_, line)| indent_of(line) != indent) {
+		return None;
+	}
+
+	let joined = paragraph
+		.iter()
+		.map(|(_, line)| line.trim())
+		.collect::<Vec<_>>()
+		.join(" ");
+	let title = joined.strip_suffix(':')?.trim_end();
+	if !title.starts_with(|c: char| c.is_alphanumeric()) {
+		return None;
+	}
+
+	let next = lines[end..].iter().find(|(_, line)| !is_blank(line));
+	match next {
+		// Only an unindented title may close a comment, as in "Function calls
+		// with Ownership: ...*/" right before the code it introduces.
+		None if indent > 0 => return None,
+		None => {}
+		Some((_, next)) if indent > 0 && !is_subheading(title, indent, next) => return None,
+		Some(_) => {}
+	}
+	if indent > 0 && paragraph.len() > 1 {
+		return None;
+	}
+
+	Some(Heading {
+		level: indent + 1,
+		title: title.to_string(),
+		lines: Lines::new(paragraph[0].0, paragraph[paragraph.len() - 1].0),
+	})
+}
+
+/// Tells an indented title such as "Scalar Types:" apart from an indented
+/// sentence that merely ends in a colon, such as "Here is the error in full:".
+fn is_subheading(title: &str, indent: usize, next: &str) -> bool {
+	const OPENERS: [&str; 12] = [
+		"here", "this", "these", "that", "we", "but", "so", "then", "now", "let's", "it", "which",
+	];
+	let words: Vec<&str> = title.split_whitespace().collect();
+	let opener = words[0].to_lowercase();
+	words.len() <= 6
+		&& !title.contains([',', ';', '!', '?', '`', '"'])
+		&& !OPENERS.contains(&opener.as_str())
+		&& indent_of(next) > indent
+}
+
+/// Bracket nesting depth at the start of each line of code, counting `{`,
+/// `(` and `[` alike and ignoring brackets in strings, chars and comments.
+pub(crate) fn nesting_depths(lines: &[&str]) -> Vec<usize> {
+	let mut depths = Vec::with_capacity(lines.len());
+	let mut depth: usize = 0;
+	for line in lines {
+		depths.push(depth);
+		let mut chars = line.chars();
+		while let Some(c) = chars.next() {
+			match c {
+				'{' | '(' | '[' => depth += 1,
+				'}' | ')' | ']' => depth = depth.saturating_sub(1),
+				'/' if chars.as_str().starts_with('/') => break,
+				'"' => {
+					while let Some(c) = chars.next() {
+						match c {
+							'\\' => {
+								chars.next();
+							}
+							'"' => break,
+							_ => {}
+						}
+					}
+				}
+				'\'' => {
+					// Skip a bracket in a char literal; anything else, such as
+					// a lifetime, is left alone.
+					let rest = chars.as_str();
+					let mut inner = rest.chars();
+					if inner.next().is_some_and(|c| "{}()[]".contains(c))
+						&& inner.next() == Some('\'')
+					{
+						chars.nth(1);
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+	depths
+}
+
+pub(crate) fn is_blank(line: &str) -> bool {
+	line.trim().is_empty()
+}
+
+fn is_line_comment(line: &str) -> bool {
+	line.trim_start().starts_with("//")
+}
+
+/// Removes the `//` from a comment line, keeping the indentation before it.
+fn strip_line_comment(line: &str) -> String {
+	let body = line.trim_start();
+	let indent = &line[..line.len() - body.len()];
+	let text = body.trim_start_matches('/');
+	let text = text.strip_prefix(' ').unwrap_or(text);
+	format!("{indent}{text}")
+}
+
+/// Indentation depth, counting a tab or four spaces as one level.
+pub(crate) fn indent_of(line: &str) -> usize {
+	let mut tabs = 0;
+	let mut spaces = 0;
+	for c in line.chars() {
+		match c {
+			'\t' => tabs += 1,
+			' ' => spaces += 1,
+			_ => break,
+		}
+	}
+	tabs + spaces / 4
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::Lang;
+
+	/// The kind of every block with its first and last line.
+	fn shapes(source: &str) -> Vec<(&'static str, usize, usize)> {
+		blocks(source)
+			.iter()
+			.map(|block| {
+				let kind = match block {
+					Block::Heading(_) => "heading",
+					Block::Prose(prose) if prose.style == ProseStyle::Line => "line prose",
+					Block::Prose(_) => "prose",
+					Block::Code(_) => "code",
+				};
+				(kind, block.lines().start, block.lines().end)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn splits_prose_headings_and_code() {
+		let source = "/*
+
+These are my notes.
+
+Setup:
+
+	We can create a new project with*/
+
+	$ cargo new hello_cargo
+
+	/*
+	Running It:
+
+		Like so:*/ fn main() {}
+
+	// A paragraph of line comments
+	// between two snippets.
+
+	fn second() {}
+";
+		assert_eq!(
+			shapes(source),
+			[
+				("prose", 3, 3),
+				("heading", 5, 5),
+				("prose", 7, 7),
+				("code", 9, 9),
+				("heading", 12, 12),
+				("prose", 14, 14),
+				("code", 14, 14),
+				("line prose", 16, 17),
+				("code", 19, 19),
+			]
+		);
+		let blocks = blocks(source);
+		let Block::Heading(heading) = &blocks[4] else {
+			panic!("line 12 is a heading");
+		};
+		assert_eq!((heading.level, heading.title.as_str()), (2, "Running It"));
+		let Block::Code(console) = &blocks[3] else {
+			panic!("line 9 is code");
+		};
+		assert_eq!(console.lang(), Lang::Console);
+		let Block::Code(code) = &blocks[6] else {
+			panic!("the end of line 14 is code");
+		};
+		assert_eq!(code.text.trim(), "fn main() {}");
+	}
+
+	#[test]
+	fn comments_nest_and_strings_hide_delimiters() {
+		let source = "/* Nested /* comment */ still prose. */
+let s = \"/* not a comment\"; // nor /* this
+let t = 1;
+";
+		assert_eq!(shapes(source), [("prose", 1, 1), ("code", 2, 3)]);
+	}
+
+	#[test]
+	fn a_colon_before_code_is_not_a_heading() {
+		let source = "/*
+	Consider this struct:*/
+
+	struct Point { x: i32 }
+";
+		assert_eq!(shapes(source), [("prose", 2, 2), ("code", 4, 4)]);
+	}
+
+	#[test]
+	fn chapter_1_commands_are_console_blocks() {
+		let source = include_str!("../rust_chapter_1_project_setup.rs");
+		let commands: Vec<usize> = source
+			.lines()
+			.enumerate()
+			.filter(|(_, line)| line.trim_start().starts_with("$ cargo"))
+			.map(|(i, _)| i + 1)
+			.collect();
+		assert!(!commands.is_empty());
+		for line in commands {
+			let code = blocks(source).into_iter().find_map(|block| match block {
+				Block::Code(code) if code.lines.contains(line) => Some(code),
+				_ => None,
+			});
+			assert_eq!(
+				code.map(|code| code.lang()),
+				Some(Lang::Console),
+				"line {line}"
+			);
+		}
+	}
+}
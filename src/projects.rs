@@ -0,0 +1,508 @@
+//! The Cargo projects the notes were written in, rebuilt from the snippets.
+//!
+//! The notes refer to real projects: `hello_cargo` in chapter 1, the guessing
+//! game, the `variables/` project, and the `backyard` and `restaurant` crates
+//! of chapter 7. [`PROJECTS`] says where each one's code is in the notes. The
+//! last snippet under a file name is that file; a snippet that is neither
+//! named nor expected to fail is the crate root if it has a `fn main`.
+//!
+//! A snippet is named by the last `src/....rs` path in the prose just before
+//! it, as in "The crate root is src/main.rs, and it looks like this:", or by
+//! a comment line such as `//src/front_of_house.rs:`, which may also split
+//! one block into several files. The manifest is put together from the TOML
+//! blocks of the same stretch of notes. A `src/main.rs` with no `fn main` is
+//! a library, and becomes `src/lib.rs`.
+
+use std::path::{Path, PathBuf};
+
+use crate::check::{code_part, strip_annotation, top_level_items, Expectation};
+use crate::error::Result;
+use crate::export;
+use crate::model::{Block, Code, Document, Lang};
+use crate::source::FileKind;
+use crate::text;
+use crate::toml::{self, Table, Value};
+
+/// Where a project's code is in the notes: the chapter, and the headings
+/// its stretch of the notes starts at and ends before. `None` means the top
+/// or the bottom of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Project {
+	pub name: &'static str,
+	pub chapter: u32,
+	pub from: Option<&'static str>,
+	pub until: Option<&'static str>,
+}
+
+pub const PROJECTS: [Project; 5] = [
+	Project {
+		name: "hello_cargo",
+		chapter: 1,
+		from: None,
+		until: None,
+	},
+	Project {
+		name: "guessing_game",
+		chapter: 2,
+		from: None,
+		until: None,
+	},
+	Project {
+		name: "variables",
+		chapter: 3,
+		from: None,
+		until: Some("Data Types"),
+	},
+	Project {
+		name: "backyard",
+		chapter: 7,
+		from: Some("Modules Cheat Sheet"),
+		until: Some("Grouping Related Code in Modules"),
+	},
+	Project {
+		name: "restaurant",
+		chapter: 7,
+		from: Some("Separating Modules into Different Files"),
+		until: None,
+	},
+];
+
+/// A source file of a generated project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+	/// The path inside the project, e.g. `src/garden/vegetables.rs`.
+	pub path: String,
+	pub text: String,
+	/// The lines of the notes file the text was taken from.
+	pub lines: (usize, usize),
+}
+
+/// A project rebuilt from the notes, ready to be written out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+	pub name: &'static str,
+	/// Index of the notes file in the documents it was built from.
+	pub doc: usize,
+	pub manifest: String,
+	/// The crate root first, then the other files in the order the notes
+	/// give them.
+	pub files: Vec<SourceFile>,
+	/// What keeps the project from building as the notes describe it.
+	pub problems: Vec<String>,
+}
+
+/// Every project of [`PROJECTS`] whose chapter is among `docs`.
+pub fn members(docs: &[Document]) -> Vec<Member> {
+	PROJECTS
+		.iter()
+		.filter_map(|project| {
+			let doc = docs.iter().position(|doc| {
+				doc.file.kind == FileKind::Notes && doc.file.chapter == project.chapter
+			})?;
+			Some(member(project, doc, &docs[doc]))
+		})
+		.collect()
+}
+
+fn member(project: &Project, doc_index: usize, doc: &Document) -> Member {
+	let mut files: Vec<SourceFile> = Vec::new();
+	let mut manifests: Vec<Table> = Vec::new();
+	let mut problems = Vec::new();
+	// The file named by the prose since the last code block.
+	let mut named: Option<String> = None;
+	for block in stretch(project, doc) {
+		match block {
+			Block::Heading(_) => {}
+			Block::Prose(prose) => {
+				if let Some(path) = src_paths(&prose.text).pop() {
+					named = Some(path);
+				}
+			}
+			Block::Code(code) if code.lang() == Lang::Toml => {
+				match toml::parse(&text::dedent(&code.text)) {
+					Ok(table) => manifests.push(table),
+					Err(e) => problems.push(format!(
+						"the TOML block at line {} is not valid: {e}",
+						code.lines.start
+					)),
+				}
+				named = None;
+			}
+			Block::Code(code) => {
+				if code.lang() == Lang::Rust && Expectation::of(code) == Expectation::Compiles {
+					for file in parts(code, named.take()) {
+						files.retain(|old| old.path != file.path);
+						files.push(file);
+					}
+				}
+				named = None;
+			}
+		}
+	}
+
+	if let Some(root) = files.iter_mut().find(|file| file.path == "src/main.rs") {
+		if !defines_main(&root.text) {
+			root.path = "src/lib.rs".to_string();
+		}
+	}
+	match files.iter().position(|file| is_root(&file.path)) {
+		Some(root) => {
+			let root = files.remove(root);
+			files.insert(0, root);
+		}
+		None => problems.push("no snippet has a `fn main` or is named src/lib.rs".to_string()),
+	}
+	problems.extend(module_problems(&files));
+	Member {
+		name: project.name,
+		doc: doc_index,
+		manifest: manifest(project.name, &manifests),
+		files,
+		problems,
+	}
+}
+
+/// The blocks of `doc` from the project's first heading up to its last.
+fn stretch<'a>(project: &'a Project, doc: &'a Document) -> impl Iterator<Item = &'a Block> {
+	let is = |block: &Block, title: Option<&str>| matches!((block, title), (Block::Heading(h), Some(title)) if h.title == title);
+	let start = match project.from {
+		None => 0,
+		Some(_) => doc
+			.blocks
+			.iter()
+			.position(|block| is(block, project.from))
+			.unwrap_or(doc.blocks.len()),
+	};
+	doc.blocks[start..]
+		.iter()
+		.take_while(move |block| !is(block, project.until))
+}
+
+/// The files a code block gives: one for each `//src/....rs:` comment line,
+/// and one for the code before the first, named `named` or, if it has a
+/// `fn main`, `src/main.rs`.
+fn parts(code: &Code, named: Option<String>) -> Vec<SourceFile> {
+	let mut found: Vec<(Option<String>, usize, Vec<&str>)> = vec![(named, 0, Vec::new())];
+	for (i, line) in code.text.lines().enumerate() {
+		let comment = line.trim();
+		let marker = comment
+			.strip_prefix("//")
+			.filter(|rest| rest.ends_with(':'))
+			.and_then(|rest| src_paths(rest).pop());
+		match marker {
+			Some(path) => found.push((Some(path), i + 1, Vec::new())),
+			None => found.last_mut().unwrap().2.push(strip_annotation(line)),
+		}
+	}
+
+	let mut files = Vec::new();
+	for (path, first, lines) in found {
+		let Some(start) = lines.iter().position(|line| !line.trim().is_empty()) else {
+			continue;
+		};
+		let end = lines
+			.iter()
+			.rposition(|line| !line.trim().is_empty())
+			.unwrap();
+		let text = text::dedent(&lines[start..=end].join("\n"));
+		let path = match path {
+			Some(path) => path,
+			None if defines_main(&text) => "src/main.rs".to_string(),
+			None => continue,
+		};
+		files.push(SourceFile {
+			path,
+			text: text + "\n",
+			lines: (
+				code.lines.start + first + start,
+				code.lines.start + first + end,
+			),
+		});
+	}
+	files
+}
+
+/// The `src/....rs` paths mentioned in `text`, in order.
+fn src_paths(text: &str) -> Vec<String> {
+	let mut paths = Vec::new();
+	for (at, _) in text.match_indices("src/") {
+		let before = text[..at].chars().next_back();
+		if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+			continue;
+		}
+		let path: String = text[at..]
+			.chars()
+			.take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '/' | '.'))
+			.collect();
+		let path = path.trim_end_matches('.');
+		// Compiler output like `--> src/main.rs:4:5` points into a file
+		// rather than naming the next snippet.
+		let after = &text[at + path.len()..];
+		let position = after
+			.strip_prefix(':')
+			.is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+		if path.ends_with(".rs") && path.len() > "src/.rs".len() && !position {
+			paths.push(path.to_string());
+		}
+	}
+	paths
+}
+
+fn defines_main(text: &str) -> bool {
+	top_level_items(text).iter().any(|item| item.key == "main")
+}
+
+fn is_root(path: &str) -> bool {
+	path == "src/main.rs" || path == "src/lib.rs"
+}
+
+/// The `mod name;` declarations with no file to go with them, and the files
+/// no module declares.
+fn module_problems(files: &[SourceFile]) -> Vec<String> {
+	let mut problems = Vec::new();
+	let mut declared: Vec<String> = Vec::new();
+	for file in files {
+		// Where the files of this file's submodules live.
+		let dir = if is_root(&file.path) || file.path.ends_with("/mod.rs") {
+			file.path
+				.rsplit_once('/')
+				.map_or("", |(dir, _)| dir)
+				.to_string()
+		} else {
+			file.path.trim_end_matches(".rs").to_string()
+		};
+		for line in file.text.lines() {
+			let line = code_part(line).trim();
+			let line = line.strip_prefix("pub ").unwrap_or(line);
+			let Some(name) = line.strip_prefix("mod ").and_then(|m| m.strip_suffix(';')) else {
+				continue;
+			};
+			let candidates = [
+				format!("{dir}/{}.rs", name.trim()),
+				format!("{dir}/{}/mod.rs", name.trim()),
+			];
+			match candidates
+				.iter()
+				.find(|path| files.iter().any(|file| file.path == **path))
+			{
+				Some(path) => declared.push(path.clone()),
+				None => problems.push(format!(
+					"{} declares `mod {};` but no snippet gives {}",
+					file.path,
+					name.trim(),
+					candidates[0]
+				)),
+			}
+		}
+	}
+	for file in files {
+		if !is_root(&file.path) && !declared.contains(&file.path) {
+			problems.push(format!("{} is not declared by any module", file.path));
+		}
+	}
+	problems
+}
+
+/// The project's `Cargo.toml`: the `[package]` the notes give, if any, under
+/// the project's name, then every other table of the notes' TOML blocks,
+/// later keys replacing earlier ones.
+fn manifest(name: &str, blocks: &[Table]) -> String {
+	let mut tables: Vec<(String, Table)> = vec![
+		("package".to_string(), Table::default()),
+		("dependencies".to_string(), Table::default()),
+	];
+	for block in blocks {
+		for entry in &block.entries {
+			let Value::Table(table) = &entry.value else {
+				continue;
+			};
+			let at = match tables.iter().position(|(key, _)| *key == entry.key) {
+				Some(at) => at,
+				None => {
+					tables.push((entry.key.clone(), Table::default()));
+					tables.len() - 1
+				}
+			};
+			let merged = &mut tables[at].1;
+			for inner in &table.entries {
+				merged.entries.retain(|old| old.key != inner.key);
+				merged.entries.push(inner.clone());
+			}
+		}
+	}
+
+	// The name is the project's; the version and edition are the notes', or
+	// what `cargo new` would write.
+	let package = &mut tables[0].1;
+	let given = |key: &str, default: &str| {
+		package
+			.get(key)
+			.cloned()
+			.unwrap_or_else(|| Value::String(default.to_string()))
+	};
+	let first = [
+		("name", Value::String(name.to_string())),
+		("version", given("version", "0.1.0")),
+		("edition", given("edition", "2021")),
+	];
+	package
+		.entries
+		.retain(|entry| !first.iter().any(|(key, _)| entry.key == *key));
+	for (i, (key, value)) in first.into_iter().enumerate() {
+		package.entries.insert(
+			i,
+			toml::Entry {
+				key: key.to_string(),
+				value,
+				line: 0,
+			},
+		);
+	}
+
+	let mut out = String::new();
+	for (i, (key, table)) in tables.iter().enumerate() {
+		if i > 0 {
+			out.push('\n');
+		}
+		out.push_str(&format!("[{}]\n", toml::format_key(key)));
+		for entry in &table.entries {
+			out.push_str(&format!(
+				"{} = {}\n",
+				toml::format_key(&entry.key),
+				entry.value
+			));
+		}
+	}
+	out
+}
+
+/// Writes a workspace of `members` under `out`: a `Cargo.toml` listing them,
+/// and a directory for each. Returns the paths written.
+pub fn write_workspace(members: &[Member], docs: &[Document], out: &Path) -> Result<Vec<PathBuf>> {
+	let mut written = Vec::new();
+	let names: Vec<String> = members
+		.iter()
+		.map(|member| format!("\t\"{}\",\n", member.name))
+		.collect();
+	let workspace = format!(
+		"# Written by `notes workspace` from the chapter notes.\n\n\
+		 [workspace]\nmembers = [\n{}]\nresolver = \"2\"\n",
+		names.concat()
+	);
+	let path = out.join("Cargo.toml");
+	export::write(&path, &workspace)?;
+	written.push(path);
+
+	for member in members {
+		let dir = out.join(member.name);
+		let path = dir.join("Cargo.toml");
+		export::write(&path, &member.manifest)?;
+		written.push(path);
+		let source = docs[member.doc].file.file_name();
+		for file in &member.files {
+			let lines = match file.lines {
+				(start, end) if start == end => start.to_string(),
+				(start, end) => format!("{start}-{end}"),
+			};
+			let path = dir.join(&file.path);
+			export::write(
+				&path,
+				&format!("// From {source}:{lines}.\n\n{}", file.text),
+			)?;
+			written.push(path);
+		}
+	}
+	Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	/// A chapter 7 with the `backyard` project in it, declaring its garden
+	/// module as `declaration`.
+	fn chapter_7(declaration: &str) -> Document {
+		let source = format!(
+			"/*
+Modules Cheat Sheet:
+
+	The manifest gets a dependency:*/
+
+	[dependencies]
+	rand = \"0.8.5\"
+
+	/*
+	The crate root is src/main.rs:*/
+
+	use crate::garden::vegetables::Asparagus;
+
+	{declaration}
+
+	fn main() {{
+		let plant = Asparagus {{}};
+		println!(\"I'm growing {{plant:?}}!\");
+	}}
+
+	/*
+	The module is in src/garden.rs:*/
+
+	pub mod vegetables;
+
+	/*
+	And src/garden/vegetables.rs has:*/
+
+	#[derive(Debug)]
+	pub struct Asparagus {{}}
+
+	/*
+Grouping Related Code in Modules:
+
+	Not part of the project.*/
+
+	fn main() {{}}
+"
+		);
+		Document::parse(
+			NoteFile::from_path("rust_chapter_7_packages_crates_modules.rs").unwrap(),
+			&source,
+		)
+	}
+
+	#[test]
+	fn a_project_is_rebuilt_from_its_stretch_of_the_notes() {
+		let members = members(&[chapter_7("pub mod garden;")]);
+		let names: Vec<&str> = members.iter().map(|member| member.name).collect();
+		assert_eq!(names, ["backyard", "restaurant"]);
+		let backyard = &members[0];
+		assert_eq!(
+			backyard.manifest,
+			"[package]\nname = \"backyard\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+			 [dependencies]\nrand = \"0.8.5\"\n"
+		);
+		let files: Vec<(&str, (usize, usize))> = backyard
+			.files
+			.iter()
+			.map(|file| (file.path.as_str(), file.lines))
+			.collect();
+		assert_eq!(
+			files,
+			[
+				("src/main.rs", (12, 19)),
+				("src/garden.rs", (24, 24)),
+				("src/garden/vegetables.rs", (29, 30)),
+			]
+		);
+		assert!(backyard.files[0].text.starts_with("use crate::garden"));
+		assert!(backyard.problems.is_empty());
+	}
+
+	#[test]
+	fn undeclared_modules_are_problems() {
+		let members = members(&[chapter_7("// no garden")]);
+		assert_eq!(
+			members[0].problems,
+			["src/garden.rs is not declared by any module"]
+		);
+	}
+}
@@ -0,0 +1,239 @@
+//! The paragraph structure inside a prose block.
+//!
+//! Prose in the notes is plain text laid out with tabs: paragraphs are
+//! separated by blank lines, bullets start with `-`, and nested bullets are
+//! indented one more level. Compiler output and `$ cargo` sessions are
+//! pasted in as they appeared in the terminal, and there are a few tables
+//! and directory trees lined up in columns. The exporters need to tell
+//! these apart: running text can be rewrapped, but the rest has to keep its
+//! line breaks.
+
+use crate::parse::{indent_of, is_blank};
+use crate::text;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Paragraph {
+	/// Running text, with its lines joined by spaces. `indent` is the
+	/// indentation level of its first line in the file.
+	Text { indent: usize, text: String },
+	/// A `-` bullet, with its lines joined. `depth` is the nesting within
+	/// the list, from 0.
+	Item {
+		indent: usize,
+		depth: usize,
+		text: String,
+	},
+	/// Text whose line breaks matter, dedented and with tabs expanded.
+	Preformatted { kind: Preformatted, text: String },
+}
+
+impl Paragraph {
+	/// The words of the paragraph, or its lines for preformatted text.
+	pub fn text(&self) -> &str {
+		match self {
+			Paragraph::Text { text, .. }
+			| Paragraph::Item { text, .. }
+			| Paragraph::Preformatted { text, .. } => text,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preformatted {
+	/// A terminal session or compiler output.
+	Console,
+	/// A table, a directory tree or anything else laid out in columns.
+	Plain,
+}
+
+/// Splits the text of a prose block into paragraphs.
+pub fn paragraphs(text: &str) -> Vec<Paragraph> {
+	let lines: Vec<&str> = text.lines().collect();
+	let mut paragraphs: Vec<Paragraph> = Vec::new();
+	// The raw lines of a console session being built, if any.
+	let mut session: Option<Vec<&str>> = None;
+	for chunk in lines.split(|line| is_blank(line)) {
+		if chunk.is_empty() {
+			continue;
+		}
+		let first = chunk[0].trim_start();
+		if let Some(raw) = session.as_mut() {
+			if is_compiler_output(first) {
+				raw.push("");
+				raw.extend_from_slice(chunk);
+				continue;
+			}
+		}
+		flush(&mut paragraphs, &mut session);
+
+		if first.starts_with("$ ") || is_compiler_output(first) {
+			session = Some(chunk.to_vec());
+		} else if bullet(first).is_some() {
+			items(&mut paragraphs, chunk);
+		} else if is_laid_out(chunk) {
+			paragraphs.push(Paragraph::Preformatted {
+				kind: Preformatted::Plain,
+				text: text::dedent(&chunk.join("\n")),
+			});
+		} else {
+			paragraphs.push(Paragraph::Text {
+				indent: indent_of(chunk[0]),
+				text: join(chunk),
+			});
+		}
+	}
+	flush(&mut paragraphs, &mut session);
+	nest(&mut paragraphs);
+	paragraphs
+}
+
+fn flush(paragraphs: &mut Vec<Paragraph>, session: &mut Option<Vec<&str>>) {
+	if let Some(raw) = session.take() {
+		paragraphs.push(Paragraph::Preformatted {
+			kind: Preformatted::Console,
+			text: text::dedent(&raw.join("\n")),
+		});
+	}
+}
+
+/// Splits a chunk of bullets into items; lines without a marker continue
+/// the item before them.
+fn items(paragraphs: &mut Vec<Paragraph>, chunk: &[&str]) {
+	let mut current: Option<(usize, Vec<&str>)> = None;
+	for line in chunk {
+		match bullet(line.trim_start()) {
+			Some(rest) => {
+				push_item(paragraphs, current.take());
+				current = Some((indent_of(line), vec![rest]));
+			}
+			None => {
+				if let Some((_, words)) = current.as_mut() {
+					words.push(line);
+				}
+			}
+		}
+	}
+	push_item(paragraphs, current);
+}
+
+fn push_item(paragraphs: &mut Vec<Paragraph>, item: Option<(usize, Vec<&str>)>) {
+	if let Some((indent, lines)) = item {
+		paragraphs.push(Paragraph::Item {
+			indent,
+			depth: 0,
+			text: join(&lines),
+		});
+	}
+}
+
+/// Works out the nesting of each run of items from their indentation.
+fn nest(paragraphs: &mut [Paragraph]) {
+	let mut stack: Vec<usize> = Vec::new();
+	for paragraph in paragraphs {
+		match paragraph {
+			Paragraph::Item { indent, depth, .. } => {
+				while stack.last().is_some_and(|&top| top > *indent) {
+					stack.pop();
+				}
+				if stack.last() != Some(indent) {
+					stack.push(*indent);
+				}
+				*depth = stack.len() - 1;
+			}
+			_ => stack.clear(),
+		}
+	}
+}
+
+fn join(lines: &[&str]) -> String {
+	lines
+		.iter()
+		.map(|line| line.trim())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// The text after a `-` bullet marker.
+fn bullet(line: &str) -> Option<&str> {
+	let rest = line.strip_prefix('-')?;
+	rest.starts_with([' ', '\t']).then_some(rest)
+}
+
+/// Whether a chunk is laid out in columns, so its spacing has to be kept:
+/// the integer type table in chapter 3, or the directory tree in chapter 7.
+fn is_laid_out(chunk: &[&str]) -> bool {
+	const BOX: [char; 4] = ['├', '└', '│', '─'];
+	let aligned = chunk
+		.iter()
+		.filter(|line| {
+			let line = line.trim();
+			line.contains("  ") || line.contains('\t')
+		})
+		.count();
+	chunk.iter().any(|line| line.contains(BOX)) || aligned >= 2
+}
+
+/// Lines as `rustc` and `cargo` print them.
+fn is_compiler_output(line: &str) -> bool {
+	const PREFIXES: [&str; 15] = [
+		"error[",
+		"error:",
+		"warning:",
+		"warning[",
+		"note:",
+		"help:",
+		"-->",
+		"|",
+		"= ",
+		"For more information",
+		"Compiling ",
+		"Finished dev",
+		"Finished `",
+		"Running `",
+		"thread '",
+	];
+	if PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+		return true;
+	}
+	// A numbered source line, as in `9 |     crate::front_of_house::...`.
+	let digits = line.chars().take_while(char::is_ascii_digit).count();
+	digits > 0 && line[digits..].trim_start().starts_with('|')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn console_sessions_keep_their_compiler_output() {
+		let text = "\tRunning it:\n\n\t$ cargo run\n\t   Compiling hello v0.1.0\n\n\terror[E0384]: cannot assign twice\n\n\tSo it fails.";
+		assert_eq!(
+			paragraphs(text),
+			[
+				Paragraph::Text {
+					indent: 1,
+					text: "Running it:".to_string()
+				},
+				Paragraph::Preformatted {
+					kind: Preformatted::Console,
+					text: "$ cargo run\n   Compiling hello v0.1.0\n\nerror[E0384]: cannot assign twice"
+						.to_string()
+				},
+				Paragraph::Text {
+					indent: 1,
+					text: "So it fails.".to_string()
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn tables_keep_their_columns() {
+		let text = "\tLength\t\tSigned\n\t8-bit\t\ti8\n\t16-bit\t\ti16";
+		let [Paragraph::Preformatted { kind, text }] = &paragraphs(text)[..] else {
+			panic!("a table is one preformatted paragraph");
+		};
+		assert_eq!(*kind, Preformatted::Plain);
+		assert_eq!(text.lines().count(), 3);
+	}
+}
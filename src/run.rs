@@ -0,0 +1,356 @@
+//! Running snippets and comparing what they print with what the notes say.
+//!
+//! A line of expected output is written as an annotation, usually on the
+//! `println!` that prints it:
+//!
+//! ```text
+//! println!("End count = {count}"); // expect-stdout: End count = 2
+//! ```
+//!
+//! The expected lines of a snippet must all show up in its output, in the
+//! order they are written; anything else it prints in between is fine. With
+//! `// expect-stdout-any-order:` the entries inside every `{...}` and `[...]`
+//! of the line may come in any order, which is what printing a `HashMap`
+//! needs.
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::check::{self, annotation, Checked, Checker, Item, Outcome, Unit};
+use crate::error::{Error, Result};
+use crate::model::{Code, Document};
+
+/// How long a snippet may run before it is killed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One line a snippet is expected to print.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedLine {
+	pub text: String,
+	pub any_order: bool,
+	/// The notes line the annotation is on.
+	pub line: usize,
+}
+
+impl ExpectedLine {
+	fn matches(&self, printed: &str) -> bool {
+		if self.any_order {
+			canonical(printed.trim()) == canonical(&self.text)
+		} else {
+			printed.trim() == self.text
+		}
+	}
+}
+
+/// The `expect-stdout` annotations of a block, in order.
+pub fn expected_stdout(code: &Code) -> Vec<ExpectedLine> {
+	let mut expected = Vec::new();
+	for (i, line) in code.text.lines().enumerate() {
+		let (text, any_order) = match annotation(line, "expect-stdout") {
+			Some(text) => (text, false),
+			None => match annotation(line, "expect-stdout-any-order") {
+				Some(text) => (text, true),
+				None => continue,
+			},
+		};
+		expected.push(ExpectedLine {
+			text: text.to_string(),
+			any_order,
+			line: code.lines.start + i,
+		});
+	}
+	expected
+}
+
+/// Sorts the comma-separated entries of every bracketed group in `line`, so
+/// that two orderings of the same map print the same.
+pub fn canonical(line: &str) -> String {
+	let chars: Vec<char> = line.chars().collect();
+	let mut at = 0;
+	let mut out = String::new();
+	while at < chars.len() {
+		out.push_str(&canonical_from(&chars, &mut at, None));
+	}
+	out
+}
+
+/// Reads from `at` up to the bracket `close`, or to the end of the line.
+fn canonical_from(chars: &[char], at: &mut usize, close: Option<char>) -> String {
+	let mut entries = vec![String::new()];
+	while *at < chars.len() {
+		let c = chars[*at];
+		*at += 1;
+		match c {
+			'{' | '[' => {
+				let inner = canonical_from(chars, at, Some(if c == '{' { '}' } else { ']' }));
+				let entry = entries.last_mut().unwrap();
+				entry.push(c);
+				entry.push_str(&inner);
+			}
+			'"' => {
+				let entry = entries.last_mut().unwrap();
+				entry.push(c);
+				while *at < chars.len() {
+					let c = chars[*at];
+					*at += 1;
+					entry.push(c);
+					if c == '\\' && *at < chars.len() {
+						entry.push(chars[*at]);
+						*at += 1;
+					} else if c == '"' {
+						break;
+					}
+				}
+			}
+			',' if close.is_some() => entries.push(String::new()),
+			_ if Some(c) == close => {
+				let mut entries: Vec<String> = entries
+					.iter()
+					.map(|entry| entry.trim().to_string())
+					.filter(|entry| !entry.is_empty())
+					.collect();
+				entries.sort();
+				return format!("{}{c}", entries.join(", "));
+			}
+			_ => entries.last_mut().unwrap().push(c),
+		}
+	}
+	// A group left open is given back as it was.
+	entries.join(",")
+}
+
+/// One line of the comparison between expected and actual output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+	/// Printed and expected.
+	Same(String),
+	/// Printed, but the notes do not mention it.
+	Extra(String),
+	/// Expected, but never printed in its place.
+	Missing(String),
+}
+
+/// Lines up the expected lines with the printed ones, keeping as many
+/// matches as possible.
+pub fn diff(expected: &[ExpectedLine], printed: &[&str]) -> Vec<DiffLine> {
+	let (n, m) = (expected.len(), printed.len());
+	// lengths[i][j]: the longest match of expected[i..] against printed[j..].
+	let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+	for i in (0..n).rev() {
+		for j in (0..m).rev() {
+			lengths[i][j] = if expected[i].matches(printed[j]) {
+				lengths[i + 1][j + 1] + 1
+			} else {
+				lengths[i + 1][j].max(lengths[i][j + 1])
+			};
+		}
+	}
+
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < n || j < m {
+		if i < n && j < m && expected[i].matches(printed[j]) {
+			lines.push(DiffLine::Same(printed[j].to_string()));
+			i += 1;
+			j += 1;
+		} else if j < m && (i == n || lengths[i][j + 1] >= lengths[i + 1][j]) {
+			lines.push(DiffLine::Extra(printed[j].to_string()));
+			j += 1;
+		} else {
+			lines.push(DiffLine::Missing(expected[i].text.clone()));
+			i += 1;
+		}
+	}
+	lines
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+	/// Every expected line was printed.
+	Matched,
+	/// Some expected lines were not printed.
+	Differs(Vec<DiffLine>),
+	/// The snippet was not run because it does not compile.
+	NotCompiled,
+	/// The program failed, e.g. with a panic.
+	Crashed {
+		status: String,
+		stderr: String,
+	},
+	TimedOut,
+}
+
+impl RunOutcome {
+	pub fn is_problem(&self) -> bool {
+		!matches!(self, RunOutcome::Matched)
+	}
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			RunOutcome::Matched => "output matches",
+			RunOutcome::Differs(_) => "OUTPUT DIFFERS",
+			RunOutcome::NotCompiled => "NOT RUN: does not compile",
+			RunOutcome::Crashed { .. } => "CRASHED",
+			RunOutcome::TimedOut => "TIMED OUT",
+		}
+	}
+}
+
+impl Checker {
+	/// Runs every snippet with `expect-stdout` annotations that compiled,
+	/// given the results of [`Checker::check_all`] for the same `docs`.
+	///
+	/// Snippets without annotations get `None`.
+	pub fn run_all(
+		&self,
+		docs: &[Document],
+		checked: &[Checked],
+	) -> Result<Vec<Option<RunOutcome>>> {
+		let codes: Vec<&Code> = docs.iter().flat_map(Document::code_blocks).collect();
+		let tasks: Vec<(&Code, &Checked)> = codes.into_iter().zip(checked).collect();
+		self.parallel(&tasks, |&(code, checked)| {
+			let expected = expected_stdout(code);
+			if expected.is_empty() {
+				return Ok(None);
+			}
+			if checked.outcome != Outcome::Compiled {
+				return Ok(Some(RunOutcome::NotCompiled));
+			}
+			self.run(code, &checked.context, &expected).map(Some)
+		})
+		.into_iter()
+		.collect()
+	}
+
+	/// Builds and runs one snippet and compares its output with `expected`.
+	pub fn run(
+		&self,
+		code: &Code,
+		context: &[Item],
+		expected: &[ExpectedLine],
+	) -> Result<RunOutcome> {
+		let unit = Unit::wrap(code, context);
+		let dir = check::temp_dir()?;
+		let result = self.build_and_run(&dir, &unit.source);
+		let _ = fs::remove_dir_all(&dir);
+		let Some(output) = result? else {
+			return Ok(RunOutcome::NotCompiled);
+		};
+		let (status, stdout, stderr) = match output {
+			Run::Finished {
+				status,
+				stdout,
+				stderr,
+			} => (status, stdout, stderr),
+			Run::TimedOut => return Ok(RunOutcome::TimedOut),
+		};
+		if !status.success() {
+			return Ok(RunOutcome::Crashed {
+				status: status.to_string(),
+				stderr,
+			});
+		}
+
+		let printed: Vec<&str> = stdout.lines().collect();
+		let lines = diff(expected, &printed);
+		if lines
+			.iter()
+			.any(|line| matches!(line, DiffLine::Missing(_)))
+		{
+			Ok(RunOutcome::Differs(lines))
+		} else {
+			Ok(RunOutcome::Matched)
+		}
+	}
+
+	/// `None` if the crate does not build.
+	fn build_and_run(&self, dir: &Path, source: &str) -> Result<Option<Run>> {
+		if !self.build(dir, source)?.is_empty() {
+			return Ok(None);
+		}
+		execute(dir, &[]).map(Some)
+	}
+}
+
+/// Runs the program `dir/snippet` built by [`Checker::build`] with `args`,
+/// killing it after [`TIMEOUT`].
+pub(crate) fn execute(dir: &Path, args: &[&str]) -> Result<Run> {
+	// Output goes to files rather than pipes, so a chatty program cannot
+	// block on a full pipe while we wait for it.
+	let stdout_path = dir.join("stdout");
+	let stderr_path = dir.join("stderr");
+	let stdout = File::create(&stdout_path).map_err(|e| Error::io(&stdout_path, e))?;
+	let stderr = File::create(&stderr_path).map_err(|e| Error::io(&stderr_path, e))?;
+	let program = dir.join("snippet");
+	let mut child = Command::new(&program)
+		.args(args)
+		.env("RUST_BACKTRACE", "0")
+		.current_dir(dir)
+		.stdin(Stdio::null())
+		.stdout(stdout)
+		.stderr(stderr)
+		.spawn()
+		.map_err(|e| Error::io(&program, e))?;
+
+	let started = Instant::now();
+	let status = loop {
+		if let Some(status) = child.try_wait().map_err(|e| Error::io(&program, e))? {
+			break status;
+		}
+		if started.elapsed() > TIMEOUT {
+			let _ = child.kill();
+			let _ = child.wait();
+			return Ok(Run::TimedOut);
+		}
+		thread::sleep(Duration::from_millis(10));
+	};
+
+	let read = |path: &Path| {
+		fs::read(path)
+			.map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+			.map_err(|e| Error::io(path, e))
+	};
+	Ok(Run::Finished {
+		status,
+		stdout: read(&stdout_path)?,
+		stderr: read(&stderr_path)?,
+	})
+}
+
+pub(crate) enum Run {
+	Finished {
+		status: ExitStatus,
+		stdout: String,
+		stderr: String,
+	},
+	TimedOut,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn canonical_sorts_the_entries_of_maps_and_sets() {
+		assert_eq!(
+			canonical(r#"{"Yellow": 50, "Blue": 10}"#),
+			canonical(r#"{"Blue": 10, "Yellow": 50}"#)
+		);
+		assert_eq!(canonical("{3, 1, 2}"), "{1, 2, 3}");
+		assert_eq!(
+			canonical("scores: {b: [2, 1], a: {}}"),
+			"scores: {a: {}, b: [1, 2]}"
+		);
+	}
+
+	#[test]
+	fn canonical_leaves_strings_and_loose_text_alone() {
+		assert_eq!(canonical(r#"["b, a", "c]"]"#), r#"["b, a", "c]"]"#);
+		assert_eq!(canonical(r#"["q\"b", "a"]"#), r#"["a", "q\"b"]"#);
+		assert_eq!(canonical("x, y and z"), "x, y and z");
+		assert_eq!(canonical("unclosed [b, a"), "unclosed [b, a");
+	}
+}
@@ -0,0 +1,765 @@
+//! Links from the std items the notes mention to the standard library's
+//! documentation on doc.rust-lang.org.
+//!
+//! The items come from the `std/all.html` page of the active toolchain's
+//! docs as `rustup doc` installs them, and the methods, variants and fields
+//! of each type and trait from the anchors of its page. The links go to the
+//! same pages online, so that they work wherever the exports are read. A reference is a path like `std::io::stdin` or
+//! `HashMap::entry`, in prose or code, or a bare name called like a method,
+//! as in `or_insert()` or `.split_whitespace()`.
+//!
+//! Names the notes' own code defines shadow std, so the chapter 6 `IpAddr`
+//! is not taken for `std::net::IpAddr`. A path whose head is std's but whose
+//! rest names nothing in std does not resolve; that is how a misremembered
+//! method name shows up. A bare name that is not a std method is taken to
+//! belong to another crate, like `gen_range()`, and is left alone.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Error, Result};
+use crate::lex::{self, TokenKind};
+use crate::model::{Block, Document, Lang};
+use crate::prose::{self, Paragraph};
+
+/// Where the std docs are published; the pages under it are laid out as
+/// they are in the installed docs.
+const ONLINE: &str = "https://doc.rust-lang.org/std";
+
+/// The crates whose paths are std's: std re-exports the public items of
+/// the other two.
+const ROOTS: [&str; 3] = ["std", "core", "alloc"];
+
+/// The keywords after which the notes' code names an item of its own.
+const DEFINERS: [&str; 9] = [
+	"fn", "struct", "enum", "trait", "mod", "type", "const", "static", "union",
+];
+
+/// The kinds of page whose anchors are members worth linking to.
+const OWNERS: [&str; 6] = ["struct", "enum", "union", "trait", "primitive", "type"];
+
+/// The anchors of members, by the prefix rustdoc gives their ids.
+const MEMBERS: [&str; 6] = [
+	"method.",
+	"tymethod.",
+	"variant.",
+	"structfield.",
+	"associatedconstant.",
+	"associatedtype.",
+];
+
+/// An item of std, as `all.html` lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+	/// The path below `std`, e.g. `collections::HashMap`.
+	pub path: String,
+	/// The page, relative to the `std` directory of the docs.
+	pub file: String,
+	/// `struct`, `fn`, `macro` and so on, from the page's name.
+	pub kind: String,
+}
+
+/// The std docs of a toolchain.
+#[derive(Debug)]
+pub struct Index {
+	/// The `html` directory the `std` directory is in.
+	root: PathBuf,
+	items: Vec<Item>,
+	by_path: HashMap<String, usize>,
+	by_name: HashMap<String, Vec<usize>>,
+	modules: HashSet<String>,
+	/// The member anchors of each item's page, e.g. `method.entry`.
+	members: Vec<Vec<String>>,
+	/// The items with a member of each name, and the member's anchor.
+	/// Only the members an item has of its own count here, not the ones it
+	/// gets from a trait or through `Deref`.
+	by_member: HashMap<String, Vec<(usize, String)>>,
+}
+
+/// Where a reference leads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+	/// A page of the docs: the full path of the item and its URL.
+	Linked { path: String, url: String },
+	/// A path into std that names nothing there, and why.
+	Unresolved(String),
+	/// A bare name several std items have, none of them more likely here.
+	Ambiguous(Vec<String>),
+	/// The notes' own code, or another crate.
+	Elsewhere,
+}
+
+/// A possible reference to std in a piece of text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+	/// Where it is in the text; just the path, without any `()`.
+	pub range: Range<usize>,
+	pub path: String,
+	/// Whether it is called, as a function or method.
+	pub call: bool,
+	/// Whether it is a method, called with `.` before it.
+	pub method: bool,
+}
+
+impl Index {
+	/// The docs of the toolchain `rustc` belongs to (`$RUSTC` if set), if
+	/// they are installed.
+	pub fn locate() -> Option<PathBuf> {
+		let rustc = env::var_os("RUSTC").map_or_else(|| PathBuf::from("rustc"), PathBuf::from);
+		let output = Command::new(rustc)
+			.args(["--print", "sysroot"])
+			.output()
+			.ok()?;
+		let sysroot = String::from_utf8(output.stdout).ok()?;
+		let html = Path::new(sysroot.trim()).join("share/doc/rust/html");
+		html.join("std/all.html").is_file().then_some(html)
+	}
+
+	/// Reads the list of items from `html/std/all.html` and the members of
+	/// every type and trait from their pages.
+	pub fn load(html: &Path) -> Result<Index> {
+		let root = html.canonicalize().map_err(|e| Error::io(html, e))?;
+		let std = root.join("std");
+		let all_path = std.join("all.html");
+		let all = fs::read_to_string(&all_path).map_err(|e| Error::io(&all_path, e))?;
+		let mut index = Index {
+			root,
+			items: Vec::new(),
+			by_path: HashMap::new(),
+			by_name: HashMap::new(),
+			modules: HashSet::new(),
+			members: Vec::new(),
+			by_member: HashMap::new(),
+		};
+		let mut seen = HashSet::new();
+		for entry in all.split("<li><a href=\"").skip(1) {
+			let Some((file, rest)) = entry.split_once("\">") else {
+				continue;
+			};
+			let Some((path, _)) = rest.split_once("</a>") else {
+				continue;
+			};
+			let page = file.rsplit('/').next().unwrap_or(file);
+			let kind = page.split('.').next().unwrap_or_default();
+			if !seen.insert(path.to_string()) {
+				continue;
+			}
+			index.add(Item {
+				path: path.to_string(),
+				file: file.to_string(),
+				kind: kind.to_string(),
+			});
+		}
+		for i in 0..index.items.len() {
+			if !OWNERS.contains(&index.items[i].kind.as_str()) {
+				continue;
+			}
+			let page = std.join(&index.items[i].file);
+			let html = fs::read_to_string(&page).map_err(|e| Error::io(&page, e))?;
+			let (anchors, own) = member_anchors(&html);
+			for anchor in &anchors[..own] {
+				let name = member_name(anchor);
+				index
+					.by_member
+					.entry(name.to_string())
+					.or_default()
+					.push((i, anchor.clone()));
+			}
+			index.members[i] = anchors;
+		}
+		Ok(index)
+	}
+
+	fn add(&mut self, item: Item) {
+		let i = self.items.len();
+		let segments: Vec<&str> = item.path.split("::").collect();
+		for end in 1..segments.len() {
+			self.modules.insert(segments[..end].join("::"));
+		}
+		let name = segments.last().copied().unwrap_or_default();
+		self.by_name.entry(name.to_string()).or_default().push(i);
+		self.by_path.insert(item.path.clone(), i);
+		self.items.push(item);
+		self.members.push(Vec::new());
+	}
+
+	/// The `html` directory of the docs.
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	pub fn items(&self) -> &[Item] {
+		&self.items
+	}
+
+	/// How references in `doc` resolve: its own names shadow std's, and its
+	/// words decide between std items of the same name.
+	pub fn resolver(&self, doc: &Document) -> Resolver<'_> {
+		let mut local: HashSet<String> = ["crate", "self", "super", "Self"]
+			.into_iter()
+			.map(str::to_string)
+			.collect();
+		let mut words: HashMap<String, usize> = HashMap::new();
+		for block in &doc.blocks {
+			let text = match block {
+				Block::Prose(prose) => &prose.text,
+				Block::Code(code) => &code.text,
+				Block::Heading(_) => continue,
+			};
+			for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+				if !word.is_empty() {
+					*words.entry(normalize(word)).or_default() += 1;
+				}
+			}
+			let Block::Code(code) = block else {
+				continue;
+			};
+			if code.lang() != Lang::Rust {
+				continue;
+			}
+			let tokens: Vec<lex::Token> = lex::tokens(&code.text)
+				.into_iter()
+				.filter(|token| !token.is_trivia())
+				.collect();
+			for pair in tokens.windows(2) {
+				let defines = DEFINERS.contains(&pair[0].text)
+					|| (pair[0].text == "use" || pair[0].text == "crate")
+						&& !ROOTS.contains(&pair[1].text);
+				if defines && pair[1].kind == TokenKind::Ident {
+					local.insert(pair[1].text.to_string());
+				}
+			}
+		}
+		Resolver {
+			index: self,
+			local,
+			words,
+		}
+	}
+
+	/// The page of `item`, at `anchor` if it names one member. A name that
+	/// several trait impls share, like `String::from`, stays at the top of
+	/// the page rather than pointing at whichever impl comes first.
+	fn url(&self, item: usize, anchor: Option<&str>) -> String {
+		let page = format!("{ONLINE}/{}", self.items[item].file);
+		let shared = |anchor: &str| {
+			let name = member_name(anchor);
+			self.members[item]
+				.iter()
+				.filter(|other| member_name(other) == name)
+				.count() > 1
+		};
+		match anchor.filter(|anchor| !shared(anchor)) {
+			Some(anchor) => format!("{page}#{anchor}"),
+			None => page,
+		}
+	}
+
+	fn module_url(&self, module: &str) -> String {
+		let mut url = ONLINE.to_string();
+		for segment in module.split("::").filter(|s| !s.is_empty()) {
+			url.push('/');
+			url.push_str(segment);
+		}
+		url + "/index.html"
+	}
+}
+
+/// Resolves the references of one document.
+#[derive(Debug)]
+pub struct Resolver<'a> {
+	index: &'a Index,
+	/// The names the document's code defines, and the crates it uses.
+	local: HashSet<String>,
+	/// How often each word comes up in the document, normalised.
+	words: HashMap<String, usize>,
+}
+
+impl Resolver<'_> {
+	pub fn resolve(&self, reference: &Reference) -> Resolution {
+		let index = self.index;
+		let mut segments: Vec<&str> = reference.path.split("::").collect();
+		if self.local.contains(segments[0]) {
+			return Resolution::Elsewhere;
+		}
+		let rooted = ROOTS.contains(&segments[0]);
+		if rooted {
+			segments.remove(0);
+		}
+		if segments.is_empty() {
+			return Resolution::Linked {
+				path: "std".to_string(),
+				url: index.module_url(""),
+			};
+		}
+		let path = format!("std::{}", segments.join("::"));
+		if segments.len() == 1 && !rooted && reference.call {
+			return self.bare(segments[0], reference.method);
+		}
+		if let Some(item) = self.item(&segments, None) {
+			return Resolution::Linked {
+				path: format!("std::{}", index.items[item].path),
+				url: index.url(item, None),
+			};
+		}
+		let joined = segments.join("::");
+		if index.modules.contains(&joined) {
+			return Resolution::Linked {
+				path,
+				url: index.module_url(&joined),
+			};
+		}
+		let (member, owner) = segments.split_last().expect("a path has a segment");
+		if !owner.is_empty() {
+			if let Some(item) = self.item(owner, Some(member)) {
+				let anchor = self.anchor(item, member);
+				return match anchor {
+					Some(anchor) => Resolution::Linked {
+						path: format!("std::{}::{member}", index.items[item].path),
+						url: index.url(item, Some(anchor)),
+					},
+					None => Resolution::Unresolved(format!(
+						"std::{} has no `{member}`",
+						index.items[item].path
+					)),
+				};
+			}
+		}
+		if rooted || index.modules.contains(segments[0]) {
+			Resolution::Unresolved(format!("no `{path}` in std"))
+		} else {
+			Resolution::Elsewhere
+		}
+	}
+
+	/// A name used on its own: a function like `stdin`, or a method. A
+	/// method resolves if the document favours one of the types that have
+	/// it over the others.
+	fn bare(&self, name: &str, method: bool) -> Resolution {
+		let index = self.index;
+		let functions: Vec<usize> = index
+			.by_name
+			.get(name)
+			.into_iter()
+			.flatten()
+			.copied()
+			.filter(|&i| !method && index.items[i].kind == "fn")
+			.collect();
+		if let Some(&item) = functions.iter().min_by_key(|&&i| self.rank(i)) {
+			return Resolution::Linked {
+				path: format!("std::{}", index.items[item].path),
+				url: index.url(item, None),
+			};
+		}
+		let Some(owners) = index.by_member.get(name) else {
+			return Resolution::Elsewhere;
+		};
+		let mut ranked: Vec<&(usize, String)> = owners.iter().collect();
+		ranked.sort_by_key(|(item, _)| self.rank(*item));
+		let (item, anchor) = ranked[0];
+		let owner = last_segment(&index.items[*item].path);
+		// Slices and `str` are seldom called by name, so a document not
+		// mentioning them says nothing about whether a call is theirs.
+		let contested = |other: usize| {
+			last_segment(&index.items[other].path) != owner
+				&& (index.items[other].kind == "primitive"
+					|| self.score(other).0 >= self.score(*item).0)
+		};
+		if ranked.iter().any(|(other, _)| contested(*other)) {
+			let mut names: Vec<String> = ranked
+				.iter()
+				.map(|(item, _)| format!("std::{}::{name}", index.items[*item].path))
+				.collect();
+			names.dedup();
+			return Resolution::Ambiguous(names);
+		}
+		Resolution::Linked {
+			path: format!("std::{}::{name}", index.items[*item].path),
+			url: index.url(*item, Some(anchor)),
+		}
+	}
+
+	/// The item `segments` names: by its full path, or else the likeliest
+	/// item whose path ends that way, preferring one with `member`.
+	fn item(&self, segments: &[&str], member: Option<&str>) -> Option<usize> {
+		let index = self.index;
+		let joined = segments.join("::");
+		let suffix = format!("::{joined}");
+		let mut candidates: Vec<usize> = index
+			.by_name
+			.get(*segments.last()?)?
+			.iter()
+			.copied()
+			.filter(|&i| index.items[i].path == joined || index.items[i].path.ends_with(&suffix))
+			.collect();
+		if let Some(member) = member {
+			let owners: Vec<usize> = candidates
+				.iter()
+				.copied()
+				.filter(|&i| self.anchor(i, member).is_some())
+				.collect();
+			if !owners.is_empty() {
+				candidates = owners;
+			} else {
+				candidates.retain(|&i| OWNERS.contains(&index.items[i].kind.as_str()));
+			}
+		}
+		if index.by_path.contains_key(&joined) && candidates.is_empty() {
+			return index.by_path.get(&joined).copied();
+		}
+		candidates.into_iter().min_by_key(|&i| self.rank(i))
+	}
+
+	/// The anchor of `item`'s member called `name`, if it has one.
+	fn anchor(&self, item: usize, name: &str) -> Option<&str> {
+		self.index.members[item]
+			.iter()
+			.find(|anchor| member_name(anchor) == name)
+			.map(String::as_str)
+	}
+
+	/// How often the document mentions an item's name, then the modules
+	/// it is in.
+	fn score(&self, item: usize) -> (usize, usize) {
+		let count = |segment: &str| self.words.get(&normalize(segment)).copied().unwrap_or(0);
+		let path = &self.index.items[item].path;
+		let (modules, name) = path.rsplit_once("::").unwrap_or(("", path));
+		(count(name), modules.split("::").map(count).sum())
+	}
+
+	/// Orders items from the likeliest: the ones the document talks about,
+	/// then the ones in a module of their own name like `string::String`,
+	/// then the shortest paths.
+	fn rank(&self, item: usize) -> (Reverse<(usize, usize)>, bool, usize, &str) {
+		let path = &self.index.items[item].path;
+		let segments: Vec<&str> = path.split("::").collect();
+		let own_module = segments.len() == 2 && normalize(segments[0]) == normalize(segments[1]);
+		(Reverse(self.score(item)), !own_module, segments.len(), path)
+	}
+}
+
+/// The references in a paragraph of prose: `::` paths and `name()` calls.
+pub fn prose_references(text: &str) -> Vec<Reference> {
+	let mut references = Vec::new();
+	let bytes = text.as_bytes();
+	let is_part = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b':';
+	let mut i = 0;
+	while i < bytes.len() {
+		if !is_part(bytes[i]) || (i > 0 && is_part(bytes[i - 1])) {
+			i += 1;
+			continue;
+		}
+		let mut end = i;
+		while end < bytes.len() && is_part(bytes[end]) {
+			end += 1;
+		}
+		let word = &text[i..end];
+		let path = word.trim_end_matches(':');
+		let call = text[i + path.len()..].starts_with("()");
+		let method = text[..i].ends_with('.');
+		let segments: Vec<&str> = path.split("::").collect();
+		let is_path = segments.len() > 1 && segments.iter().all(|s| is_ident(s));
+		let is_call = segments.len() == 1 && call && is_ident(path);
+		if is_path || is_call {
+			references.push(Reference {
+				range: i..i + path.len(),
+				path: path.to_string(),
+				call,
+				method,
+			});
+		}
+		i = end;
+	}
+	references
+}
+
+/// The references in Rust code: paths, and methods called with `.name(`.
+pub fn code_references(code: &str) -> Vec<Reference> {
+	let tokens: Vec<lex::Token> = lex::tokens(code)
+		.into_iter()
+		.filter(|token| !token.is_trivia())
+		.collect();
+	let mut references = Vec::new();
+	let mut i = 0;
+	while i < tokens.len() {
+		if tokens[i].kind != TokenKind::Ident
+			&& !(tokens[i].kind == TokenKind::Keyword
+				&& ["crate", "self", "super", "Self"].contains(&tokens[i].text))
+		{
+			i += 1;
+			continue;
+		}
+		let mut end = i;
+		while end + 2 < tokens.len()
+			&& tokens[end + 1].text == "::"
+			&& tokens[end + 2].kind == TokenKind::Ident
+		{
+			end += 2;
+		}
+		let after = tokens.get(end + 1).map(|token| token.text);
+		let call = after == Some("(");
+		let method = i > 0 && tokens[i - 1].text == "." && call;
+		let macro_call = after == Some("!");
+		if (end > i || method) && !macro_call {
+			let range = tokens[i].start..tokens[end].start + tokens[end].text.len();
+			references.push(Reference {
+				path: code[range.clone()].split_whitespace().collect(),
+				range,
+				call,
+				method,
+			});
+		}
+		i = end + 1;
+	}
+	references
+}
+
+/// A reference found in the notes, for the report.
+#[derive(Debug, Clone)]
+pub struct Found {
+	pub doc: usize,
+	pub line: usize,
+	pub path: String,
+	pub in_code: bool,
+	pub resolution: Resolution,
+}
+
+/// Every reference in the prose and Rust code of `docs`, resolved. Text
+/// whose line breaks matter, such as console output, is left out.
+pub fn references(index: &Index, docs: &[Document]) -> Vec<Found> {
+	let mut found = Vec::new();
+	for (d, doc) in docs.iter().enumerate() {
+		let resolver = index.resolver(doc);
+		for block in &doc.blocks {
+			match block {
+				Block::Prose(prose) => {
+					let lines: Vec<&str> = prose.text.lines().collect();
+					let mut at = 0;
+					for paragraph in prose::paragraphs(&prose.text) {
+						if matches!(paragraph, Paragraph::Preformatted { .. }) {
+							continue;
+						}
+						for reference in prose_references(paragraph.text()) {
+							if let Some(offset) = lines[at..]
+								.iter()
+								.position(|line| line.contains(&reference.path))
+							{
+								at += offset;
+							}
+							found.push(Found {
+								doc: d,
+								line: prose.lines.start + at,
+								resolution: resolver.resolve(&reference),
+								path: reference.path,
+								in_code: false,
+							});
+						}
+					}
+				}
+				Block::Code(code) if code.lang() == Lang::Rust => {
+					for reference in code_references(&code.text) {
+						let line = code.text[..reference.range.start].matches('\n').count();
+						found.push(Found {
+							doc: d,
+							line: code.lines.start + line,
+							resolution: resolver.resolve(&reference),
+							path: reference.path,
+							in_code: true,
+						});
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+	found
+}
+
+/// The ids of the members on a rustdoc page, each once, and how many of
+/// them come first as the item's own: the inherent methods of a type, or
+/// the methods a trait declares. The rest come from trait implementations
+/// and `Deref`.
+fn member_anchors(html: &str) -> (Vec<String>, usize) {
+	const BORROWED: [&str; 6] = [
+		" id=\"deref-methods",
+		" id=\"trait-implementations\"",
+		" id=\"synthetic-implementations\"",
+		" id=\"blanket-implementations\"",
+		" id=\"implementors\"",
+		" id=\"foreign-impls\"",
+	];
+	let borrowed = BORROWED
+		.iter()
+		.filter_map(|marker| html.find(marker))
+		.min()
+		.unwrap_or(html.len());
+	let mut anchors = Vec::new();
+	let mut own = 0;
+	let mut seen = HashSet::new();
+	for (at, _) in html.match_indices(" id=\"") {
+		let rest = &html[at + 5..];
+		let Some(end) = rest.find('"') else {
+			continue;
+		};
+		let id = &rest[..end];
+		if MEMBERS.iter().any(|prefix| id.starts_with(prefix)) && seen.insert(id) {
+			anchors.push(id.to_string());
+			if at < borrowed {
+				own = anchors.len();
+			}
+		}
+	}
+	(anchors, own)
+}
+
+/// `entry` for `method.entry` and for `method.entry-1`, the id rustdoc
+/// gives the second member of that name on a page.
+fn member_name(anchor: &str) -> &str {
+	let name = anchor.split_once('.').map_or(anchor, |(_, name)| name);
+	match name.rsplit_once('-') {
+		Some((name, n)) if n.chars().all(|c| c.is_ascii_digit()) => name,
+		_ => name,
+	}
+}
+
+fn last_segment(path: &str) -> &str {
+	path.rsplit("::").next().unwrap_or(path)
+}
+
+/// A word lowercased and without underscores, so that `hash_map` and
+/// `HashMap` count as the same.
+fn normalize(word: &str) -> String {
+	word.chars()
+		.filter(|&c| c != '_')
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+fn is_ident(text: &str) -> bool {
+	let mut chars = text.chars();
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::check;
+	use crate::source::NoteFile;
+
+	/// Docs with a few items, laid out the way rustdoc writes them.
+	fn index() -> Index {
+		let html = check::temp_dir().unwrap();
+		let std = html.join("std");
+		for dir in ["collections", "net", "io"] {
+			fs::create_dir_all(std.join(dir)).unwrap();
+		}
+		fs::write(
+			std.join("all.html"),
+			"<ul>\
+			 <li><a href=\"collections/struct.HashMap.html\">collections::HashMap</a></li>\
+			 <li><a href=\"net/enum.IpAddr.html\">net::IpAddr</a></li>\
+			 <li><a href=\"io/fn.stdin.html\">io::stdin</a></li>\
+			 </ul>",
+		)
+		.unwrap();
+		fs::write(
+			std.join("collections/struct.HashMap.html"),
+			"<section id=\"method.entry\"></section>\
+			 <h2 id=\"trait-implementations\"></h2>\
+			 <section id=\"method.from\"></section>",
+		)
+		.unwrap();
+		fs::write(std.join("net/enum.IpAddr.html"), "").unwrap();
+		let index = Index::load(&html).unwrap();
+		let _ = fs::remove_dir_all(&html);
+		index
+	}
+
+	fn doc(source: &str) -> Document {
+		Document::parse(
+			NoteFile::from_path("rust_chapter_6_enums.rs").unwrap(),
+			source,
+		)
+	}
+
+	fn path(resolution: Resolution) -> String {
+		match resolution {
+			Resolution::Linked { path, .. } => path,
+			other => panic!("not linked: {other:?}"),
+		}
+	}
+
+	#[test]
+	fn prose_mentions_paths_and_calls() {
+		let text = "Call std::io::stdin, then .read_line() but not gen.";
+		let references = prose_references(text);
+		let found: Vec<(&str, bool, bool)> = references
+			.iter()
+			.map(|r| (&text[r.range.clone()], r.call, r.method))
+			.collect();
+		assert_eq!(
+			found,
+			[("std::io::stdin", false, false), ("read_line", true, true)]
+		);
+	}
+
+	#[test]
+	fn code_mentions_paths_and_methods_but_not_macros() {
+		let references = code_references("let m = HashMap::new();\nm.entry(k);\nprintln!(\"\");");
+		let paths: Vec<&str> = references.iter().map(|r| r.path.as_str()).collect();
+		assert_eq!(paths, ["HashMap::new", "entry"]);
+		assert!(references[1].method);
+	}
+
+	#[test]
+	fn members_from_trait_impls_are_not_the_types_own() {
+		let (anchors, own) = member_anchors(
+			"<p id=\"method.len\"><p id=\"method.len\"><p id=\"impl-Trait\">\
+			 <h2 id=\"trait-implementations\"><p id=\"method.fmt\">",
+		);
+		assert_eq!(anchors, ["method.len", "method.fmt"]);
+		assert_eq!(own, 1);
+		assert_eq!(member_name("method.entry-1"), "entry");
+	}
+
+	#[test]
+	fn references_resolve_to_std_unless_the_notes_define_them() {
+		let index = index();
+		let doc = doc("\tuse std::collections::HashMap;\n\n\tenum IpAddr { V4 }\n");
+		let resolver = index.resolver(&doc);
+		let resolve = |text: &str| resolver.resolve(&prose_references(text)[0]);
+
+		assert_eq!(
+			path(resolve("HashMap::entry")),
+			"std::collections::HashMap::entry"
+		);
+		assert_eq!(
+			path(resolve("a .entry() call")),
+			"std::collections::HashMap::entry"
+		);
+		assert_eq!(path(resolve("stdin()")), "std::io::stdin");
+		assert_eq!(resolve("IpAddr::V4"), Resolution::Elsewhere);
+		assert_eq!(resolve("a .gen_range() call"), Resolution::Elsewhere);
+		assert_eq!(
+			resolve("HashMap::entri"),
+			Resolution::Unresolved("std::collections::HashMap has no `entri`".to_string())
+		);
+		assert_eq!(
+			resolve("std::collections"),
+			Resolution::Linked {
+				path: "std::collections".to_string(),
+				url: "https://doc.rust-lang.org/std/collections/index.html".to_string(),
+			}
+		);
+		// Borrowed from a trait, so not looked for on its own.
+		assert_eq!(resolve("a .from() call"), Resolution::Elsewhere);
+	}
+}
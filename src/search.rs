@@ -0,0 +1,435 @@
+//! Full-text search over the prose and code of the notes.
+//!
+//! The notes are split into passages: a heading, a paragraph of prose, or a
+//! run of code between blank lines. Each passage is indexed by its terms and
+//! scored against a query with BM25, so a rare word counts for more than a
+//! common one and a long passage does not win just by being long.
+//!
+//! Terms are lowercased words, with a plural `s` dropped. A Rust path such as
+//! `std::collections::HashMap` is indexed as each of its names and as every
+//! run of two or more of them (`collections::hashmap`,
+//! `std::collections::hashmap`, ...), so searching for `HashMap` finds it, and
+//! searching for `Ordering::Less` prefers lines where the whole path appears.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::lex::{is_ident_continue, is_ident_start};
+use crate::model::{Block, Document, Heading};
+use crate::parse::is_blank;
+
+/// Words left out of queries, so that "where are loop labels" looks for
+/// loop labels.
+const STOPWORDS: [&str; 24] = [
+	"a", "an", "and", "are", "as", "at", "be", "by", "do", "for", "how", "i", "in", "is", "it",
+	"of", "on", "or", "the", "to", "we", "what", "where", "why",
+];
+
+/// BM25 parameters, at their usual values.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// How many matching lines of a passage a result shows.
+const SNIPPET_LINES: usize = 3;
+
+/// A term found in some text, with the bytes it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+	pub text: String,
+	pub span: Range<usize>,
+	/// Whether the term is a single name rather than a path of several.
+	pub word: bool,
+}
+
+/// Splits text into terms.
+pub fn terms(text: &str) -> Vec<Term> {
+	let mut terms = Vec::new();
+	let mut chars = text.char_indices().peekable();
+	while let Some(&(start, c)) = chars.peek() {
+		if !is_ident_start(c) && !c.is_ascii_digit() {
+			chars.next();
+			continue;
+		}
+		// The names of one path, with their spans.
+		let mut names: Vec<Range<usize>> = Vec::new();
+		let mut name_start = start;
+		loop {
+			let mut end = name_start;
+			while let Some(&(at, c)) = chars.peek() {
+				if !is_ident_continue(c) {
+					break;
+				}
+				end = at + c.len_utf8();
+				chars.next();
+			}
+			names.push(name_start..end);
+			let rest = &text[end..];
+			match rest.strip_prefix("::") {
+				Some(after) if after.starts_with(is_ident_start) => {
+					chars.next();
+					chars.next();
+					name_start = end + 2;
+				}
+				_ => break,
+			}
+		}
+		for name in &names {
+			terms.push(Term {
+				text: normalize(&text[name.clone()]),
+				span: name.clone(),
+				word: true,
+			});
+		}
+		for len in 2..=names.len() {
+			for run in names.windows(len) {
+				let span = run[0].start..run[len - 1].end;
+				terms.push(Term {
+					text: text[span.clone()].to_lowercase(),
+					span,
+					word: false,
+				});
+			}
+		}
+	}
+	terms
+}
+
+/// Lowercases a word and drops a plural `s`.
+fn normalize(word: &str) -> String {
+	let word = word.to_lowercase();
+	match word.strip_suffix('s') {
+		Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+		_ => word,
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassageKind {
+	Heading,
+	Prose,
+	Code,
+}
+
+/// A unit of search results.
+#[derive(Debug, Clone)]
+struct Passage {
+	doc: usize,
+	kind: PassageKind,
+	/// The lines of the passage with their line numbers.
+	lines: Vec<(usize, String)>,
+	/// Terms per line, in the same order as `lines`.
+	terms: Vec<Vec<Term>>,
+	/// The single-word terms in order, for phrase matches.
+	words: Vec<String>,
+	len: usize,
+}
+
+/// An inverted index over a set of documents.
+pub struct Index<'a> {
+	docs: &'a [Document],
+	passages: Vec<Passage>,
+	/// For every term, the passages it occurs in and how often.
+	postings: HashMap<String, Vec<(usize, usize)>>,
+	average_len: f64,
+}
+
+/// One line of a result, with the byte ranges of the matched terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetLine {
+	pub line: usize,
+	pub text: String,
+	pub matches: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit<'a> {
+	pub doc: &'a Document,
+	/// The section the match is in, if it is past the first heading.
+	pub heading: Option<&'a Heading>,
+	pub kind: PassageKind,
+	/// The line matching the most query terms.
+	pub line: usize,
+	pub score: f64,
+	/// A few lines of the passage that have matches, around `line`.
+	pub snippet: Vec<SnippetLine>,
+}
+
+impl<'a> Index<'a> {
+	pub fn new(docs: &'a [Document]) -> Index<'a> {
+		let mut passages = Vec::new();
+		for (doc_index, doc) in docs.iter().enumerate() {
+			for block in &doc.blocks {
+				let (kind, start, text) = match block {
+					Block::Heading(heading) => (
+						PassageKind::Heading,
+						heading.lines.start,
+						heading.title.as_str(),
+					),
+					Block::Prose(prose) => {
+						(PassageKind::Prose, prose.lines.start, prose.text.as_str())
+					}
+					Block::Code(code) => (PassageKind::Code, code.lines.start, code.text.as_str()),
+				};
+				let lines: Vec<(usize, &str)> = text
+					.lines()
+					.enumerate()
+					.map(|(i, line)| (start + i, line))
+					.collect();
+				for chunk in lines.split(|(_, line)| is_blank(line)) {
+					if !chunk.is_empty() {
+						passages.push(Passage::new(doc_index, kind, chunk));
+					}
+				}
+			}
+		}
+
+		let mut postings: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+		for (i, passage) in passages.iter().enumerate() {
+			let mut counts: HashMap<&str, usize> = HashMap::new();
+			for term in passage.terms.iter().flatten() {
+				*counts.entry(&term.text).or_default() += 1;
+			}
+			for (term, count) in counts {
+				postings
+					.entry(term.to_string())
+					.or_default()
+					.push((i, count));
+			}
+		}
+		let total: usize = passages.iter().map(|passage| passage.len).sum();
+		let average_len = total as f64 / passages.len().max(1) as f64;
+		Index {
+			docs,
+			passages,
+			postings,
+			average_len,
+		}
+	}
+
+	/// The passages matching `query`, best first.
+	pub fn search(&self, query: &str) -> Vec<Hit<'a>> {
+		let wanted = query_terms(query);
+		if wanted.is_empty() {
+			return Vec::new();
+		}
+		let query_words: Vec<String> = wanted
+			.iter()
+			.filter(|term| term.word)
+			.map(|term| term.text.clone())
+			.collect();
+
+		let mut scores: HashMap<usize, f64> = HashMap::new();
+		let n = self.passages.len() as f64;
+		for term in &wanted {
+			let Some(postings) = self.postings.get(&term.text) else {
+				continue;
+			};
+			let df = postings.len() as f64;
+			let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+			for &(i, tf) in postings {
+				let passage = &self.passages[i];
+				let tf = tf as f64;
+				let norm = K1 * (1.0 - B + B * passage.len as f64 / self.average_len);
+				*scores.entry(i).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+			}
+		}
+
+		let mut hits: Vec<Hit<'a>> = scores
+			.into_iter()
+			.map(|(i, score)| {
+				let passage = &self.passages[i];
+				let mut score = score;
+				if passage.kind == PassageKind::Heading {
+					score *= 2.0;
+				}
+				if query_words.len() > 1 && contains_run(&passage.words, &query_words) {
+					score *= 1.5;
+				}
+				self.hit(passage, &wanted, score)
+			})
+			.collect();
+		hits.sort_by(|a, b| {
+			b.score
+				.total_cmp(&a.score)
+				.then_with(|| a.doc.file.order().cmp(&b.doc.file.order()))
+				.then_with(|| a.line.cmp(&b.line))
+		});
+		hits
+	}
+
+	fn hit(&self, passage: &Passage, wanted: &[Term], score: f64) -> Hit<'a> {
+		let wanted: HashSet<&str> = wanted.iter().map(|term| term.text.as_str()).collect();
+		// Matching lines, with how many different query terms each has.
+		let mut matching = Vec::new();
+		for ((line, text), terms) in passage.lines.iter().zip(&passage.terms) {
+			let found: Vec<&Term> = terms
+				.iter()
+				.filter(|term| wanted.contains(term.text.as_str()))
+				.collect();
+			if found.is_empty() {
+				continue;
+			}
+			let distinct: HashSet<&str> = found.iter().map(|term| term.text.as_str()).collect();
+			let line = SnippetLine {
+				line: *line,
+				text: text.clone(),
+				matches: merge(found.iter().map(|term| term.span.clone()).collect()),
+			};
+			matching.push((distinct.len(), line));
+		}
+		// The snippet is centred on the line that matches the most terms,
+		// the first of them on a tie.
+		let best = matching
+			.iter()
+			.enumerate()
+			.rev()
+			.max_by_key(|(_, (distinct, _))| *distinct)
+			.map_or(0, |(i, _)| i);
+		let start = best
+			.saturating_sub(SNIPPET_LINES / 2)
+			.min(matching.len().saturating_sub(SNIPPET_LINES));
+		let snippet: Vec<SnippetLine> = matching
+			.into_iter()
+			.skip(start)
+			.take(SNIPPET_LINES)
+			.map(|(_, line)| line)
+			.collect();
+
+		let doc = &self.docs[passage.doc];
+		let line = snippet
+			.get(best - start)
+			.map_or(passage.lines[0].0, |best| best.line);
+		Hit {
+			doc,
+			heading: doc.heading_for_line(line),
+			kind: passage.kind,
+			line,
+			score,
+			snippet,
+		}
+	}
+}
+
+impl Passage {
+	fn new(doc: usize, kind: PassageKind, lines: &[(usize, &str)]) -> Passage {
+		let terms: Vec<Vec<Term>> = lines.iter().map(|(_, line)| terms(line)).collect();
+		let words: Vec<String> = terms
+			.iter()
+			.flatten()
+			.filter(|term| term.word)
+			.map(|term| term.text.clone())
+			.collect();
+		Passage {
+			doc,
+			kind,
+			lines: lines
+				.iter()
+				.map(|&(number, line)| (number, line.to_string()))
+				.collect(),
+			len: words.len(),
+			terms,
+			words,
+		}
+	}
+}
+
+/// The distinct terms of a query, without stopwords unless that would leave
+/// nothing.
+fn query_terms(query: &str) -> Vec<Term> {
+	let mut all = terms(query);
+	let mut seen = HashSet::new();
+	all.retain(|term| seen.insert(term.text.clone()));
+	let kept: Vec<Term> = all
+		.iter()
+		.filter(|term| !(term.word && STOPWORDS.contains(&term.text.as_str())))
+		.cloned()
+		.collect();
+	if kept.is_empty() {
+		all
+	} else {
+		kept
+	}
+}
+
+/// Whether `run` appears in `words` as consecutive words.
+fn contains_run(words: &[String], run: &[String]) -> bool {
+	words.windows(run.len()).any(|window| window == run)
+}
+
+/// Sorts ranges and merges the ones that overlap, such as a path and the
+/// names inside it.
+fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+	ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+	let mut merged: Vec<Range<usize>> = Vec::new();
+	for range in ranges {
+		match merged.last_mut() {
+			Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+			_ => merged.push(range),
+		}
+	}
+	merged
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	#[test]
+	fn paths_are_indexed_as_names_and_runs() {
+		let texts: Vec<(String, bool)> = terms("use std::collections::HashMap; Ordering::Less")
+			.into_iter()
+			.map(|term| (term.text, term.word))
+			.collect();
+		let has = |text: &str, word: bool| texts.contains(&(text.to_string(), word));
+		assert!(has("use", true));
+		assert!(has("hashmap", true));
+		assert!(has("collections::hashmap", false));
+		assert!(has("std::collections::hashmap", false));
+		assert!(has("ordering::less", false));
+		assert_eq!(normalize("Vectors"), "vector");
+		assert_eq!(normalize("class"), "class");
+	}
+
+	#[test]
+	fn hits_are_ranked_and_point_at_the_matching_line() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap(),
+			"/*
+Loops:
+
+	Loops repeat code.
+
+	Loop Labels:
+
+		A loop label tells break which loop to leave.
+		Labels start with a quote.
+*/
+
+	'outer: loop {
+		break 'outer;
+	}
+",
+		);
+		let docs = [doc];
+		let index = Index::new(&docs);
+		let hits = index.search("where are loop labels");
+		assert!(!hits.is_empty());
+		let found: Vec<(PassageKind, usize, Option<&str>)> = hits
+			.iter()
+			.map(|hit| {
+				let heading = hit.heading.map(|heading| heading.title.as_str());
+				(hit.kind, hit.line, heading)
+			})
+			.collect();
+		assert_eq!(
+			found[..2],
+			[
+				(PassageKind::Heading, 6, Some("Loop Labels")),
+				(PassageKind::Prose, 8, Some("Loop Labels")),
+			]
+		);
+		assert!(hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+		assert!(index.search("vector").is_empty());
+	}
+}
@@ -0,0 +1,109 @@
+//! Finding the notes files on disk and putting them in chapter order.
+//!
+//! Two kinds of file make up the corpus: `rust_chapter_N_topic.rs`, which
+//! holds the notes proper, and `chapter_N_suggested_exercises.rs`, which holds
+//! solutions to the exercises a chapter ends with. Files are ordered by their
+//! chapter number, not by name, so chapter 10 comes after chapter 9.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FileKind {
+	Notes,
+	Exercises,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteFile {
+	pub path: PathBuf,
+	pub chapter: u32,
+	pub kind: FileKind,
+	/// The topic part of the file name, e.g. `common_concepts`.
+	pub topic: String,
+}
+
+impl NoteFile {
+	/// Recognises a notes file by its name; anything else gives `None`.
+	pub fn from_path(path: impl Into<PathBuf>) -> Option<NoteFile> {
+		let path = path.into();
+		let stem = path.file_stem()?.to_str()?;
+		if path.extension()? != "rs" {
+			return None;
+		}
+
+		let (kind, rest) = if let Some(rest) = stem.strip_prefix("rust_chapter_") {
+			(FileKind::Notes, rest)
+		} else if let Some(rest) = stem.strip_prefix("chapter_") {
+			(FileKind::Exercises, rest)
+		} else {
+			return None;
+		};
+
+		let (number, topic) = rest.split_once('_')?;
+		let chapter = number.parse().ok()?;
+		if kind == FileKind::Exercises && topic != "suggested_exercises" {
+			return None;
+		}
+
+		Some(NoteFile {
+			chapter,
+			kind,
+			topic: topic.to_string(),
+			path,
+		})
+	}
+
+	pub fn file_name(&self) -> &str {
+		self.path
+			.file_name()
+			.and_then(|name| name.to_str())
+			.unwrap_or_default()
+	}
+
+	/// The file name without its `.rs` extension.
+	pub fn stem(&self) -> &str {
+		self.path
+			.file_stem()
+			.and_then(|stem| stem.to_str())
+			.unwrap_or_default()
+	}
+
+	/// A human title such as `Chapter 3: Common Concepts`.
+	pub fn title(&self) -> String {
+		let topic: Vec<String> = self
+			.topic
+			.split('_')
+			.filter(|word| !word.is_empty())
+			.map(|word| {
+				let mut chars = word.chars();
+				match chars.next() {
+					Some(first) => first.to_uppercase().chain(chars).collect(),
+					None => String::new(),
+				}
+			})
+			.collect();
+		format!("Chapter {}: {}", self.chapter, topic.join(" "))
+	}
+
+	/// The key the corpus is sorted by.
+	pub fn order(&self) -> (u32, FileKind) {
+		(self.chapter, self.kind)
+	}
+}
+
+/// Lists the notes files in `dir`, in chapter order.
+pub fn discover(dir: &Path) -> Result<Vec<NoteFile>> {
+	let entries = fs::read_dir(dir).map_err(|e| Error::io(dir, e))?;
+	let mut files = Vec::new();
+	for entry in entries {
+		let entry = entry.map_err(|e| Error::io(dir, e))?;
+		if let Some(file) = NoteFile::from_path(entry.path()) {
+			files.push(file);
+		}
+	}
+	files.sort_by_key(NoteFile::order);
+	Ok(files)
+}
@@ -0,0 +1,504 @@
+//! A writer for SQLite database files, enough for the collections some
+//! exports are packaged as.
+//!
+//! A database is written once, whole: each table's rows are laid out in
+//! rowid order as a b-tree of leaf pages, with interior pages above them as
+//! needed and overflow pages for rows too long for a page. There are no
+//! indexes and no free pages, and the file needs no journal. The format is
+//! the one described at <https://www.sqlite.org/fileformat2.html>.
+
+use crate::error::{Error, Result};
+
+const PAGE_SIZE: usize = 4096;
+
+/// The header at the start of the file, before the schema's root page.
+const HEADER_SIZE: usize = 100;
+
+const LEAF: u8 = 0x0D;
+const INTERIOR: u8 = 0x05;
+
+/// A value of a column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Null,
+	Integer(i64),
+	Real(f64),
+	Text(String),
+}
+
+impl From<i64> for Value {
+	fn from(n: i64) -> Value {
+		Value::Integer(n)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(text: &str) -> Value {
+		Value::Text(text.to_string())
+	}
+}
+
+impl From<String> for Value {
+	fn from(text: String) -> Value {
+		Value::Text(text)
+	}
+}
+
+/// A table to write: its name, the `CREATE TABLE` statement it was made
+/// with, and its rows by rowid.
+///
+/// A column declared `integer primary key` is the rowid itself, and has to
+/// be given as [`Value::Null`] in the row.
+#[derive(Debug, Clone, Default)]
+struct Table {
+	name: String,
+	sql: String,
+	rows: Vec<(i64, Vec<Value>)>,
+}
+
+/// A database being written in memory.
+#[derive(Debug, Default)]
+pub struct Database {
+	tables: Vec<Table>,
+}
+
+impl Database {
+	pub fn new() -> Database {
+		Database::default()
+	}
+
+	/// Adds a table with its rows. No two rows may have the same rowid, as
+	/// the b-tree would not be in order.
+	pub fn table(&mut self, name: &str, sql: &str, mut rows: Vec<(i64, Vec<Value>)>) -> Result<()> {
+		rows.sort_by_key(|(rowid, _)| *rowid);
+		if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+			return Err(Error::Conflict(format!(
+				"table {name} has two rows with rowid {}",
+				pair[0].0
+			)));
+		}
+		self.tables.push(Table {
+			name: name.to_string(),
+			sql: sql.to_string(),
+			rows,
+		});
+		Ok(())
+	}
+
+	/// The bytes of the finished database file.
+	pub fn finish(self) -> Vec<u8> {
+		// Page 1 holds the schema, so the tables start on page 2.
+		let mut pages = vec![Vec::new()];
+		let mut schema = Vec::new();
+		for (i, table) in self.tables.iter().enumerate() {
+			let rows: Vec<(i64, Vec<u8>)> = table
+				.rows
+				.iter()
+				.map(|(rowid, values)| (*rowid, record(values)))
+				.collect();
+			let root = btree(&mut pages, &rows);
+			schema.push((
+				i as i64 + 1,
+				record(&[
+					"table".into(),
+					table.name.as_str().into(),
+					table.name.as_str().into(),
+					Value::Integer(root as i64),
+					table.sql.as_str().into(),
+				]),
+			));
+		}
+		// The schema's table is kept to that one page, which holds a few
+		// dozen tables.
+		let schema: Vec<(i64, Vec<u8>)> = schema
+			.iter()
+			.map(|(rowid, payload)| (*rowid, leaf_cell(&mut pages, *rowid, payload)))
+			.collect();
+		assert!(
+			fits(&schema, HEADER_SIZE + 8),
+			"too many tables for the first page"
+		);
+		pages[0] = page(LEAF, &schema, None, HEADER_SIZE);
+
+		let count = pages.len() as u32;
+		let mut file = Vec::with_capacity(pages.len() * PAGE_SIZE);
+		for page in &pages {
+			file.extend_from_slice(page);
+		}
+		file[..HEADER_SIZE].copy_from_slice(&header(count));
+		file
+	}
+}
+
+/// The 100-byte file header of a database of `pages` pages.
+fn header(pages: u32) -> [u8; HEADER_SIZE] {
+	let mut header = [0; HEADER_SIZE];
+	header[..16].copy_from_slice(b"SQLite format 3\0");
+	header[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+	header[18] = 1; // written and read with a rollback journal
+	header[19] = 1;
+	header[20] = 0; // no space reserved at the end of pages
+	header[21] = 64; // the payload fractions, which are fixed
+	header[22] = 32;
+	header[23] = 32;
+	header[24..28].copy_from_slice(&1u32.to_be_bytes()); // change counter
+	header[28..32].copy_from_slice(&pages.to_be_bytes());
+	header[40..44].copy_from_slice(&1u32.to_be_bytes()); // schema cookie
+	header[44..48].copy_from_slice(&4u32.to_be_bytes()); // schema format
+	header[56..60].copy_from_slice(&1u32.to_be_bytes()); // UTF-8
+													  // The page count above is good for this change counter.
+	header[92..96].copy_from_slice(&1u32.to_be_bytes());
+	header[96..100].copy_from_slice(&3_045_000u32.to_be_bytes());
+	header
+}
+
+/// Lays out `rows` as a table b-tree and returns the number of its root
+/// page.
+fn btree(pages: &mut Vec<Vec<u8>>, rows: &[(i64, Vec<u8>)]) -> u32 {
+	let cells: Vec<(i64, Vec<u8>)> = rows
+		.iter()
+		.map(|(rowid, payload)| (*rowid, leaf_cell(pages, *rowid, payload)))
+		.collect();
+	// Each level is a list of pages with the largest rowid under each.
+	let mut level: Vec<(u32, i64)> = pack(&cells, 8)
+		.into_iter()
+		.map(|group| {
+			let last = group.last().map_or(0, |(rowid, _)| *rowid);
+			(add(pages, page(LEAF, group, None, 0)), last)
+		})
+		.collect();
+	while level.len() > 1 {
+		let cells: Vec<(i64, Vec<u8>)> = level
+			.iter()
+			.map(|&(child, key)| {
+				let mut cell = child.to_be_bytes().to_vec();
+				varint(&mut cell, key as u64);
+				(key, cell)
+			})
+			.collect();
+		let mut groups = pack(&cells, 12);
+		// The last child of a page is its right-most pointer rather than a
+		// cell, and a page needs at least one cell besides.
+		let n = groups.len();
+		if n > 1 && groups[n - 1].len() == 1 {
+			let previous = groups[n - 2];
+			groups[n - 2] = &previous[..previous.len() - 1];
+			groups[n - 1] = &cells[cells.len() - 2..];
+		}
+		let mut next = Vec::new();
+		for group in groups {
+			let (key, ref cell) = group[group.len() - 1];
+			let right = u32::from_be_bytes([cell[0], cell[1], cell[2], cell[3]]);
+			let number = add(
+				pages,
+				page(INTERIOR, &group[..group.len() - 1], Some(right), 0),
+			);
+			next.push((number, key));
+		}
+		level = next;
+	}
+	level
+		.first()
+		.map_or_else(|| add(pages, page(LEAF, &[], None, 0)), |&(root, _)| root)
+}
+
+/// Appends a page and returns its number.
+fn add(pages: &mut Vec<Vec<u8>>, page: Vec<u8>) -> u32 {
+	pages.push(page);
+	pages.len() as u32
+}
+
+/// Whether `cells` fit on one page after `used` bytes of headers.
+fn fits(cells: &[(i64, Vec<u8>)], used: usize) -> bool {
+	used + cells.iter().map(|(_, cell)| cell.len() + 2).sum::<usize>() <= PAGE_SIZE
+}
+
+/// Splits `cells` into runs that each fit on a page with a `header` byte
+/// b-tree header.
+fn pack(cells: &[(i64, Vec<u8>)], header: usize) -> Vec<&[(i64, Vec<u8>)]> {
+	let mut groups = Vec::new();
+	let mut start = 0;
+	let mut used = header;
+	for (i, (_, cell)) in cells.iter().enumerate() {
+		if used + cell.len() + 2 > PAGE_SIZE && i > start {
+			groups.push(&cells[start..i]);
+			start = i;
+			used = header;
+		}
+		used += cell.len() + 2;
+	}
+	groups.push(&cells[start..]);
+	groups
+}
+
+/// A b-tree page of `kind` holding `cells`, with its header at `offset`.
+fn page(kind: u8, cells: &[(i64, Vec<u8>)], right: Option<u32>, offset: usize) -> Vec<u8> {
+	let mut page = vec![0; PAGE_SIZE];
+	let header = if right.is_some() { 12 } else { 8 };
+	let mut end = PAGE_SIZE;
+	let mut pointers = offset + header;
+	for (_, cell) in cells {
+		end -= cell.len();
+		page[end..end + cell.len()].copy_from_slice(cell);
+		page[pointers..pointers + 2].copy_from_slice(&(end as u16).to_be_bytes());
+		pointers += 2;
+	}
+	page[offset] = kind;
+	page[offset + 3..offset + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+	page[offset + 5..offset + 7].copy_from_slice(&(end as u16).to_be_bytes());
+	if let Some(right) = right {
+		page[offset + 8..offset + 12].copy_from_slice(&right.to_be_bytes());
+	}
+	page
+}
+
+/// The leaf cell of a row: what of the record fits on the page, then the
+/// first of the overflow pages the rest is written to.
+fn leaf_cell(pages: &mut Vec<Vec<u8>>, rowid: i64, payload: &[u8]) -> Vec<u8> {
+	let mut cell = Vec::new();
+	varint(&mut cell, payload.len() as u64);
+	varint(&mut cell, rowid as u64);
+	let local = local_size(payload.len());
+	cell.extend_from_slice(&payload[..local]);
+	if local < payload.len() {
+		let overflow = overflow(pages, &payload[local..]);
+		cell.extend_from_slice(&overflow.to_be_bytes());
+	}
+	cell
+}
+
+/// How many bytes of a payload stay in its leaf cell.
+fn local_size(payload: usize) -> usize {
+	let usable = PAGE_SIZE;
+	let max = usable - 35;
+	if payload <= max {
+		return payload;
+	}
+	let min = (usable - 12) * 32 / 255 - 23;
+	let local = min + (payload - min) % (usable - 4);
+	if local <= max {
+		local
+	} else {
+		min
+	}
+}
+
+/// Writes `rest` to a chain of overflow pages and returns the first.
+fn overflow(pages: &mut Vec<Vec<u8>>, rest: &[u8]) -> u32 {
+	let chunks: Vec<&[u8]> = rest.chunks(PAGE_SIZE - 4).collect();
+	let first = pages.len() as u32 + 1;
+	for (i, chunk) in chunks.iter().enumerate() {
+		let next = if i + 1 < chunks.len() {
+			first + i as u32 + 1
+		} else {
+			0
+		};
+		let mut page = vec![0; PAGE_SIZE];
+		page[..4].copy_from_slice(&next.to_be_bytes());
+		page[4..4 + chunk.len()].copy_from_slice(chunk);
+		pages.push(page);
+	}
+	first
+}
+
+/// A row in the record format: a header of serial types, then the values.
+fn record(values: &[Value]) -> Vec<u8> {
+	let mut types = Vec::new();
+	let mut body = Vec::new();
+	for value in values {
+		let serial = match value {
+			Value::Null => 0,
+			Value::Integer(0) => 8,
+			Value::Integer(1) => 9,
+			&Value::Integer(n) => {
+				let (serial, size) = match n {
+					-0x80..=0x7F => (1, 1),
+					-0x8000..=0x7FFF => (2, 2),
+					-0x80_0000..=0x7F_FFFF => (3, 3),
+					-0x8000_0000..=0x7FFF_FFFF => (4, 4),
+					-0x8000_0000_0000..=0x7FFF_FFFF_FFFF => (5, 6),
+					_ => (6, 8),
+				};
+				body.extend_from_slice(&n.to_be_bytes()[8 - size..]);
+				serial
+			}
+			Value::Real(x) => {
+				body.extend_from_slice(&x.to_be_bytes());
+				7
+			}
+			Value::Text(text) => {
+				body.extend_from_slice(text.as_bytes());
+				text.len() as u64 * 2 + 13
+			}
+		};
+		varint(&mut types, serial);
+	}
+	// The header's size counts itself, and may need a longer varint for it.
+	let mut size = types.len() + 1;
+	while varint_len(size as u64) + types.len() > size {
+		size += 1;
+	}
+	let mut out = Vec::with_capacity(size + body.len());
+	varint(&mut out, size as u64);
+	out.extend(types);
+	out.extend(body);
+	out
+}
+
+/// Appends SQLite's big-endian varint: seven bits a byte with the high bit
+/// set on all but the last, and a whole ninth byte if needed.
+fn varint(out: &mut Vec<u8>, n: u64) {
+	if n > 0x00FF_FFFF_FFFF_FFFF {
+		for i in (1..9).rev() {
+			out.push((n >> (i * 7 + 1)) as u8 & 0x7F | 0x80);
+		}
+		out.push(n as u8);
+		return;
+	}
+	let mut bytes = vec![(n & 0x7F) as u8];
+	let mut rest = n >> 7;
+	while rest > 0 {
+		bytes.push((rest & 0x7F) as u8 | 0x80);
+		rest >>= 7;
+	}
+	out.extend(bytes.iter().rev());
+}
+
+fn varint_len(n: u64) -> usize {
+	let mut out = Vec::new();
+	varint(&mut out, n);
+	out.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn varint_bytes(n: u64) -> Vec<u8> {
+		let mut out = Vec::new();
+		varint(&mut out, n);
+		out
+	}
+
+	/// Reads a varint at `at`, returning it and its length.
+	fn read_varint(bytes: &[u8], at: usize) -> (u64, usize) {
+		let mut n = 0;
+		for i in 0..8 {
+			let byte = bytes[at + i];
+			n = (n << 7) | u64::from(byte & 0x7F);
+			if byte & 0x80 == 0 {
+				return (n, i + 1);
+			}
+		}
+		((n << 8) | u64::from(bytes[at + 8]), 9)
+	}
+
+	/// The rowids of the table b-tree rooted at `page`, in order.
+	fn rowids(file: &[u8], page: u32) -> Vec<i64> {
+		let start = (page as usize - 1) * PAGE_SIZE;
+		let offset = if page == 1 { HEADER_SIZE } else { 0 };
+		let header = start + offset;
+		let count = u16::from_be_bytes([file[header + 3], file[header + 4]]) as usize;
+		let interior = file[header] == INTERIOR;
+		let pointers = header + if interior { 12 } else { 8 };
+		let mut found = Vec::new();
+		for i in 0..count {
+			let at = pointers + 2 * i;
+			let cell = start + u16::from_be_bytes([file[at], file[at + 1]]) as usize;
+			if interior {
+				let child = u32::from_be_bytes(file[cell..cell + 4].try_into().unwrap());
+				found.extend(rowids(file, child));
+			} else {
+				let (_, len) = read_varint(file, cell);
+				found.push(read_varint(file, cell + len).0 as i64);
+			}
+		}
+		if interior {
+			let right = u32::from_be_bytes(file[header + 8..header + 12].try_into().unwrap());
+			found.extend(rowids(file, right));
+		}
+		found
+	}
+
+	#[test]
+	fn varints_are_big_endian_seven_bits_a_byte() {
+		assert_eq!(varint_bytes(0), [0x00]);
+		assert_eq!(varint_bytes(0x7F), [0x7F]);
+		assert_eq!(varint_bytes(0x80), [0x81, 0x00]);
+		assert_eq!(varint_bytes(0x3FFF), [0xFF, 0x7F]);
+		assert_eq!(varint_bytes(u64::MAX).len(), 9);
+		for n in [0, 1, 0x80, 0x1234_5678, 0x00FF_FFFF_FFFF_FFFF, u64::MAX] {
+			assert_eq!(read_varint(&varint_bytes(n), 0), (n, varint_len(n)));
+		}
+	}
+
+	#[test]
+	fn records_have_a_header_of_serial_types() {
+		assert_eq!(
+			record(&[Value::Null, 0.into(), 1.into(), 300.into(), "ab".into()]),
+			[6, 0, 8, 9, 2, 17, 0x01, 0x2C, b'a', b'b']
+		);
+	}
+
+	#[test]
+	fn every_row_is_in_the_tree() {
+		let long = "x".repeat(10_000);
+		let rows: Vec<(i64, Vec<Value>)> = (1..=2000)
+			.rev()
+			.map(|rowid| {
+				let text = if rowid == 7 {
+					long.clone()
+				} else {
+					format!("row {rowid}")
+				};
+				(rowid, vec![Value::Null, text.into()])
+			})
+			.collect();
+		let mut database = Database::new();
+		database
+			.table(
+				"notes",
+				"CREATE TABLE notes (id integer primary key, text)",
+				rows,
+			)
+			.unwrap();
+		let file = database.finish();
+
+		assert_eq!(&file[..16], b"SQLite format 3\0");
+		assert_eq!(file.len() % PAGE_SIZE, 0);
+		let pages = u32::from_be_bytes(file[28..32].try_into().unwrap()) as usize;
+		assert_eq!(pages * PAGE_SIZE, file.len());
+
+		// The schema on page 1 has one row, whose fourth column is the
+		// table's root page.
+		assert_eq!(rowids(&file, 1), [1]);
+		let cell = u16::from_be_bytes([file[HEADER_SIZE + 8], file[HEADER_SIZE + 9]]) as usize;
+		let (_, len) = read_varint(&file, cell);
+		let (_, rowid_len) = read_varint(&file, cell + len);
+		let record = cell + len + rowid_len;
+		// Header size, then "table", name, name, root and sql.
+		let serial = |i| read_varint(&file, record + 1 + i).0;
+		let lengths: usize = (0..3).map(|i| (serial(i) as usize - 13) / 2).sum();
+		assert_eq!(serial(3), 1);
+		let root = file[record + file[record] as usize + lengths];
+		assert_eq!(file[(root as usize - 1) * PAGE_SIZE], INTERIOR);
+		assert_eq!(
+			rowids(&file, u32::from(root)),
+			(1..=2000).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn rows_with_the_same_rowid_are_refused() {
+		let mut database = Database::new();
+		let rows = vec![
+			(1, vec![Value::Null]),
+			(2, vec![Value::Null]),
+			(1, vec![Value::Null]),
+		];
+		let error = database
+			.table("notes", "CREATE TABLE notes (id integer primary key)", rows)
+			.unwrap_err();
+		assert_eq!(error.to_string(), "table notes has two rows with rowid 1");
+	}
+}
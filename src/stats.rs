@@ -0,0 +1,348 @@
+//! Reading statistics for each notes file, and the sections a reader has
+//! marked as reviewed.
+//!
+//! The statistics count what the notes are made of: sections, lines of prose
+//! against lines of code, how the snippets fare with the compiler and how
+//! many of the chapter's exercises are solved. Written as JSON they can be
+//! kept next to the notes to see them grow.
+//!
+//! Progress is a file with one reviewed section a line, as
+//! `YYYY-MM-DD<TAB>file#anchor`: the stem of the notes file and the anchor
+//! the exports give the section's heading, so that a mark survives edits to
+//! the rest of the file. The text before a file's first heading is a section
+//! too, `file#top`, so that a file without headings can be marked.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::check::Outcome;
+use crate::date::{self, Day};
+use crate::error::{Error, Result};
+use crate::exercises::{Linked, Status};
+use crate::export::anchors;
+use crate::json::Value;
+use crate::model::{Block, Document};
+use crate::parse::is_blank;
+
+/// The anchor of the text before a file's first heading, which is where a
+/// page's `#top` leads.
+const TOP: &str = "top";
+
+/// The statistics of one notes file, or of several added up.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+	pub title: String,
+	pub file: String,
+	pub sections: usize,
+	/// How many of the sections are marked as reviewed.
+	pub reviewed: usize,
+	/// Lines of prose, headings included, without the blank ones.
+	pub prose_lines: usize,
+	/// Lines of code, without the blank ones.
+	pub code_lines: usize,
+	/// Snippets that compile, whether or not the notes expect them to.
+	pub compiled: usize,
+	/// Snippets that do not compile, whether or not the notes expect them to.
+	pub failing: usize,
+	/// Snippets that are not compiled, such as TOML or console output.
+	pub skipped: usize,
+	/// The exercises the chapter ends with; counted with the chapter's
+	/// notes, not its solutions.
+	pub exercises: usize,
+	pub solved: usize,
+}
+
+impl Stats {
+	/// Lines of prose for every line of code; `None` without code.
+	pub fn ratio(&self) -> Option<f64> {
+		(self.code_lines > 0).then(|| self.prose_lines as f64 / self.code_lines as f64)
+	}
+
+	pub fn to_json(&self) -> Value {
+		Value::object([
+			("title", self.title.as_str().into()),
+			("file", self.file.as_str().into()),
+			("sections", self.sections.into()),
+			("reviewed", self.reviewed.into()),
+			("prose_lines", self.prose_lines.into()),
+			("code_lines", self.code_lines.into()),
+			(
+				"prose_per_code_line",
+				self.ratio().map_or(Value::Null, |ratio| {
+					Value::Number((ratio * 100.0).round() / 100.0)
+				}),
+			),
+			(
+				"snippets",
+				Value::object([
+					("compiled", self.compiled.into()),
+					("failing", self.failing.into()),
+					("skipped", self.skipped.into()),
+				]),
+			),
+			(
+				"exercises",
+				Value::object([
+					("total", self.exercises.into()),
+					("solved", self.solved.into()),
+				]),
+			),
+		])
+	}
+}
+
+/// The statistics of every document, in order.
+///
+/// `outcomes` has the outcome of every snippet of `docs` in order, and
+/// `statuses` the status of every exercise of `linked`.
+pub fn stats(
+	docs: &[Document],
+	outcomes: &[Outcome],
+	linked: &[Linked],
+	statuses: &[Status],
+	marks: &[Mark],
+) -> Vec<Stats> {
+	let marked: HashSet<&str> = marks.iter().map(|mark| mark.section.as_str()).collect();
+	let mut outcomes = outcomes.iter();
+	docs.iter()
+		.map(|doc| {
+			let mut stats = Stats {
+				title: doc.file.title(),
+				file: doc.file.file_name().to_string(),
+				..Stats::default()
+			};
+			for block in &doc.blocks {
+				match block {
+					Block::Heading(_) => stats.prose_lines += 1,
+					Block::Prose(prose) => stats.prose_lines += non_blank(&prose.text),
+					Block::Code(code) => {
+						stats.code_lines += non_blank(&code.text);
+						match outcomes.next() {
+							Some(Outcome::Compiled | Outcome::UnexpectedSuccess) => {
+								stats.compiled += 1
+							}
+							Some(Outcome::Skipped(_)) | None => stats.skipped += 1,
+							Some(_) => stats.failing += 1,
+						}
+					}
+				}
+			}
+			let sections = sections(doc);
+			stats.sections = sections.len();
+			stats.reviewed = sections
+				.iter()
+				.filter(|section| marked.contains(section.as_str()))
+				.count();
+			for (linked, status) in linked.iter().zip(statuses) {
+				if linked.notes.file == doc.file {
+					stats.exercises += 1;
+					stats.solved += usize::from(*status == Status::Solved);
+				}
+			}
+			stats
+		})
+		.collect()
+}
+
+/// The statistics of `stats` added up.
+pub fn total(stats: &[Stats]) -> Stats {
+	let mut total = Stats {
+		title: "Total".to_string(),
+		..Stats::default()
+	};
+	for stats in stats {
+		total.sections += stats.sections;
+		total.reviewed += stats.reviewed;
+		total.prose_lines += stats.prose_lines;
+		total.code_lines += stats.code_lines;
+		total.compiled += stats.compiled;
+		total.failing += stats.failing;
+		total.skipped += stats.skipped;
+		total.exercises += stats.exercises;
+		total.solved += stats.solved;
+	}
+	total
+}
+
+fn non_blank(text: &str) -> usize {
+	text.lines().filter(|line| !is_blank(line)).count()
+}
+
+/// The sections of `doc` as progress names them: `file#anchor`, starting
+/// with `file#top` if there is anything before the first heading.
+pub fn sections(doc: &Document) -> Vec<String> {
+	let preamble = doc
+		.blocks
+		.iter()
+		.take_while(|block| !matches!(block, Block::Heading(_)))
+		.any(|block| match block {
+			Block::Prose(prose) => non_blank(&prose.text) > 0,
+			_ => true,
+		});
+	preamble
+		.then(|| TOP.to_string())
+		.into_iter()
+		.chain(anchors(doc))
+		.map(|anchor| format!("{}#{anchor}", doc.file.stem()))
+		.collect()
+}
+
+/// The sections `name` stands for: `FILE#ANCHOR` for one section, or `FILE`
+/// for all of them, where `FILE` is the file's name or stem. Empty if it
+/// names none.
+pub fn find_sections(docs: &[Document], name: &str) -> Vec<String> {
+	let (file, anchor) = match name.split_once('#') {
+		Some((file, anchor)) => (file, Some(anchor)),
+		None => (name, None),
+	};
+	let file = file.strip_suffix(".rs").unwrap_or(file);
+	let Some(doc) = docs.iter().find(|doc| doc.file.stem() == file) else {
+		return Vec::new();
+	};
+	sections(doc)
+		.into_iter()
+		.filter(|section| anchor.is_none_or(|anchor| section[file.len() + 1..] == *anchor))
+		.collect()
+}
+
+/// A section marked as reviewed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+	pub day: Day,
+	/// `file#anchor`.
+	pub section: String,
+}
+
+/// Reads a progress file. A missing file means nothing is reviewed yet.
+pub fn read_progress(path: &Path) -> Result<Vec<Mark>> {
+	let text = match fs::read_to_string(path) {
+		Ok(text) => text,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(Error::io(path, e)),
+	};
+	let mut marks = Vec::new();
+	for (i, line) in text.lines().enumerate() {
+		if is_blank(line) {
+			continue;
+		}
+		let mark = line
+			.split_once('\t')
+			.filter(|(_, section)| section.contains('#'))
+			.and_then(|(day, section)| {
+				Some(Mark {
+					day: date::parse(day)?,
+					section: section.to_string(),
+				})
+			})
+			.ok_or_else(|| Error::Malformed {
+				path: path.to_path_buf(),
+				line: i + 1,
+				message: "expected `YYYY-MM-DD<TAB>file#anchor`".to_string(),
+			})?;
+		marks.push(mark);
+	}
+	Ok(marks)
+}
+
+/// Writes a progress file, one mark a line in the order given.
+pub fn write_progress(path: &Path, marks: &[Mark]) -> Result<()> {
+	let text: String = marks
+		.iter()
+		.map(|mark| format!("{}\t{}\n", date::format(mark.day), mark.section))
+		.collect();
+	fs::write(path, text).map_err(|e| Error::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::check;
+	use crate::source::NoteFile;
+
+	fn docs() -> Vec<Document> {
+		vec![Document::parse(
+			NoteFile::from_path("rust_chapter_3_common_concepts.rs").unwrap(),
+			"/*\nVariables:\n\n\tLet binds a name.\n\n\tShadowing:\n\n\t\tAgain.\n*/\n\n\
+			 \tlet x = 5;\n\tlet x = x + 1;\n\n\t// expect-error: E0384\n\tx = 7;\n",
+		)]
+	}
+
+	#[test]
+	fn lines_and_snippets_are_counted() {
+		let docs = docs();
+		let outcomes = [Outcome::FailedAsExpected(Vec::new())];
+		let marks = [Mark {
+			day: 0,
+			section: "rust_chapter_3_common_concepts#shadowing".to_string(),
+		}];
+		let stats = stats(&docs, &outcomes, &[], &[], &marks);
+		assert_eq!(stats[0].sections, 2);
+		assert_eq!(stats[0].reviewed, 1);
+		assert_eq!((stats[0].prose_lines, stats[0].code_lines), (4, 4));
+		assert_eq!((stats[0].compiled, stats[0].failing), (0, 1));
+		assert_eq!(stats[0].ratio(), Some(1.0));
+		assert_eq!(total(&stats).sections, 2);
+	}
+
+	#[test]
+	fn a_file_or_one_of_its_sections_can_be_named() {
+		let docs = docs();
+		assert_eq!(
+			find_sections(&docs, "rust_chapter_3_common_concepts.rs"),
+			[
+				"rust_chapter_3_common_concepts#variables",
+				"rust_chapter_3_common_concepts#shadowing"
+			]
+		);
+		assert_eq!(
+			find_sections(&docs, "rust_chapter_3_common_concepts#shadowing"),
+			["rust_chapter_3_common_concepts#shadowing"]
+		);
+		assert!(find_sections(&docs, "rust_chapter_3_common_concepts#loops").is_empty());
+		assert!(find_sections(&docs, "rust_chapter_4_ownership").is_empty());
+	}
+
+	#[test]
+	fn the_text_before_the_first_heading_is_a_section() {
+		let docs = [Document::parse(
+			NoteFile::from_path("rust_chapter_1_project_setup.rs").unwrap(),
+			"/*\n\nThese are my notes on Chapter 1.\n\n*/\n\nfn main() {}\n",
+		)];
+		assert_eq!(
+			find_sections(&docs, "rust_chapter_1_project_setup.rs"),
+			["rust_chapter_1_project_setup#top"]
+		);
+		let marks = [Mark {
+			day: 0,
+			section: "rust_chapter_1_project_setup#top".to_string(),
+		}];
+		let stats = stats(&docs, &[], &[], &[], &marks);
+		assert_eq!((stats[0].sections, stats[0].reviewed), (1, 1));
+	}
+
+	#[test]
+	fn progress_reads_back_as_written() {
+		let dir = check::temp_dir().unwrap();
+		let path = dir.join(".progress");
+		assert!(read_progress(&path).unwrap().is_empty());
+		let marks = [
+			Mark {
+				day: date::parse("2026-10-17").unwrap(),
+				section: "rust_chapter_3_common_concepts#variables".to_string(),
+			},
+			Mark {
+				day: date::parse("2026-10-18").unwrap(),
+				section: "rust_chapter_3_common_concepts#shadowing".to_string(),
+			},
+		];
+		write_progress(&path, &marks).unwrap();
+		assert_eq!(read_progress(&path).unwrap(), marks);
+
+		fs::write(&path, "2026-10-17\tno anchor\n").unwrap();
+		let error = read_progress(&path).unwrap_err();
+		let _ = fs::remove_dir_all(&dir);
+		assert!(matches!(error, Error::Malformed { line: 1, .. }));
+	}
+}
@@ -0,0 +1,95 @@
+//! Whitespace handling shared by the exporters.
+//!
+//! The notes were written with four-column tabs, and the trailing comments
+//! in the code are lined up with them. Anything that shows the code outside
+//! an editor expands the tabs at that width so the columns survive.
+
+use crate::parse::{indent_of, is_blank};
+
+/// The tab width the notes were written with.
+pub const TAB_WIDTH: usize = 4;
+
+/// Replaces tabs by spaces up to the next tab stop.
+pub fn expand_tabs(line: &str) -> String {
+	let mut out = String::with_capacity(line.len());
+	let mut column = 0;
+	for c in line.chars() {
+		if c == '\t' {
+			let width = TAB_WIDTH - column % TAB_WIDTH;
+			out.extend(std::iter::repeat_n(' ', width));
+			column += width;
+		} else {
+			out.push(c);
+			column += 1;
+		}
+	}
+	out
+}
+
+/// Removes the indentation shared by every non-blank line, then expands
+/// the remaining tabs.
+///
+/// Indentation is removed a whole level (a tab or four spaces) at a time,
+/// so tab stops further along the line stay where they were.
+pub fn dedent(text: &str) -> String {
+	let levels = text
+		.lines()
+		.filter(|line| !is_blank(line))
+		.map(indent_of)
+		.min()
+		.unwrap_or(0);
+	text.lines()
+		.map(|line| {
+			expand_tabs(strip_levels(line, levels))
+				.trim_end()
+				.to_string()
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Strips `levels` levels of indentation from the start of `line`.
+fn strip_levels(line: &str, levels: usize) -> &str {
+	let mut rest = line;
+	for _ in 0..levels {
+		rest = match rest.strip_prefix('\t') {
+			Some(rest) => rest,
+			None => match rest.strip_prefix("    ") {
+				Some(rest) => rest,
+				None => rest.trim_start_matches(' '),
+			},
+		};
+	}
+	rest
+}
+
+/// Breaks `text` into lines of at most `width` characters, at spaces.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	for word in text.split_whitespace() {
+		match lines.last_mut() {
+			Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+				line.push(' ');
+				line.push_str(word);
+			}
+			_ => lines.push(word.to_string()),
+		}
+	}
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn words_wrap_at_the_width() {
+		assert_eq!(
+			wrap("one two  three\nfour", 9),
+			["one two", "three", "four"]
+		);
+		// A word longer than the width gets a line of its own.
+		assert_eq!(wrap("a abcdefghij b", 5), ["a", "abcdefghij", "b"]);
+		assert!(wrap("  ", 5).is_empty());
+	}
+}
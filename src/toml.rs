@@ -0,0 +1,620 @@
+//! A small TOML reader, enough for the `Cargo.toml` excerpts in the notes.
+//!
+//! It reads tables and arrays of tables, dotted keys, strings of all four
+//! kinds except the multi-line ones, integers, floats, booleans, arrays
+//! (which may span lines) and inline tables. Dates and times are refused.
+//! Every key remembers the line it was written on, so that a problem found
+//! later can be pointed at.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	String(String),
+	Integer(i64),
+	Float(f64),
+	Boolean(bool),
+	Array(Vec<Value>),
+	Table(Table),
+}
+
+impl Value {
+	/// What kind of value this is, for messages.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Value::String(_) => "a string",
+			Value::Integer(_) => "an integer",
+			Value::Float(_) => "a float",
+			Value::Boolean(_) => "a boolean",
+			Value::Array(_) => "an array",
+			Value::Table(_) => "a table",
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(text) => Some(text),
+			_ => None,
+		}
+	}
+
+	pub fn as_table(&self) -> Option<&Table> {
+		match self {
+			Value::Table(table) => Some(table),
+			_ => None,
+		}
+	}
+}
+
+/// Writes the value back as TOML, with tables inline.
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::String(text) => write!(f, "\"{}\"", escape(text)),
+			Value::Integer(n) => write!(f, "{n}"),
+			Value::Float(x) if x.is_finite() && x.fract() == 0.0 => write!(f, "{x:.1}"),
+			Value::Float(x) => write!(f, "{x}"),
+			Value::Boolean(b) => write!(f, "{b}"),
+			Value::Array(items) => {
+				write!(f, "[")?;
+				for (i, item) in items.iter().enumerate() {
+					let comma = if i == 0 { "" } else { ", " };
+					write!(f, "{comma}{item}")?;
+				}
+				write!(f, "]")
+			}
+			Value::Table(table) if table.entries.is_empty() => write!(f, "{{}}"),
+			Value::Table(table) => {
+				write!(f, "{{ ")?;
+				for (i, entry) in table.entries.iter().enumerate() {
+					let comma = if i == 0 { "" } else { ", " };
+					write!(f, "{comma}{} = {}", format_key(&entry.key), entry.value)?;
+				}
+				write!(f, " }}")
+			}
+		}
+	}
+}
+
+/// `key` as it has to be written: bare if it can be, quoted otherwise.
+pub fn format_key(key: &str) -> String {
+	let bare = !key.is_empty()
+		&& key
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+	if bare {
+		key.to_string()
+	} else {
+		format!("\"{}\"", escape(key))
+	}
+}
+
+/// The text of a basic string, with quotes, backslashes and control
+/// characters escaped.
+fn escape(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\t' => out.push_str("\\t"),
+			'\r' => out.push_str("\\r"),
+			c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out
+}
+
+/// A table, keeping its keys in the order they were written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+	pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+	pub key: String,
+	pub value: Value,
+	/// The line of the text the key was written on, from 1.
+	pub line: usize,
+}
+
+impl Table {
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		self.entry(key).map(|entry| &entry.value)
+	}
+
+	pub fn entry(&self, key: &str) -> Option<&Entry> {
+		self.entries.iter().find(|entry| entry.key == key)
+	}
+
+	fn entry_mut(&mut self, key: &str) -> Option<&mut Entry> {
+		self.entries.iter_mut().find(|entry| entry.key == key)
+	}
+}
+
+/// Why some text is not TOML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+/// Reads a TOML document.
+pub fn parse(text: &str) -> Result<Table, ParseError> {
+	let mut root = Table::default();
+	// The path of the table that keys go into, and whether its last part is
+	// an array of tables.
+	let mut current: (Vec<String>, bool) = (Vec::new(), false);
+	// The `[table]` headers seen so far, which may not be repeated.
+	let mut headers: Vec<Vec<String>> = Vec::new();
+	let mut parser = Parser {
+		text,
+		at: 0,
+		line: 1,
+	};
+	loop {
+		parser.skip_blank_lines();
+		if parser.at == text.len() {
+			return Ok(root);
+		}
+		let line = parser.line;
+		if parser.eat("[[") {
+			let path = parser.key()?;
+			parser.expect("]]")?;
+			push_array_table(&mut root, &path, line)?;
+			current = (path, true);
+		} else if parser.eat("[") {
+			let path = parser.key()?;
+			parser.expect("]")?;
+			if headers.contains(&path) {
+				return Err(ParseError {
+					line,
+					message: format!("table `[{}]` is defined twice", path.join(".")),
+				});
+			}
+			table_at(&mut root, &path, false, line)?;
+			headers.push(path.clone());
+			current = (path, false);
+		} else {
+			let path = parser.key()?;
+			parser.expect("=")?;
+			let value = parser.value()?;
+			let table = table_at(&mut root, &current.0, current.1, line)?;
+			insert(table, &path, value, line)?;
+		}
+		parser.end_of_line()?;
+	}
+}
+
+/// The table at `path`, creating the tables on the way. With `last_array`,
+/// the last part of the path names an array of tables and its last element
+/// is returned.
+fn table_at<'a>(
+	root: &'a mut Table,
+	path: &[String],
+	last_array: bool,
+	line: usize,
+) -> Result<&'a mut Table, ParseError> {
+	let mut table = root;
+	for (i, key) in path.iter().enumerate() {
+		if table.entry(key).is_none() {
+			table.entries.push(Entry {
+				key: key.clone(),
+				value: Value::Table(Table::default()),
+				line,
+			});
+		}
+		let entry = table.entry_mut(key).unwrap();
+		let is_last = i + 1 == path.len();
+		table = match &mut entry.value {
+			Value::Table(inner) => inner,
+			Value::Array(items) if !is_last || last_array => match items.last_mut() {
+				Some(Value::Table(inner)) => inner,
+				_ => return Err(not_a_table(key, line)),
+			},
+			_ => return Err(not_a_table(key, line)),
+		};
+	}
+	Ok(table)
+}
+
+fn push_array_table(root: &mut Table, path: &[String], line: usize) -> Result<(), ParseError> {
+	let (last, parents) = path.split_last().expect("a key has at least one part");
+	let parent = table_at(root, parents, false, line)?;
+	match parent.entry_mut(last) {
+		None => parent.entries.push(Entry {
+			key: last.clone(),
+			value: Value::Array(vec![Value::Table(Table::default())]),
+			line,
+		}),
+		Some(Entry {
+			value: Value::Array(items),
+			..
+		}) => items.push(Value::Table(Table::default())),
+		Some(_) => return Err(not_a_table(last, line)),
+	}
+	Ok(())
+}
+
+fn insert(table: &mut Table, path: &[String], value: Value, line: usize) -> Result<(), ParseError> {
+	let (last, parents) = path.split_last().expect("a key has at least one part");
+	let table = table_at(table, parents, false, line)?;
+	if table.entry(last).is_some() {
+		return Err(ParseError {
+			line,
+			message: format!("`{}` is defined twice", path.join(".")),
+		});
+	}
+	table.entries.push(Entry {
+		key: last.clone(),
+		value,
+		line,
+	});
+	Ok(())
+}
+
+fn not_a_table(key: &str, line: usize) -> ParseError {
+	ParseError {
+		line,
+		message: format!("`{key}` is already a value, not a table"),
+	}
+}
+
+struct Parser<'a> {
+	text: &'a str,
+	at: usize,
+	line: usize,
+}
+
+impl Parser<'_> {
+	fn rest(&self) -> &str {
+		&self.text[self.at..]
+	}
+
+	fn error(&self, message: impl Into<String>) -> ParseError {
+		ParseError {
+			line: self.line,
+			message: message.into(),
+		}
+	}
+
+	fn advance(&mut self, len: usize) {
+		self.line += self.text[self.at..self.at + len].matches('\n').count();
+		self.at += len;
+	}
+
+	fn eat(&mut self, token: &str) -> bool {
+		self.skip_spaces();
+		let ok = self.rest().starts_with(token);
+		if ok {
+			self.advance(token.len());
+		}
+		ok
+	}
+
+	fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+		if self.eat(token) {
+			Ok(())
+		} else {
+			Err(self.error(format!("expected `{token}`")))
+		}
+	}
+
+	fn skip_spaces(&mut self) {
+		let rest = self.rest();
+		let len = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+		self.advance(len);
+	}
+
+	/// Skips spaces, comments and line breaks.
+	fn skip_blank_lines(&mut self) {
+		loop {
+			self.skip_spaces();
+			if self.rest().starts_with('#') {
+				let len = self.rest().find('\n').unwrap_or(self.rest().len());
+				self.advance(len);
+			}
+			if self.rest().starts_with('\n') || self.rest().starts_with("\r\n") {
+				let len = if self.rest().starts_with('\n') { 1 } else { 2 };
+				self.advance(len);
+			} else {
+				return;
+			}
+		}
+	}
+
+	fn end_of_line(&mut self) -> Result<(), ParseError> {
+		self.skip_spaces();
+		let rest = self.rest();
+		if rest.is_empty() || rest.starts_with(['\n', '\r', '#']) {
+			Ok(())
+		} else {
+			let found: String = rest.chars().take_while(|&c| c != '\n').collect();
+			Err(self.error(format!(
+				"unexpected `{}` at the end of the line",
+				found.trim()
+			)))
+		}
+	}
+
+	/// A key, dotted or not.
+	fn key(&mut self) -> Result<Vec<String>, ParseError> {
+		let mut parts = vec![self.simple_key()?];
+		while self.eat(".") {
+			parts.push(self.simple_key()?);
+		}
+		Ok(parts)
+	}
+
+	fn simple_key(&mut self) -> Result<String, ParseError> {
+		self.skip_spaces();
+		match self.rest().chars().next() {
+			Some('"') => self.basic_string(),
+			Some('\'') => self.literal_string(),
+			_ => {
+				let len = self
+					.rest()
+					.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+					.unwrap_or(self.rest().len());
+				if len == 0 {
+					return Err(self.error("expected a key"));
+				}
+				let key = self.rest()[..len].to_string();
+				self.advance(len);
+				Ok(key)
+			}
+		}
+	}
+
+	fn value(&mut self) -> Result<Value, ParseError> {
+		self.skip_spaces();
+		let rest = self.rest();
+		if rest.starts_with("\"\"\"") || rest.starts_with("'''") {
+			return Err(self.error("multi-line strings are not supported"));
+		}
+		match rest.chars().next() {
+			Some('"') => self.basic_string().map(Value::String),
+			Some('\'') => self.literal_string().map(Value::String),
+			Some('[') => self.array(),
+			Some('{') => self.inline_table(),
+			Some(_) => self.scalar(),
+			None => Err(self.error("expected a value")),
+		}
+	}
+
+	fn basic_string(&mut self) -> Result<String, ParseError> {
+		self.advance(1);
+		let mut out = String::new();
+		let mut chars = self.rest().char_indices();
+		while let Some((i, c)) = chars.next() {
+			match c {
+				'"' => {
+					self.advance(i + 1);
+					return Ok(out);
+				}
+				'\n' => break,
+				'\\' => match chars.next().map(|(_, c)| c) {
+					Some('n') => out.push('\n'),
+					Some('t') => out.push('\t'),
+					Some('r') => out.push('\r'),
+					Some('"') => out.push('"'),
+					Some('\\') => out.push('\\'),
+					Some('u') => {
+						let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+						let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+						out.push(c.ok_or_else(|| self.error("bad `\\u` escape"))?);
+					}
+					_ => return Err(self.error("unknown escape in a string")),
+				},
+				_ => out.push(c),
+			}
+		}
+		Err(self.error("unterminated string"))
+	}
+
+	fn literal_string(&mut self) -> Result<String, ParseError> {
+		self.advance(1);
+		match self.rest().find(['\'', '\n']) {
+			Some(end) if self.rest()[end..].starts_with('\'') => {
+				let text = self.rest()[..end].to_string();
+				self.advance(end + 1);
+				Ok(text)
+			}
+			_ => Err(self.error("unterminated string")),
+		}
+	}
+
+	fn array(&mut self) -> Result<Value, ParseError> {
+		self.advance(1);
+		let mut items = Vec::new();
+		loop {
+			self.skip_blank_lines();
+			if self.eat("]") {
+				return Ok(Value::Array(items));
+			}
+			items.push(self.value()?);
+			self.skip_blank_lines();
+			if !self.eat(",") {
+				self.skip_blank_lines();
+				self.expect("]")?;
+				return Ok(Value::Array(items));
+			}
+		}
+	}
+
+	fn inline_table(&mut self) -> Result<Value, ParseError> {
+		self.advance(1);
+		let mut table = Table::default();
+		if self.eat("}") {
+			return Ok(Value::Table(table));
+		}
+		loop {
+			let line = self.line;
+			let path = self.key()?;
+			self.expect("=")?;
+			let value = self.value()?;
+			insert(&mut table, &path, value, line)?;
+			if self.eat("}") {
+				return Ok(Value::Table(table));
+			}
+			self.expect(",")?;
+		}
+	}
+
+	/// A boolean or a number.
+	fn scalar(&mut self) -> Result<Value, ParseError> {
+		let len = self
+			.rest()
+			.find(|c: char| c.is_whitespace() || ",]}#".contains(c))
+			.unwrap_or(self.rest().len());
+		let word = &self.rest()[..len];
+		let value = match word {
+			"true" => Value::Boolean(true),
+			"false" => Value::Boolean(false),
+			_ => {
+				let digits = word.replace('_', "");
+				if let Ok(n) = digits.parse::<i64>() {
+					Value::Integer(n)
+				} else if let (Ok(x), true) = (
+					digits.parse::<f64>(),
+					digits.contains(['.', 'e', 'E'])
+						|| ["inf", "nan"].contains(&digits.trim_start_matches(['+', '-'])),
+				) {
+					Value::Float(x)
+				} else if word.len() >= 10 && word.as_bytes()[4] == b'-' {
+					return Err(self.error("dates and times are not supported"));
+				} else {
+					return Err(self.error(format!("`{word}` is not a value; strings need quotes")));
+				}
+			}
+		};
+		self.advance(len);
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MANIFEST: &str = r#"[package]
+name = "guessing_game"
+version = "0.1.0"   # a comment
+edition = '2021'
+
+[dependencies]
+rand = { version = "0.8.5", features = ["small_rng"] }
+"serde json" = "1"
+
+[[bin]]
+name = "a"
+path.main = "src/a.rs"
+
+[[bin]]
+name = "b"
+weights = [
+	1.5,
+	-2,
+	1_000,
+]
+"#;
+
+	#[test]
+	fn reads_tables_keys_and_values() {
+		let table = parse(MANIFEST).unwrap();
+		let package = table.get("package").unwrap().as_table().unwrap();
+		assert_eq!(package.get("edition"), Some(&Value::String("2021".into())));
+		assert_eq!(package.entry("version").unwrap().line, 3);
+
+		let dependencies = table.get("dependencies").unwrap().as_table().unwrap();
+		let rand = dependencies.get("rand").unwrap().as_table().unwrap();
+		assert_eq!(
+			rand.get("features"),
+			Some(&Value::Array(vec![Value::String("small_rng".into())]))
+		);
+		assert!(dependencies.get("serde json").is_some());
+
+		let Some(Value::Array(bins)) = table.get("bin") else {
+			panic!("`bin` is an array of tables");
+		};
+		assert_eq!(bins.len(), 2);
+		let a = bins[0].as_table().unwrap();
+		let path = a.get("path").unwrap().as_table().unwrap();
+		assert_eq!(path.get("main").unwrap().as_str(), Some("src/a.rs"));
+		assert_eq!(
+			bins[1].as_table().unwrap().get("weights"),
+			Some(&Value::Array(vec![
+				Value::Float(1.5),
+				Value::Integer(-2),
+				Value::Integer(1000),
+			]))
+		);
+	}
+
+	#[test]
+	fn values_read_back_as_written() {
+		let table = parse(MANIFEST).unwrap();
+		// Written on one line, the keys all come back on line 1.
+		let written = Value::Table(table).to_string();
+		let read = parse(&format!("all = {written}")).unwrap();
+		assert_eq!(read.get("all").unwrap().to_string(), written);
+
+		let text = Value::String("a \"quoted\"\\path\n\u{1}".into());
+		let read = parse(&format!("text = {text}")).unwrap();
+		assert_eq!(read.get("text"), Some(&text));
+		assert_eq!(format_key("serde json"), "\"serde json\"");
+		assert_eq!(format_key("serde_json"), "serde_json");
+	}
+
+	#[test]
+	fn errors_name_their_line() {
+		let error = |text: &str| {
+			let error = parse(text).unwrap_err();
+			(error.line, error.message)
+		};
+		assert_eq!(
+			error("[a]\nx = 1\n[a]\n"),
+			(3, "table `[a]` is defined twice".to_string())
+		);
+		assert_eq!(
+			error("x = 1\nx = 2\n"),
+			(2, "`x` is defined twice".to_string())
+		);
+		assert_eq!(
+			error("x = 1\n[x.y]\n"),
+			(2, "`x` is already a value, not a table".to_string())
+		);
+		assert_eq!(
+			error("\nname = guessing_game\n"),
+			(
+				2,
+				"`guessing_game` is not a value; strings need quotes".to_string()
+			)
+		);
+		assert_eq!(
+			error("date = 1979-05-27\n"),
+			(1, "dates and times are not supported".to_string())
+		);
+		assert_eq!(
+			error("x = \"open\n"),
+			(1, "unterminated string".to_string())
+		);
+		assert_eq!(
+			error("x = 1 2\n"),
+			(1, "unexpected `2` at the end of the line".to_string())
+		);
+		assert_eq!(
+			error("x = \"\"\"\nlong\"\"\"\n"),
+			(1, "multi-line strings are not supported".to_string())
+		);
+	}
+}
@@ -0,0 +1,510 @@
+//! Checking the blocks that are not Rust.
+//!
+//! The first chapters show `Cargo.toml` excerpts and `$ cargo ...` commands
+//! as bare code between comments. The compiler has nothing to say about
+//! those, so they are checked here instead: TOML blocks are read as Cargo
+//! manifests, and console blocks as commands a shell would run, with cargo
+//! subcommands checked against the ones cargo has. A block of unknown
+//! language is a problem in itself.
+
+use crate::model::{Code, Lang};
+use crate::toml::{self, Table, Value};
+
+/// The subcommands of cargo, with their one-letter aliases.
+const CARGO_SUBCOMMANDS: [&str; 47] = [
+	"add",
+	"b",
+	"bench",
+	"build",
+	"c",
+	"check",
+	"clean",
+	"clippy",
+	"config",
+	"d",
+	"doc",
+	"fetch",
+	"fix",
+	"fmt",
+	"generate-lockfile",
+	"help",
+	"info",
+	"init",
+	"install",
+	"locate-project",
+	"login",
+	"logout",
+	"metadata",
+	"miri",
+	"new",
+	"owner",
+	"package",
+	"pkgid",
+	"publish",
+	"r",
+	"remove",
+	"report",
+	"rm",
+	"run",
+	"rustc",
+	"rustdoc",
+	"search",
+	"t",
+	"test",
+	"tree",
+	"uninstall",
+	"update",
+	"vendor",
+	"verify-project",
+	"version",
+	"yank",
+	"--version",
+];
+
+/// Programs other than cargo a session in the notes may run.
+const PROGRAMS: [&str; 12] = [
+	"cargo", "cat", "cd", "echo", "git", "ls", "mkdir", "rustc", "rustdoc", "rustfmt", "rustup",
+	"./main",
+];
+
+/// The top-level tables of a manifest.
+const MANIFEST_TABLES: [&str; 18] = [
+	"badges",
+	"bench",
+	"bin",
+	"build-dependencies",
+	"cargo-features",
+	"dependencies",
+	"dev-dependencies",
+	"example",
+	"features",
+	"lib",
+	"lints",
+	"package",
+	"patch",
+	"profile",
+	"replace",
+	"target",
+	"test",
+	"workspace",
+];
+
+const PACKAGE_KEYS: [&str; 27] = [
+	"authors",
+	"autobenches",
+	"autobins",
+	"autoexamples",
+	"autotests",
+	"build",
+	"categories",
+	"default-run",
+	"description",
+	"documentation",
+	"edition",
+	"exclude",
+	"homepage",
+	"include",
+	"keywords",
+	"license",
+	"license-file",
+	"links",
+	"metadata",
+	"name",
+	"publish",
+	"readme",
+	"repository",
+	"resolver",
+	"rust-version",
+	"version",
+	"workspace",
+];
+
+const DEPENDENCY_KEYS: [&str; 13] = [
+	"branch",
+	"default-features",
+	"default_features",
+	"features",
+	"git",
+	"optional",
+	"package",
+	"path",
+	"registry",
+	"rev",
+	"tag",
+	"version",
+	"workspace",
+];
+
+const EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
+
+/// Something wrong with a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+	/// The line of the notes file.
+	pub line: usize,
+	pub message: String,
+}
+
+/// The problems of a block that is not Rust. Rust blocks are left to the
+/// compiler and always come back clean.
+pub fn problems(code: &Code) -> Vec<Problem> {
+	let at = |line: usize, message: String| Problem {
+		line: code.lines.start + line - 1,
+		message,
+	};
+	match code.lang() {
+		Lang::Rust => Vec::new(),
+		Lang::Unknown => vec![at(
+			1,
+			"neither Rust, TOML nor a console session; is it prose outside its comment?"
+				.to_string(),
+		)],
+		Lang::Toml => match toml::parse(&code.text) {
+			Ok(table) => manifest(&table)
+				.into_iter()
+				.map(|(line, message)| at(line, message))
+				.collect(),
+			Err(error) => vec![at(error.line, format!("not TOML: {}", error.message))],
+		},
+		Lang::Console => code
+			.text
+			.lines()
+			.enumerate()
+			.filter_map(|(i, line)| Some((i, line.trim().strip_prefix("$ ")?)))
+			.flat_map(|(i, command)| {
+				self::command(command)
+					.into_iter()
+					.map(move |message| at(i + 1, message))
+			})
+			.collect(),
+	}
+}
+
+/// Checks a manifest, or an excerpt of one: only what is there is checked,
+/// so `[dependencies]` on its own is fine. Returns lines of the text with
+/// what is wrong on them.
+pub fn manifest(root: &Table) -> Vec<(usize, String)> {
+	let mut problems = Vec::new();
+	for entry in &root.entries {
+		if !MANIFEST_TABLES.contains(&entry.key.as_str()) {
+			problems.push((entry.line, format!("unknown manifest key `{}`", entry.key)));
+		}
+	}
+	if let Some(entry) = root.entry("package") {
+		match &entry.value {
+			Value::Table(package) => self::package(package, &mut problems),
+			other => problems.push((
+				entry.line,
+				format!("`package` is {}, not a table", other.kind()),
+			)),
+		}
+	}
+	for table in ["dependencies", "dev-dependencies", "build-dependencies"] {
+		let Some(entry) = root.entry(table) else {
+			continue;
+		};
+		match &entry.value {
+			Value::Table(dependencies) => {
+				for dependency in &dependencies.entries {
+					if let Err(message) = self::dependency(&dependency.value) {
+						problems.push((
+							dependency.line,
+							format!("dependency `{}`: {message}", dependency.key),
+						));
+					}
+				}
+			}
+			other => problems.push((
+				entry.line,
+				format!("`{table}` is {}, not a table", other.kind()),
+			)),
+		}
+	}
+	problems.sort();
+	problems
+}
+
+fn package(package: &Table, problems: &mut Vec<(usize, String)>) {
+	for entry in &package.entries {
+		let key = entry.key.as_str();
+		let line = entry.line;
+		if !PACKAGE_KEYS.contains(&key) {
+			problems.push((line, format!("unknown key `package.{key}`")));
+			continue;
+		}
+		let workspace = entry
+			.value
+			.as_table()
+			.is_some_and(|table| table.get("workspace") == Some(&Value::Boolean(true)));
+		if workspace {
+			continue;
+		}
+		let text = entry.value.as_str();
+		let message = match key {
+			"name" => match text {
+				Some(name) => package_name(name).err(),
+				None => Some(format!("is {}, not a string", entry.value.kind())),
+			},
+			"version" => match text {
+				Some(version) if !is_version(version) => {
+					Some(format!("`{version}` is not a MAJOR.MINOR.PATCH version"))
+				}
+				Some(_) => None,
+				None => Some(format!("is {}, not a string", entry.value.kind())),
+			},
+			"edition" => match text {
+				Some(edition) if !EDITIONS.contains(&edition) => Some(format!(
+					"`{edition}` is not an edition; use one of {}",
+					EDITIONS.join(", ")
+				)),
+				Some(_) => None,
+				None => Some(format!(
+					"is {}, not a string like \"2021\"",
+					entry.value.kind()
+				)),
+			},
+			_ => None,
+		};
+		if let Some(message) = message {
+			problems.push((line, format!("`package.{key}` {message}")));
+		}
+	}
+	if package.get("name").is_none() {
+		let line = package.entries.first().map_or(1, |entry| entry.line);
+		problems.push((line, "`[package]` has no `name`".to_string()));
+	}
+}
+
+/// Checks one dependency: a version requirement, or a table saying where
+/// the crate comes from.
+fn dependency(value: &Value) -> Result<(), String> {
+	match value {
+		Value::String(requirement) => version_requirement(requirement),
+		Value::Table(table) => {
+			if let Some(entry) = table
+				.entries
+				.iter()
+				.find(|entry| !DEPENDENCY_KEYS.contains(&entry.key.as_str()))
+			{
+				return Err(format!("unknown key `{}`", entry.key));
+			}
+			if !["version", "path", "git", "workspace"]
+				.iter()
+				.any(|key| table.get(key).is_some())
+			{
+				return Err("needs a `version`, `path` or `git`".to_string());
+			}
+			match table.get("version") {
+				Some(Value::String(requirement)) => version_requirement(requirement),
+				Some(other) => Err(format!("`version` is {}, not a string", other.kind())),
+				None => Ok(()),
+			}
+		}
+		other => Err(format!(
+			"is {}; write a version such as \"0.8.5\" or a table",
+			other.kind()
+		)),
+	}
+}
+
+/// Checks a requirement such as `0.8.5`, `^1.2`, `>= 1, < 2` or `1.*`.
+fn version_requirement(requirement: &str) -> Result<(), String> {
+	if requirement.trim() == "*" {
+		return Ok(());
+	}
+	for comparator in requirement.split(',') {
+		let version = comparator
+			.trim()
+			.trim_start_matches(['=', '>', '<', '~', '^'])
+			.trim();
+		let mut parts = version.splitn(3, '.');
+		let major = parts.next().unwrap_or("");
+		let rest: Vec<&str> = parts.collect();
+		let numbers_ok = major.parse::<u64>().is_ok()
+			&& rest.iter().enumerate().all(|(i, part)| {
+				let part = if i == 1 {
+					part.split(['-', '+']).next().unwrap_or("")
+				} else {
+					part
+				};
+				part == "*" || part.parse::<u64>().is_ok()
+			});
+		if !numbers_ok {
+			return Err(format!("`{requirement}` is not a version requirement"));
+		}
+	}
+	Ok(())
+}
+
+/// Whether `version` is a semantic version: `1.2.3`, maybe with a
+/// `-pre` release and `+build` metadata.
+fn is_version(version: &str) -> bool {
+	let core = version.split(['-', '+']).next().unwrap_or("");
+	let parts: Vec<&str> = core.split('.').collect();
+	parts.len() == 3 && parts.iter().all(|part| part.parse::<u64>().is_ok())
+}
+
+/// Checks a package name the way `cargo new` does.
+fn package_name(name: &str) -> Result<(), String> {
+	if name.is_empty() {
+		return Err("is empty".to_string());
+	}
+	if name.starts_with(|c: char| c.is_ascii_digit()) {
+		return Err(format!("`{name}` starts with a digit"));
+	}
+	match name
+		.chars()
+		.find(|&c| !(c.is_alphanumeric() || c == '_' || c == '-'))
+	{
+		Some(c) => Err(format!(
+			"`{name}` has the character `{c}`, which a package name cannot"
+		)),
+		None => Ok(()),
+	}
+}
+
+/// The problems of one command, written without its `$ ` prompt.
+pub fn command(command: &str) -> Vec<String> {
+	let mut problems = Vec::new();
+	let mut words: Vec<&str> = command.split_whitespace().collect();
+	if let Some(at) = words
+		.iter()
+		.position(|word| word.starts_with("//") || word.starts_with("/*"))
+	{
+		problems.push(format!(
+			"`{}` is not a shell comment; comments start with `#`",
+			words[at..].join(" ")
+		));
+		words.truncate(at);
+	}
+	if let Some(at) = words.iter().position(|word| word.starts_with('#')) {
+		words.truncate(at);
+	}
+	if let Some(&last) = words.last() {
+		let trimmed = last.trim_end_matches(['.', ',', ';', ':']);
+		if trimmed.len() < last.len() && !trimmed.is_empty() {
+			problems.push(format!(
+				"stray `{}` at the end of `{}`",
+				&last[trimmed.len()..],
+				words.join(" ")
+			));
+			words.pop();
+			words.push(trimmed);
+		}
+	}
+	let Some((&program, args)) = words.split_first() else {
+		return problems;
+	};
+	if !PROGRAMS.contains(&program) {
+		problems.push(format!("unknown program `{program}`"));
+		return problems;
+	}
+	if program != "cargo" {
+		return problems;
+	}
+	let mut args = args.iter().skip_while(|arg| arg.starts_with('+'));
+	let Some(&subcommand) = args.next() else {
+		return problems;
+	};
+	if !CARGO_SUBCOMMANDS.contains(&subcommand) {
+		let suggestion = CARGO_SUBCOMMANDS
+			.iter()
+			.filter(|known| known.len() > 1)
+			.map(|known| (edit_distance(subcommand, known), known))
+			.filter(|(distance, _)| *distance <= 2)
+			.min();
+		problems.push(match suggestion {
+			Some((_, known)) => {
+				format!("`{subcommand}` is not a cargo subcommand; did you mean `{known}`?")
+			}
+			None => format!("`{subcommand}` is not a cargo subcommand"),
+		});
+		return problems;
+	}
+	if ["new", "init"].contains(&subcommand) {
+		let name = args.find(|arg| !arg.starts_with('-'));
+		if let Some(Err(message)) = name.map(|name| package_name(name.trim_end_matches('/'))) {
+			problems.push(format!("package name {message}"));
+		}
+	}
+	problems
+}
+
+/// The Levenshtein distance between two words.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		for (j, cb) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(ca != *cb);
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+	previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn manifest_problems(text: &str) -> Vec<(usize, String)> {
+		manifest(&toml::parse(text).unwrap())
+	}
+
+	#[test]
+	fn manifests_are_checked_as_cargo_would() {
+		assert!(manifest_problems("[dependencies]\nrand = \"0.8.5\"\n").is_empty());
+		assert_eq!(
+			manifest_problems(
+				"[package]\nname = \"2fast\"\nversion = \"0.1\"\nedition = \"2020\"\nauthor = \"me\"\n\n\
+				 [dependencies]\nrand = \"latest\"\nserde = { features = [\"derive\"] }\n\n[dependency]\n"
+			),
+			[
+				(2, "`package.name` `2fast` starts with a digit".to_string()),
+				(
+					3,
+					"`package.version` `0.1` is not a MAJOR.MINOR.PATCH version".to_string()
+				),
+				(
+					4,
+					"`package.edition` `2020` is not an edition; use one of 2015, 2018, 2021, 2024"
+						.to_string()
+				),
+				(5, "unknown key `package.author`".to_string()),
+				(
+					8,
+					"dependency `rand`: `latest` is not a version requirement".to_string()
+				),
+				(
+					9,
+					"dependency `serde`: needs a `version`, `path` or `git`".to_string()
+				),
+				(11, "unknown manifest key `dependency`".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn commands_are_checked_against_cargo() {
+		assert!(command("cargo new hello_cargo").is_empty());
+		assert!(command("cargo +nightly build --release # fast").is_empty());
+		assert_eq!(
+			command("cargo biuld"),
+			["`biuld` is not a cargo subcommand; did you mean `build`?"]
+		);
+		assert_eq!(
+			command("cargo new hello-cargo.rs"),
+			["package name `hello-cargo.rs` has the character `.`, which a package name cannot"]
+		);
+		assert_eq!(
+			command("cargo run. // then run it"),
+			[
+				"`// then run it` is not a shell comment; comments start with `#`",
+				"stray `.` at the end of `cargo run.`"
+			]
+		);
+		assert_eq!(command("carg run"), ["unknown program `carg`"]);
+	}
+}
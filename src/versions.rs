@@ -0,0 +1,348 @@
+//! How an item changes from one snippet to the next.
+//!
+//! The notes often build a program up step by step, writing out a new
+//! version of the same `fn main` or `fn area` each time. Successive
+//! definitions of one item in a file form its history, and each step can be
+//! shown as a token-level diff against the one before it.
+//!
+//! Methods are followed under their own name, so a free `fn area` that
+//! later becomes `impl Rectangle { fn area(&self) ... }` is one history. A
+//! definition that shares little with the previous one under the same name,
+//! like the unrelated `fn main`s of chapter 3, starts a new history instead.
+//! Definitions repeated unchanged, such as a struct carried into every
+//! snippet, are not versions.
+
+use crate::check::{code_part, top_level_items};
+use crate::lex::{self, Token};
+use crate::model::{Code, Document};
+use crate::text;
+
+/// How much of two definitions has to match, as a share of their tokens,
+/// for the second to count as a new version of the first.
+const SIMILARITY: f64 = 0.55;
+
+/// One definition of an item.
+#[derive(Debug, Clone)]
+pub struct Version<'a> {
+	pub code: &'a Code,
+	/// The line of the notes file the definition starts on.
+	pub line: usize,
+	/// E.g. `fn area`, or `impl Rectangle { fn area }` for a method.
+	pub label: String,
+	pub text: String,
+}
+
+/// Successive versions of one item, oldest first.
+#[derive(Debug, Clone)]
+pub struct History<'a> {
+	pub name: String,
+	pub versions: Vec<Version<'a>>,
+}
+
+/// One token of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a> {
+	Kept(Token<'a>),
+	Added(Token<'a>),
+	Removed(Token<'a>),
+}
+
+/// The histories in `doc` with more than one version, in the order their
+/// first versions appear.
+pub fn histories(doc: &Document) -> Vec<History<'_>> {
+	let mut histories: Vec<History> = Vec::new();
+	// For each name, the history its latest definition belongs to.
+	let mut open: Vec<(String, usize)> = Vec::new();
+	for code in doc.code_blocks() {
+		for (name, version) in definitions(code) {
+			let current = open.iter().position(|(open_name, _)| *open_name == name);
+			if let Some(at) = current {
+				let history = &mut histories[open[at].1];
+				let last = history.versions.last().unwrap();
+				if significant(&last.text) == significant(&version.text) {
+					continue;
+				}
+				if similarity(&last.text, &version.text) >= SIMILARITY {
+					history.versions.push(version);
+					continue;
+				}
+				open.remove(at);
+			}
+			open.push((name.clone(), histories.len()));
+			histories.push(History {
+				name,
+				versions: vec![version],
+			});
+		}
+	}
+	histories.retain(|history| history.versions.len() > 1);
+	histories
+}
+
+/// The items a block defines, with the methods of its impl blocks, keyed by
+/// name.
+pub fn definitions(code: &Code) -> Vec<(String, Version<'_>)> {
+	let mut found = Vec::new();
+	let mut from = 0;
+	for item in top_level_items(&code.text) {
+		let text = item.text.trim_end().to_string();
+		let offset = code.text[from..].find(&text).map_or(from, |at| from + at);
+		from = offset + text.len();
+		let line = code.lines.start + code.text[..offset].matches('\n').count();
+		let header = header(&text);
+		let text = text::dedent(&text);
+		if header.starts_with("impl") {
+			for (name, method, method_line) in methods(&text) {
+				let label = format!("{header} {{ fn {name} }}");
+				found.push((
+					name,
+					Version {
+						code,
+						line: line + method_line,
+						label,
+						text: text::dedent(&method),
+					},
+				));
+			}
+			continue;
+		}
+		if header.starts_with("use ") || header.starts_with("pub use ") {
+			continue;
+		}
+		let kind = header
+			.split_whitespace()
+			.find(|word| !word.starts_with("pub"))
+			.unwrap_or("");
+		found.push((
+			item.key.clone(),
+			Version {
+				code,
+				line,
+				label: format!("{kind} {}", item.key),
+				text,
+			},
+		));
+	}
+	found
+}
+
+/// The first line of code of an item, without attributes, up to its `{`.
+fn header(text: &str) -> String {
+	let line = text
+		.lines()
+		.map(|line| code_part(line).trim())
+		.find(|line| !line.is_empty() && !line.starts_with('#'))
+		.unwrap_or("");
+	line.split('{').next().unwrap_or(line).trim().to_string()
+}
+
+/// The `fn`s inside an impl block: name, text and line within the block.
+fn methods(text: &str) -> Vec<(String, String, usize)> {
+	let (Some(open), Some(close)) = (text.find('{'), text.rfind('}')) else {
+		return Vec::new();
+	};
+	if close <= open {
+		return Vec::new();
+	}
+	let body = &text[open + 1..close];
+	let body_line = text[..open].matches('\n').count();
+	let mut from = 0;
+	let mut methods = Vec::new();
+	for item in top_level_items(body) {
+		let method = item.text.trim_end();
+		let offset = body[from..].find(method).map_or(from, |at| from + at);
+		from = offset + method.len();
+		if !header(method).contains("fn ") {
+			continue;
+		}
+		let line = body_line + body[..offset].matches('\n').count();
+		methods.push((item.key, method.to_string(), line));
+	}
+	methods
+}
+
+/// The tokens that carry meaning: comments and layout are left out of
+/// comparisons, so rewording a comment is not a new version.
+fn significant(text: &str) -> Vec<&str> {
+	lex::significant(text)
+		.into_iter()
+		.map(|token| token.text)
+		.collect()
+}
+
+/// The share of tokens two definitions have in common, from 0 to 1.
+pub fn similarity(old: &str, new: &str) -> f64 {
+	let (old, new) = (significant(old), significant(new));
+	if old.is_empty() && new.is_empty() {
+		return 1.0;
+	}
+	let common = lcs_table(&old, &new)[0][0];
+	2.0 * common as f64 / (old.len() + new.len()) as f64
+}
+
+/// `table[i][j]`: the length of the longest common subsequence of
+/// `a[i..]` and `b[j..]`.
+fn lcs_table<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Vec<usize>> {
+	let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+	for i in (0..a.len()).rev() {
+		for j in (0..b.len()).rev() {
+			table[i][j] = if a[i] == b[j] {
+				table[i + 1][j + 1] + 1
+			} else {
+				table[i + 1][j].max(table[i][j + 1])
+			};
+		}
+	}
+	table
+}
+
+/// The token-level diff from `old` to `new`.
+///
+/// Only significant tokens are compared. The whitespace and comments of
+/// `new` come out as kept, so that the diff reads as the new version with
+/// the changes marked; those of `old` are dropped.
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+	let old: Vec<Token> = lex::significant(old);
+	let new_all: Vec<Token> = lex::tokens(new);
+	let new: Vec<Token> = new_all
+		.iter()
+		.copied()
+		.filter(|token| !token.is_trivia())
+		.collect();
+	let old_text: Vec<&str> = old.iter().map(|token| token.text).collect();
+	let new_text: Vec<&str> = new.iter().map(|token| token.text).collect();
+	let table = lcs_table(&old_text, &new_text);
+
+	// The changes to the significant tokens, in order.
+	let mut changes = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old_text[i] == new_text[j] {
+			changes.push(Change::Kept(new[j]));
+			i += 1;
+			j += 1;
+		} else if i < old.len() && (j == new.len() || table[i + 1][j] >= table[i][j + 1]) {
+			changes.push(Change::Removed(old[i]));
+			i += 1;
+		} else {
+			changes.push(Change::Added(new[j]));
+			j += 1;
+		}
+	}
+
+	// Put the trivia of the new version back between them. Removed tokens
+	// go right after the new token they follow, before any whitespace;
+	// unless something was added in their place, in which case they go
+	// right before it, so that a replacement reads `[-old-]{+new+}`.
+	let mut out = Vec::with_capacity(new_all.len() + changes.len());
+	let mut k = 0;
+	for token in new_all {
+		let removed = changes[k..]
+			.iter()
+			.take_while(|change| matches!(change, Change::Removed(_)))
+			.count();
+		let replaced = matches!(changes.get(k + removed), Some(Change::Added(_)));
+		if !token.is_trivia() || !replaced {
+			out.extend_from_slice(&changes[k..k + removed]);
+			k += removed;
+		}
+		if token.is_trivia() {
+			out.push(Change::Kept(token));
+		} else if let Some(&change) = changes.get(k) {
+			out.push(change);
+			k += 1;
+		}
+	}
+	out.extend_from_slice(&changes[k..]);
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	/// The diff with additions as `{+…+}` and removals as `[-…-]`.
+	fn marked(old: &str, new: &str) -> String {
+		diff(old, new)
+			.iter()
+			.map(|change| match change {
+				Change::Kept(token) => token.text.to_string(),
+				Change::Added(token) => format!("{{+{}+}}", token.text),
+				Change::Removed(token) => format!("[-{}-]", token.text),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn diff_marks_the_changed_tokens() {
+		assert_eq!(
+			marked(
+				"let x = 5; // immutable\nprintln!(\"{x}\");",
+				"let mut x = 6; // now mutable\nprintln!(\"{x}\");"
+			),
+			"let {+mut+} x = [-5-]{+6+}; // now mutable\nprintln!(\"{x}\");"
+		);
+		// Removed on its own, a token stays next to the one it followed.
+		assert_eq!(marked("let mut x = 5;", "let x = 5;"), "let[-mut-] x = 5;");
+	}
+
+	#[test]
+	fn methods_continue_the_history_of_functions() {
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_5_structs.rs").unwrap(),
+			"/*
+Area:
+*/
+
+	fn area(width: u32, height: u32) -> u32 {
+		width * height
+	}
+
+	/*
+	As a method:*/
+
+	struct Rectangle { width: u32, height: u32 }
+
+	impl Rectangle {
+		fn area(&self) -> u32 {
+			self.width * self.height
+		}
+	}
+
+	/*
+	Unrelated:*/
+
+	fn main() {
+		let x = 5;
+	}
+
+	fn main() {
+		let guess: u32 = \"42\".trim().parse().expect(\"a number\");
+		println!(\"You guessed: {guess}\");
+	}
+
+	struct Rectangle { width: u32, height: u32 }
+",
+		);
+		let histories = histories(&doc);
+		let found: Vec<(&str, Vec<(usize, &str)>)> = histories
+			.iter()
+			.map(|history| {
+				let versions = history
+					.versions
+					.iter()
+					.map(|version| (version.line, version.label.as_str()))
+					.collect();
+				(history.name.as_str(), versions)
+			})
+			.collect();
+		assert_eq!(
+			found,
+			[(
+				"area",
+				vec![(5, "fn area"), (15, "impl Rectangle { fn area }")]
+			)]
+		);
+	}
+}
@@ -0,0 +1,134 @@
+//! Following the notes directory with Linux's inotify, so that a command can
+//! wait for a notes file to be saved.
+//!
+//! The directory is watched rather than each file: editors often save by
+//! writing a new file and renaming it over the old one, which would leave a
+//! watch on the old file looking at nothing. Both a file written and closed
+//! and a file renamed into the directory count as a save.
+
+use std::ffi::{CString, OsStr};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_int, c_short, c_ulong};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+const IN_CLOEXEC: c_int = 0o2000000;
+const IN_CLOSE_WRITE: u32 = 0x0000_0008;
+const IN_MOVED_TO: u32 = 0x0000_0080;
+const POLLIN: c_short = 0x1;
+
+/// The size of `struct inotify_event` before the name that follows it.
+const EVENT_HEADER: usize = 16;
+
+/// How long the directory has to be quiet before a batch of saves is
+/// handed over, in milliseconds. One save can be several events.
+const SETTLE: c_int = 100;
+
+#[repr(C)]
+struct PollFd {
+	fd: c_int,
+	events: c_short,
+	revents: c_short,
+}
+
+extern "C" {
+	fn inotify_init1(flags: c_int) -> c_int;
+	fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+	fn poll(fds: *mut PollFd, count: c_ulong, timeout: c_int) -> c_int;
+}
+
+/// A watch on one directory.
+pub struct Watcher {
+	dir: PathBuf,
+	/// The inotify instance, closed when the watcher is dropped.
+	events: File,
+}
+
+impl Watcher {
+	pub fn new(dir: &Path) -> Result<Watcher> {
+		let failed = || Error::io(dir, io::Error::last_os_error());
+		// SAFETY: inotify_init1 takes no pointers; a descriptor it returns
+		// is ours alone, and `File` closes it.
+		let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+		if fd < 0 {
+			return Err(failed());
+		}
+		// SAFETY: see above.
+		let events = unsafe { File::from_raw_fd(fd) };
+		let path = CString::new(dir.as_os_str().as_bytes()).map_err(|_| {
+			Error::io(
+				dir,
+				io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"),
+			)
+		})?;
+		// SAFETY: `path` is a NUL-terminated string that outlives the call.
+		if unsafe { inotify_add_watch(fd, path.as_ptr(), IN_CLOSE_WRITE | IN_MOVED_TO) } < 0 {
+			return Err(failed());
+		}
+		Ok(Watcher {
+			dir: dir.to_path_buf(),
+			events,
+		})
+	}
+
+	/// Waits for files in the directory to be saved and returns their
+	/// paths, each once, in the order they were first saved.
+	pub fn changes(&mut self) -> Result<Vec<PathBuf>> {
+		let mut changed = Vec::new();
+		loop {
+			self.read_into(&mut changed)?;
+			if !self.settles()? {
+				continue;
+			}
+			if !changed.is_empty() {
+				return Ok(changed);
+			}
+		}
+	}
+
+	/// Reads at least one event, blocking until there is one.
+	fn read_into(&mut self, changed: &mut Vec<PathBuf>) -> Result<()> {
+		let mut buffer = [0u8; 4096];
+		let read = self
+			.events
+			.read(&mut buffer)
+			.map_err(|e| Error::io(&self.dir, e))?;
+		let mut at = 0;
+		while at + EVENT_HEADER <= read {
+			let field = |offset: usize| {
+				let bytes = &buffer[at + offset..at + offset + 4];
+				u32::from_ne_bytes(bytes.try_into().expect("four bytes"))
+			};
+			let len = field(12) as usize;
+			let name = &buffer[at + EVENT_HEADER..(at + EVENT_HEADER + len).min(read)];
+			let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+			if !name.is_empty() {
+				let path = self.dir.join(OsStr::from_bytes(name));
+				if !changed.contains(&path) {
+					changed.push(path);
+				}
+			}
+			at += EVENT_HEADER + len;
+		}
+		Ok(())
+	}
+
+	/// Whether no further event comes within [`SETTLE`].
+	fn settles(&self) -> Result<bool> {
+		let mut fd = PollFd {
+			fd: self.events.as_raw_fd(),
+			events: POLLIN,
+			revents: 0,
+		};
+		// SAFETY: `fd` is one valid `pollfd` for the length of the call.
+		match unsafe { poll(&mut fd, 1, SETTLE) } {
+			0 => Ok(true),
+			n if n > 0 => Ok(false),
+			_ => Err(Error::io(&self.dir, io::Error::last_os_error())),
+		}
+	}
+}
@@ -0,0 +1,702 @@
+//! Cross-references inside the notes.
+//!
+//! Some prose points at other parts of its own file by line number: "line 69
+//! of this file", "(Line 75 of this file)", "after line 90". Those break as
+//! soon as a line is added above their target. This module finds them,
+//! works out what they point at now, and can replace them by a reference to
+//! the section the target is in, written `<#anchor>` with the same anchors
+//! the exports give headings. The exports turn `<#anchor>` into a link.
+//!
+//! References to lines of other files, such as "line 4 of main.rs" in a
+//! compiler message, are recognised and left alone.
+
+use std::ops::Range;
+
+use crate::export::anchors;
+use crate::format::Formatter;
+use crate::model::{Block, Document, ProseStyle};
+use crate::parse::is_blank;
+
+/// A reference by line number found in the prose of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRef {
+	/// The line the reference starts on.
+	pub line: usize,
+	/// Byte range of the reference in the source, from `line` up to the
+	/// number and an `of this file` after it.
+	pub span: Range<usize>,
+	pub text: String,
+	/// The lines referred to.
+	pub target: Range<usize>,
+	/// The file named after the number, if it is not this one.
+	pub other_file: Option<String>,
+}
+
+/// What a line reference points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolved {
+	/// A line of code or prose under the section with this anchor and title.
+	Section {
+		anchor: String,
+		title: String,
+		code: bool,
+		/// The text of the first target line.
+		text: String,
+	},
+	/// A line of another file; nothing to check.
+	OtherFile(String),
+	/// The reference cannot be what the writer meant.
+	Stale(String),
+}
+
+impl Resolved {
+	pub fn is_problem(&self) -> bool {
+		matches!(self, Resolved::Stale(_))
+	}
+}
+
+/// A piece of prose: plain text, or a `<#anchor>` reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Piece<'a> {
+	Text(&'a str),
+	Anchor(&'a str),
+}
+
+/// Splits prose at its `<#anchor>` references.
+pub fn pieces(text: &str) -> Vec<Piece<'_>> {
+	let mut pieces = Vec::new();
+	let mut rest = text;
+	while let Some(open) = rest.find("<#") {
+		let after = &rest[open + 2..];
+		let Some(close) = after.find('>') else {
+			break;
+		};
+		let anchor = &after[..close];
+		if anchor.is_empty() || !anchor.chars().all(is_anchor_char) {
+			pieces.push(Piece::Text(&rest[..open + 2]));
+			rest = after;
+			continue;
+		}
+		if open > 0 {
+			pieces.push(Piece::Text(&rest[..open]));
+		}
+		pieces.push(Piece::Anchor(anchor));
+		rest = &after[close + 1..];
+	}
+	if !rest.is_empty() {
+		pieces.push(Piece::Text(rest));
+	}
+	pieces
+}
+
+fn is_anchor_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// The `<#anchor>` references in the prose of `doc` that name no heading,
+/// with their lines.
+pub fn broken_anchors(doc: &Document) -> Vec<(usize, String)> {
+	let known = anchors(doc);
+	let mut broken = Vec::new();
+	for block in &doc.blocks {
+		let Block::Prose(prose) = block else {
+			continue;
+		};
+		for (i, line) in prose.text.lines().enumerate() {
+			for piece in pieces(line) {
+				if let Piece::Anchor(anchor) = piece {
+					if !known.iter().any(|known| known == anchor) {
+						broken.push((prose.lines.start + i, anchor.to_string()));
+					}
+				}
+			}
+		}
+	}
+	broken
+}
+
+/// Finds the line references in the prose of `doc`, whose file reads
+/// `source`.
+pub fn line_refs(doc: &Document, source: &str) -> Vec<LineRef> {
+	let starts = line_starts(source);
+	let mut refs = Vec::new();
+	for block in &doc.blocks {
+		let Block::Prose(prose) = block else {
+			continue;
+		};
+		let start = starts[prose.lines.start - 1];
+		let end = starts.get(prose.lines.end).copied().unwrap_or(source.len());
+		let text = &source[start..end];
+		for found in find_refs(text, doc.file.file_name()) {
+			let span = start + found.span.start..start + found.span.end;
+			refs.push(LineRef {
+				line: starts.partition_point(|&at| at <= span.start),
+				text: source[span.clone()].to_string(),
+				span,
+				target: found.target,
+				other_file: found.other_file,
+			});
+		}
+	}
+	refs
+}
+
+/// Works out what a reference in `doc` points at.
+pub fn resolve(doc: &Document, source: &str, reference: &LineRef) -> Resolved {
+	if let Some(file) = &reference.other_file {
+		return Resolved::OtherFile(file.clone());
+	}
+	let lines: Vec<&str> = source.lines().collect();
+	let first = reference.target.start;
+	if first == 0 || reference.target.end - 1 > lines.len() {
+		return Resolved::Stale(format!("the file has {} lines", lines.len()));
+	}
+	let text = lines[first - 1].trim();
+	if is_blank(text) {
+		return Resolved::Stale(format!("line {first} is blank"));
+	}
+	if ["}", "};", "/*", "*/", "{"].contains(&text) {
+		return Resolved::Stale(format!("line {first} is only `{text}`"));
+	}
+	let block = doc
+		.blocks
+		.iter()
+		.find(|block| block.lines().contains(first));
+	match block {
+		Some(Block::Heading(heading)) => {
+			return Resolved::Stale(format!("line {first} is the heading `{}`", heading.title));
+		}
+		Some(Block::Prose(prose)) if prose.lines.contains(reference.line) => {
+			return Resolved::Stale(format!(
+				"line {first} is in the same paragraph as the reference"
+			));
+		}
+		_ => {}
+	}
+	let headings: Vec<_> = doc.headings().collect();
+	let Some(index) = headings
+		.iter()
+		.rposition(|heading| heading.lines.start <= first)
+	else {
+		return Resolved::Stale(format!("line {first} is above the first heading"));
+	};
+	Resolved::Section {
+		anchor: anchors(doc)[index].clone(),
+		title: headings[index].title.clone(),
+		code: matches!(block, Some(Block::Code(_))),
+		text: text.to_string(),
+	}
+}
+
+/// What [`rewrite`] made of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+	pub source: String,
+	/// The references replaced, with what replaced them.
+	pub done: Vec<(LineRef, String)>,
+	/// The references to a section that were left as they are, with why.
+	pub kept: Vec<(LineRef, String)>,
+}
+
+/// Words that can stand in front of "line N" and read as well in front of
+/// a section: "on line 69" becomes "in <#anchor>".
+const PREPOSITIONS: [&str; 5] = ["on", "at", "in", "from", "under"];
+
+/// Replaces the line references of `doc` that resolve to a section by an
+/// anchor reference, and rewraps the paragraphs they are in.
+///
+/// A reference to the section it is already in is kept, as is one that
+/// shares its sentence with another reference to the same section: as
+/// anchors, neither would say anything. Line references left in the file
+/// are renumbered if rewrapping moved their targets.
+pub fn rewrite(doc: &Document, source: &str) -> Rewrite {
+	let refs = line_refs(doc, source);
+	let resolved: Vec<Resolved> = refs
+		.iter()
+		.map(|reference| resolve(doc, source, reference))
+		.collect();
+	let sentences: Vec<usize> = refs
+		.iter()
+		.map(|reference| sentence_start(source, reference.span.start))
+		.collect();
+	let headings: Vec<_> = doc.headings().collect();
+	let names = anchors(doc);
+
+	let mut edits = Vec::new();
+	let mut done = Vec::new();
+	let mut kept = Vec::new();
+	for (i, reference) in refs.iter().enumerate() {
+		let Resolved::Section { anchor, .. } = &resolved[i] else {
+			continue;
+		};
+		let own = headings
+			.iter()
+			.rposition(|heading| heading.lines.start <= reference.line)
+			.map(|index| &names[index]);
+		if own == Some(anchor) {
+			kept.push((
+				reference.clone(),
+				format!("the reference is itself in <#{anchor}>"),
+			));
+			continue;
+		}
+		let twin = (0..refs.len()).any(|j| {
+			j != i
+				&& sentences[j] == sentences[i]
+				&& matches!(&resolved[j], Resolved::Section { anchor: other, .. } if other == anchor)
+		});
+		if twin {
+			kept.push((
+				reference.clone(),
+				format!("another reference in the sentence is to <#{anchor}>"),
+			));
+			continue;
+		}
+
+		let (start, replacement) = replacement(source, reference, anchor);
+		edits.push((start..reference.span.end, replacement.clone()));
+		done.push((reference.clone(), replacement));
+	}
+	let starts = line_starts(source);
+	let mut paragraphs: Vec<(Range<usize>, bool)> = Vec::new();
+	for (reference, _) in &done {
+		// Prose between snippets has its lines kept.
+		let paragraph = paragraph_at(doc, source, reference.line).map_or_else(
+			|| {
+				let last = starts.partition_point(|&at| at < reference.span.end);
+				(reference.line..last + 1, false)
+			},
+			|lines| (lines, true),
+		);
+		if !paragraphs.contains(&paragraph) {
+			paragraphs.push(paragraph);
+		}
+	}
+	let (out, moved) = rewrap(source, &edits, &paragraphs);
+	let source = renumber(doc, &out, &moved);
+	Rewrite { source, done, kept }
+}
+
+/// Where the replacement of `reference` starts, and the replacement: "on
+/// line 69" reads "in <#anchor>", "(Line 75)" reads "(See <#anchor>)" and
+/// "after line 90" reads "after <#anchor>".
+fn replacement(source: &str, reference: &LineRef, anchor: &str) -> (usize, String) {
+	let before = source[..reference.span.start].trim_end();
+	let word_start = before.trim_end_matches(|c: char| c.is_alphabetic()).len();
+	let word = &before[word_start..];
+	if PREPOSITIONS.contains(&word.to_lowercase().as_str()) {
+		let preposition = if word.starts_with(char::is_uppercase) {
+			"In"
+		} else {
+			"in"
+		};
+		return (word_start, format!("{preposition} <#{anchor}>"));
+	}
+	if reference.text.starts_with('L') {
+		return (reference.span.start, format!("See <#{anchor}>"));
+	}
+	(reference.span.start, format!("<#{anchor}>"))
+}
+
+/// Byte offset where the sentence around `at` starts: after the last full
+/// stop, question or exclamation mark followed by a space, or at the start
+/// of its paragraph.
+fn sentence_start(source: &str, at: usize) -> usize {
+	let bytes = source.as_bytes();
+	let mut i = at;
+	while i > 0 {
+		i -= 1;
+		let next = bytes.get(i + 1).copied().unwrap_or(b' ');
+		if matches!(bytes[i], b'.' | b'?' | b'!') && next.is_ascii_whitespace() {
+			return i + 1;
+		}
+		if bytes[i] == b'\n' && source[..i].trim_end_matches([' ', '\t']).ends_with('\n') {
+			return i;
+		}
+	}
+	0
+}
+
+/// The lines of the paragraph of block comment prose that `line` is in,
+/// split the way `notes fmt` splits it.
+fn paragraph_at(doc: &Document, source: &str, line: usize) -> Option<Range<usize>> {
+	let prose = doc.blocks.iter().find_map(|block| match block {
+		Block::Prose(prose) if prose.style == ProseStyle::Block && prose.lines.contains(line) => {
+			Some(prose)
+		}
+		_ => None,
+	})?;
+	let lines: Vec<&str> = source.split('\n').collect();
+	// A paragraph ends at a blank line, or where an `A:` answer starts.
+	let blank = |line: usize| is_blank(lines[line - 1]);
+	let answer = |line: usize| lines[line - 1].trim_start().starts_with("A:");
+	let mut start = line;
+	while start > prose.lines.start && !blank(start - 1) && !answer(start) {
+		start -= 1;
+	}
+	let mut end = line;
+	while end < prose.lines.end && !blank(end + 1) && !answer(end + 1) {
+		end += 1;
+	}
+	Some(start..end + 1)
+}
+
+/// `source` with `edits` made and the paragraphs they are in rewrapped
+/// as `notes fmt` would, where the flag says so, and the line each old
+/// line moved to.
+fn rewrap(
+	source: &str,
+	edits: &[(Range<usize>, String)],
+	paragraphs: &[(Range<usize>, bool)],
+) -> (String, Vec<usize>) {
+	let starts = line_starts(source);
+	let lines: Vec<&str> = source.split('\n').collect();
+	let formatter = Formatter::new();
+	let mut out: Vec<String> = Vec::with_capacity(lines.len());
+	let mut moved = Vec::with_capacity(lines.len());
+	let mut number = 1;
+	while number <= lines.len() {
+		let Some((paragraph, reflow)) = paragraphs.iter().find(|(lines, _)| lines.start == number)
+		else {
+			out.push(lines[number - 1].to_string());
+			moved.push(out.len());
+			number += 1;
+			continue;
+		};
+		let from = starts[paragraph.start - 1];
+		let to = starts
+			.get(paragraph.end - 1)
+			.map_or(source.len(), |&at| at - 1);
+		let mut text = String::new();
+		let mut at = from;
+		for (span, replacement) in edits
+			.iter()
+			.filter(|(span, _)| from <= span.start && span.end <= to)
+		{
+			text.push_str(&source[at..span.start]);
+			text.push_str(replacement);
+			at = span.end;
+		}
+		text.push_str(&source[at..to]);
+
+		let new_lines: Vec<&str> = text.split('\n').collect();
+		let new = reflow
+			.then(|| formatter.reflow(&new_lines))
+			.flatten()
+			.unwrap_or_else(|| new_lines.iter().map(|line| line.to_string()).collect());
+		let first = out.len() + 1;
+		for offset in 0..paragraph.len() {
+			moved.push(first + offset.min(new.len() - 1));
+		}
+		out.extend(new);
+		number = paragraph.end;
+	}
+	(out.join("\n"), moved)
+}
+
+/// `source` with the numbers of its remaining line references changed to
+/// where `moved` says their targets went.
+pub(crate) fn renumber(doc: &Document, source: &str, moved: &[usize]) -> String {
+	if moved.iter().enumerate().all(|(i, &line)| line == i + 1) {
+		return source.to_string();
+	}
+	let doc = Document::parse(doc.file.clone(), source);
+	let mut out = String::with_capacity(source.len());
+	let mut at = 0;
+	for reference in line_refs(&doc, source) {
+		if reference.other_file.is_some() {
+			continue;
+		}
+		out.push_str(&source[at..reference.span.start]);
+		let mut rest = reference.text.as_str();
+		while let Some(digit) = rest.find(|c: char| c.is_ascii_digit()) {
+			out.push_str(&rest[..digit]);
+			rest = &rest[digit..];
+			let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+			let number: usize = rest[..digits].parse().unwrap_or(0);
+			match moved.get(number.wrapping_sub(1)) {
+				Some(line) => out.push_str(&line.to_string()),
+				None => out.push_str(&rest[..digits]),
+			}
+			rest = &rest[digits..];
+		}
+		out.push_str(rest);
+		at = reference.span.end;
+	}
+	out.push_str(&source[at..]);
+	out
+}
+
+/// Byte offset of the start of every line.
+fn line_starts(source: &str) -> Vec<usize> {
+	let mut starts = vec![0];
+	starts.extend(
+		source
+			.char_indices()
+			.filter(|&(_, c)| c == '\n')
+			.map(|(i, _)| i + 1),
+	);
+	starts
+}
+
+struct Found {
+	span: Range<usize>,
+	target: Range<usize>,
+	other_file: Option<String>,
+}
+
+/// The references in a stretch of prose: `line N`, `lines N-M`,
+/// `lines N and M` or `lines N to M`, optionally followed by `of this file`
+/// or `of <file>`.
+fn find_refs(text: &str, own_name: &str) -> Vec<Found> {
+	let lower = text.to_ascii_lowercase();
+	let mut found = Vec::new();
+	let mut from = 0;
+	while let Some(at) = lower[from..].find("line").map(|at| from + at) {
+		from = at + 4;
+		let word_start = at == 0 || !lower[..at].ends_with(|c: char| c.is_alphanumeric());
+		if !word_start {
+			continue;
+		}
+		let mut cursor = Cursor {
+			text: &lower,
+			at: at + 4,
+		};
+		cursor.eat("s");
+		if !cursor.space() {
+			continue;
+		}
+		let Some(first) = cursor.number() else {
+			continue;
+		};
+		let mut last = first;
+		let mark = cursor.at;
+		cursor.space();
+		if cursor.eat("-") || cursor.eat("and") || cursor.eat("to") {
+			cursor.space();
+			match cursor.number() {
+				Some(n) if n >= first => last = n,
+				_ => cursor.at = mark,
+			}
+		} else {
+			cursor.at = mark;
+		}
+
+		let mark = cursor.at;
+		let mut other_file = None;
+		if cursor.space() && cursor.eat("of") && cursor.space() {
+			if cursor.eat("this") && cursor.space() && cursor.eat("file") {
+				// The reference includes "of this file".
+			} else {
+				cursor.at = mark;
+			}
+		} else {
+			cursor.at = mark;
+		}
+		if cursor.at == mark {
+			other_file = named_file(&text[mark..], own_name);
+		}
+		found.push(Found {
+			span: at..cursor.at,
+			target: first..last + 1,
+			other_file,
+		});
+		from = cursor.at;
+	}
+	found
+}
+
+/// A file name such as `main.rs` in the rest of the sentence, unless it is
+/// the notes file itself.
+fn named_file(rest: &str, own_name: &str) -> Option<String> {
+	let sentence_end = rest
+		.char_indices()
+		.find(|&(i, c)| c == '.' && rest[i + 1..].starts_with(char::is_whitespace))
+		.map_or(rest.len(), |(i, _)| i);
+	rest[..sentence_end]
+		.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+		.map(|word| word.trim_end_matches(['.', ',', ';', ':']))
+		.find(|word| word.ends_with(".rs") && word.len() > 3)
+		.filter(|word| !word.ends_with(own_name))
+		.map(str::to_string)
+}
+
+/// Reading position in lowercased prose.
+struct Cursor<'a> {
+	text: &'a str,
+	at: usize,
+}
+
+impl Cursor<'_> {
+	fn eat(&mut self, word: &str) -> bool {
+		let ok = self.text[self.at..].starts_with(word);
+		if ok {
+			self.at += word.len();
+		}
+		ok
+	}
+
+	fn space(&mut self) -> bool {
+		let rest = &self.text[self.at..];
+		let skipped = rest.len() - rest.trim_start().len();
+		self.at += skipped;
+		skipped > 0
+	}
+
+	fn number(&mut self) -> Option<usize> {
+		let rest = &self.text[self.at..];
+		let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+		let n = rest[..digits].parse().ok()?;
+		self.at += digits;
+		Some(n)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::source::NoteFile;
+
+	fn found(text: &str) -> Vec<(&str, Range<usize>, Option<String>)> {
+		find_refs(text, "rust_chapter_1_test.rs")
+			.into_iter()
+			.map(|found| (&text[found.span], found.target, found.other_file))
+			.collect()
+	}
+
+	#[test]
+	fn finds_the_forms_of_a_line_reference() {
+		assert_eq!(
+			found("See line 69 of this file, lines 3-5 and Lines 7 to 9, or lines 10 and 12."),
+			[
+				("line 69 of this file", 69..70, None),
+				("lines 3-5", 3..6, None),
+				("Lines 7 to 9", 7..10, None),
+				("lines 10 and 12", 10..13, None),
+			]
+		);
+		assert_eq!(
+			found("defined on line\n\t75. For"),
+			[("line\n\t75", 75..76, None)]
+		);
+	}
+
+	#[test]
+	fn references_to_other_files_name_them() {
+		assert_eq!(
+			found("The error is at line 4 of src/main.rs, and line 9 is in main.rs."),
+			[
+				("line 4", 4..5, Some("src/main.rs".to_string())),
+				("line 9", 9..10, Some("main.rs".to_string())),
+			]
+		);
+		assert_eq!(
+			found("line 4 of rust_chapter_1_test.rs"),
+			[("line 4", 4..5, None)]
+		);
+	}
+
+	#[test]
+	fn other_words_are_not_references() {
+		// A range that runs backwards is read as its first line.
+		assert_eq!(found("lines 9-2"), [("lines 9", 9..10, None)]);
+		assert!(found("the pipeline 4, a deadline 3, a line, or line-by-line").is_empty());
+	}
+
+	const SOURCE: &str = "/*
+
+First Section:
+
+	Some code comes first:*/
+
+		fn first() {}
+
+	/*
+Second Section:
+
+	We can write it the same way as on line 7 of this file, which is a long
+	way up. Line 15 is the second function.*/
+
+		fn second() {}
+
+	/*
+	The code at line 7 and the code from line 7 are the same.*/
+";
+
+	fn doc() -> Document {
+		Document::parse(
+			NoteFile::from_path("rust_chapter_1_test.rs").unwrap(),
+			SOURCE,
+		)
+	}
+
+	#[test]
+	fn rewrite_fits_the_preposition_and_rewraps() {
+		let done = rewrite(&doc(), SOURCE);
+		let replaced: Vec<_> = done
+			.done
+			.iter()
+			.map(|(r, with)| (r.line, with.as_str()))
+			.collect();
+		assert_eq!(replaced, [(12, "in <#first-section>")]);
+		assert!(done.source.contains(
+			"\tWe can write it the same way as in <#first-section>, which is a long way up.\n\
+			 \tLine 15 is the second function.*/\n"
+		));
+	}
+
+	#[test]
+	fn rewrite_keeps_references_that_would_say_nothing() {
+		let done = rewrite(&doc(), SOURCE);
+		let kept: Vec<_> = done
+			.kept
+			.iter()
+			.map(|(r, why)| (r.line, why.as_str()))
+			.collect();
+		assert_eq!(
+			kept,
+			[
+				(13, "the reference is itself in <#second-section>"),
+				(
+					18,
+					"another reference in the sentence is to <#first-section>"
+				),
+				(
+					18,
+					"another reference in the sentence is to <#first-section>"
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn rewrite_renumbers_the_references_it_keeps() {
+		let source = "/*
+
+First Section:
+
+	Some code comes first:*/
+
+		fn first() {}
+
+	/*
+Second Section:
+
+	As on line 7
+	of this
+	file.
+
+	Then the code on line 19 and line 19 again:*/
+
+		fn second() {}
+";
+		let doc = Document::parse(
+			NoteFile::from_path("rust_chapter_1_test.rs").unwrap(),
+			source,
+		);
+		let done = rewrite(&doc, source);
+		assert!(done
+			.source
+			.contains("\tAs in <#first-section>.\n\n\tThen the code on line 17 and line 17"));
+	}
+}
@@ -0,0 +1,172 @@
+//! A writer for zip archives, enough for the containers some exports are
+//! packaged in.
+//!
+//! Files are stored as they are, without compression: every reader accepts
+//! that, and EPUB requires it of its first file anyway. Every entry is dated
+//! 1980-01-01, the earliest date zip can record, so that writing the same
+//! files twice gives the same archive.
+
+/// The earliest date a zip entry can carry, in MS-DOS form.
+const DOS_DATE: u16 = (1 << 5) | 1;
+const DOS_TIME: u16 = 0;
+
+/// The zip version needed to read the archive: 2.0, for plain stored files.
+const VERSION: u16 = 20;
+
+/// An archive being written in memory.
+#[derive(Debug, Default)]
+pub struct Zip {
+	bytes: Vec<u8>,
+	/// The central directory, written at the end.
+	directory: Vec<u8>,
+	count: u16,
+}
+
+impl Zip {
+	pub fn new() -> Zip {
+		Zip::default()
+	}
+
+	/// Adds a file, after the ones added before it.
+	pub fn add(&mut self, name: &str, data: &[u8]) {
+		let offset = self.bytes.len() as u32;
+		let crc = crc32(data);
+		let size = data.len() as u32;
+
+		let local = &mut self.bytes;
+		put32(local, 0x0403_4b50);
+		put16(local, VERSION);
+		put16(local, 0); // flags
+		put16(local, 0); // stored
+		put16(local, DOS_TIME);
+		put16(local, DOS_DATE);
+		put32(local, crc);
+		put32(local, size);
+		put32(local, size);
+		put16(local, name.len() as u16);
+		put16(local, 0); // extra field
+		local.extend_from_slice(name.as_bytes());
+		local.extend_from_slice(data);
+
+		let central = &mut self.directory;
+		put32(central, 0x0201_4b50);
+		put16(central, VERSION); // made by
+		put16(central, VERSION);
+		put16(central, 0);
+		put16(central, 0);
+		put16(central, DOS_TIME);
+		put16(central, DOS_DATE);
+		put32(central, crc);
+		put32(central, size);
+		put32(central, size);
+		put16(central, name.len() as u16);
+		put16(central, 0); // extra field
+		put16(central, 0); // comment
+		put16(central, 0); // disk
+		put16(central, 0); // internal attributes
+		put32(central, 0); // external attributes
+		put32(central, offset);
+		central.extend_from_slice(name.as_bytes());
+		self.count += 1;
+	}
+
+	/// The bytes of the finished archive.
+	pub fn finish(mut self) -> Vec<u8> {
+		let offset = self.bytes.len() as u32;
+		let size = self.directory.len() as u32;
+		self.bytes.append(&mut self.directory);
+		let end = &mut self.bytes;
+		put32(end, 0x0605_4b50);
+		put16(end, 0); // this disk
+		put16(end, 0); // the disk the directory starts on
+		put16(end, self.count);
+		put16(end, self.count);
+		put32(end, size);
+		put32(end, offset);
+		put16(end, 0); // comment
+		self.bytes
+	}
+}
+
+fn put16(out: &mut Vec<u8>, n: u16) {
+	out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, n: u32) {
+	out.extend_from_slice(&n.to_le_bytes());
+}
+
+/// The CRC-32 zip keeps of every file, bit by bit with the reversed
+/// polynomial; the files are small enough not to need a table.
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in data {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			let mask = (crc & 1).wrapping_neg();
+			crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+		}
+	}
+	!crc
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn u16_at(bytes: &[u8], at: usize) -> u16 {
+		u16::from_le_bytes([bytes[at], bytes[at + 1]])
+	}
+
+	fn u32_at(bytes: &[u8], at: usize) -> u32 {
+		u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+	}
+
+	#[test]
+	fn crc32_matches_the_standard_check_values() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+		assert_eq!(
+			crc32(b"The quick brown fox jumps over the lazy dog"),
+			0x414F_A339
+		);
+	}
+
+	#[test]
+	fn central_directory_points_at_every_file() {
+		let files: [(&str, &[u8]); 2] = [("mimetype", b"application/epub+zip"), ("a/b.txt", b"hi")];
+		let mut zip = Zip::new();
+		for (name, data) in files {
+			zip.add(name, data);
+		}
+		let bytes = zip.finish();
+
+		// The end of central directory record is the last 22 bytes.
+		let end = bytes.len() - 22;
+		assert_eq!(u32_at(&bytes, end), 0x0605_4b50);
+		assert_eq!(u16_at(&bytes, end + 8), 2);
+		assert_eq!(u16_at(&bytes, end + 10), 2);
+		let size = u32_at(&bytes, end + 12) as usize;
+		let mut at = u32_at(&bytes, end + 16) as usize;
+		assert_eq!(at + size, end);
+
+		for (name, data) in files {
+			assert_eq!(u32_at(&bytes, at), 0x0201_4b50);
+			assert_eq!(u32_at(&bytes, at + 16), crc32(data));
+			assert_eq!(u32_at(&bytes, at + 20) as usize, data.len());
+			let name_len = u16_at(&bytes, at + 28) as usize;
+			assert_eq!(&bytes[at + 46..at + 46 + name_len], name.as_bytes());
+
+			// The local header it points at holds the same name and data.
+			let local = u32_at(&bytes, at + 42) as usize;
+			assert_eq!(u32_at(&bytes, local), 0x0403_4b50);
+			assert_eq!(u32_at(&bytes, local + 14), crc32(data));
+			let start = local + 30 + name_len;
+			assert_eq!(&bytes[local + 30..start], name.as_bytes());
+			assert_eq!(&bytes[start..start + data.len()], data);
+
+			at += 46 + name_len;
+		}
+		assert_eq!(at, end);
+	}
+}